@@ -6,11 +6,15 @@ extern crate itertools;
 extern crate rustyline;
 
 use std::fs::File;
+use std::io::IsTerminal;
 use crate::repl::Repl;
+use crate::scaffold::{create_project, ProjectKind};
 use abra_core::common::fs_module_reader::FsModuleReader;
 use abra_core::{compile, compile_and_disassemble, compile_to_c, Error};
 use abra_core::builtins::common::to_string;
+use abra_core::common::diagnostic::Diagnostic;
 use abra_core::common::display_error::DisplayError;
+use abra_core::common::error_codes::lookup_error_code;
 use abra_core::module_loader::ModuleReader;
 use abra_core::parser::ast::ModuleId;
 use abra_core::vm::value::Value;
@@ -18,13 +22,23 @@ use abra_core::vm::vm::{VM, VMContext};
 use abra_llvm::{compile_to_llvm_and_run};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use itertools::Either;
-use abra_core::common::util::get_project_root;
+use abra_core::build_cache::BuildCache;
+use abra_core::common::util::{get_project_root, resolve_std_path};
+use abra_core::docgen::{DocFormat, generate_docs};
+use abra_core::dump::{DumpNode, dump_ast, dump_tokens, dump_typed_module, nodes_to_json, nodes_to_tree};
+use abra_core::formatter::format_source;
+use abra_core::lexer::lexer;
+use abra_core::linter::{LintConfig, lint_module};
+use abra_core::parser::parser::{self, ParseResult};
+use abra_core::manifest::{MANIFEST_FILE_NAME, ResolvedManifest};
 use abra_core::transpile::genc2::CCompiler2;
-use abra_core::typechecker::typechecker2::{LoadModule, ModuleLoader, Project, Typechecker2};
+use abra_core::typechecker::typechecker2::{LoadModule, ModuleLoader, Project, TypecheckError, Typechecker2};
 use abra_llvm::compiler2::LLVMCompiler2;
+use crate::watch::watch;
 
 mod repl;
+mod scaffold;
+mod watch;
 
 #[derive(Clap)]
 #[clap(name = "abra", version = crate_version!())]
@@ -43,6 +57,15 @@ enum SubCommand {
     Build(BuildOpts),
     Disassemble(DisassembleOpts),
     Test(TestOpts),
+    Init(InitOpts),
+    New(NewOpts),
+    Fmt(FmtOpts),
+    Doc(DocOpts),
+    Tokens(DumpOpts),
+    Ast(DumpOpts),
+    TypedAst(TypedAstOpts),
+    Explain(ExplainOpts),
+    Lint(LintOpts),
     Repl,
 }
 
@@ -59,6 +82,9 @@ struct RunOpts {
 struct CompileOpts {
     #[clap(help = "Path to an abra file to compile")]
     file_path: String,
+
+    #[clap(long = "message-format", help = "Format of reported errors, either 'human' or 'json' (default: human)")]
+    message_format: Option<String>,
 }
 
 #[derive(Clap)]
@@ -84,8 +110,17 @@ struct BuildOpts {
     #[clap(long = "no-gc", help = "Disable garbage collector (default: false)")]
     no_gc: Option<bool>,
 
-    #[clap(help = "Path to an abra file to compile")]
-    file_path: String,
+    #[clap(long = "no-cache", help = "Rebuild even if nothing has changed since the last build (default: false)")]
+    no_cache: bool,
+
+    #[clap(long = "watch", help = "Re-run whenever a module in the project changes (default: false)")]
+    watch: bool,
+
+    #[clap(long = "message-format", help = "Format of reported errors, either 'human' or 'json' (default: human)")]
+    message_format: Option<String>,
+
+    #[clap(help = "Path to an abra file to compile (default: the first entry point in abra.toml)")]
+    file_path: Option<String>,
 
     #[clap(last = true, help = "Arguments to pass to the abra program")]
     program_args: Vec<String>,
@@ -110,6 +145,96 @@ struct TestOpts {
 
     #[clap(short = "p", long = "pattern", help = "Glob pattern used to find tests to run (default: **/*_test.abra)")]
     test_pattern: Option<String>,
+
+    #[clap(long = "watch", help = "Re-run whenever a test file, or a module it imports, changes (default: false)")]
+    watch: bool,
+
+    #[clap(long = "message-format", help = "Format of reported errors, either 'human' or 'json' (default: human)")]
+    message_format: Option<String>,
+}
+
+#[derive(Clap)]
+struct InitOpts {
+    #[clap(long = "name", help = "Name of the package (default: the name of the current directory)")]
+    name: Option<String>,
+
+    #[clap(long = "lib", help = "Create a library rather than an executable (default: false)")]
+    lib: bool,
+}
+
+#[derive(Clap)]
+struct NewOpts {
+    #[clap(help = "Name of the package, which will be created in a new directory of the same name")]
+    name: String,
+
+    #[clap(long = "lib", help = "Create a library rather than an executable (default: false)")]
+    lib: bool,
+}
+
+#[derive(Clap)]
+struct FmtOpts {
+    #[clap(help = "Files or directories to format (default: the project's source roots, or the current directory)")]
+    paths: Vec<String>,
+
+    #[clap(long = "check", help = "Don't write any files; list the files which aren't formatted, and fail if there are any")]
+    check: bool,
+}
+
+#[derive(Clap)]
+struct DocOpts {
+    #[clap(help = "Path to the abra file whose project should be documented (default: the first entry point in abra.toml)")]
+    file_path: Option<String>,
+
+    #[clap(long = "std", help = "Path to the abra std/ directory")]
+    std_path: Option<String>,
+
+    #[clap(short = "o", help = "Directory in which to write the documentation (default: 'docs')")]
+    out_dir: Option<String>,
+
+    #[clap(long = "format", help = "Format of the generated pages, either 'markdown' or 'html' (default: markdown)")]
+    format: Option<String>,
+
+    #[clap(long = "include-std", help = "Also document the std modules used by the project, such as the prelude (default: false)")]
+    include_std: bool,
+}
+
+#[derive(Clap)]
+struct DumpOpts {
+    #[clap(help = "Path to an abra file to dump")]
+    file_path: String,
+
+    #[clap(long = "json", help = "Output JSON rather than a readable tree (default: false)")]
+    json: bool,
+}
+
+#[derive(Clap)]
+struct TypedAstOpts {
+    #[clap(help = "Path to an abra file to typecheck and dump (default: the first entry point in abra.toml)")]
+    file_path: Option<String>,
+
+    #[clap(long = "std", help = "Path to the abra std/ directory")]
+    std_path: Option<String>,
+
+    #[clap(long = "json", help = "Output JSON rather than a readable tree (default: false)")]
+    json: bool,
+}
+
+#[derive(Clap)]
+struct ExplainOpts {
+    #[clap(help = "An error code, eg. E0207")]
+    code: String,
+}
+
+#[derive(Clap)]
+struct LintOpts {
+    #[clap(help = "Path to the abra file whose project should be linted (default: the first entry point in abra.toml)")]
+    file_path: Option<String>,
+
+    #[clap(long = "std", help = "Path to the abra std/ directory")]
+    std_path: Option<String>,
+
+    #[clap(long = "message-format", help = "Format of reported errors and warnings, either 'human' or 'json' (default: human)")]
+    message_format: Option<String>,
 }
 
 fn main() -> Result<(), ()> {
@@ -124,72 +249,149 @@ fn main() -> Result<(), ()> {
         SubCommand::Build(opts) => cmd_compile_llvm_and_run_2(opts),
         SubCommand::Disassemble(opts) => cmd_disassemble(opts),
         SubCommand::Test(opts) => cmd_test(opts),
+        SubCommand::Init(opts) => cmd_init(opts),
+        SubCommand::New(opts) => cmd_new(opts),
+        SubCommand::Fmt(opts) => cmd_fmt(opts),
+        SubCommand::Doc(opts) => cmd_doc(opts),
+        SubCommand::Tokens(opts) => cmd_dump_tokens(opts),
+        SubCommand::Ast(opts) => cmd_dump_ast(opts),
+        SubCommand::TypedAst(opts) => cmd_dump_typed_ast(opts),
+        SubCommand::Explain(opts) => cmd_explain(opts),
+        SubCommand::Lint(opts) => cmd_lint(opts),
         SubCommand::Repl => Ok(Repl::run()),
     }
 }
 
 fn cmd_typecheck2(opts: BuildOpts) -> Result<(), ()> {
-    typecheck_project(&opts);
+    if opts.watch {
+        watch(|| project_module_paths(&opts.file_path, &opts.std_path));
+    }
+
+    typecheck_project(&get_entrypoint(&opts.file_path), &opts.std_path, get_message_format(&opts.message_format));
 
     Ok(())
 }
 
-fn typecheck_project(opts: &BuildOpts) -> (abra_core::typechecker::typechecker2::ModuleId, Project) {
+fn load_manifest(dir: &Path) -> Option<ResolvedManifest> {
+    let manifest = match ResolvedManifest::find(dir) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // The lock file is only written when it's missing or the dependencies now resolve differently than it records
+    if let Some(manifest) = &manifest {
+        if let Err(e) = manifest.write_lock_file() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    manifest
+}
+
+// The file which a command operates on, along with the manifest of the project it belongs to (if any). The manifest is loaded
+// once per command and passed down to everything which needs it.
+struct Entrypoint {
+    path: PathBuf,
+    manifest: Option<ResolvedManifest>,
+}
+
+// The directory from which to search for a manifest: that of the given file, or else the current directory
+fn manifest_search_dir(file_path: &Option<String>) -> PathBuf {
     let current_path = std::env::current_dir().unwrap();
-    let file_path = current_path.join(&opts.file_path);
+    match file_path {
+        Some(file_path) => current_path.join(file_path).parent().unwrap().to_path_buf(),
+        None => current_path,
+    }
+}
+
+// The given file, or else the manifest's default entry point
+fn entrypoint_path(file_path: &Option<String>, manifest: Option<&ResolvedManifest>) -> Option<PathBuf> {
+    match file_path {
+        Some(file_path) => Some(std::env::current_dir().unwrap().join(file_path)),
+        None => manifest.and_then(|manifest| manifest.default_entrypoint()).cloned(),
+    }
+}
+
+fn get_entrypoint(file_path: &Option<String>) -> Entrypoint {
+    let manifest = load_manifest(&manifest_search_dir(file_path));
+    match entrypoint_path(file_path, manifest.as_ref()) {
+        Some(path) => Entrypoint { path, manifest },
+        None => {
+            eprintln!("No file specified, and no entry point is declared in {}", MANIFEST_FILE_NAME);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn get_std_path(std_path: &Option<String>) -> PathBuf {
+    resolve_std_path(std_path.as_deref()).unwrap()
+}
+
+fn typecheck_project(entrypoint: &Entrypoint, std_path: &Option<String>, message_format: MessageFormat) -> (abra_core::typechecker::typechecker2::ModuleId, Project, Vec<String>) {
+    let file_path = &entrypoint.path;
 
     let root = file_path.parent().unwrap().to_path_buf();
     let module_name = file_path.file_name().unwrap().to_str().unwrap().to_string();
     let module_id = ModuleId::parse_module_path(&format!("./{}", module_name)).unwrap();
 
-    let std_path = opts.std_path.as_ref()
-        .map(|path| PathBuf::from(path))
-        .unwrap_or_else(|| {
-            get_project_root().unwrap().join("abra_core/std")
-        });
+    let std_path = get_std_path(std_path);
 
-    let mut module_loader = ModuleLoader::new(&root, &std_path);
+    let mut module_loader = match &entrypoint.manifest {
+        Some(manifest) => ModuleLoader::with_manifest(&root, &std_path, manifest),
+        None => ModuleLoader::new(&root, &std_path),
+    };
     let mut project = Project::default();
     let mut tc = Typechecker2::new(&mut module_loader, &mut project);
-    match tc.typecheck_prelude() {
-        Ok(_) => {}
-        Err(e) => {
-            match e {
-                Either::Left((e, _)) => {
-                    let module_id = module_loader.get_module_id(&ModuleId::prelude()).unwrap();
-                    let file_name = module_loader.get_path(&module_id).unwrap();
-                    let contents = std::fs::read_to_string(&file_name).unwrap();
-                    match e {
-                        Either::Left(e) => eprintln!("{}", e.get_message(&"prelude.abra".to_string(), &contents)),
-                        Either::Right(e) => eprintln!("{}", e.get_message(&"prelude.abra".to_string(), &contents)),
-                    }
-                }
-                Either::Right(e) => eprintln!("{}", e.message(&module_loader, &project)),
-            }
-            std::process::exit(1);
-        }
+    if let Err(e) = tc.typecheck_prelude() {
+        report_typecheck_error(e, &module_loader, &project, message_format);
     }
 
     let entrypoint_module_id = match tc.typecheck_module(&module_id, None) {
         Ok(mod_id) => mod_id,
-        Err(e) => {
-            match e {
-                Either::Left((e, module_id)) => {
-                    let module_id = module_loader.get_module_id(&module_id).unwrap();
-                    let file_name = module_loader.get_path(&module_id).unwrap();
-                    let contents = std::fs::read_to_string(&file_name).unwrap();
-                    match e {
-                        Either::Left(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
-                        Either::Right(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
-                    }
-                }
-                Either::Right(e) => eprintln!("{}", e.message(&module_loader, &project)),
-            }
-            std::process::exit(1);
-        }
+        Err(e) => report_typecheck_error(e, &module_loader, &project, message_format),
     };
 
-    (entrypoint_module_id, project)
+    let module_paths = project.modules.iter()
+        .map(|m| module_loader.get_path(&m.id).unwrap_or_else(|| m.name.clone()))
+        .collect();
+
+    (entrypoint_module_id, project, module_paths)
+}
+
+// The paths of the modules in the project's dependency graph, for watch mode. Unlike `typecheck_project`, errors aren't
+// reported (that's left to the watched command); the paths of the modules discovered before the error are still returned.
+fn project_module_paths(file_path: &Option<String>, std_path: &Option<String>) -> Vec<PathBuf> {
+    let manifest = ResolvedManifest::find(manifest_search_dir(file_path)).ok().flatten();
+    let Some(file_path) = entrypoint_path(file_path, manifest.as_ref()) else {
+        return vec![manifest_search_dir(file_path).join(MANIFEST_FILE_NAME)];
+    };
+    let root = file_path.parent().unwrap().to_path_buf();
+    let module_id = ModuleId::parse_module_path(&format!("./{}", file_path.file_name().unwrap().to_str().unwrap())).unwrap();
+    let std_path = get_std_path(std_path);
+
+    let mut module_loader = match &manifest {
+        Some(manifest) => ModuleLoader::with_manifest(&root, &std_path, manifest),
+        None => ModuleLoader::new(&root, &std_path),
+    };
+    let mut project = Project::default();
+    let mut tc = Typechecker2::new(&mut module_loader, &mut project);
+    if tc.typecheck_prelude().is_ok() {
+        let _ = tc.typecheck_module(&module_id, None);
+    }
+
+    let mut paths = module_loader.module_paths().into_iter().map(PathBuf::from).collect::<Vec<_>>();
+    paths.push(file_path);
+    paths.push(root.join(MANIFEST_FILE_NAME));
+    if let Some(manifest) = &manifest {
+        paths.push(manifest.manifest_path.clone());
+    }
+    paths.sort();
+    paths.dedup();
+    paths
 }
 
 fn cmd_compile_to_c_and_run2(opts: CompileOpts) -> Result<(), ()> {
@@ -212,38 +414,13 @@ fn cmd_compile_to_c_and_run2(opts: CompileOpts) -> Result<(), ()> {
     let prelude_stub_abra_path = get_project_root().unwrap().join("abra_core/std/prelude.abra");
     let mut module_loader = ModuleLoader::new(&root, &prelude_stub_abra_path);
     let mut project = Project::default();
+    let message_format = get_message_format(&opts.message_format);
     let mut tc = Typechecker2::new(&mut module_loader, &mut project);
-    match tc.typecheck_prelude() {
-        Ok(_) => {}
-        Err(e) => {
-            match e {
-                Either::Left((Either::Left(e), _)) => eprintln!("{}", e.get_message(&"prelude.abra".to_string(), &std::fs::read_to_string(&prelude_stub_abra_path).unwrap())),
-                Either::Left((Either::Right(e), _)) => eprintln!("{}", e.get_message(&"prelude.abra".to_string(), &std::fs::read_to_string(&prelude_stub_abra_path).unwrap())),
-                Either::Right(e) => eprintln!("{}", e.message(&module_loader, &project)),
-            }
-            std::process::exit(1);
-        }
+    if let Err(e) = tc.typecheck_prelude() {
+        report_typecheck_error(e, &module_loader, &project, message_format);
     }
-
-    match tc.typecheck_module(&module_id, None) {
-        Ok(_) => {}
-        Err(e) => {
-            match e {
-                Either::Left((e, module_id)) => {
-                    let module_id = module_loader.get_module_id(&module_id).unwrap();
-                    let file_name = module_loader.get_path(&module_id).unwrap();
-                    let contents = std::fs::read_to_string(&file_name).unwrap();
-
-                    match e {
-                        Either::Left(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
-                        Either::Right(e) => eprintln!("{}", e.get_message(&file_name, &contents))
-                    }
-                }
-                Either::Right(e) => eprintln!("{}", e.message(&module_loader, &project)),
-            }
-
-            std::process::exit(1);
-        }
+    if let Err(e) = tc.typecheck_module(&module_id, None) {
+        report_typecheck_error(e, &module_loader, &project, message_format);
     }
 
     let output_path = dotabra_dir.join(format!("{}.c", &module_name));
@@ -300,7 +477,7 @@ fn cmd_compile_and_run(opts: RunOpts) -> Result<(), ()> {
 
     let env = std::env::vars().collect();
     let mut vm = VM::new(VMContext::new(opts.args, env));
-    let result = compile_and_run(module_id, contents, root, &mut vm)?;
+    let result = compile_and_run(module_id, contents, root, &mut vm, MessageFormat::Human)?;
     if result != Value::Nil {
         println!("{}", to_string(&result, &mut vm));
     }
@@ -329,7 +506,7 @@ fn cmd_compile_to_c_and_run(opts: CompileOpts) -> Result<(), ()> {
     let exec_name = format!("main_{}", module_name.replace(".abra", ""));
     let mut module_reader = FsModuleReader::new(module_id.clone(), &root);
     if let Err(e) = compile_to_c(module_id, &contents, &root, &mut module_reader, &dotabra_dir, &exec_name) {
-        report_error(e, &module_reader);
+        report_error(e, &module_reader, get_message_format(&opts.message_format));
     }
 
     let mut run_cmd = Command::new(dotabra_dir.join(exec_name).to_str().unwrap());
@@ -355,15 +532,19 @@ fn cmd_compile_llvm_and_run(opts: JitOpts) -> Result<(), ()> {
 
     let mut module_reader = FsModuleReader::new(module_id.clone(), &root);
     if let Err(e) = compile_to_llvm_and_run(module_id, &contents, &mut module_reader) {
-        report_error(e, &module_reader);
+        report_error(e, &module_reader, MessageFormat::Human);
     }
 
     Ok(())
 }
 
 fn cmd_compile_llvm_and_run_2(opts: BuildOpts) -> Result<(), ()> {
-    let current_path = std::env::current_dir().unwrap();
-    let file_path = current_path.join(&opts.file_path);
+    if opts.watch {
+        watch(|| project_module_paths(&opts.file_path, &opts.std_path));
+    }
+
+    let entrypoint = get_entrypoint(&opts.file_path);
+    let file_path = &entrypoint.path;
 
     let working_dir = file_path.parent().unwrap();
     let dotabra_dir = if let Some(build_dir_name) = &opts.build_dir {
@@ -378,18 +559,36 @@ fn cmd_compile_llvm_and_run_2(opts: BuildOpts) -> Result<(), ()> {
         }
     }
 
-    let (entrypoint_module_id, project) = typecheck_project(&opts);
+    let use_gc = opts.run && !opts.no_gc.unwrap_or(false);
+    let out_file_name = opts.out_file_name.clone().unwrap_or("main".to_string());
+    let build_options = BuildCache::build_options(&format!("gc={};out={}", use_gc, &out_file_name), &get_std_path(&opts.std_path), entrypoint.manifest.as_ref());
+
+    let mut cache = BuildCache::load(&dotabra_dir);
+    let fresh_artifact = if opts.no_cache { None } else { cache.find_fresh_artifact(file_path, &build_options) };
+    let exec_out_file = match fresh_artifact {
+        Some(exec_out_file) => exec_out_file,
+        None => {
+            let (entrypoint_module_id, project, module_paths) = typecheck_project(&entrypoint, &opts.std_path, get_message_format(&opts.message_format));
+            let exec_out_file = LLVMCompiler2::compile(&entrypoint_module_id, &project, &dotabra_dir, Some(out_file_name), use_gc);
+
+            if exec_out_file.is_file() {
+                cache.record_build(file_path, &build_options, &exec_out_file, &module_paths, entrypoint.manifest.as_ref());
+                if let Err(e) = cache.save() {
+                    eprintln!("Could not write build cache to {}: {}", dotabra_dir.to_str().unwrap(), e);
+                }
+            }
+
+            exec_out_file
+        }
+    };
 
     if opts.run {
-        let use_gc = !opts.no_gc.unwrap_or(false);
-        let exit_status = LLVMCompiler2::compile_and_run(&entrypoint_module_id, &project, &dotabra_dir, opts.out_file_name, &opts.program_args, use_gc);
+        let exit_status = LLVMCompiler2::run(&exec_out_file, &opts.program_args);
         if let Some(status_code) = exit_status.code() {
             std::process::exit(status_code)
         } else {
             // Process terminated by signal
         }
-    } else {
-        LLVMCompiler2::compile(&entrypoint_module_id, &project, &dotabra_dir, opts.out_file_name, false);
     }
 
     Ok(())
@@ -409,13 +608,29 @@ fn cmd_disassemble(opts: DisassembleOpts) -> Result<(), ()> {
                 Some(out_file) => write_file(&out_file, output)?,
             }
         }
-        Err(error) => report_error(error, &module_reader),
+        Err(error) => report_error(error, &module_reader, MessageFormat::Human),
     };
 
     Ok(())
 }
 
 fn cmd_test(opts: TestOpts) -> Result<(), ()> {
+    if opts.watch {
+        watch(|| test_module_paths(&opts));
+    }
+
+    let (root_dir, mock_file) = test_runner_module(&opts);
+    let mock_module_id = ModuleId::parse_module_path("./tests").unwrap();
+    let mut vm = VM::new(VMContext::default());
+    let result = compile_and_run(mock_module_id, mock_file, root_dir, &mut vm, get_message_format(&opts.message_format))?;
+    match result {
+        Value::Int(0) => Ok(()),
+        _ => std::process::exit(1)
+    }
+}
+
+// Generates a module which imports each of the tests to be run and then runs them, along with the directory it's to be run from
+fn test_runner_module(opts: &TestOpts) -> (PathBuf, String) {
     let mut current_path = std::env::current_dir().unwrap();
     let is_dir = if let Some(file_path) = &opts.file_path {
         let p = current_path.join(file_path.replace("./", ""));
@@ -433,7 +648,7 @@ fn cmd_test(opts: TestOpts) -> Result<(), ()> {
     let current_path = current_path;
 
     let (root_dir, module_ids) = if !is_dir {
-        let file_path = opts.file_path.unwrap().replace("./", "");
+        let file_path = opts.file_path.as_ref().unwrap().replace("./", "");
         let file_path = current_path.join(&file_path);
 
         let file_name = file_path.file_name().unwrap().to_str().unwrap();
@@ -443,7 +658,7 @@ fn cmd_test(opts: TestOpts) -> Result<(), ()> {
         let root_dir = PathBuf::from(root_dir);
         (root_dir, vec![module_id])
     } else {
-        let test_pattern = opts.test_pattern.unwrap_or("**/*_test.abra".to_string());
+        let test_pattern = opts.test_pattern.clone().unwrap_or("**/*_test.abra".to_string());
         let glob_path = current_path.join(&test_pattern);
         let matches = match glob::glob(glob_path.to_str().unwrap()) {
             Ok(matches) => matches,
@@ -470,20 +685,300 @@ fn cmd_test(opts: TestOpts) -> Result<(), ()> {
     mock_file.push(format!("\nrunTests(showPassing: {})\n", opts.show_passing));
     let mock_file = mock_file.into_iter().collect::<String>();
 
+    (root_dir, mock_file)
+}
+
+// The paths of the test modules and every module they import, for watch mode. The directories containing them are included
+// too, so that adding a new test file triggers a re-run.
+fn test_module_paths(opts: &TestOpts) -> Vec<PathBuf> {
+    let (root_dir, mock_file) = test_runner_module(opts);
     let mock_module_id = ModuleId::parse_module_path("./tests").unwrap();
-    let mut vm = VM::new(VMContext::default());
-    let result = compile_and_run(mock_module_id, mock_file, root_dir, &mut vm)?;
-    match result {
-        Value::Int(0) => Ok(()),
-        _ => std::process::exit(1)
+    let mut module_reader = FsModuleReader::new(mock_module_id.clone(), &root_dir);
+    let _ = compile(mock_module_id.clone(), &mock_file, &mut module_reader);
+
+    let mut paths = module_reader.module_id_paths.iter()
+        .filter(|(module_id, _)| **module_id != mock_module_id)
+        .flat_map(|(_, path)| vec![path.with_extension("abra"), path.parent().unwrap().to_path_buf()])
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn cmd_init(opts: InitOpts) -> Result<(), ()> {
+    let current_path = std::env::current_dir().unwrap();
+    let name = opts.name.unwrap_or_else(|| current_path.file_name().unwrap().to_str().unwrap().to_string());
+    let kind = if opts.lib { ProjectKind::Library } else { ProjectKind::Executable };
+
+    scaffold_project(&current_path, &name, kind)
+}
+
+fn cmd_new(opts: NewOpts) -> Result<(), ()> {
+    let project_path = std::env::current_dir().unwrap().join(&opts.name);
+    if project_path.exists() {
+        eprintln!("Cannot create project: {} already exists", project_path.to_str().unwrap());
+        std::process::exit(1);
+    }
+    let kind = if opts.lib { ProjectKind::Library } else { ProjectKind::Executable };
+
+    scaffold_project(&project_path, &opts.name, kind)
+}
+
+fn cmd_fmt(opts: FmtOpts) -> Result<(), ()> {
+    let current_path = std::env::current_dir().unwrap();
+    let paths = if !opts.paths.is_empty() {
+        opts.paths.iter().map(|path| current_path.join(path)).collect()
+    } else {
+        match ResolvedManifest::find(&current_path) {
+            Ok(Some(manifest)) => manifest.package.source_roots,
+            Ok(None) => vec![current_path.clone()],
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let mut file_paths = vec![];
+    for path in paths {
+        if path.is_file() {
+            file_paths.push(path);
+            continue;
+        } else if !path.exists() {
+            eprintln!("Cannot find file: {}", path.to_str().unwrap());
+            std::process::exit(1);
+        }
+
+        let glob_path = path.join("**/*.abra");
+        for m in glob::glob(glob_path.to_str().unwrap()).unwrap() {
+            let file_path = m.unwrap();
+            // Skip anything generated into a project's .abra directory
+            if file_path.components().any(|c| c.as_os_str() == ".abra") { continue; }
+            file_paths.push(file_path);
+        }
+    }
+
+    let mut has_errors = false;
+    let mut num_unformatted = 0;
+    for file_path in file_paths {
+        let contents = read_file(&file_path)?;
+        let file_name = file_path.to_str().unwrap().to_string();
+        let display_name = file_path.strip_prefix(&current_path).unwrap_or(&file_path).to_str().unwrap().to_string();
+        let module_id = ModuleId::parse_module_path(&format!("./{}", file_path.file_name().unwrap().to_str().unwrap())).unwrap();
+
+        let formatted = match format_source(&module_id, &contents) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                match e {
+                    Error::LexerError(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
+                    Error::ParseError(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
+                    _ => unreachable!("Formatting should only raise a LexerError or ParseError"),
+                }
+                has_errors = true;
+                continue;
+            }
+        };
+        if formatted == contents { continue; }
+
+        num_unformatted += 1;
+        if opts.check {
+            println!("{}", display_name);
+        } else {
+            write_file(&file_name, formatted)?;
+            println!("Formatted {}", display_name);
+        }
+    }
+
+    if has_errors || (opts.check && num_unformatted > 0) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn cmd_doc(opts: DocOpts) -> Result<(), ()> {
+    let format = match &opts.format {
+        None => DocFormat::Markdown,
+        Some(name) => match DocFormat::from_name(name) {
+            Some(format) => format,
+            None => {
+                eprintln!("Unknown doc format '{}', expected 'markdown' or 'html'", name);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let entrypoint = get_entrypoint(&opts.file_path);
+    let (_, project, module_paths) = typecheck_project(&entrypoint, &opts.std_path, MessageFormat::Human);
+    let root = entrypoint.path.parent().unwrap().to_path_buf();
+    let std_path = get_std_path(&opts.std_path);
+
+    // Document the project's own modules and, if requested, the std modules it uses; modules named with a leading `_` are private
+    let mut modules = vec![];
+    for module in &project.modules {
+        let path = PathBuf::from(&module_paths[module.id.0]);
+        if path.file_name().map_or(true, |name| name.to_str().unwrap().starts_with('_')) { continue; }
+
+        let page_name = if let Ok(relative) = path.strip_prefix(&root) {
+            relative.with_extension("").to_str().unwrap().replace('/', ".")
+        } else if let Ok(relative) = path.strip_prefix(&std_path) {
+            if !opts.include_std { continue; }
+            format!("std.{}", relative.with_extension("").to_str().unwrap().replace('/', "."))
+        } else {
+            continue;
+        };
+        modules.push((module.id, page_name));
+    }
+
+    let out_dir = std::env::current_dir().unwrap().join(opts.out_dir.as_ref().map(|s| s.as_str()).unwrap_or("docs"));
+    if let Err(e) = std::fs::create_dir_all(&out_dir) {
+        eprintln!("Could not create directory {}: {}", out_dir.to_str().unwrap(), e);
+        std::process::exit(1);
+    }
+    for (file_name, contents) in generate_docs(&project, &modules, format) {
+        write_file(&out_dir.join(file_name).to_str().unwrap().to_string(), contents)?;
+    }
+    println!("Documented {} module(s) in {}", modules.len(), out_dir.to_str().unwrap());
+
+    Ok(())
+}
+
+fn cmd_dump_tokens(opts: DumpOpts) -> Result<(), ()> {
+    let (module_id, file_name, contents) = read_module(&opts.file_path)?;
+    let tokens = match lexer::tokenize(&module_id, &contents) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            eprintln!("{}", e.get_message(&file_name, &contents));
+            std::process::exit(1);
+        }
+    };
+
+    print_dump(&dump_tokens(&tokens), opts.json);
+    Ok(())
+}
+
+fn cmd_dump_ast(opts: DumpOpts) -> Result<(), ()> {
+    let (module_id, file_name, contents) = read_module(&opts.file_path)?;
+    let parse_result = match parse_source(&module_id, &contents) {
+        Ok(parse_result) => parse_result,
+        Err(e) => {
+            match e {
+                Error::LexerError(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
+                Error::ParseError(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
+                _ => unreachable!("Parsing should only raise a LexerError or ParseError"),
+            }
+            std::process::exit(1);
+        }
+    };
+
+    print_dump(&dump_ast(&parse_result), opts.json);
+    Ok(())
+}
+
+fn cmd_dump_typed_ast(opts: TypedAstOpts) -> Result<(), ()> {
+    let (entrypoint_module_id, project, _) = typecheck_project(&get_entrypoint(&opts.file_path), &opts.std_path, MessageFormat::Human);
+
+    print_dump(&dump_typed_module(&project, &project.modules[entrypoint_module_id.0]), opts.json);
+    Ok(())
+}
+
+fn cmd_explain(opts: ExplainOpts) -> Result<(), ()> {
+    match lookup_error_code(&opts.code) {
+        Some(error_code) => {
+            print!("{}", error_code.explain());
+            Ok(())
+        }
+        None => {
+            eprintln!("No such error code '{}'", opts.code);
+            std::process::exit(1);
+        }
     }
 }
 
-fn compile_and_run(module_id: ModuleId, contents: String, root_dir: PathBuf, vm: &mut VM) -> Result<Value, ()> {
+fn cmd_lint(opts: LintOpts) -> Result<(), ()> {
+    let message_format = get_message_format(&opts.message_format);
+    let entrypoint = get_entrypoint(&opts.file_path);
+    let (_, project, module_paths) = typecheck_project(&entrypoint, &opts.std_path, message_format);
+    let entrypoint_dir = entrypoint.path.parent().unwrap().to_path_buf();
+    let std_path = get_std_path(&opts.std_path);
+
+    let manifest = entrypoint.manifest;
+    let config = match manifest.as_ref().map(|manifest| LintConfig::from_settings(&manifest.lint)) {
+        None => LintConfig::default(),
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            eprintln!("Invalid [lint] section in {}: {}", MANIFEST_FILE_NAME, e);
+            std::process::exit(1);
+        }
+    };
+    // Only the project's own modules are linted, not those of the std library or of dependencies
+    let root = manifest.as_ref().map(|manifest| manifest.root().clone()).unwrap_or(entrypoint_dir);
+
+    let mut num_warnings = 0;
+    for module in &project.modules {
+        let path = PathBuf::from(&module_paths[module.id.0]);
+        if path.starts_with(&std_path) || !path.starts_with(&root) { continue; }
+        if manifest.as_ref().map_or(false, |manifest| manifest.dependencies.iter().any(|dep| path.starts_with(&dep.root))) { continue; }
+
+        let source = read_file(&path)?;
+        let file_name = path.to_str().unwrap().to_string();
+        for lint in lint_module(&project, &module.id, &source, &config) {
+            emit_diagnostic(&Diagnostic::from_lint(&lint, &file_name, &source), message_format);
+            num_warnings += 1;
+        }
+    }
+
+    if num_warnings > 0 {
+        if message_format == MessageFormat::Human {
+            eprintln!("{} warning(s) emitted", num_warnings);
+        }
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn read_module(file_path: &String) -> Result<(ModuleId, String, String), ()> {
+    let file_path = std::env::current_dir().unwrap().join(file_path);
+    let contents = read_file(&file_path)?;
+    let module_id = ModuleId::parse_module_path(&format!("./{}", file_path.file_name().unwrap().to_str().unwrap())).unwrap();
+
+    Ok((module_id, file_path.to_str().unwrap().to_string(), contents))
+}
+
+fn parse_source(module_id: &ModuleId, contents: &String) -> Result<ParseResult, Error> {
+    let tokens = lexer::tokenize(module_id, contents).map_err(Error::LexerError)?;
+    parser::parse(module_id.clone(), tokens).map_err(Error::ParseError)
+}
+
+fn print_dump(nodes: &Vec<DumpNode>, json: bool) {
+    if json {
+        println!("{}", nodes_to_json(nodes));
+    } else {
+        print!("{}", nodes_to_tree(nodes));
+    }
+}
+
+fn scaffold_project(project_path: &PathBuf, name: &String, kind: ProjectKind) -> Result<(), ()> {
+    match create_project(project_path, name, kind) {
+        Ok(created_files) => {
+            let kind_name = if kind == ProjectKind::Library { "library" } else { "executable" };
+            println!("Created {} package '{}' at {}", kind_name, name, project_path.to_str().unwrap());
+            for file_name in created_files {
+                println!("  {}", file_name);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Cannot create project: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn compile_and_run(module_id: ModuleId, contents: String, root_dir: PathBuf, vm: &mut VM, message_format: MessageFormat) -> Result<Value, ()> {
     let mut module_reader = FsModuleReader::new(module_id.clone(), &root_dir);
     let modules = match compile(module_id, &contents, &mut module_reader) {
         Ok(modules) => modules,
-        Err(error) => report_error(error, &module_reader),
+        Err(error) => report_error(error, &module_reader, message_format),
     };
 
     let mut result = Value::Nil;
@@ -513,7 +1008,45 @@ fn write_file(file_name: &String, output: String) -> Result<(), ()> {
     })
 }
 
-fn report_error<R: ModuleReader>(e: Error, module_reader: &R) -> ! {
+#[derive(Clone, Copy, PartialEq)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+fn get_message_format(message_format: &Option<String>) -> MessageFormat {
+    match message_format.as_ref().map(|f| f.as_str()) {
+        None | Some("human") => MessageFormat::Human,
+        Some("json") => MessageFormat::Json,
+        Some(name) => {
+            eprintln!("Unknown message format '{}', expected 'human' or 'json'", name);
+            std::process::exit(1);
+        }
+    }
+}
+
+// In json mode each diagnostic is printed as a single line to stdout, so it can be consumed as a stream by other tools
+fn emit_diagnostic(diagnostic: &Diagnostic, message_format: MessageFormat) {
+    match message_format {
+        MessageFormat::Human => {
+            // The stored rendering is uncolored; re-render with color when writing directly to a terminal
+            let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+            if color {
+                eprintln!("{}", diagnostic.render(true, |file| std::fs::read_to_string(file).ok()));
+            } else {
+                eprintln!("{}", diagnostic.rendered);
+            }
+            // Lints' codes are the names of their rules, which have no explanation
+            match diagnostic.code.as_ref().filter(|code| lookup_error_code(code).is_some()) {
+                Some(code) => eprintln!("\nFor more information about this error, try `abra explain {}`", code),
+                None => eprintln!(),
+            }
+        }
+        MessageFormat::Json => println!("{}", diagnostic.to_json()),
+    }
+}
+
+fn report_error<R: ModuleReader>(e: Error, module_reader: &R, message_format: MessageFormat) -> ! {
     let module_id = e.module_id();
     let file_name = PathBuf::from(module_reader.get_module_name(&module_id))
         .with_extension("abra")
@@ -522,11 +1055,11 @@ fn report_error<R: ModuleReader>(e: Error, module_reader: &R) -> ! {
     let contents = std::fs::read_to_string(&file_name).unwrap();
     let file_name = file_name.to_str().unwrap().to_string();
 
-    match e {
-        Error::LexerError(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
-        Error::ParseError(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
-        Error::TypecheckerError(e) => eprintln!("{}", e.get_message(&file_name, &contents)),
-        Error::InterpretError(_) => unreachable!("Compilation should not raise an InterpretError")
-    }
+    emit_diagnostic(&Diagnostic::from_error(&e, &file_name, &contents), message_format);
+    std::process::exit(1)
+}
+
+fn report_typecheck_error<L: LoadModule>(e: TypecheckError, module_loader: &L, project: &Project, message_format: MessageFormat) -> ! {
+    emit_diagnostic(&Diagnostic::from_typecheck_error(&e, module_loader, project), message_format);
     std::process::exit(1)
 }
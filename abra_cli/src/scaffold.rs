@@ -0,0 +1,192 @@
+use std::path::Path;
+use abra_core::manifest::MANIFEST_FILE_NAME;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectKind {
+    Executable,
+    Library,
+}
+
+const GITIGNORE_FILE_NAME: &str = ".gitignore";
+const GITIGNORE_BUILD_DIR: &str = ".abra/";
+
+const EXECUTABLE_MAIN: &str = r#"import greet from "./greeting"
+
+println(greet("world"))
+"#;
+
+const EXECUTABLE_GREETING: &str = r#"export func greet(name: String): String = "Hello, $name!"
+"#;
+
+const EXECUTABLE_GREETING_TEST: &str = r#"import * from "test"
+import greet from "./greeting"
+
+describe("greet", () => {
+  it("should greet the given name", () => {
+    expect(greet("Abra")).toEqual("Hello, Abra!")
+  })
+})
+"#;
+
+const LIBRARY_LIB: &str = r#"export func add(a: Int, b: Int): Int = a + b
+"#;
+
+const LIBRARY_LIB_TEST: &str = r#"import * from "test"
+import add from "./lib"
+
+describe("add", () => {
+  it("should add two numbers", () => {
+    expect(add(1, 2)).toEqual(3)
+  })
+})
+"#;
+
+fn manifest_contents(name: &str, kind: ProjectKind) -> String {
+    let entry_line = match kind {
+        ProjectKind::Executable => "entry = [\"src/main.abra\"]",
+        ProjectKind::Library => "lib = \"src/lib.abra\"",
+    };
+
+    format!("[package]\nname = \"{}\"\n{}\n\n[dependencies]\n", name, entry_line)
+}
+
+fn template_files(name: &str, kind: ProjectKind) -> Vec<(&'static str, String)> {
+    let mut files = vec![(MANIFEST_FILE_NAME, manifest_contents(name, kind))];
+    match kind {
+        ProjectKind::Executable => {
+            files.push(("src/main.abra", EXECUTABLE_MAIN.to_string()));
+            files.push(("src/greeting.abra", EXECUTABLE_GREETING.to_string()));
+            files.push(("src/greeting_test.abra", EXECUTABLE_GREETING_TEST.to_string()));
+        }
+        ProjectKind::Library => {
+            files.push(("src/lib.abra", LIBRARY_LIB.to_string()));
+            files.push(("src/lib_test.abra", LIBRARY_LIB_TEST.to_string()));
+        }
+    }
+
+    files
+}
+
+fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty() &&
+        name.chars().next().map_or(false, |ch| ch.is_alphabetic()) &&
+        name.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-')
+}
+
+// Lays out a new project within `dir`, which may already exist (as long as it's not already an abra project). Existing files are
+// never overwritten, except for `.gitignore`, which will have the `.abra/` build directory appended to it if it's not already listed.
+pub fn create_project(dir: &Path, name: &str, kind: ProjectKind) -> Result<Vec<String>, String> {
+    if !is_valid_package_name(name) {
+        return Err(format!("Invalid package name '{}': names must begin with a letter, and may only contain letters, numbers, '_' and '-'", name));
+    }
+    if dir.join(MANIFEST_FILE_NAME).exists() {
+        return Err(format!("{} already contains an {} file", dir.display(), MANIFEST_FILE_NAME));
+    }
+
+    let mut created = vec![];
+    for (file_name, contents) in template_files(name, kind) {
+        let path = dir.join(file_name);
+        if path.exists() { continue; }
+
+        let parent = path.parent().expect("Template files are always within the project directory");
+        std::fs::create_dir_all(parent).map_err(|e| format!("Could not create directory {}: {}", parent.display(), e))?;
+        std::fs::write(&path, contents).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        created.push(file_name.to_string());
+    }
+
+    let gitignore_path = dir.join(GITIGNORE_FILE_NAME);
+    let gitignore = std::fs::read_to_string(&gitignore_path).unwrap_or_default();
+    if !gitignore.lines().any(|line| line.trim() == GITIGNORE_BUILD_DIR || line.trim() == ".abra") {
+        let separator = if gitignore.is_empty() || gitignore.ends_with('\n') { "" } else { "\n" };
+        let contents = format!("{}{}{}\n", gitignore, separator, GITIGNORE_BUILD_DIR);
+        std::fs::write(&gitignore_path, contents).map_err(|e| format!("Could not write {}: {}", gitignore_path.display(), e))?;
+        if gitignore.is_empty() {
+            created.push(GITIGNORE_FILE_NAME.to_string());
+        }
+    }
+
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use abra_core::common::util::random_string;
+    use abra_core::manifest::ResolvedManifest;
+    use crate::scaffold::{create_project, ProjectKind};
+
+    fn make_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("abra_scaffold_{}", random_string(8)));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_create_executable_project() {
+        let dir = make_dir();
+
+        let created = create_project(&dir, "my-app", ProjectKind::Executable).unwrap();
+        assert_eq!(vec!["abra.toml", "src/main.abra", "src/greeting.abra", "src/greeting_test.abra", ".gitignore"], created);
+        assert_eq!(".abra/\n", std::fs::read_to_string(dir.join(".gitignore")).unwrap());
+
+        let manifest = ResolvedManifest::load(dir.join("abra.toml")).unwrap();
+        assert_eq!("my-app", manifest.package.name);
+        assert_eq!(Some(&dir.canonicalize().unwrap().join("src/main.abra")), manifest.default_entrypoint());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_library_project() {
+        let dir = make_dir();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.abra"), "export val x = 1\n").unwrap();
+
+        // Existing files are kept
+        let created = create_project(&dir, "mylib", ProjectKind::Library).unwrap();
+        assert_eq!(vec!["abra.toml", "src/lib_test.abra", ".gitignore"], created);
+        assert_eq!("export val x = 1\n", std::fs::read_to_string(dir.join("src/lib.abra")).unwrap());
+
+        let manifest = ResolvedManifest::load(dir.join("abra.toml")).unwrap();
+        assert_eq!(Some(dir.canonicalize().unwrap().join("src/lib.abra")), manifest.package.lib);
+        assert_eq!(None, manifest.default_entrypoint());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_project_failures() {
+        let dir = make_dir();
+
+        for name in ["", "1app", "my app", "app!"] {
+            let err = create_project(&dir, name, ProjectKind::Executable).unwrap_err();
+            assert!(err.starts_with(&format!("Invalid package name '{}'", name)));
+        }
+        assert!(std::fs::read_dir(&dir).unwrap().next().is_none());
+
+        std::fs::write(dir.join("abra.toml"), "[package]\nname = \"existing\"\n").unwrap();
+        let err = create_project(&dir, "app", ProjectKind::Executable).unwrap_err();
+        assert!(err.ends_with("already contains an abra.toml file"));
+        assert_eq!("[package]\nname = \"existing\"\n", std::fs::read_to_string(dir.join("abra.toml")).unwrap());
+        assert!(!dir.join("src").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_project_appends_to_gitignore() {
+        let dir = make_dir();
+        std::fs::write(dir.join(".gitignore"), "target/\n*.log").unwrap();
+
+        let created = create_project(&dir, "app", ProjectKind::Executable).unwrap();
+        assert!(!created.contains(&".gitignore".to_string()));
+        assert_eq!("target/\n*.log\n.abra/\n", std::fs::read_to_string(dir.join(".gitignore")).unwrap());
+
+        // The build directory isn't listed twice
+        std::fs::remove_file(dir.join("abra.toml")).unwrap();
+        create_project(&dir, "app", ProjectKind::Executable).unwrap();
+        assert_eq!("target/\n*.log\n.abra/\n", std::fs::read_to_string(dir.join(".gitignore")).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
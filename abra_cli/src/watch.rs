@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Editors often save a file as several writes (or a write followed by a rename), so wait until files have stopped changing for
+// this long before re-running
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(200);
+
+type Snapshot = HashMap<PathBuf, Option<SystemTime>>;
+
+// Runs the current command (without `--watch`) in a child process, and re-runs it whenever any of the files returned by
+// `collect_paths` changes. The paths are re-collected before every run, since edits may add or remove imports. The command
+// is run as a separate process so that its errors (which exit the process) don't end the watch, and so that it can be
+// killed if files change while it's still running (eg. a long-running program started by `build --run`).
+pub fn watch<F>(collect_paths: F) -> !
+    where F: Fn() -> Vec<PathBuf>
+{
+    let exe = std::env::current_exe().expect("Could not determine the path of the abra executable");
+    let args = child_args(std::env::args().skip(1));
+
+    loop {
+        let paths = collect_paths();
+        let mut snapshot = take_snapshot(&paths);
+
+        clear_screen();
+        let mut child = match Command::new(&exe).args(&args).spawn() {
+            Ok(child) => Some(child),
+            Err(e) => {
+                eprintln!("Could not run {}: {}", exe.to_str().unwrap(), e);
+                None
+            }
+        };
+
+        loop {
+            sleep(POLL_INTERVAL);
+            if let Some(status) = child.as_mut().and_then(|c| c.try_wait().ok().flatten()) {
+                let outcome = if status.success() { "Finished".to_string() } else { format!("Failed ({})", status) };
+                eprintln!("\n{}; watching {} file(s) for changes...", outcome, paths.len());
+                child = None;
+            }
+
+            let current = take_snapshot(&paths);
+            if current != snapshot {
+                snapshot = current;
+                wait_until_settled(&paths, &mut snapshot);
+                break;
+            }
+        }
+
+        if let Some(child) = child {
+            stop(child);
+        }
+    }
+}
+
+// Drops the `--watch` flag from the arguments, leaving any arguments after `--` (which are passed to the abra program) as-is
+fn child_args<I: Iterator<Item = String>>(args: I) -> Vec<String> {
+    let mut seen_separator = false;
+    args
+        .filter(|arg| {
+            if arg == "--" { seen_separator = true; }
+            seen_separator || arg != "--watch"
+        })
+        .collect()
+}
+
+// Files which don't exist are included (with no modification time), so that creating a missing module also triggers a re-run
+fn take_snapshot(paths: &Vec<PathBuf>) -> Snapshot {
+    paths.iter()
+        .map(|path| (path.clone(), std::fs::metadata(path).and_then(|m| m.modified()).ok()))
+        .collect()
+}
+
+fn wait_until_settled(paths: &Vec<PathBuf>, snapshot: &mut Snapshot) {
+    loop {
+        sleep(DEBOUNCE_INTERVAL);
+        let current = take_snapshot(paths);
+        if current == *snapshot { return; }
+        *snapshot = current;
+    }
+}
+
+fn stop(mut child: Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn clear_screen() {
+    // Only clear when writing to a terminal, so as not to litter redirected (eg. json) output with escape codes
+    if std::io::stdout().is_terminal() {
+        print!("\x1b[2J\x1b[H");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watch::child_args;
+
+    #[test]
+    fn test_child_args() {
+        let args = vec!["build", "--watch", "-r", "main.abra", "--", "--watch"].into_iter().map(|s| s.to_string());
+        assert_eq!(vec!["build", "-r", "main.abra", "--", "--watch"], child_args(args));
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::manifest::ResolvedManifest;
+
+const CACHE_DIR_NAME: &str = "cache";
+const CACHE_INDEX_FILE_NAME: &str = "modules.toml";
+const CACHE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// FNV-1a; unlike std's DefaultHasher, its output is guaranteed to be stable across compiler versions, so it's safe to persist.
+fn hash_str<S: AsRef<str>>(s: S) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in s.as_ref().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct BuildRecord {
+    entrypoint: String,
+    options: String,
+    artifact: String,
+    modules: Vec<String>,
+    // Files which didn't exist at the time of the build, but which would change what its imports resolve to if they were created
+    #[serde(default)]
+    shadowing_paths: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CacheIndex {
+    version: String,
+    #[serde(default)]
+    builds: Vec<BuildRecord>,
+    // The hash of each module's source, keyed by the module's path; only modules which went into a recorded build are kept
+    #[serde(default)]
+    modules: BTreeMap<String, String>,
+}
+
+// A record of the modules which went into each previous build (keyed by the entrypoint and the options it was built with), stored
+// in `.abra/cache`. Codegen produces a single LLVM module for the whole program, so a build's artifact can only be reused as a
+// whole: if any of its modules' sources have changed, or a file has since been created which would change how its imports resolve,
+// the program is rebuilt.
+pub struct BuildCache {
+    cache_dir: PathBuf,
+    index: CacheIndex,
+}
+
+impl BuildCache {
+    pub fn load(dotabra_dir: &Path) -> BuildCache {
+        let cache_dir = dotabra_dir.join(CACHE_DIR_NAME);
+        let index = std::fs::read_to_string(cache_dir.join(CACHE_INDEX_FILE_NAME)).ok()
+            .and_then(|contents| toml::from_str::<CacheIndex>(&contents).ok())
+            // A cache written by a different version of the compiler can't be trusted
+            .filter(|index| index.version == CACHE_VERSION)
+            .unwrap_or_else(|| CacheIndex { version: CACHE_VERSION.to_string(), ..CacheIndex::default() });
+
+        BuildCache { cache_dir, index }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.cache_dir)?;
+        let contents = toml::to_string(&self.index).expect("The cache index should always be serializable");
+        std::fs::write(self.cache_dir.join(CACHE_INDEX_FILE_NAME), contents)
+    }
+
+    // The options a build is recorded under. Besides the compiler's own options, these include everything which determines the
+    // files that the entrypoint's imports resolve to: the std path, and the project's manifest and resolved dependencies.
+    pub fn build_options(compiler_options: &str, std_path: &Path, manifest: Option<&ResolvedManifest>) -> String {
+        let manifest_hash = match manifest {
+            Some(manifest) => {
+                let contents = std::fs::read_to_string(&manifest.manifest_path).unwrap_or_default();
+                hash_str(format!("{}\n{}", contents, manifest.lock_file_contents()))
+            }
+            None => "none".to_string(),
+        };
+
+        format!("{};std={};manifest={}", compiler_options, std_path.to_str().unwrap(), manifest_hash)
+    }
+
+    // Computes the hash of each module's source, keyed by the module's path
+    fn hash_modules(module_paths: &[String]) -> BTreeMap<String, String> {
+        module_paths.iter()
+            .map(|path| (path.clone(), hash_str(std::fs::read_to_string(path).unwrap_or_default())))
+            .collect()
+    }
+
+    // Returns the artifact produced by a previous build of this entrypoint with these options, if none of the modules which went
+    // into it have changed since. This doesn't require typechecking, since the previous build recorded the full set of modules.
+    pub fn find_fresh_artifact(&self, entrypoint: &Path, options: &str) -> Option<PathBuf> {
+        let entrypoint = entrypoint.to_str()?;
+        let build = self.index.builds.iter().find(|b| b.entrypoint == entrypoint && b.options == options)?;
+
+        let artifact = PathBuf::from(&build.artifact);
+        if !artifact.is_file() { return None; }
+
+        if build.shadowing_paths.iter().any(|path| Path::new(path).exists()) { return None; }
+
+        let all_modules_unchanged = build.modules.iter().all(|path| {
+            let Some(cached_hash) = self.index.modules.get(path) else { return false; };
+            match std::fs::read_to_string(path) {
+                Ok(source) => hash_str(source) == *cached_hash,
+                Err(_) => false,
+            }
+        });
+        if all_modules_unchanged { Some(artifact) } else { None }
+    }
+
+    pub fn record_build(&mut self, entrypoint: &Path, options: &str, artifact: &Path, module_paths: &[String], manifest: Option<&ResolvedManifest>) {
+        let entrypoint = entrypoint.to_str().unwrap().to_string();
+        let artifact = artifact.to_str().unwrap().to_string();
+        let module_hashes = Self::hash_modules(module_paths);
+        let shadowing_paths = match manifest {
+            Some(manifest) => module_paths.iter()
+                .flat_map(|path| manifest.shadowing_paths(Path::new(path)))
+                .filter(|path| !path.exists())
+                .map(|path| path.to_str().unwrap().to_string())
+                .collect(),
+            None => vec![],
+        };
+        let record = BuildRecord {
+            entrypoint: entrypoint.clone(),
+            options: options.to_string(),
+            artifact: artifact.clone(),
+            modules: module_hashes.keys().cloned().collect(),
+            shadowing_paths,
+        };
+
+        // This build overwrites the artifact, so no other build which produced it can be reused any longer
+        self.index.builds.retain(|b| b.artifact != artifact && (b.entrypoint != entrypoint || b.options != options));
+        self.index.builds.push(record);
+        self.index.modules.extend(module_hashes);
+
+        let referenced_modules = self.index.builds.iter().flat_map(|b| &b.modules).collect::<BTreeSet<_>>();
+        self.index.modules.retain(|path, _| referenced_modules.contains(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use crate::build_cache::BuildCache;
+    use crate::common::test_utils::make_temp_dir;
+    use crate::manifest::ResolvedManifest;
+
+    #[test]
+    fn test_find_fresh_artifact() {
+        let dir = make_temp_dir("abra_build_cache", &[("main.abra", "import a from \"./a\""), ("a.abra", "export val a = 1"), ("main", "")]);
+        let entrypoint = dir.join("main.abra");
+        let module_paths = vec![entrypoint.to_str().unwrap().to_string(), dir.join("a.abra").to_str().unwrap().to_string()];
+
+        let mut cache = BuildCache::load(&dir.join(".abra"));
+        assert_eq!(None, cache.find_fresh_artifact(&entrypoint, "gc=true"));
+        cache.record_build(&entrypoint, "gc=true", &dir.join("main"), &module_paths, None);
+        cache.save().unwrap();
+
+        // The cache persists across loads, and only applies to builds with the same options
+        let cache = BuildCache::load(&dir.join(".abra"));
+        assert_eq!(Some(dir.join("main")), cache.find_fresh_artifact(&entrypoint, "gc=true"));
+        assert_eq!(None, cache.find_fresh_artifact(&entrypoint, "gc=false"));
+
+        // Changing any module which went into the build invalidates it
+        std::fs::write(dir.join("a.abra"), "export val a = 2").unwrap();
+        assert_eq!(None, cache.find_fresh_artifact(&entrypoint, "gc=true"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_build_replaces_overwritten_artifacts() {
+        let dir = make_temp_dir("abra_build_cache", &[("main.abra", "import a from \"./a\""), ("a.abra", "export val a = 1"), ("main", "")]);
+        let entrypoint = dir.join("main.abra");
+        let main_path = entrypoint.to_str().unwrap().to_string();
+        let a_path = dir.join("a.abra").to_str().unwrap().to_string();
+
+        let mut cache = BuildCache::load(&dir.join(".abra"));
+        cache.record_build(&entrypoint, "gc=true", &dir.join("main"), &[main_path.clone(), a_path.clone()], None);
+
+        // A build with other options which writes the same artifact means the earlier build can no longer be reused, and the hashes
+        // of modules which no recorded build uses anymore are dropped
+        std::fs::write(&entrypoint, "val a = 1").unwrap();
+        cache.record_build(&entrypoint, "gc=false", &dir.join("main"), &[main_path.clone()], None);
+        assert_eq!(None, cache.find_fresh_artifact(&entrypoint, "gc=true"));
+        assert_eq!(Some(dir.join("main")), cache.find_fresh_artifact(&entrypoint, "gc=false"));
+        assert_eq!(1, cache.index.builds.len());
+        assert_eq!(vec![&main_path], cache.index.modules.keys().collect::<Vec<_>>());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_new_files_which_change_module_resolution() {
+        let dir = make_temp_dir("abra_build_cache", &[
+            ("abra.toml", "[package]\nname = \"app\"\nsource-roots = [\"src\", \"vendor\"]"),
+            ("src/main.abra", "import a from \"utils\""),
+            ("vendor/utils.abra", "export val a = 1"),
+            ("main", ""),
+        ]);
+        let manifest = ResolvedManifest::load(dir.join("abra.toml")).unwrap();
+        let entrypoint = dir.join("src/main.abra");
+        let module_paths = vec![entrypoint.to_str().unwrap().to_string(), dir.join("vendor/utils.abra").to_str().unwrap().to_string()];
+
+        let mut cache = BuildCache::load(&dir.join(".abra"));
+        cache.record_build(&entrypoint, "gc=true", &dir.join("main"), &module_paths, Some(&manifest));
+        assert_eq!(Some(dir.join("main")), cache.find_fresh_artifact(&entrypoint, "gc=true"));
+
+        // A module in an earlier source root would now be imported instead, so the build is stale even though no module changed
+        std::fs::write(dir.join("src/utils.abra"), "export val a = 2").unwrap();
+        assert_eq!(None, cache.find_fresh_artifact(&entrypoint, "gc=true"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_options() {
+        let dir = make_temp_dir("abra_build_cache", &[
+            ("abra.toml", "[package]\nname = \"app\"\n\n[dependencies]\nlib = { path = \"lib\" }"),
+            ("lib/abra.toml", "[package]\nname = \"lib\""),
+        ]);
+        let options = |std_path: &str| {
+            let manifest = ResolvedManifest::load(dir.join("abra.toml")).unwrap();
+            BuildCache::build_options("gc=true", Path::new(std_path), Some(&manifest))
+        };
+
+        let initial = options("/std");
+        assert_eq!(initial, options("/std"));
+        assert_ne!(initial, options("/other/std"));
+        assert_ne!(BuildCache::build_options("gc=true", Path::new("/std"), None), initial);
+
+        // Changing how a dependency resolves changes the options, even if the root manifest is unchanged
+        std::fs::write(dir.join("lib/abra.toml"), "[package]\nname = \"lib\"\nsource-roots = [\"lib_src\"]").unwrap();
+        assert_ne!(initial, options("/std"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
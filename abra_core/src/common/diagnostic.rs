@@ -0,0 +1,257 @@
+use itertools::Either;
+use serde_json::{json, Value};
+use crate::common::diagnostic_renderer::render_diagnostic;
+use crate::common::display_error::{DisplayError, ErrorDescription};
+use crate::lexer::lexer_error::{LexerError, LexerErrorKind};
+use crate::lexer::tokens::{Position, Range};
+use crate::linter::Lint;
+use crate::parser::parse_error::ParseError;
+use crate::typechecker::typechecker2::{LoadModule, Project, Span, TypecheckError, TypeError};
+use crate::typechecker::typechecker_error::TypecheckerError;
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelatedSpan {
+    pub file: String,
+    pub range: Range,
+    pub message: String,
+}
+
+// A structured form of a lexer, parse or type error, for consumption by tools (editor plugins, CI annotators, etc). The
+// `message` is the error's headline; any further explanation is split into `notes` and `help`. The human-readable output,
+// with source excerpts for the primary and related spans, is kept in `rendered`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub range: Range,
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    pub related: Vec<RelatedSpan>,
+    pub rendered: String,
+}
+
+impl Diagnostic {
+    pub fn from_lexer_error(error: &LexerError, file_name: &String, source: &String) -> Diagnostic {
+        let related = match &error.kind {
+            LexerErrorKind::UnterminatedString(start, _) => {
+                vec![RelatedSpan { file: file_name.clone(), range: Range::with_length(start, 0), message: "string begins here".to_string() }]
+            }
+            _ => vec![],
+        };
+
+        Diagnostic::new(file_name, error.get_range(), error.code(), related, error.describe(), |_| Some(source.clone()))
+    }
+
+    pub fn from_parse_error(error: &ParseError, file_name: &String, source: &String) -> Diagnostic {
+        Diagnostic::new(file_name, error.get_range(), error.code(), vec![], error.describe(), |_| Some(source.clone()))
+    }
+
+    pub fn from_typechecker_error(error: &TypecheckerError, file_name: &String, source: &String) -> Diagnostic {
+        Diagnostic::new(file_name, error.get_range(), error.code(), vec![], error.describe(), |_| Some(source.clone()))
+    }
+
+    pub fn from_error(error: &Error, file_name: &String, source: &String) -> Diagnostic {
+        match error {
+            Error::LexerError(e) => Diagnostic::from_lexer_error(e, file_name, source),
+            Error::ParseError(e) => Diagnostic::from_parse_error(e, file_name, source),
+            Error::TypecheckerError(e) => Diagnostic::from_typechecker_error(e, file_name, source),
+            Error::InterpretError(_) => unreachable!("Compilation should not raise an InterpretError"),
+        }
+    }
+
+    pub fn from_typecheck_error<L: LoadModule>(error: &TypecheckError, loader: &L, project: &Project) -> Diagnostic {
+        match error {
+            Either::Left((e, m_id)) => {
+                let module_id = loader.get_module_id(m_id).expect("A module which failed to parse should have been registered");
+                let file_name = loader.get_path(module_id).unwrap();
+                let source = loader.load_file(&file_name).unwrap_or_default();
+                match e {
+                    Either::Left(e) => Diagnostic::from_lexer_error(e, &file_name, &source),
+                    Either::Right(e) => Diagnostic::from_parse_error(e, &file_name, &source),
+                }
+            }
+            Either::Right(e) => Diagnostic::from_type_error(e, loader, project),
+        }
+    }
+
+    pub fn from_type_error<L: LoadModule>(error: &TypeError, loader: &L, project: &Project) -> Diagnostic {
+        let file_of = |span: &Span| loader.get_path(span.module_id()).unwrap_or_default();
+
+        let span = error.span();
+        let related = error.related_spans().into_iter()
+            .map(|(span, message)| RelatedSpan { file: file_of(span), range: span.range.clone(), message: message.to_string() })
+            .collect();
+
+        Diagnostic::new(&file_of(span), span.range.clone(), error.code(), related, error.describe(project), |file| loader.load_file(file))
+    }
+
+    pub fn from_lint(lint: &Lint, file_name: &String, source: &String) -> Diagnostic {
+        let related = lint.related.iter()
+            .map(|(range, message)| RelatedSpan { file: file_name.clone(), range: range.clone(), message: message.clone() })
+            .collect();
+
+        let mut diagnostic = Diagnostic {
+            file: file_name.clone(),
+            range: lint.range.clone(),
+            severity: Severity::Warning,
+            code: Some(lint.rule.name().to_string()),
+            message: lint.message.clone(),
+            notes: vec![],
+            help: lint.help.clone(),
+            related,
+            rendered: String::new(),
+        };
+        diagnostic.rendered = render_diagnostic(&diagnostic, false, |_| Some(source.clone()));
+        diagnostic
+    }
+
+    // Only the text of the error's notes is kept; the locations which an error refers to (besides its own) are its `related` spans
+    fn new<L, F>(file_name: &String, range: Range, code: &'static str, related: Vec<RelatedSpan>, description: ErrorDescription<L>, load_source: F) -> Diagnostic
+        where F: Fn(&String) -> Option<String>
+    {
+        let mut diagnostic = Diagnostic {
+            file: file_name.clone(),
+            range,
+            severity: Severity::Error,
+            code: Some(code.to_string()),
+            message: description.message,
+            notes: description.notes.into_iter().map(|(note, _)| note).collect(),
+            help: description.help,
+            related,
+            rendered: String::new(),
+        };
+        diagnostic.rendered = render_diagnostic(&diagnostic, false, load_source);
+        diagnostic
+    }
+
+    // Renders the diagnostic for display in a terminal, highlighting it with ANSI colors if `color` is set
+    pub fn render<F>(&self, color: bool, load_source: F) -> String
+        where F: Fn(&String) -> Option<String>
+    {
+        render_diagnostic(self, color, load_source)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let related = self.related.iter()
+            .map(|r| json!({ "file": r.file, "range": range_to_json(&r.range), "message": r.message }))
+            .collect::<Vec<_>>();
+
+        json!({
+            "file": self.file,
+            "range": range_to_json(&self.range),
+            "severity": self.severity.name(),
+            "code": self.code,
+            "message": self.message,
+            "notes": self.notes,
+            "help": self.help,
+            "related": related,
+            "rendered": self.rendered,
+        })
+    }
+}
+
+fn range_to_json(range: &Range) -> Value {
+    let position = |p: &Position| json!({ "line": p.line, "col": p.col });
+    json!({ "start": position(&range.start), "end": position(&range.end) })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::common::diagnostic::Diagnostic;
+    use crate::lexer::lexer::tokenize;
+    use crate::parser::ast::ModuleId;
+    use crate::parser::parser::parse;
+    use crate::typechecker::test_helpers::typecheck_main;
+
+    #[test]
+    fn test_diagnostic_from_lexer_error() {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let source = "val s = \"abc\nval t = 1".to_string();
+        let error = tokenize(&module_id, &source).unwrap_err();
+        let diagnostic = Diagnostic::from_lexer_error(&error, &"test.abra".to_string(), &source);
+
+        assert_eq!("E0002", diagnostic.code.as_ref().unwrap());
+        assert_eq!("Unterminated string", diagnostic.message);
+        assert_eq!(vec!["String begins at (1:9)", "String is terminated at (1:13)"], diagnostic.notes);
+        assert_eq!(None, diagnostic.help);
+        assert_eq!(vec!["string begins here"], diagnostic.related.iter().map(|r| r.message.as_str()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_diagnostic_from_parse_error() {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let source = "val x = 1 +".to_string();
+        let error = parse(module_id.clone(), tokenize(&module_id, &source).unwrap()).unwrap_err();
+        let diagnostic = Diagnostic::from_parse_error(&error, &"test.abra".to_string(), &source);
+
+        let expected = json!({
+            "file": "test.abra",
+            "range": { "start": { "line": 1, "col": 11 }, "end": { "line": 1, "col": 12 } },
+            "severity": "error",
+            "code": "E0100",
+            "message": "Unexpected end of file",
+            "notes": [],
+            "help": null,
+            "related": [],
+            "rendered": diagnostic.rendered,
+        });
+        assert_eq!(expected, diagnostic.to_json());
+    }
+
+    #[test]
+    fn test_diagnostic_from_type_error() {
+        let (loader, project, result) = typecheck_main(&[("main.abra", "val x = 1\nval x = 2")]);
+        let error = result.unwrap_err().right().unwrap();
+        let diagnostic = Diagnostic::from_type_error(&error, &loader, &project);
+
+        let expected = json!({
+            "file": "/project/main.abra",
+            "range": { "start": { "line": 2, "col": 5 }, "end": { "line": 2, "col": 5 } },
+            "severity": "error",
+            "code": "E0207",
+            "message": "Duplicate name 'x'",
+            "notes": ["This name is already declared at (1:5)"],
+            "help": null,
+            "related": [
+                {
+                    "file": "/project/main.abra",
+                    "range": { "start": { "line": 1, "col": 5 }, "end": { "line": 1, "col": 5 } },
+                    "message": "originally declared here",
+                },
+            ],
+            "rendered": diagnostic.rendered,
+        });
+        assert_eq!(expected, diagnostic.to_json());
+
+        let expected = "\
+error[E0207]: Duplicate name 'x'
+ --> /project/main.abra:2:5
+  |
+1 | val x = 1
+  |     - originally declared here
+2 | val x = 2
+  |     ^
+  |
+  = note: This name is already declared at (1:5)";
+        assert_eq!(expected, diagnostic.rendered);
+    }
+}
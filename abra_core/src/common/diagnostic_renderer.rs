@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use crate::common::diagnostic::{Diagnostic, Severity};
+use crate::lexer::tokens::Range;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+
+struct Label<'a> {
+    range: &'a Range,
+    message: &'a str,
+    is_primary: bool,
+}
+
+// Renders a diagnostic in the style of rustc: a header line, followed by an excerpt of each file which the diagnostic refers to with
+// every labeled span underlined (the primary span with `^`, and related spans with `-`), and finally any notes and help text. Lines
+// which contain no labels are elided. `load_source` is called with the name of each file to be excerpted; files which can't be
+// loaded are still mentioned, but without an excerpt.
+pub fn render_diagnostic<F>(diagnostic: &Diagnostic, color: bool, load_source: F) -> String
+    where F: Fn(&String) -> Option<String>
+{
+    let paint = |style: &str, text: &str| if color { format!("{}{}{}", style, text, RESET) } else { text.to_string() };
+    let severity_style = match diagnostic.severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+    };
+    let label_style = |is_primary: bool| if is_primary { severity_style } else { BLUE };
+
+    // Group labels by file, keeping the primary file first and the rest in order of first appearance
+    let mut files: Vec<(&String, Vec<Label>)> = vec![(&diagnostic.file, vec![Label { range: &diagnostic.range, message: "", is_primary: true }])];
+    for related in &diagnostic.related {
+        let label = Label { range: &related.range, message: &related.message, is_primary: false };
+        match files.iter_mut().find(|(file, _)| *file == &related.file) {
+            Some((_, labels)) => labels.push(label),
+            None => files.push((&related.file, vec![label])),
+        }
+    }
+
+    let max_line = files.iter()
+        .flat_map(|(_, labels)| labels.iter().map(|l| l.range.end.line.max(l.range.start.line)))
+        .max()
+        .unwrap_or(1);
+    let gutter_width = max_line.to_string().len();
+    let gutter = |line_num: Option<usize>| {
+        let num = line_num.map(|n| n.to_string()).unwrap_or_default();
+        paint(BLUE, &format!("{:>width$} |", num, width = gutter_width))
+    };
+
+    let header = match &diagnostic.code {
+        Some(code) => format!("{}[{}]", diagnostic.severity.name(), code),
+        None => diagnostic.severity.name().to_string(),
+    };
+    let mut out = vec![format!("{}{}", paint(severity_style, &header), paint(BOLD, &format!(": {}", diagnostic.message)))];
+
+    for (idx, (file, labels)) in files.iter().enumerate() {
+        // The primary file is introduced with the location of the primary span; other files with the location of their first span
+        let (arrow, location) = if idx == 0 {
+            ("-->", &diagnostic.range)
+        } else {
+            (":::", labels.iter().map(|l| l.range).min_by_key(|r| (r.start.line, r.start.col)).unwrap())
+        };
+        out.push(format!("{}{} {}:{}:{}", " ".repeat(gutter_width), paint(BLUE, arrow), file, location.start.line, location.start.col));
+
+        // Spans at a bogus (0:0) position have no source to point at, so they're left out of the excerpt
+        let labels = labels.iter().filter(|l| l.range.start.line > 0 && l.range.start.col > 0).collect::<Vec<_>>();
+        if labels.is_empty() { continue; }
+        let Some(source) = load_source(file) else { continue; };
+        let source_lines = source.lines().collect::<Vec<_>>();
+
+        // For each line, the underlines beneath it along with the label (if any) to show at the end of the underline
+        let mut underlines: BTreeMap<usize, Vec<(usize, usize, &Label)>> = BTreeMap::new();
+        for label in labels {
+            let start_line = label.range.start.line;
+            let end_line = label.range.end.line.max(start_line);
+            for line_num in start_line..=end_line {
+                let line_len = source_lines.get(line_num - 1).map(|l| l.chars().count()).unwrap_or(0);
+                let start_col = if line_num == start_line { label.range.start.col } else { 1 };
+                let end_col = if line_num == end_line { label.range.end.col.max(start_col) } else { line_len.max(start_col) };
+                underlines.entry(line_num).or_default().push((start_col, end_col, label));
+            }
+        }
+
+        out.push(gutter(None));
+        let mut prev_line_num = None;
+        for (line_num, line_underlines) in &underlines {
+            if let Some(prev) = prev_line_num {
+                if line_num - prev > 1 { out.push(paint(BLUE, "...")); }
+            }
+            prev_line_num = Some(*line_num);
+
+            let line = source_lines.get(line_num - 1).copied().unwrap_or("");
+            out.push(format!("{} {}", gutter(Some(*line_num)), line));
+
+            for (start_col, end_col, label) in line_underlines {
+                let marker = if label.is_primary { "^" } else { "-" };
+                let underline = marker.repeat(end_col - start_col + 1);
+                // Only show the label beneath the last line of a multi-line span
+                let message = if *line_num == label.range.end.line.max(label.range.start.line) { label.message } else { "" };
+                let text = if message.is_empty() { underline } else { format!("{} {}", underline, message) };
+                out.push(format!("{} {}{}", gutter(None), " ".repeat(start_col - 1), paint(label_style(label.is_primary), &text)));
+            }
+        }
+    }
+
+    if !diagnostic.notes.is_empty() || diagnostic.help.is_some() {
+        out.push(gutter(None));
+    }
+    for note in &diagnostic.notes {
+        out.push(format!("{} {} {}", " ".repeat(gutter_width), paint(BOLD, "= note:"), note));
+    }
+    if let Some(help) = &diagnostic.help {
+        out.push(format!("{} {} {}", " ".repeat(gutter_width), paint(CYAN, "= help:"), help));
+    }
+
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::diagnostic::{Diagnostic, RelatedSpan, Severity};
+    use crate::common::diagnostic_renderer::render_diagnostic;
+    use crate::lexer::tokens::{Position, Range};
+
+    fn range(start: (usize, usize), end: (usize, usize)) -> Range {
+        Range { start: Position::new(start.0, start.1), end: Position::new(end.0, end.1) }
+    }
+
+    fn diagnostic(range: Range, related: Vec<RelatedSpan>) -> Diagnostic {
+        Diagnostic {
+            file: "main.abra".to_string(),
+            range,
+            severity: Severity::Error,
+            code: Some("E0207".to_string()),
+            message: "Duplicate name 'x'".to_string(),
+            notes: vec!["This name is already declared at (1:5)".to_string()],
+            help: Some("did you mean 'y'?".to_string()),
+            related,
+            rendered: "".to_string(),
+        }
+    }
+
+    fn load_source(file: &String) -> Option<String> {
+        match file.as_str() {
+            "main.abra" => Some("val x = 1\nval y = 2\nval z = 3\nval x = 4".to_string()),
+            "other.abra" => Some("func f(\n  a: Int\n) = a".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_render_related_spans_in_same_file() {
+        let related = vec![RelatedSpan { file: "main.abra".to_string(), range: range((1, 5), (1, 5)), message: "originally declared here".to_string() }];
+        let rendered = render_diagnostic(&diagnostic(range((4, 5), (4, 5)), related), false, load_source);
+        let expected = "\
+error[E0207]: Duplicate name 'x'
+ --> main.abra:4:5
+  |
+1 | val x = 1
+  |     - originally declared here
+...
+4 | val x = 4
+  |     ^
+  |
+  = note: This name is already declared at (1:5)
+  = help: did you mean 'y'?";
+        assert_eq!(expected, rendered);
+    }
+
+    #[test]
+    fn test_render_related_spans_across_files() {
+        let related = vec![
+            RelatedSpan { file: "other.abra".to_string(), range: range((1, 6), (3, 1)), message: "declared here".to_string() },
+            RelatedSpan { file: "missing.abra".to_string(), range: range((2, 1), (2, 3)), message: "and here".to_string() },
+        ];
+        let mut diagnostic = diagnostic(range((2, 1), (2, 3)), related);
+        diagnostic.notes = vec![];
+        diagnostic.help = None;
+        let rendered = render_diagnostic(&diagnostic, false, load_source);
+        let expected = "\
+error[E0207]: Duplicate name 'x'
+ --> main.abra:2:1
+  |
+2 | val y = 2
+  | ^^^
+ ::: other.abra:1:6
+  |
+1 | func f(
+  |      --
+2 |   a: Int
+  | --------
+3 | ) = a
+  | - declared here
+ ::: missing.abra:2:1";
+        assert_eq!(expected, rendered);
+    }
+
+    #[test]
+    fn test_render_with_color() {
+        let rendered = render_diagnostic(&diagnostic(range((4, 5), (4, 5)), vec![]), true, load_source);
+        assert!(rendered.starts_with("\x1b[1;31merror[E0207]\x1b[0m\x1b[1m: Duplicate name 'x'\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_bogus_spans() {
+        let related = vec![RelatedSpan { file: "main.abra".to_string(), range: range((0, 0), (0, 0)), message: "declared here".to_string() }];
+        let rendered = render_diagnostic(&diagnostic(range((0, 0), (0, 0)), related), false, load_source);
+        let expected = "\
+error[E0207]: Duplicate name 'x'
+ --> main.abra:0:0
+  |
+  = note: This name is already declared at (1:5)
+  = help: did you mean 'y'?";
+        assert_eq!(expected, rendered);
+
+        let related = vec![RelatedSpan { file: "main.abra".to_string(), range: range((0, 0), (0, 0)), message: "declared here".to_string() }];
+        let mut diagnostic = diagnostic(range((4, 5), (4, 5)), related);
+        diagnostic.notes = vec![];
+        diagnostic.help = None;
+        let rendered = render_diagnostic(&diagnostic, false, load_source);
+        let expected = "\
+error[E0207]: Duplicate name 'x'
+ --> main.abra:4:5
+  |
+4 | val x = 4
+  |     ^";
+        assert_eq!(expected, rendered);
+    }
+}
@@ -2,6 +2,36 @@ use crate::lexer::tokens::{Token, Range, Position};
 
 pub const IND_AMT: usize = 2;
 
+// The parts of an error's explanation, independent of how it's displayed: a headline, any further notes (each of which may refer to
+// another location, which the human-readable message underlines beneath the note), and help text. Both the human-readable message
+// and the structured `Diagnostic` are built from this.
+pub struct ErrorDescription<L> {
+    pub message: String,
+    pub notes: Vec<(String, Option<L>)>,
+    pub help: Option<String>,
+}
+
+impl<L> ErrorDescription<L> {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        ErrorDescription { message: message.into(), notes: vec![], help: None }
+    }
+
+    pub fn note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push((note.into(), None));
+        self
+    }
+
+    pub fn note_at<S: Into<String>>(mut self, note: S, location: L) -> Self {
+        self.notes.push((note.into(), Some(location)));
+        self
+    }
+
+    pub fn help<S: Into<String>>(mut self, help: S) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
 pub trait DisplayError {
     fn get_underline(left_padding: usize, length: usize) -> String {
         format!("{}{}", " ".repeat(left_padding), "^".repeat(length))
@@ -30,5 +60,28 @@ pub trait DisplayError {
         self.message_for_error(file_name, &lines)
     }
 
+    // The usual layout of a human-readable error: its location and headline, the underlined source, and then each note (followed by
+    // the source it refers to, if any) and the help text
+    fn render_description(file_name: &str, lines: &Vec<&str>, range: &Range, description: &ErrorDescription<Range>) -> String {
+        let mut out = vec![
+            format!("Error at {}:{}:{}", file_name, range.start.line, range.start.col),
+            description.message.clone(),
+            Self::get_underlined_line_no_token(lines, &range.start, range),
+        ];
+        for (note, location) in &description.notes {
+            out.push(note.clone());
+            if let Some(location) = location {
+                out.push(Self::get_underlined_line_no_token(lines, &location.start, location));
+            }
+        }
+        if let Some(help) = &description.help {
+            out.push(format!("help: {}", help));
+        }
+
+        out.join("\n")
+    }
+
+    fn describe(&self) -> ErrorDescription<Range>;
+
     fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String;
 }
\ No newline at end of file
@@ -0,0 +1,572 @@
+// Stable identifiers for every kind of lexer, parse and type error, along with a longer explanation of each (see
+// `abra explain <code>`). Codes are grouped by the stage which reports them:
+//   E00xx: lexer errors
+//   E01xx: parse errors
+//   E02xx: type errors
+//   E03xx: type errors which are only reported by the legacy typechecker
+// Once assigned, a code must never be reused for a different error, even if the error it refers to is removed.
+
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub example: &'static str,
+    pub fixed: &'static str,
+}
+
+impl ErrorCode {
+    pub fn explain(&self) -> String {
+        let indent = |src: &str| src.lines().map(|line| if line.is_empty() { "".to_string() } else { format!("    {}", line) }).collect::<Vec<_>>().join("\n");
+
+        format!(
+            "{}: {}\n\n{}\n\nErroneous code example:\n\n{}\n\nFixed example:\n\n{}\n",
+            self.code, self.title, self.description, indent(self.example), indent(self.fixed)
+        )
+    }
+}
+
+pub fn lookup_error_code<S: AsRef<str>>(code: S) -> Option<&'static ErrorCode> {
+    let code = code.as_ref().to_uppercase();
+    ERROR_CODES.iter().find(|c| c.code == code)
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        title: "Unexpected character",
+        description: "A character was found which cannot begin (or continue) any token. This often happens within number literals: hexadecimal literals may only contain the digits 0-9 and a-f, binary literals may only contain 0 and 1, and decimal literals may not have a leading zero.",
+        example: "val mask = 0xg1",
+        fixed: "val mask = 0xf1",
+    },
+    ErrorCode {
+        code: "E0002",
+        title: "Unterminated string",
+        description: "A string literal was opened with a `\"` but the line (or file) ended before the closing `\"` was found. String literals cannot span multiple lines; use `\\n` to include a newline.",
+        example: "val greeting = \"hello",
+        fixed: "val greeting = \"hello\"",
+    },
+    ErrorCode {
+        code: "E0003",
+        title: "Unexpected end of file while reading a token",
+        description: "The file ended in the middle of a token, for example directly after the `0x` prefix of a hexadecimal literal or within an interpolated `${...}` expression.",
+        example: "val mask = 0x",
+        fixed: "val mask = 0x0",
+    },
+    ErrorCode {
+        code: "E0004",
+        title: "Unsupported escape sequence",
+        description: "A string literal contains a `\\` followed by a character which does not form a valid escape sequence. The supported escape sequences are `\\n`, `\\r`, `\\t`, `\\\\`, `\\'`, `\\\"`, `\\$` and unicode escapes of the form `\\uXXXX`.",
+        example: "val path = \"C:\\dir\"",
+        fixed: "val path = \"C:\\\\dir\"",
+    },
+    ErrorCode {
+        code: "E0100",
+        title: "Unexpected end of file",
+        description: "The file ended while an expression or statement was still incomplete, for example after a binary operator or before a block's closing `}`.",
+        example: "val sum = 1 +",
+        fixed: "val sum = 1 + 2",
+    },
+    ErrorCode {
+        code: "E0101",
+        title: "Unexpected token",
+        description: "A token appeared in a position where it cannot be used, for example an infix operator at the start of an expression.",
+        example: "val product = * 2",
+        fixed: "val product = 3 * 2",
+    },
+    ErrorCode {
+        code: "E0102",
+        title: "Expected token",
+        description: "A specific token was required but a different one was found, for example a missing `:` between a field's name and its type.",
+        example: "type Point {\n  x: Int\n  y Int\n}",
+        fixed: "type Point {\n  x: Int\n  y: Int\n}",
+    },
+    ErrorCode {
+        code: "E0103",
+        title: "Expected one of several tokens",
+        description: "One of a set of tokens was required but a different one was found, for example an import statement which is missing its `from` keyword.",
+        example: "import helper \"./utils\"",
+        fixed: "// utils.abra exports helper\nimport helper from \"./utils\"",
+    },
+    ErrorCode {
+        code: "E0104",
+        title: "Invalid import path",
+        description: "The path of an import statement is malformed. Paths are made up of `/`-separated segments; no segment may be empty, and the last segment must name a module.",
+        example: "import \"./utils/\" as utils",
+        fixed: "// utils.abra exists alongside this file\nimport \"./utils\" as utils",
+    },
+    ErrorCode {
+        code: "E0200",
+        title: "Unimplemented feature",
+        description: "The code uses a language feature which is not yet supported by the compiler. Rewrite the code so as to not depend on that feature.",
+        example: "val x = 1\nmatch x {\n  Int(n) => println(n)\n  _ => println(\"other\")\n}",
+        fixed: "val x = 1\nmatch x {\n  Int => println(x)\n  _ => println(\"other\")\n}",
+    },
+    ErrorCode {
+        code: "E0201",
+        title: "Type mismatch",
+        description: "An expression's type is not compatible with the type required in its position, such as a variable's type annotation or a function's parameter type.",
+        example: "val count: Int = \"three\"",
+        fixed: "val count: Int = 3",
+    },
+    ErrorCode {
+        code: "E0202",
+        title: "Branch type mismatch",
+        description: "When an if- or match-expression is used as a value, every branch must produce a value of the same type. The type of the first branch determines the type that the remaining branches are expected to have.",
+        example: "val x = if true { 1 } else { \"one\" }",
+        fixed: "val x = if true { 1 } else { 2 }",
+    },
+    ErrorCode {
+        code: "E0203",
+        title: "Illegal operator",
+        description: "A binary operator was used with operands whose types it does not support.",
+        example: "val x = true - 1",
+        fixed: "val x = 2 - 1",
+    },
+    ErrorCode {
+        code: "E0204",
+        title: "Unknown type",
+        description: "A type annotation refers to a type which has not been declared or imported.",
+        example: "val x: Integer = 1",
+        fixed: "val x: Int = 1",
+    },
+    ErrorCode {
+        code: "E0205",
+        title: "Unknown identifier",
+        description: "A name is used which does not refer to any variable, function or type which is in scope. Check the spelling, and whether the name needs to be declared or imported first.",
+        example: "println(count)",
+        fixed: "val count = 1\nprintln(count)",
+    },
+    ErrorCode {
+        code: "E0206",
+        title: "Missing initializer",
+        description: "A variable was declared without an initial value. Immutable (`val`) variables must always be initialized, since they can never be assigned to later.",
+        example: "val x: Int",
+        fixed: "val x: Int = 1",
+    },
+    ErrorCode {
+        code: "E0207",
+        title: "Duplicate name",
+        description: "A name is declared more than once within the same scope. This applies to variables, functions, types, enums, fields, methods, enum variants and type arguments.",
+        example: "val x = 1\nval x = 2",
+        fixed: "val x = 1\nval y = 2",
+    },
+    ErrorCode {
+        code: "E0208",
+        title: "Forbidden type",
+        description: "A value of a type which cannot be stored was used as a variable or parameter value. For example, a function which returns nothing produces no value which can be assigned.",
+        example: "func greet() { println(\"hello\") }\nval x = greet()",
+        fixed: "func greet(): String = \"hello\"\nval x = greet()",
+    },
+    ErrorCode {
+        code: "E0209",
+        title: "Destructuring mismatch",
+        description: "A destructuring pattern does not match the shape of the value being destructured, for example a tuple pattern with a different number of elements than the tuple, or an array pattern used on a value which is not an array.",
+        example: "val (a, b, c) = (1, 2)",
+        fixed: "val (a, b) = (1, 2)",
+    },
+    ErrorCode {
+        code: "E0210",
+        title: "Duplicate splat",
+        description: "An array destructuring pattern may contain at most one splat (`*`) binding, since otherwise it would be ambiguous which elements each splat receives.",
+        example: "val [first, *middle, *rest] = [1, 2, 3]",
+        fixed: "val [first, *rest] = [1, 2, 3]",
+    },
+    ErrorCode {
+        code: "E0211",
+        title: "Duplicate parameter",
+        description: "Two parameters of the same function have the same name.",
+        example: "func add(a: Int, a: Int): Int = a + a",
+        fixed: "func add(a: Int, b: Int): Int = a + b",
+    },
+    ErrorCode {
+        code: "E0212",
+        title: "Return type mismatch",
+        description: "The value returned from a function does not match the function's declared return type.",
+        example: "func one(): Int {\n  return \"one\"\n}",
+        fixed: "func one(): Int {\n  return 1\n}",
+    },
+    ErrorCode {
+        code: "E0213",
+        title: "Illegal invocation",
+        description: "Something which is not a function (or a type which can be instantiated) was called as if it were one.",
+        example: "val x = 1\nx()",
+        fixed: "func x(): Int = 1\nx()",
+    },
+    ErrorCode {
+        code: "E0214",
+        title: "Illegal enum variant construction",
+        description: "An enum variant which has no fields was called as if it were a function. Variants without fields are values on their own, and should be referenced without parentheses.",
+        example: "enum Color { Red, Green }\nval c = Color.Red()",
+        fixed: "enum Color { Red, Green }\nval c = Color.Red",
+    },
+    ErrorCode {
+        code: "E0215",
+        title: "Unexpected argument label",
+        description: "A function was called (or a type instantiated) with a labeled argument whose label doesn't match the name of any parameter (or field).",
+        example: "func add(a: Int, b: Int): Int = a + b\nadd(a: 1, c: 2)",
+        fixed: "func add(a: Int, b: Int): Int = a + b\nadd(a: 1, b: 2)",
+    },
+    ErrorCode {
+        code: "E0216",
+        title: "Mixed argument types",
+        description: "Within a single call, arguments must either all be labeled or all be positional.",
+        example: "func add(a: Int, b: Int): Int = a + b\nadd(a: 1, 2)",
+        fixed: "func add(a: Int, b: Int): Int = a + b\nadd(a: 1, b: 2)",
+    },
+    ErrorCode {
+        code: "E0217",
+        title: "Duplicate argument label",
+        description: "The same argument label was provided more than once within a single call.",
+        example: "func add(a: Int, b: Int): Int = a + b\nadd(a: 1, a: 2)",
+        fixed: "func add(a: Int, b: Int): Int = a + b\nadd(a: 1, b: 2)",
+    },
+    ErrorCode {
+        code: "E0218",
+        title: "Invalid arity",
+        description: "A function was called with too few or too many arguments. Every parameter without a default value must be given an argument.",
+        example: "func add(a: Int, b: Int): Int = a + b\nadd(1)",
+        fixed: "func add(a: Int, b: Int): Int = a + b\nadd(1, 2)",
+    },
+    ErrorCode {
+        code: "E0219",
+        title: "Invalid self parameter",
+        description: "A `self` parameter may only be declared by methods within a type or enum declaration.",
+        example: "func describe(self): String = \"thing\"",
+        fixed: "type Thing {\n  func describe(self): String = \"thing\"\n}",
+    },
+    ErrorCode {
+        code: "E0220",
+        title: "Invalid position for self parameter",
+        description: "A method's `self` parameter must be the first in its parameter list.",
+        example: "type Counter {\n  count: Int\n\n  func add(n: Int, self): Int = self.count + n\n}",
+        fixed: "type Counter {\n  count: Int\n\n  func add(self, n: Int): Int = self.count + n\n}",
+    },
+    ErrorCode {
+        code: "E0221",
+        title: "Invalid position for required parameter",
+        description: "Parameters which have default values must come after all required parameters (and a variadic parameter must come after all of them).",
+        example: "func f(a = 1, b: Int): Int = a + b",
+        fixed: "func f(b: Int, a = 1): Int = a + b",
+    },
+    ErrorCode {
+        code: "E0222",
+        title: "Invalid position for variadic parameter",
+        description: "A variadic (`*`) parameter collects all remaining arguments, so it must be the last parameter of a function.",
+        example: "func f(*xs: Int[], y: Int) {}",
+        fixed: "func f(y: Int, *xs: Int[]) {}",
+    },
+    ErrorCode {
+        code: "E0223",
+        title: "Invalid type for variadic parameter",
+        description: "A variadic (`*`) parameter receives its arguments as an array, so its type annotation must be an array type.",
+        example: "func sum(*xs: Int) {}",
+        fixed: "func sum(*xs: Int[]) {}",
+    },
+    ErrorCode {
+        code: "E0224",
+        title: "Invalid type argument arity",
+        description: "A generic type was given a different number of type arguments than it declares.",
+        example: "func f(r: Result<Int>) {}",
+        fixed: "func f(r: Result<Int, String>) {}",
+    },
+    ErrorCode {
+        code: "E0225",
+        title: "Unknown member",
+        description: "A field or method was accessed which does not exist on the type of the value.",
+        example: "val s = \"abc\"\nprintln(s.size)",
+        fixed: "val s = \"abc\"\nprintln(s.length)",
+    },
+    ErrorCode {
+        code: "E0226",
+        title: "Missing argument labels",
+        description: "Instances of types must be created with labeled arguments, one for each field.",
+        example: "type Point {\n  x: Int\n  y: Int\n}\nval p = Point(1, 2)",
+        fixed: "type Point {\n  x: Int\n  y: Int\n}\nval p = Point(x: 1, y: 2)",
+    },
+    ErrorCode {
+        code: "E0227",
+        title: "Unknown type for parameter",
+        description: "The type of a lambda's parameter could not be determined, since it has no type annotation and the lambda is not used where a particular function type is expected. Add a type annotation to the parameter.",
+        example: "val inc = x => x + 1",
+        fixed: "val inc = (x: Int) => x + 1",
+    },
+    ErrorCode {
+        code: "E0228",
+        title: "Assignment to immutable",
+        description: "A value was assigned to something which cannot be reassigned, such as a `val` variable, a parameter or a method. Declare the variable with `var` if it needs to change.",
+        example: "val x = 1\nx = 2",
+        fixed: "var x = 1\nx = 2",
+    },
+    ErrorCode {
+        code: "E0229",
+        title: "Invalid indexable type",
+        description: "An index (`[]`) operation was used on a value whose type does not support indexing (or range indexing).",
+        example: "val x = 1\nval y = x[0]",
+        fixed: "val x = [1]\nval y = x[0]",
+    },
+    ErrorCode {
+        code: "E0230",
+        title: "Invalid index type",
+        description: "The value used as an index is not of the type required by the indexed value, for example a String used to index into an array.",
+        example: "val xs = [1, 2]\nval y = xs[\"a\"]",
+        fixed: "val xs = [1, 2]\nval y = xs[0]",
+    },
+    ErrorCode {
+        code: "E0231",
+        title: "Invalid tuple index",
+        description: "Tuples may only be indexed by integer literals, and the index must be within the bounds of the tuple.",
+        example: "val t = (1, 2)\nval x = t[2]",
+        fixed: "val t = (1, 2)\nval x = t[1]",
+    },
+    ErrorCode {
+        code: "E0232",
+        title: "Invalid assignment target",
+        description: "The left-hand side of an assignment is not something which can be assigned to. Strings, tuples and ranges cannot be modified by assigning to an index.",
+        example: "var s = \"abc\"\ns[0] = \"x\"",
+        fixed: "var s = \"abc\"\ns = \"xbc\"",
+    },
+    ErrorCode {
+        code: "E0233",
+        title: "Empty block in if-expression",
+        description: "When an if-expression is used as a value, both its then- and else-blocks must be present and must end in an expression.",
+        example: "val x = if true { 1 } else { }",
+        fixed: "val x = if true { 1 } else { 2 }",
+    },
+    ErrorCode {
+        code: "E0234",
+        title: "Duplicate match case",
+        description: "The same case appears more than once within a match. The later case could never be reached.",
+        example: "val x: Int? = None\nmatch x {\n  None => println(\"none\")\n  None => println(\"none again\")\n  _ => println(\"some\")\n}",
+        fixed: "val x: Int? = None\nmatch x {\n  None => println(\"none\")\n  _ => println(\"some\")\n}",
+    },
+    ErrorCode {
+        code: "E0235",
+        title: "Empty match block",
+        description: "When a match is used as a value, the block of each of its cases must end in an expression.",
+        example: "val x: Int? = None\nval y = match x {\n  None => {}\n  _ => 1\n}",
+        fixed: "val x: Int? = None\nval y = match x {\n  None => 0\n  _ => 1\n}",
+    },
+    ErrorCode {
+        code: "E0236",
+        title: "Unreachable match case",
+        description: "A match case can never be reached, either because an earlier case already covers every value it would match, or because its type has no overlap with the type of the value being matched.",
+        example: "val x: Int? = None\nmatch x {\n  _ => println(\"anything\")\n  None => println(\"none\")\n}",
+        fixed: "val x: Int? = None\nmatch x {\n  None => println(\"none\")\n  _ => println(\"anything\")\n}",
+    },
+    ErrorCode {
+        code: "E0237",
+        title: "Non-exhaustive match",
+        description: "A match used as a value must handle every possible value of the matched expression. Add the missing cases, or a catch-all `_` case.",
+        example: "val x: Int? = None\nval y = match x {\n  None => 0\n}",
+        fixed: "val x: Int? = None\nval y = match x {\n  None => 0\n  _ => 1\n}",
+    },
+    ErrorCode {
+        code: "E0238",
+        title: "Invalid control flow target",
+        description: "The value used as the condition of an if or while, or as the iteratee of a for-loop, has a type which cannot be used in that position. For-loops require an array, set, map or iterator.",
+        example: "for x in 5 {\n  println(x)\n}",
+        fixed: "for x in range(0, 5) {\n  println(x)\n}",
+    },
+    ErrorCode {
+        code: "E0239",
+        title: "Invalid control flow terminator",
+        description: "`break` and `continue` may only appear within a loop, and `return` may only appear within a function.",
+        example: "break",
+        fixed: "while true {\n  break\n}",
+    },
+    ErrorCode {
+        code: "E0240",
+        title: "Unreachable code",
+        description: "Code follows a `return`, `break` or `continue` within the same block, so it can never run.",
+        example: "func f(): Int {\n  return 1\n  println(\"done\")\n}",
+        fixed: "func f(): Int {\n  println(\"done\")\n  return 1\n}",
+    },
+    ErrorCode {
+        code: "E0241",
+        title: "Invalid export scope",
+        description: "The `export` modifier may only be used on declarations at the top level of a module.",
+        example: "func f() {\n  export val x = 1\n}",
+        fixed: "export val x = 1",
+    },
+    ErrorCode {
+        code: "E0242",
+        title: "Circular module import",
+        description: "A module imports (directly or indirectly) a module which is still in the process of being imported, forming a cycle which cannot be resolved. Move the shared declarations into a separate module which both can import.",
+        example: "// a.abra\nimport b from \"./b\"\nexport val a = 1\n\n// b.abra\nimport a from \"./a\"\nexport val b = a + 1",
+        fixed: "// a.abra\nexport val a = 1\n\n// b.abra\nimport a from \"./a\"\nexport val b = a + 1",
+    },
+    ErrorCode {
+        code: "E0243",
+        title: "Unknown module",
+        description: "An import refers to a module which could not be found. Relative paths are resolved with respect to the importing file; other paths are resolved within the standard library.",
+        example: "import \"./nonexistent\" as utils",
+        fixed: "// utils.abra exists alongside this file\nimport \"./utils\" as utils",
+    },
+    ErrorCode {
+        code: "E0244",
+        title: "Unknown export",
+        description: "An import names a value which the imported module does not export. Only top-level declarations marked with `export` can be imported.",
+        example: "// utils.abra\nval helper = 1\n\n// main.abra\nimport helper from \"./utils\"",
+        fixed: "// utils.abra\nexport val helper = 1\n\n// main.abra\nimport helper from \"./utils\"",
+    },
+    ErrorCode {
+        code: "E0300",
+        title: "Duplicate map key",
+        description: "A map literal contains the same key more than once; only one of the values could be kept.",
+        example: "val m = { a: 1, a: 2 }",
+        fixed: "val m = { a: 1, b: 2 }",
+    },
+    ErrorCode {
+        code: "E0301",
+        title: "Unbound type argument",
+        description: "The type of a value could not be fully determined because one of its type arguments has nothing to infer it from. Add a type annotation.",
+        example: "type List<T> { items: T[] }\nval l = List(items: [])",
+        fixed: "type List<T> { items: T[] }\nval l: List<Int> = List(items: [])",
+    },
+    ErrorCode {
+        code: "E0302",
+        title: "Invalid location for type declaration",
+        description: "Types and enums may only be declared at the top level of a module.",
+        example: "func f() {\n  type Point { x: Int }\n}",
+        fixed: "type Point { x: Int }\nfunc f() {}",
+    },
+    ErrorCode {
+        code: "E0303",
+        title: "Invalid usage of variadic parameter",
+        description: "Variadic (`*`) parameters may only be declared by functions and methods; they cannot be used for enum variant fields.",
+        example: "enum Temp { Hot(*degs: Int[]), Cold }",
+        fixed: "enum Temp { Hot(degs: Int[]), Cold }",
+    },
+    ErrorCode {
+        code: "E0304",
+        title: "Invalid type for method",
+        description: "A method with special meaning, such as `toString`, was declared with a different signature than required.",
+        example: "type Thing {\n  func toString(self): Int = 1\n}",
+        fixed: "type Thing {\n  func toString(self): String = \"1\"\n}",
+    },
+    ErrorCode {
+        code: "E0305",
+        title: "Invalid access",
+        description: "A field marked as `readonly` may be read, but it cannot be assigned to from outside of its type's methods.",
+        example: "type Person {\n  name: String readonly\n}\nval a = Person(name: \"abc\")\na.name = \"hello\"",
+        fixed: "type Person {\n  name: String\n}\nval a = Person(name: \"abc\")\na.name = \"hello\"",
+    },
+    ErrorCode {
+        code: "E0306",
+        title: "Invalid placement of try",
+        description: "A `try` expression may only be used within a function whose return type is a Result, since an error is returned from that function.",
+        example: "val a = try Result.Ok(123)",
+        fixed: "func f(): Result<Int, Int> {\n  val a = try Result.Ok(123)\n  Result.Ok(a)\n}",
+    },
+    ErrorCode {
+        code: "E0307",
+        title: "Invalid type for try",
+        description: "A `try` expression may only be applied to a Result value.",
+        example: "func f(): Result<Int, Int> {\n  val a = try [1, 2, 3]\n  Result.Ok(a[0] ?: 0)\n}",
+        fixed: "func f(): Result<Int, Int> {\n  val a = try Result.Ok(1)\n  Result.Ok(a)\n}",
+    },
+    ErrorCode {
+        code: "E0308",
+        title: "Try type mismatch",
+        description: "The error type of the Result in a `try` expression must match the error type of the enclosing function's return type.",
+        example: "func f(): Result<Int, Int> {\n  val a = try Result.Err(\"error\")\n  Result.Ok(1)\n}",
+        fixed: "func f(): Result<Int, String> {\n  val a = try Result.Err(\"error\")\n  Result.Ok(1)\n}",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use itertools::Either;
+    use crate::common::error_codes::{ERROR_CODES, ErrorCode, lookup_error_code};
+    use crate::common::test_utils::MockModuleReader;
+    use crate::lexer::lexer::tokenize;
+    use crate::module_loader::ModuleLoader;
+    use crate::parser::ast::ModuleId;
+    use crate::parser::parser::parse;
+    use crate::typechecker::test_helpers::typecheck_main;
+
+    // Errors which typechecker2 doesn't (yet) report for the examples given, but the legacy typechecker does
+    const REPORTED_BY_LEGACY_TYPECHECKER: &[&str] = &["E0230", "E0235"];
+
+    // Returns the code of the error which the source results in, if any
+    fn error_code_for(code: &ErrorCode, source: &str) -> Option<&'static str> {
+        let module_id = ModuleId::parse_module_path("./main").unwrap();
+        let source = source.to_string();
+        let tokens = match tokenize(&module_id, &source) {
+            Ok(tokens) => tokens,
+            Err(e) => return Some(e.code()),
+        };
+        if let Err(e) = parse(module_id.clone(), tokens) {
+            return Some(e.code());
+        }
+
+        if code.code.starts_with("E03") || REPORTED_BY_LEGACY_TYPECHECKER.contains(&code.code) {
+            let mut reader = MockModuleReader::new(vec![]);
+            let mut loader = ModuleLoader::new(&mut reader);
+            return match crate::typecheck(module_id, &source, &mut loader) {
+                Ok(_) => None,
+                Err(crate::Error::TypecheckerError(e)) => Some(e.code()),
+                Err(e) => unreachable!("Unexpected error {:?}", e),
+            };
+        }
+
+        match typecheck_main(&[("main.abra", &source)]).2 {
+            Ok(_) => None,
+            Err(Either::Left((Either::Left(e), _))) => Some(e.code()),
+            Err(Either::Left((Either::Right(e), _))) => Some(e.code()),
+            Err(Either::Right(e)) => Some(e.code()),
+        }
+    }
+
+    #[test]
+    fn test_error_codes_are_unique_and_ordered() {
+        for pair in ERROR_CODES.windows(2) {
+            assert!(pair[0].code < pair[1].code, "{} should come before {}", pair[0].code, pair[1].code);
+        }
+    }
+
+    #[test]
+    fn test_error_code_examples() {
+        let mut failures = vec![];
+        for code in ERROR_CODES {
+            // Examples which span multiple modules are illustrative only
+            if code.example.starts_with("//") { continue; }
+
+            let actual = error_code_for(code, code.example);
+            if actual != Some(code.code) {
+                failures.push(format!("Example for {} failed with {:?}", code.code, actual));
+            }
+            if code.fixed.starts_with("//") { continue; }
+
+            let actual = error_code_for(code, code.fixed);
+            if actual.is_some() {
+                failures.push(format!("Fixed example for {} failed with {:?}", code.code, actual));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_lookup_error_code() {
+        assert_eq!("E0207", lookup_error_code("e0207").unwrap().code);
+        assert!(lookup_error_code("E9999").is_none());
+
+        let explanation = lookup_error_code("E0207").unwrap().explain();
+        let expected = "\
+E0207: Duplicate name
+
+A name is declared more than once within the same scope. This applies to variables, functions, types, enums, fields, methods, enum variants and type arguments.
+
+Erroneous code example:
+
+    val x = 1
+    val x = 2
+
+Fixed example:
+
+    val x = 1
+    val y = 2
+";
+        assert_eq!(expected, explanation);
+    }
+}
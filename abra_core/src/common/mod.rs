@@ -1,6 +1,10 @@
 pub mod ast_visitor;
+pub mod diagnostic;
+pub mod diagnostic_renderer;
 pub mod display_error;
+pub mod error_codes;
 pub mod fs_module_reader;
+pub mod suggestions;
 pub mod test_utils;
 pub mod typed_ast_visitor;
 pub mod util;
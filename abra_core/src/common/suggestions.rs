@@ -0,0 +1,82 @@
+// The edit distance between two strings, ie. the number of single-character insertions, deletions, substitutions and transpositions
+// of adjacent characters required to transform one into the other (the "optimal string alignment" distance).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() { row[0] = i; }
+    for (j, cell) in d[0].iter_mut().enumerate() { *cell = j; }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution_cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j - 1] + substitution_cost)
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+// Finds the candidate most similar to `name`, for "did you mean ...?" suggestions. A candidate which differs only in case is always
+// preferred; otherwise the candidate must be within an edit distance of roughly a third of the name's length (and must share at
+// least one character with it), so that unrelated names aren't suggested. Ties are broken by whichever candidate comes first.
+pub fn find_similar_name<'a, I>(name: &str, candidates: I) -> Option<String>
+    where I: IntoIterator<Item = &'a str>
+{
+    let name_len = name.chars().count();
+    let max_distance = (name_len / 3).max(1).min(name_len.saturating_sub(1));
+    let lowercase_name = name.to_lowercase();
+
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        if candidate == name { continue; }
+
+        let distance = if candidate.to_lowercase() == lowercase_name { 0 } else { edit_distance(name, candidate) };
+        if distance > max_distance { continue; }
+
+        match best {
+            Some((best_distance, _)) if best_distance <= distance => {}
+            _ => best = Some((distance, candidate)),
+        }
+    }
+
+    best.map(|(_, candidate)| candidate.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::suggestions::{edit_distance, find_similar_name};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("abc", "abc"));
+        assert_eq!(3, edit_distance("", "abc"));
+        assert_eq!(3, edit_distance("abc", ""));
+        assert_eq!(1, edit_distance("count", "cont"));
+        assert_eq!(1, edit_distance("length", "lenght"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+    }
+
+    #[test]
+    fn test_find_similar_name() {
+        let candidates = vec!["count", "counter", "println", "print", "Point"];
+
+        assert_eq!(Some("count".to_string()), find_similar_name("cont", candidates.clone()));
+        assert_eq!(Some("println".to_string()), find_similar_name("printn", candidates.clone()));
+        assert_eq!(Some("Point".to_string()), find_similar_name("point", candidates.clone()));
+        // Ties go to the first candidate
+        assert_eq!(Some("count".to_string()), find_similar_name("counte", candidates.clone()));
+
+        assert_eq!(None, find_similar_name("count", vec!["count"]));
+        assert_eq!(None, find_similar_name("x", vec!["abc"]));
+        assert_eq!(None, find_similar_name("x", vec!["a"]));
+        assert_eq!(Some("X".to_string()), find_similar_name("x", vec!["a", "X"]));
+        assert_eq!(None, find_similar_name("foo", candidates.clone()));
+    }
+}
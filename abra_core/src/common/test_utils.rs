@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
+use crate::common::util::random_string;
 use crate::parser::ast::ModuleId;
 use crate::module_loader::ModuleReader;
 
@@ -27,3 +29,16 @@ impl ModuleReader for MockModuleReader {
         module_id.get_path("")
     }
 }
+
+// Creates a uniquely-named directory in the system's temp dir containing the given files (whose paths are relative to it), and
+// returns its canonical path
+pub fn make_temp_dir(prefix: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", prefix, random_string(8)));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (path, contents) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir.canonicalize().unwrap()
+}
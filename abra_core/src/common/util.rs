@@ -42,3 +42,12 @@ pub fn get_project_root() -> io::Result<PathBuf> {
     }
     Err(io::Error::new(ErrorKind::NotFound, "Ran out of places to find Cargo.toml"))
 }
+
+// The std library to typecheck against: the explicitly-provided path if there is one, otherwise the std directory of the
+// enclosing abra repository. Shared by the cli and the language server, so that both see the same std library.
+pub fn resolve_std_path(std_path: Option<&str>) -> io::Result<PathBuf> {
+    match std_path {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => get_project_root().map(|root| root.join("abra_core/std")),
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use itertools::Itertools;
+use crate::lexer::tokens::Position;
+use crate::typechecker::typechecker2::{Enum, EnumId, EnumVariantKind, FuncId, Function, ModuleId, PrimitiveType, Project, Span, Struct, StructId, Type, TypeId, TypeKind, ExportedValue, Variable, VariableAlias, PRELUDE_MODULE_ID, PRELUDE_UNIT_TYPE_ID};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    pub fn from_name(name: &str) -> Option<DocFormat> {
+        match name {
+            "md" | "markdown" => Some(DocFormat::Markdown),
+            "html" => Some(DocFormat::Html),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocFormat::Markdown => "md",
+            DocFormat::Html => "html",
+        }
+    }
+}
+
+enum Item<'a> {
+    Function(&'a Function),
+    Struct(&'a Struct),
+    Enum(&'a Enum),
+    Value(&'a Variable),
+}
+
+impl<'a> Item<'a> {
+    fn name(&self) -> &'a String {
+        match self {
+            Item::Function(func) => &func.name,
+            Item::Struct(struct_) => &struct_.name,
+            Item::Enum(enum_) => &enum_.name,
+            Item::Value(var) => &var.name,
+        }
+    }
+
+    fn position(&self) -> Position {
+        let span = match self {
+            Item::Function(func) => func.defined_span.as_ref(),
+            Item::Struct(struct_) => struct_.defined_span.as_ref(),
+            Item::Enum(enum_) => Some(&enum_.defined_span),
+            Item::Value(var) => var.defined_span.as_ref(),
+        };
+        span.map(|span| span.range.start.clone()).unwrap_or(Position::new(0, 0))
+    }
+}
+
+// Generates a page for each of the given modules (each paired with the name of its page), plus an index page linking to them all,
+// returning the file name and contents of each page. A module's page documents its exported functions, types, enums and values
+// (or, for the prelude, which is implicitly imported everywhere, all of its non-underscored top-level declarations), along with
+// their `///` doc comments. Type names in signatures link to the type's documentation, if it's on one of the generated pages.
+pub fn generate_docs(project: &Project, modules: &Vec<(ModuleId, String)>, format: DocFormat) -> Vec<(String, String)> {
+    let ext = format.extension();
+
+    let mut struct_links = HashMap::new();
+    let mut enum_links = HashMap::new();
+    let module_items = modules.iter()
+        .map(|(module_id, page_name)| {
+            let items = public_items(project, module_id);
+            for item in &items {
+                match item {
+                    Item::Struct(struct_) => { struct_links.insert(struct_.id, format!("{page_name}.{ext}#{}", struct_.name)); }
+                    Item::Enum(enum_) => { enum_links.insert(enum_.id, format!("{page_name}.{ext}#{}", enum_.name)); }
+                    _ => {}
+                }
+            }
+            (page_name, items)
+        })
+        .collect_vec();
+
+    let generator = DocGenerator { project, struct_links, enum_links };
+    let mut pages = vec![];
+
+    let mut index = PageWriter::new(format);
+    index.heading(1, None, "Modules");
+    index.link_list(modules.iter().map(|(_, page_name)| (format!("{page_name}.{ext}"), page_name.clone())).collect());
+    pages.push((format!("index.{ext}"), index.finish("Modules")));
+
+    for (page_name, items) in module_items {
+        let title = format!("Module {page_name}");
+        let mut page = PageWriter::new(format);
+        page.heading(1, None, &title);
+        generator.write_items(&mut page, &items);
+        pages.push((format!("{page_name}.{ext}"), page.finish(&title)));
+    }
+
+    pages
+}
+
+fn is_public_name(name: &String) -> bool {
+    !name.starts_with('_')
+}
+
+fn public_items<'a>(project: &'a Project, module_id: &ModuleId) -> Vec<Item<'a>> {
+    let module = &project.modules[module_id.0];
+
+    let mut items = if *module_id == PRELUDE_MODULE_ID {
+        let root_scope = &module.scopes[0];
+        let functions = root_scope.funcs.iter().filter(|f| f.defined_span.is_some()).map(Item::Function);
+        let structs = module.structs.iter().filter(|s| s.defined_span.is_some()).map(Item::Struct);
+        let enums = module.enums.iter().map(Item::Enum);
+        let values = root_scope.vars.iter().filter(|v| v.alias == VariableAlias::None && v.defined_span.is_some()).map(Item::Value);
+
+        functions.chain(structs).chain(enums).chain(values).filter(|item| is_public_name(item.name())).collect_vec()
+    } else {
+        module.exports.values()
+            .map(|exported_value| match exported_value {
+                ExportedValue::Function(func_id) => Item::Function(project.get_func_by_id(func_id)),
+                ExportedValue::Type(TypeKind::Struct(struct_id)) => Item::Struct(project.get_struct_by_id(struct_id)),
+                ExportedValue::Type(TypeKind::Enum(enum_id)) => Item::Enum(project.get_enum_by_id(enum_id)),
+                ExportedValue::Variable(var_id) => Item::Value(project.get_var_by_id(var_id)),
+            })
+            .collect_vec()
+    };
+
+    items.sort_by_key(|item| { let pos = item.position(); (pos.line, pos.col) });
+    items
+}
+
+struct DocGenerator<'a> {
+    project: &'a Project,
+    struct_links: HashMap<StructId, String>,
+    enum_links: HashMap<EnumId, String>,
+}
+
+impl<'a> DocGenerator<'a> {
+    fn write_items(&self, page: &mut PageWriter, items: &Vec<Item>) {
+        let types = items.iter().filter(|item| matches!(item, Item::Struct(_) | Item::Enum(_))).collect_vec();
+        if !types.is_empty() {
+            page.heading(2, None, "Types");
+            for item in types {
+                match item {
+                    Item::Struct(struct_) => self.write_struct(page, struct_),
+                    Item::Enum(enum_) => self.write_enum(page, enum_),
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        let functions = items.iter().filter_map(|item| if let Item::Function(func) = item { Some(func) } else { None }).collect_vec();
+        if !functions.is_empty() {
+            page.heading(2, None, "Functions");
+            for func in functions {
+                page.heading(3, Some(&func.name), &func.name);
+                page.signature(&self.function_signature(func));
+                page.doc(&func.doc_comment);
+            }
+        }
+
+        let values = items.iter().filter_map(|item| if let Item::Value(var) = item { Some(var) } else { None }).collect_vec();
+        if !values.is_empty() {
+            page.heading(2, None, "Values");
+            for var in values {
+                let keyword = if var.is_mutable { "var" } else { "val" };
+                page.heading(3, Some(&var.name), &var.name);
+                page.signature(&format!("{keyword} {}: {}", escape(&var.name), self.type_html(&var.type_id)));
+            }
+        }
+    }
+
+    fn write_struct(&self, page: &mut PageWriter, struct_: &Struct) {
+        page.heading(3, Some(&struct_.name), &struct_.name);
+        page.signature(&format!("type {}{}", escape(&struct_.name), self.generics_html(&struct_.generic_ids)));
+        page.doc(&struct_.doc_comment);
+
+        let fields = struct_.fields.iter().filter(|f| is_public_name(&f.name)).collect_vec();
+        if !fields.is_empty() {
+            page.heading(4, None, "Fields");
+            for field in fields {
+                let readonly = if field.is_readonly { " readonly" } else { "" };
+                page.signature(&format!("{}: {}{readonly}", escape(&field.name), self.type_html(&field.type_id)));
+                page.doc(&field.doc_comment);
+            }
+        }
+
+        self.write_methods(page, &struct_.name, &struct_.static_methods, &struct_.methods);
+    }
+
+    fn write_enum(&self, page: &mut PageWriter, enum_: &Enum) {
+        page.heading(3, Some(&enum_.name), &enum_.name);
+        page.signature(&format!("enum {}{}", escape(&enum_.name), self.generics_html(&enum_.generic_ids)));
+        page.doc(&enum_.doc_comment);
+
+        page.heading(4, None, "Variants");
+        for variant in &enum_.variants {
+            match &variant.kind {
+                EnumVariantKind::Constant => page.signature(&escape(&variant.name)),
+                EnumVariantKind::Container(func_id) => {
+                    let func = self.project.get_func_by_id(func_id);
+                    page.signature(&format!("{}({})", escape(&variant.name), self.params_html(func)));
+                }
+            }
+        }
+
+        self.write_methods(page, &enum_.name, &enum_.static_methods, &enum_.methods);
+    }
+
+    fn write_methods(&self, page: &mut PageWriter, type_name: &String, static_methods: &Vec<FuncId>, methods: &Vec<FuncId>) {
+        // Methods without a span (eg. a type's default `toString`) are generated, rather than declared
+        let methods = static_methods.iter().chain(methods.iter())
+            .map(|func_id| self.project.get_func_by_id(func_id))
+            .filter(|func| func.defined_span.is_some() && is_public_name(&func.name))
+            .sorted_by_key(|func| func.defined_span.as_ref().map(|span: &Span| (span.range.start.line, span.range.start.col)))
+            .collect_vec();
+        if methods.is_empty() { return; }
+
+        page.heading(4, None, "Methods");
+        for func in methods {
+            page.heading(5, Some(&format!("{type_name}.{}", func.name)), &func.name);
+            page.signature(&self.function_signature(func));
+            page.doc(&func.doc_comment);
+        }
+    }
+
+    fn function_signature(&self, func: &Function) -> String {
+        let mut sig = format!("func {}{}({})", escape(&func.name), self.generics_html(&func.generic_ids), self.params_html(func));
+        if func.return_type_id != PRELUDE_UNIT_TYPE_ID {
+            sig.push_str(&format!(": {}", self.type_html(&func.return_type_id)));
+        }
+        sig
+    }
+
+    fn params_html(&self, func: &Function) -> String {
+        func.params.iter()
+            .map(|param| {
+                if func.has_self() && param.name == "self" { return "self".to_string(); }
+
+                // A variadic param's type is its element type, so its array suffix is re-added to match the source
+                let (vararg, array) = if param.is_variadic { ("*", "[]") } else { ("", "") };
+                let default_value = if param.default_value.is_some() { " = ..." } else { "" };
+                format!("{vararg}{}: {}{array}{default_value}", escape(&param.name), self.type_html(&param.type_id))
+            })
+            .join(", ")
+    }
+
+    fn generics_html(&self, generic_ids: &Vec<TypeId>) -> String {
+        if generic_ids.is_empty() { return "".to_string(); }
+
+        escape(&format!("<{}>", generic_ids.iter().map(|type_id| self.project.type_repr(type_id)).join(", ")))
+    }
+
+    // The type's representation, with each documented type it refers to linked to its documentation
+    fn type_html(&self, type_id: &TypeId) -> String {
+        let mut links = HashMap::new();
+        self.collect_links(type_id, &mut links);
+
+        let repr = self.project.type_repr(type_id);
+        let mut html = String::new();
+        let mut ident = String::new();
+        for ch in repr.chars().chain(std::iter::once('\0')) {
+            if ch.is_alphanumeric() || ch == '_' {
+                ident.push(ch);
+                continue;
+            }
+
+            if !ident.is_empty() {
+                match links.get(&ident) {
+                    Some(href) => html.push_str(&format!("<a href=\"{href}\">{ident}</a>")),
+                    None => html.push_str(&ident),
+                }
+                ident.clear();
+            }
+            if ch != '\0' {
+                html.push_str(&escape(&ch.to_string()));
+            }
+        }
+        html
+    }
+
+    fn collect_links(&self, type_id: &TypeId, links: &mut HashMap<String, String>) {
+        let add_struct_link = |struct_id: &StructId, links: &mut HashMap<String, String>| {
+            if let Some(href) = self.struct_links.get(struct_id) {
+                links.insert(self.project.get_struct_by_id(struct_id).name.clone(), href.clone());
+            }
+        };
+
+        match self.project.get_type_by_id(type_id) {
+            Type::Primitive(primitive_type) => {
+                let struct_id = match primitive_type {
+                    PrimitiveType::Int => self.project.prelude_int_struct_id,
+                    PrimitiveType::Float => self.project.prelude_float_struct_id,
+                    PrimitiveType::Bool => self.project.prelude_bool_struct_id,
+                    PrimitiveType::String => self.project.prelude_string_struct_id,
+                    PrimitiveType::Unit | PrimitiveType::Any => return,
+                };
+                add_struct_link(&struct_id, links);
+            }
+            Type::GenericInstance(struct_id, generic_ids) => {
+                add_struct_link(struct_id, links);
+                generic_ids.iter().for_each(|type_id| self.collect_links(type_id, links));
+            }
+            Type::GenericEnumInstance(enum_id, generic_ids, _) => {
+                if let Some(href) = self.enum_links.get(enum_id) {
+                    links.insert(self.project.get_enum_by_id(enum_id).name.clone(), href.clone());
+                }
+                generic_ids.iter().for_each(|type_id| self.collect_links(type_id, links));
+            }
+            Type::Function(param_type_ids, _, _, return_type_id) => {
+                param_type_ids.iter().for_each(|type_id| self.collect_links(type_id, links));
+                self.collect_links(return_type_id, links);
+            }
+            Type::Type(TypeKind::Struct(struct_id)) => add_struct_link(struct_id, links),
+            Type::Type(TypeKind::Enum(enum_id)) => {
+                if let Some(href) = self.enum_links.get(enum_id) {
+                    links.insert(self.project.get_enum_by_id(enum_id).name.clone(), href.clone());
+                }
+            }
+            Type::Generic(_, _) | Type::ModuleAlias => {}
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Both formats render signatures as html (markdown can't contain links within code), but markdown passes doc comments through as-is
+struct PageWriter {
+    format: DocFormat,
+    out: String,
+}
+
+impl PageWriter {
+    fn new(format: DocFormat) -> Self {
+        PageWriter { format, out: String::new() }
+    }
+
+    fn heading(&mut self, level: usize, anchor: Option<&String>, text: &str) {
+        match self.format {
+            DocFormat::Markdown => {
+                if let Some(anchor) = anchor {
+                    self.out.push_str(&format!("<a id=\"{}\"></a>\n\n", escape(anchor)));
+                }
+                self.out.push_str(&format!("{} {}\n\n", "#".repeat(level), text));
+            }
+            DocFormat::Html => {
+                let id = anchor.map(|anchor| format!(" id=\"{}\"", escape(anchor))).unwrap_or_default();
+                self.out.push_str(&format!("<h{level}{id}>{}</h{level}>\n", escape(text)));
+            }
+        }
+    }
+
+    fn signature(&mut self, html: &String) {
+        self.out.push_str(&format!("<pre><code>{html}</code></pre>\n"));
+        if self.format == DocFormat::Markdown { self.out.push('\n'); }
+    }
+
+    fn doc(&mut self, doc_comment: &Option<String>) {
+        let Some(doc_comment) = doc_comment else { return; };
+
+        match self.format {
+            DocFormat::Markdown => self.out.push_str(&format!("{doc_comment}\n\n")),
+            DocFormat::Html => {
+                for paragraph in doc_comment.split("\n\n") {
+                    self.out.push_str(&format!("<p>{}</p>\n", escape(paragraph.trim())));
+                }
+            }
+        }
+    }
+
+    fn link_list(&mut self, links: Vec<(String, String)>) {
+        match self.format {
+            DocFormat::Markdown => {
+                for (href, text) in links {
+                    self.out.push_str(&format!("- [{text}]({href})\n"));
+                }
+            }
+            DocFormat::Html => {
+                self.out.push_str("<ul>\n");
+                for (href, text) in links {
+                    self.out.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", escape(&href), escape(&text)));
+                }
+                self.out.push_str("</ul>\n");
+            }
+        }
+    }
+
+    fn finish(self, title: &str) -> String {
+        match self.format {
+            DocFormat::Markdown => format!("{}\n", self.out.trim_end()),
+            DocFormat::Html => format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+                escape(title),
+                self.out,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docgen::{DocFormat, generate_docs};
+    use crate::typechecker::test_helpers::typecheck_main;
+    use crate::typechecker::typechecker2::PRELUDE_MODULE_ID;
+
+    fn document(source: &str, format: DocFormat) -> Vec<(String, String)> {
+        let (_, project, result) = typecheck_main(&[("main.abra", source)]);
+        let module_id = result.unwrap();
+        let modules = vec![(module_id, "main".to_string()), (PRELUDE_MODULE_ID, "prelude".to_string())];
+        generate_docs(&project, &modules, format)
+    }
+
+    #[test]
+    fn test_generate_markdown_docs() {
+        let source = "\
+/// A point in space
+export type Point {
+  /// The horizontal position
+  x: Int
+  _y: Int = 0
+
+  /// Moves the point
+  func moveBy(self, dx: Int, dy = 0): Point = self
+}
+
+/// Finds a point
+export func find(points: Point[], *names: String[]): Point? = None
+
+export enum Shape { Dot(p: Point), Empty }
+
+func helper() {}
+";
+        let pages = document(source, DocFormat::Markdown);
+        assert_eq!(vec!["index.md", "main.md", "prelude.md"], pages.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>());
+        assert_eq!("# Modules\n\n- [main](main.md)\n- [prelude](prelude.md)\n", pages[0].1);
+
+        let expected = "\
+# Module main
+
+## Types
+
+<a id=\"Point\"></a>
+
+### Point
+
+<pre><code>type Point</code></pre>
+
+A point in space
+
+#### Fields
+
+<pre><code>x: <a href=\"prelude.md#Int\">Int</a></code></pre>
+
+The horizontal position
+
+#### Methods
+
+<a id=\"Point.moveBy\"></a>
+
+##### moveBy
+
+<pre><code>func moveBy(self, dx: <a href=\"prelude.md#Int\">Int</a>, dy: <a href=\"prelude.md#Int\">Int</a> = ...): <a href=\"main.md#Point\">Point</a></code></pre>
+
+Moves the point
+
+<a id=\"Shape\"></a>
+
+### Shape
+
+<pre><code>enum Shape</code></pre>
+
+#### Variants
+
+<pre><code>Dot(p: <a href=\"main.md#Point\">Point</a>)</code></pre>
+
+<pre><code>Empty</code></pre>
+
+## Functions
+
+<a id=\"find\"></a>
+
+### find
+
+<pre><code>func find(points: <a href=\"main.md#Point\">Point</a>[], *names: <a href=\"prelude.md#String\">String</a>[]): <a href=\"main.md#Point\">Point</a>?</code></pre>
+
+Finds a point
+";
+        assert_eq!(expected, pages[1].1);
+
+        // The prelude's declarations are documented even though they aren't exported, since they're implicitly imported everywhere
+        let prelude = &pages[2].1;
+        assert!(prelude.contains("### println\n\n<pre><code>func println(*items: Any[])</code></pre>"));
+        assert!(prelude.contains("<pre><code>enum Option&lt;V&gt;</code></pre>"));
+    }
+
+    #[test]
+    fn test_generate_html_docs() {
+        let pages = document("/// Doubles a number.\n///\n/// Returns an <Int>.\nexport func double(i: Int): Int = i * 2", DocFormat::Html);
+        assert_eq!(vec!["index.html", "main.html", "prelude.html"], pages.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>());
+
+        let main = &pages[1].1;
+        assert!(main.starts_with("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Module main</title>"));
+        assert!(main.contains("<h3 id=\"double\">double</h3>\n<pre><code>func double(i: <a href=\"prelude.html#Int\">Int</a>): <a href=\"prelude.html#Int\">Int</a></code></pre>\n<p>Doubles a number.</p>\n<p>Returns an &lt;Int&gt;.</p>\n"));
+    }
+}
@@ -0,0 +1,849 @@
+use itertools::Itertools;
+use serde_json::{json, Map, Value};
+use crate::lexer::tokens::{Position, Range, Token, TokenType};
+use crate::parser::ast::{AstLiteralNode, AstNode, BindingPattern, DecoratorNode, ImportKind, ImportNode, IndexingMode, MatchCaseArgument, MatchCaseType, ModuleId, TypeIdentifier, UnaryOp};
+use crate::parser::parser::ParseResult;
+use crate::typechecker::typechecker2::{AccessorKind, AssignmentKind, EnumVariantKind, FuncId, Function, FunctionParam, Project, Type, TypeId, TypeKind, TypedLiteral, TypedMatchCaseArgument, TypedMatchCaseKind, TypedModule, TypedNode, VarId};
+
+// The token, AST and typed AST dumps are all first converted into this tree, so that their readable and JSON renderings
+// always agree. The JSON rendering is meant to be consumed by external tools: each node is an object with a `kind`, an
+// optional `position` (`[line, col]`) or `span` (`{"start": [line, col], "end": [line, col]}`), and its fields.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DumpValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Node(DumpNode),
+    List(Vec<DumpValue>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DumpNode {
+    pub kind: String,
+    pub position: Option<Position>,
+    pub span: Option<Range>,
+    pub fields: Vec<(String, DumpValue)>,
+}
+
+impl DumpNode {
+    pub fn new<S: AsRef<str>>(kind: S) -> Self {
+        DumpNode { kind: kind.as_ref().to_string(), position: None, span: None, fields: vec![] }
+    }
+
+    fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    fn spanning(mut self, span: Range) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    fn field<V: Into<DumpValue>>(mut self, name: &str, value: V) -> Self {
+        self.fields.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut obj = Map::new();
+        obj.insert("kind".to_string(), json!(self.kind));
+        if let Some(position) = &self.position {
+            obj.insert("position".to_string(), position_to_json(position));
+        }
+        if let Some(span) = &self.span {
+            obj.insert("span".to_string(), json!({ "start": position_to_json(&span.start), "end": position_to_json(&span.end) }));
+        }
+        for (name, value) in &self.fields {
+            obj.insert(name.clone(), value.to_json());
+        }
+
+        Value::Object(obj)
+    }
+
+    pub fn to_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    // Renders the node's header (its kind, location and scalar fields) on one line, followed by its child nodes and lists,
+    // each on their own indented lines
+    fn write_tree(&self, out: &mut String, indent: usize) {
+        out.push_str(&self.kind);
+        if let Some(Position { line, col }) = &self.position {
+            out.push_str(&format!(" [{}:{}]", line, col));
+        }
+        if let Some(Range { start, end }) = &self.span {
+            out.push_str(&format!(" [{}:{}-{}:{}]", start.line, start.col, end.line, end.col));
+        }
+        for (name, value) in &self.fields {
+            if let Some(scalar) = value.scalar_repr() {
+                out.push_str(&format!(" {}={}", name, scalar));
+            }
+        }
+        out.push('\n');
+
+        for (name, value) in &self.fields {
+            match value {
+                DumpValue::Node(node) => {
+                    out.push_str(&format!("{}{}: ", "  ".repeat(indent + 1), name));
+                    node.write_tree(out, indent + 1);
+                }
+                DumpValue::List(items) if !items.is_empty() => {
+                    out.push_str(&format!("{}{}:\n", "  ".repeat(indent + 1), name));
+                    for item in items {
+                        out.push_str(&format!("{}- ", "  ".repeat(indent + 2)));
+                        match item {
+                            DumpValue::Node(node) => node.write_tree(out, indent + 2),
+                            DumpValue::List(_) => out.push_str("[...]\n"),
+                            _ => out.push_str(&format!("{}\n", item.scalar_repr().unwrap_or_default())),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl DumpValue {
+    pub fn to_json(&self) -> Value {
+        match self {
+            DumpValue::Null => Value::Null,
+            DumpValue::Bool(b) => json!(b),
+            DumpValue::Int(i) => json!(i),
+            DumpValue::Float(f) => json!(f),
+            DumpValue::Str(s) => json!(s),
+            DumpValue::Node(node) => node.to_json(),
+            DumpValue::List(items) => Value::Array(items.iter().map(|item| item.to_json()).collect()),
+        }
+    }
+
+    fn scalar_repr(&self) -> Option<String> {
+        match self {
+            DumpValue::Null => Some("null".to_string()),
+            DumpValue::Bool(b) => Some(b.to_string()),
+            DumpValue::Int(i) => Some(i.to_string()),
+            DumpValue::Float(f) => Some(f.to_string()),
+            DumpValue::Str(s) => Some(format!("{:?}", s)),
+            DumpValue::List(items) if items.is_empty() => Some("[]".to_string()),
+            DumpValue::Node(_) | DumpValue::List(_) => None,
+        }
+    }
+}
+
+impl From<bool> for DumpValue {
+    fn from(b: bool) -> Self { DumpValue::Bool(b) }
+}
+
+impl From<i64> for DumpValue {
+    fn from(i: i64) -> Self { DumpValue::Int(i) }
+}
+
+impl From<usize> for DumpValue {
+    fn from(i: usize) -> Self { DumpValue::Int(i as i64) }
+}
+
+impl From<f64> for DumpValue {
+    fn from(f: f64) -> Self { DumpValue::Float(f) }
+}
+
+impl From<String> for DumpValue {
+    fn from(s: String) -> Self { DumpValue::Str(s) }
+}
+
+impl From<&String> for DumpValue {
+    fn from(s: &String) -> Self { DumpValue::Str(s.clone()) }
+}
+
+impl From<&str> for DumpValue {
+    fn from(s: &str) -> Self { DumpValue::Str(s.to_string()) }
+}
+
+impl From<DumpNode> for DumpValue {
+    fn from(node: DumpNode) -> Self { DumpValue::Node(node) }
+}
+
+impl<V: Into<DumpValue>> From<Vec<V>> for DumpValue {
+    fn from(items: Vec<V>) -> Self { DumpValue::List(items.into_iter().map(|item| item.into()).collect()) }
+}
+
+impl<V: Into<DumpValue>> From<Option<V>> for DumpValue {
+    fn from(value: Option<V>) -> Self { value.map(|v| v.into()).unwrap_or(DumpValue::Null) }
+}
+
+fn position_to_json(position: &Position) -> Value {
+    json!([position.line, position.col])
+}
+
+pub fn nodes_to_json(nodes: &Vec<DumpNode>) -> String {
+    serde_json::to_string_pretty(&Value::Array(nodes.iter().map(|node| node.to_json()).collect())).unwrap()
+}
+
+pub fn nodes_to_tree(nodes: &Vec<DumpNode>) -> String {
+    nodes.iter().map(|node| node.to_tree()).join("")
+}
+
+// Tokens
+
+pub fn dump_tokens(tokens: &Vec<Token>) -> Vec<DumpNode> {
+    tokens.iter().map(dump_token).collect()
+}
+
+fn dump_token(token: &Token) -> DumpNode {
+    let node = DumpNode::new(TokenType::from(token).to_string()).at(token.get_position());
+    match token {
+        Token::Int(_, i) => node.field("value", *i),
+        Token::Float(_, f) => node.field("value", *f),
+        Token::String(_, s) => node.field("value", s),
+        Token::Bool(_, b) => node.field("value", *b),
+        Token::Ident(_, name) => node.field("value", name),
+        Token::StringInterp(_, parts) => node.field("parts", parts.iter().map(dump_token).collect_vec()),
+        _ => node,
+    }
+}
+
+// Untyped AST
+
+// Import statements are included among the parse result's nodes, so its separate list of imports isn't needed
+pub fn dump_ast(parse_result: &ParseResult) -> Vec<DumpNode> {
+    dump_ast_nodes(&parse_result.nodes)
+}
+
+fn dump_ast_nodes(nodes: &Vec<AstNode>) -> Vec<DumpNode> {
+    nodes.iter().map(dump_ast_node).collect()
+}
+
+fn dump_ast_node(node: &AstNode) -> DumpNode {
+    let position = node.get_token().get_position();
+    match node {
+        AstNode::Literal(_, lit) => {
+            let value = match lit {
+                AstLiteralNode::IntLiteral(i) => DumpValue::Int(*i),
+                AstLiteralNode::FloatLiteral(f) => DumpValue::Float(*f),
+                AstLiteralNode::StringLiteral(s) => DumpValue::Str(s.clone()),
+                AstLiteralNode::BoolLiteral(b) => DumpValue::Bool(*b),
+            };
+            DumpNode::new("Literal").at(position).field("value", value)
+        }
+        AstNode::Unary(_, n) => DumpNode::new("Unary").at(position)
+            .field("op", unary_op_repr(&n.op))
+            .field("expr", dump_ast_node(&n.expr)),
+        AstNode::Binary(_, n) => DumpNode::new("Binary").at(position)
+            .field("op", n.op.repr())
+            .field("left", dump_ast_node(&n.left))
+            .field("right", dump_ast_node(&n.right)),
+        AstNode::Grouped(_, n) => DumpNode::new("Grouped").at(position).field("expr", dump_ast_node(&n.expr)),
+        AstNode::Array(_, n) => DumpNode::new("Array").at(position).field("items", dump_ast_nodes(&n.items)),
+        AstNode::Set(_, n) => DumpNode::new("Set").at(position).field("items", dump_ast_nodes(&n.items)),
+        AstNode::Map(_, n) => {
+            let entries = n.items.iter()
+                .map(|(key, value)| DumpNode::new("MapEntry").field("key", dump_ast_node(key)).field("value", dump_ast_node(value)))
+                .collect_vec();
+            DumpNode::new("Map").at(position).field("entries", entries)
+        }
+        AstNode::Tuple(_, items) => DumpNode::new("Tuple").at(position).field("items", dump_ast_nodes(items)),
+        AstNode::BindingDecl(_, n) => DumpNode::new("BindingDecl").at(position)
+            .field("decorators", n.decorators.iter().map(dump_decorator).collect_vec())
+            .field("isExported", n.export_token.is_some())
+            .field("isMutable", n.is_mutable)
+            .field("pattern", dump_binding_pattern(&n.binding))
+            .field("typeAnnotation", n.type_ann.as_ref().map(type_identifier_repr))
+            .field("expr", n.expr.as_ref().map(|expr| dump_ast_node(expr))),
+        AstNode::FunctionDecl(_, n) => DumpNode::new("FunctionDecl").at(position)
+            .field("name", Token::get_ident_name(&n.name))
+            .field("decorators", n.decorators.iter().map(dump_decorator).collect_vec())
+            .field("isExported", n.export_token.is_some())
+            .field("typeArgs", n.type_args.iter().map(Token::get_ident_name).collect_vec())
+            .field("params", n.args.iter().map(dump_ast_param).collect_vec())
+            .field("returnType", n.ret_type.as_ref().map(type_identifier_repr))
+            .field("body", dump_ast_nodes(&n.body)),
+        AstNode::Lambda(_, n) => DumpNode::new("Lambda").at(position)
+            .field("params", n.args.iter().map(dump_ast_param).collect_vec())
+            .field("body", dump_ast_nodes(&n.body)),
+        AstNode::TypeDecl(_, n) => {
+            let fields = n.fields.iter()
+                .map(|field| {
+                    DumpNode::new("Field").at(field.ident.get_position())
+                        .field("name", Token::get_ident_name(&field.ident))
+                        .field("typeAnnotation", type_identifier_repr(&field.type_ident))
+                        .field("isReadonly", field.readonly.is_some())
+                        .field("defaultValue", field.default_value.as_ref().map(dump_ast_node))
+                })
+                .collect_vec();
+            DumpNode::new("TypeDecl").at(position)
+                .field("name", Token::get_ident_name(&n.name))
+                .field("decorators", n.decorators.iter().map(dump_decorator).collect_vec())
+                .field("isExported", n.export_token.is_some())
+                .field("typeArgs", n.type_args.iter().map(Token::get_ident_name).collect_vec())
+                .field("fields", fields)
+                .field("methods", dump_ast_nodes(&n.methods))
+        }
+        AstNode::EnumDecl(_, n) => {
+            let variants = n.variants.iter()
+                .map(|(ident, args)| {
+                    DumpNode::new("Variant").at(ident.get_position())
+                        .field("name", Token::get_ident_name(ident))
+                        .field("params", args.as_ref().map(|args| args.iter().map(dump_ast_param).collect_vec()))
+                })
+                .collect_vec();
+            DumpNode::new("EnumDecl").at(position)
+                .field("name", Token::get_ident_name(&n.name))
+                .field("decorators", n.decorators.iter().map(dump_decorator).collect_vec())
+                .field("isExported", n.export_token.is_some())
+                .field("typeArgs", n.type_args.iter().map(Token::get_ident_name).collect_vec())
+                .field("variants", variants)
+                .field("methods", dump_ast_nodes(&n.methods))
+        }
+        AstNode::Identifier(token, type_args) => DumpNode::new("Identifier").at(position)
+            .field("name", Token::get_ident_name(token))
+            .field("typeArgs", type_args.as_ref().map(|type_args| type_args.iter().map(type_identifier_repr).collect_vec())),
+        AstNode::Assignment(_, n) => DumpNode::new("Assignment").at(position)
+            .field("target", dump_ast_node(&n.target))
+            .field("expr", dump_ast_node(&n.expr)),
+        AstNode::Indexing(_, n) => dump_indexing_mode(DumpNode::new("Indexing").at(position).field("target", dump_ast_node(&n.target)), &n.index, dump_ast_node),
+        AstNode::IfStatement(_, n) | AstNode::IfExpression(_, n) => {
+            let kind = if let AstNode::IfStatement(_, _) = node { "IfStatement" } else { "IfExpression" };
+            DumpNode::new(kind).at(position)
+                .field("condition", dump_ast_node(&n.condition))
+                .field("conditionBinding", n.condition_binding.as_ref().map(dump_binding_pattern))
+                .field("ifBlock", dump_ast_nodes(&n.if_block))
+                .field("elseBlock", n.else_block.as_ref().map(dump_ast_nodes))
+        }
+        AstNode::Invocation(_, n) => {
+            let args = n.args.iter()
+                .map(|(label, arg)| DumpNode::new("Argument").field("label", label.as_ref().map(Token::get_ident_name)).field("value", dump_ast_node(arg)))
+                .collect_vec();
+            DumpNode::new("Invocation").at(position)
+                .field("target", dump_ast_node(&n.target))
+                .field("args", args)
+        }
+        AstNode::ForLoop(_, n) => DumpNode::new("ForLoop").at(position)
+            .field("binding", dump_binding_pattern(&n.binding))
+            .field("index", n.index_ident.as_ref().map(Token::get_ident_name))
+            .field("iterator", dump_ast_node(&n.iterator))
+            .field("body", dump_ast_nodes(&n.body)),
+        AstNode::WhileLoop(_, n) => DumpNode::new("WhileLoop").at(position)
+            .field("condition", dump_ast_node(&n.condition))
+            .field("conditionBinding", n.condition_binding.as_ref().map(Token::get_ident_name))
+            .field("body", dump_ast_nodes(&n.body)),
+        AstNode::Break(_) => DumpNode::new("Break").at(position),
+        AstNode::Continue(_) => DumpNode::new("Continue").at(position),
+        AstNode::Accessor(_, n) => {
+            let field_name = match &*n.field {
+                AstNode::Identifier(token, _) => Token::get_ident_name(token),
+                _ => unreachable!("An accessor's field must be an identifier"),
+            };
+            DumpNode::new("Accessor").at(position)
+                .field("target", dump_ast_node(&n.target))
+                .field("field", field_name)
+                .field("isOptSafe", n.is_opt_safe)
+        }
+        AstNode::Try(_, n) => DumpNode::new("Try").at(position).field("expr", dump_ast_node(&n.expr)),
+        AstNode::MatchStatement(_, n) | AstNode::MatchExpression(_, n) => {
+            let kind = if let AstNode::MatchStatement(_, _) = node { "MatchStatement" } else { "MatchExpression" };
+            let cases = n.branches.iter()
+                .map(|(case, body)| {
+                    DumpNode::new("Case").at(case.token.get_position())
+                        .field("pattern", dump_match_case_type(&case.match_type))
+                        .field("binding", case.case_binding.as_ref().map(Token::get_ident_name))
+                        .field("body", dump_ast_nodes(body))
+                })
+                .collect_vec();
+            DumpNode::new(kind).at(position)
+                .field("target", dump_ast_node(&n.target))
+                .field("cases", cases)
+        }
+        AstNode::ReturnStatement(_, expr) => DumpNode::new("Return").at(position).field("expr", expr.as_ref().map(|expr| dump_ast_node(expr))),
+        AstNode::ImportStatement(token, n) => dump_import(token, n),
+    }
+}
+
+fn dump_ast_param((ident, type_ident, is_variadic, default_value): &(Token, Option<TypeIdentifier>, bool, Option<AstNode>)) -> DumpNode {
+    DumpNode::new("Param").at(ident.get_position())
+        .field("name", Token::get_ident_name(ident))
+        .field("typeAnnotation", type_ident.as_ref().map(type_identifier_repr))
+        .field("isVariadic", *is_variadic)
+        .field("defaultValue", default_value.as_ref().map(dump_ast_node))
+}
+
+fn dump_decorator(decorator: &DecoratorNode) -> DumpNode {
+    let args = decorator.args.iter()
+        .map(|(label, arg)| DumpNode::new("Argument").field("label", label.as_ref().map(Token::get_ident_name)).field("value", dump_ast_node(arg)))
+        .collect_vec();
+    DumpNode::new("Decorator").at(decorator.at_token.get_position())
+        .field("name", Token::get_ident_name(&decorator.name))
+        .field("args", args)
+}
+
+fn dump_import(token: &Token, import_node: &ImportNode) -> DumpNode {
+    let node = DumpNode::new("Import").at(token.get_position()).field("module", module_id_repr(&import_node.module_id));
+    match &import_node.kind {
+        ImportKind::ImportAll(_) => node.field("importAll", true),
+        ImportKind::ImportList(names) => node.field("names", names.iter().map(Token::get_ident_name).collect_vec()),
+        ImportKind::Alias(alias) => node.field("alias", Token::get_ident_name(alias)),
+    }
+}
+
+fn dump_binding_pattern(pattern: &BindingPattern) -> DumpNode {
+    let position = pattern.get_token().get_position();
+    match pattern {
+        BindingPattern::Variable(ident) => DumpNode::new("VariablePattern").at(position).field("name", Token::get_ident_name(ident)),
+        BindingPattern::Tuple(_, patterns) => DumpNode::new("TuplePattern").at(position).field("patterns", patterns.iter().map(dump_binding_pattern).collect_vec()),
+        BindingPattern::Array(_, patterns, is_string) => {
+            let patterns = patterns.iter()
+                .map(|(pattern, is_splat)| dump_binding_pattern(pattern).field("isSplat", *is_splat))
+                .collect_vec();
+            DumpNode::new("ArrayPattern").at(position).field("patterns", patterns).field("isString", *is_string)
+        }
+    }
+}
+
+fn dump_match_case_type(match_type: &MatchCaseType) -> DumpNode {
+    let dump_args = |args: &Option<Vec<MatchCaseArgument>>| {
+        args.as_ref().map(|args| {
+            args.iter()
+                .map(|arg| match arg {
+                    MatchCaseArgument::Pattern(pattern) => dump_binding_pattern(pattern),
+                    MatchCaseArgument::Literal(node) => dump_ast_node(node),
+                })
+                .collect_vec()
+        })
+    };
+
+    match match_type {
+        MatchCaseType::None(token) => DumpNode::new("NoneCase").at(token.get_position()),
+        MatchCaseType::Wildcard(token) => DumpNode::new("WildcardCase").at(token.get_position()),
+        MatchCaseType::Ident(ident, args) => DumpNode::new("TypeCase").at(ident.get_position())
+            .field("name", Token::get_ident_name(ident))
+            .field("args", dump_args(args)),
+        MatchCaseType::Compound(idents, args) => DumpNode::new("TypeCase").at(idents[0].get_position())
+            .field("name", idents.iter().map(Token::get_ident_name).join("."))
+            .field("args", dump_args(args)),
+        MatchCaseType::Constant(node) => DumpNode::new("ConstantCase").at(node.get_token().get_position()).field("value", dump_ast_node(node)),
+        MatchCaseType::Tuple(token, items) => DumpNode::new("TupleCase").at(token.get_position()).field("items", dump_ast_nodes(items)),
+    }
+}
+
+fn dump_indexing_mode<T, F: Fn(&T) -> DumpNode>(node: DumpNode, index: &IndexingMode<T>, dump: F) -> DumpNode {
+    match index {
+        IndexingMode::Index(idx) => node.field("index", dump(idx)),
+        IndexingMode::Range(start, end) => node
+            .field("rangeStart", start.as_ref().map(|n| dump(n)))
+            .field("rangeEnd", end.as_ref().map(|n| dump(n))),
+    }
+}
+
+fn unary_op_repr(op: &UnaryOp) -> &'static str {
+    match op {
+        UnaryOp::Minus => "-",
+        UnaryOp::Negate => "!",
+    }
+}
+
+fn module_id_repr(module_id: &ModuleId) -> String {
+    module_id.get_path("")
+}
+
+// Renders a type annotation the way it would be written in source
+pub fn type_identifier_repr(type_ident: &TypeIdentifier) -> String {
+    match type_ident {
+        TypeIdentifier::Normal { ident, type_args } => match type_args {
+            Some(type_args) => format!("{}<{}>", Token::get_ident_name(ident), type_args.iter().map(type_identifier_repr).join(", ")),
+            None => Token::get_ident_name(ident),
+        },
+        TypeIdentifier::Array { inner } => format!("{}[]", type_identifier_repr(inner)),
+        TypeIdentifier::Tuple { types } => format!("({})", types.iter().map(type_identifier_repr).join(", ")),
+        TypeIdentifier::Option { inner } => format!("{}?", type_identifier_repr(inner)),
+        TypeIdentifier::Union { left, right } => format!("{} | {}", type_identifier_repr(left), type_identifier_repr(right)),
+        TypeIdentifier::Func { args, ret } => format!("({}) => {}", args.iter().map(type_identifier_repr).join(", "), type_identifier_repr(ret)),
+    }
+}
+
+// Typed AST
+
+// Dumps the typed code of a module, with each expression's type rendered via `Project::type_repr`. Function, type and
+// enum declarations are expanded in place, so their bodies and methods are included.
+pub fn dump_typed_module(project: &Project, module: &TypedModule) -> Vec<DumpNode> {
+    let dumper = TypedDumper { project };
+    dumper.nodes(&module.code)
+}
+
+struct TypedDumper<'a> {
+    project: &'a Project,
+}
+
+impl<'a> TypedDumper<'a> {
+    fn nodes(&self, nodes: &Vec<TypedNode>) -> Vec<DumpNode> {
+        nodes.iter().map(|node| self.node(node)).collect()
+    }
+
+    fn type_repr(&self, type_id: &TypeId) -> String {
+        self.project.type_repr(type_id)
+    }
+
+    fn var_name(&self, var_id: &VarId) -> String {
+        self.project.get_var_by_id(var_id).name.clone()
+    }
+
+    fn expr(&self, kind: &str, node: &TypedNode) -> DumpNode {
+        DumpNode::new(kind).spanning(node.span()).field("type", self.type_repr(node.type_id()))
+    }
+
+    fn node(&self, node: &TypedNode) -> DumpNode {
+        match node {
+            TypedNode::Literal { value, .. } => {
+                let value = match value {
+                    TypedLiteral::Int(i) => DumpValue::Int(*i),
+                    TypedLiteral::Float(f) => DumpValue::Float(*f),
+                    TypedLiteral::Bool(b) => DumpValue::Bool(*b),
+                    TypedLiteral::String(s) => DumpValue::Str(s.clone()),
+                };
+                self.expr("Literal", node).field("value", value)
+            }
+            TypedNode::Unary { op, expr, .. } => self.expr("Unary", node).field("op", unary_op_repr(op)).field("expr", self.node(expr)),
+            TypedNode::Binary { op, left, right, .. } => self.expr("Binary", node)
+                .field("op", op.repr())
+                .field("left", self.node(left))
+                .field("right", self.node(right)),
+            TypedNode::Grouped { expr, .. } => self.expr("Grouped", node).field("expr", self.node(expr)),
+            TypedNode::Array { items, .. } => self.expr("Array", node).field("items", self.nodes(items)),
+            TypedNode::Tuple { items, .. } => self.expr("Tuple", node).field("items", self.nodes(items)),
+            TypedNode::Set { items, .. } => self.expr("Set", node).field("items", self.nodes(items)),
+            TypedNode::Map { items, .. } => {
+                let entries = items.iter()
+                    .map(|(key, value)| DumpNode::new("MapEntry").field("key", self.node(key)).field("value", self.node(value)))
+                    .collect_vec();
+                self.expr("Map", node).field("entries", entries)
+            }
+            TypedNode::Identifier { var_id, type_arg_ids, .. } => self.expr("Identifier", node)
+                .field("name", self.var_name(var_id))
+                .field("typeArgs", type_arg_ids.iter().map(|(type_id, _)| self.type_repr(type_id)).collect_vec()),
+            TypedNode::NoneValue { .. } => self.expr("None", node),
+            TypedNode::Invocation { target, arguments, type_arg_ids, .. } => {
+                let args = arguments.iter()
+                    .map(|arg| arg.as_ref().map(|arg| self.node(arg)))
+                    .collect_vec();
+                self.expr("Invocation", node)
+                    .field("target", self.node(target))
+                    .field("typeArgs", type_arg_ids.iter().map(|type_id| self.type_repr(type_id)).collect_vec())
+                    .field("args", args)
+            }
+            TypedNode::Accessor { target, kind, is_opt_safe, member_idx, .. } => self.expr("Accessor", node)
+                .field("target", self.node(target))
+                .field("member", self.member_name(target.type_id(), kind, *member_idx, *is_opt_safe))
+                .field("accessorKind", accessor_kind_repr(kind))
+                .field("isOptSafe", *is_opt_safe),
+            TypedNode::Indexing { target, index, .. } => dump_indexing_mode(self.expr("Indexing", node).field("target", self.node(target)), index, |n| self.node(n)),
+            TypedNode::Lambda { func_id, .. } => {
+                let func = self.project.get_func_by_id(func_id);
+                self.expr("Lambda", node)
+                    .field("params", func.params.iter().map(|param| self.param(param)).collect_vec())
+                    .field("returnType", self.type_repr(&func.return_type_id))
+                    .field("body", self.nodes(&func.body))
+            }
+            TypedNode::Assignment { kind, expr, .. } => {
+                let target = match kind {
+                    AssignmentKind::Identifier { var_id } => DumpNode::new("Identifier").field("name", self.var_name(var_id)),
+                    AssignmentKind::Accessor { target, kind, member_idx } => DumpNode::new("Accessor")
+                        .field("target", self.node(target))
+                        .field("member", self.member_name(target.type_id(), kind, *member_idx, false)),
+                    AssignmentKind::Indexing { target, index } => DumpNode::new("Indexing")
+                        .field("target", self.node(target))
+                        .field("index", self.node(index)),
+                };
+                self.expr("Assignment", node).field("target", target).field("expr", self.node(expr))
+            }
+            TypedNode::If { condition, condition_binding, if_block, else_block, is_statement, .. } => self.expr("If", node)
+                .field("isStatement", *is_statement)
+                .field("condition", self.node(condition))
+                .field("conditionBinding", condition_binding.as_ref().map(|(pattern, var_ids)| self.pattern(pattern, var_ids)))
+                .field("ifBlock", self.nodes(if_block))
+                .field("elseBlock", self.nodes(else_block)),
+            TypedNode::Match { target, cases, is_statement, .. } => {
+                let cases = cases.iter()
+                    .map(|case| {
+                        DumpNode::new("Case")
+                            .field("pattern", self.match_case_kind(&case.kind))
+                            .field("binding", case.case_binding.as_ref().map(|var_id| self.var_name(var_id)))
+                            .field("body", self.nodes(&case.body))
+                    })
+                    .collect_vec();
+                self.expr("Match", node)
+                    .field("isStatement", *is_statement)
+                    .field("target", self.node(target))
+                    .field("cases", cases)
+            }
+            TypedNode::FuncDeclaration(func_id) => self.function("FunctionDecl", self.project.get_func_by_id(func_id)),
+            TypedNode::TypeDeclaration(struct_id) => {
+                let struct_ = self.project.get_struct_by_id(struct_id);
+                let fields = struct_.fields.iter()
+                    .map(|field| {
+                        DumpNode::new("Field").spanning(field.defined_span.range.clone())
+                            .field("name", &field.name)
+                            .field("type", self.type_repr(&field.type_id))
+                            .field("isReadonly", field.is_readonly)
+                            .field("defaultValue", field.default_value.as_ref().map(|n| self.node(n)))
+                    })
+                    .collect_vec();
+                let node = DumpNode::new("TypeDecl")
+                    .field("name", &struct_.name)
+                    .field("typeArgs", struct_.generic_ids.iter().map(|type_id| self.type_repr(type_id)).collect_vec())
+                    .field("fields", fields)
+                    .field("methods", self.methods(&struct_.static_methods, &struct_.methods));
+                match &struct_.defined_span {
+                    Some(span) => node.spanning(span.range.clone()),
+                    None => node,
+                }
+            }
+            TypedNode::EnumDeclaration(enum_id) => {
+                let enum_ = self.project.get_enum_by_id(enum_id);
+                let variants = enum_.variants.iter()
+                    .map(|variant| {
+                        let params = match &variant.kind {
+                            EnumVariantKind::Constant => None,
+                            EnumVariantKind::Container(func_id) => {
+                                Some(self.project.get_func_by_id(func_id).params.iter().map(|param| self.param(param)).collect_vec())
+                            }
+                        };
+                        DumpNode::new("Variant").spanning(variant.defined_span.range.clone())
+                            .field("name", &variant.name)
+                            .field("params", params)
+                    })
+                    .collect_vec();
+                DumpNode::new("EnumDecl").spanning(enum_.defined_span.range.clone())
+                    .field("name", &enum_.name)
+                    .field("typeArgs", enum_.generic_ids.iter().map(|type_id| self.type_repr(type_id)).collect_vec())
+                    .field("variants", variants)
+                    .field("methods", self.methods(&enum_.static_methods, &enum_.methods))
+            }
+            TypedNode::BindingDeclaration { is_exported, pattern, vars, expr, .. } => DumpNode::new("BindingDecl").spanning(node.span())
+                .field("isExported", *is_exported)
+                .field("pattern", self.pattern(pattern, vars))
+                .field("expr", expr.as_ref().map(|expr| self.node(expr))),
+            TypedNode::ForLoop { binding, binding_var_ids, index_var_id, iterator, body, .. } => DumpNode::new("ForLoop").spanning(node.span())
+                .field("binding", self.pattern(binding, binding_var_ids))
+                .field("index", index_var_id.as_ref().map(|var_id| self.var_name(var_id)))
+                .field("iterator", self.node(iterator))
+                .field("body", self.nodes(body)),
+            TypedNode::WhileLoop { condition, condition_var_id, body, .. } => DumpNode::new("WhileLoop").spanning(node.span())
+                .field("condition", self.node(condition))
+                .field("conditionBinding", condition_var_id.as_ref().map(|var_id| self.var_name(var_id)))
+                .field("body", self.nodes(body)),
+            TypedNode::Break { .. } => DumpNode::new("Break").spanning(node.span()),
+            TypedNode::Continue { .. } => DumpNode::new("Continue").spanning(node.span()),
+            TypedNode::Return { expr, .. } => DumpNode::new("Return").spanning(node.span()).field("expr", expr.as_ref().map(|expr| self.node(expr))),
+        }
+    }
+
+    fn function(&self, kind: &str, func: &Function) -> DumpNode {
+        let node = DumpNode::new(kind)
+            .field("name", &func.name)
+            .field("typeArgs", func.generic_ids.iter().map(|type_id| self.type_repr(type_id)).collect_vec())
+            .field("params", func.params.iter().map(|param| self.param(param)).collect_vec())
+            .field("returnType", self.type_repr(&func.return_type_id))
+            .field("body", self.nodes(&func.body));
+        match &func.defined_span {
+            Some(span) => node.spanning(span.range.clone()),
+            None => node,
+        }
+    }
+
+    fn methods(&self, static_methods: &Vec<FuncId>, methods: &Vec<FuncId>) -> Vec<DumpNode> {
+        static_methods.iter()
+            .chain(methods.iter())
+            .map(|func_id| self.project.get_func_by_id(func_id))
+            // Skip generated methods (eg. `toString`), which have no source
+            .filter(|func| func.defined_span.is_some())
+            .map(|func| self.function("FunctionDecl", func))
+            .collect()
+    }
+
+    fn param(&self, param: &FunctionParam) -> DumpNode {
+        let node = DumpNode::new("Param")
+            .field("name", &param.name)
+            .field("type", self.type_repr(&param.type_id))
+            .field("isVariadic", param.is_variadic)
+            .field("defaultValue", param.default_value.as_ref().map(|n| self.node(n)));
+        match &param.defined_span {
+            Some(span) => node.spanning(span.range.clone()),
+            None => node,
+        }
+    }
+
+    // Binding patterns are annotated with the types of the variables they introduce, which are listed in the order the
+    // pattern's identifiers appear
+    fn pattern(&self, pattern: &BindingPattern, var_ids: &Vec<VarId>) -> DumpNode {
+        let vars = var_ids.iter()
+            .map(|var_id| {
+                let var = self.project.get_var_by_id(var_id);
+                DumpNode::new("Variable").field("name", &var.name).field("type", self.type_repr(&var.type_id))
+            })
+            .collect_vec();
+        dump_binding_pattern(pattern).field("vars", vars)
+    }
+
+    fn match_case_kind(&self, kind: &TypedMatchCaseKind) -> DumpNode {
+        match kind {
+            TypedMatchCaseKind::None => DumpNode::new("NoneCase"),
+            TypedMatchCaseKind::Wildcard(type_id) => DumpNode::new("WildcardCase").field("type", self.type_repr(type_id)),
+            TypedMatchCaseKind::Type(type_id, args) => {
+                let args = args.iter()
+                    .map(|arg| match arg {
+                        TypedMatchCaseArgument::Pattern(pattern, var_ids) => self.pattern(pattern, var_ids),
+                        TypedMatchCaseArgument::Literal(node) => self.node(node),
+                    })
+                    .collect_vec();
+                DumpNode::new("TypeCase").field("type", self.type_repr(type_id)).field("args", args)
+            }
+            TypedMatchCaseKind::Constant(type_id, node) => DumpNode::new("ConstantCase").field("type", self.type_repr(type_id)).field("value", self.node(node)),
+        }
+    }
+
+    fn member_name(&self, target_type_id: &TypeId, kind: &AccessorKind, member_idx: usize, is_opt_safe: bool) -> Option<String> {
+        // Opt-safe accessors (ie. `a?.b`) access members of the Option's inner type
+        let target_type_id = if is_opt_safe { self.project.type_is_option(target_type_id).unwrap_or(*target_type_id) } else { *target_type_id };
+        let target_type = self.project.get_type_by_id(&target_type_id);
+
+        match kind {
+            AccessorKind::Field => target_type.get_field(self.project, member_idx).map(|field| field.name.clone()),
+            AccessorKind::Method => target_type.get_method(self.project, member_idx).map(|func_id| self.project.get_func_by_id(&func_id).name.clone()),
+            AccessorKind::StaticMethod => target_type.get_static_method(self.project, member_idx).map(|func_id| self.project.get_func_by_id(&func_id).name.clone()),
+            AccessorKind::EnumVariant => match target_type {
+                Type::Type(TypeKind::Enum(enum_id)) => self.project.get_enum_by_id(enum_id).variants.get(member_idx).map(|v| v.name.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+fn accessor_kind_repr(kind: &AccessorKind) -> &'static str {
+    match kind {
+        AccessorKind::Field => "field",
+        AccessorKind::Method => "method",
+        AccessorKind::StaticMethod => "staticMethod",
+        AccessorKind::EnumVariant => "enumVariant",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::dump::{dump_ast, dump_tokens, dump_typed_module, nodes_to_tree};
+    use crate::lexer::lexer::tokenize;
+    use crate::parser::ast::ModuleId;
+    use crate::parser::parser::parse;
+    use crate::typechecker::test_helpers::typecheck_main;
+
+    #[test]
+    fn test_dump_tokens() {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let tokens = tokenize(&module_id, &"val x = 1 + \"a\"".to_string()).unwrap();
+        let dump = dump_tokens(&tokens);
+
+        let expected = "\
+Val [1:1]
+Ident [1:5] value=\"x\"
+Assign [1:7]
+Int [1:9] value=1
+Plus [1:11]
+String [1:13] value=\"a\"
+";
+        assert_eq!(expected, nodes_to_tree(&dump));
+
+        let expected = json!({ "kind": "Ident", "position": [1, 5], "value": "x" });
+        assert_eq!(expected, dump[1].to_json());
+    }
+
+    #[test]
+    fn test_dump_ast() {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let source = "import Foo from \"./foo\"\nfunc f(a: Int[], b = 2): Int? = a[0]\nf(a: [1])".to_string();
+        let parse_result = parse(module_id.clone(), tokenize(&module_id, &source).unwrap()).unwrap();
+        let dump = dump_ast(&parse_result);
+
+        let expected = "\
+Import [1:1] module=\"./foo\"
+  names:
+    - \"Foo\"
+FunctionDecl [2:1] name=\"f\" decorators=[] isExported=false typeArgs=[] returnType=\"Int?\"
+  params:
+    - Param [2:8] name=\"a\" typeAnnotation=\"Int[]\" isVariadic=false defaultValue=null
+    - Param [2:18] name=\"b\" typeAnnotation=null isVariadic=false
+      defaultValue: Literal [2:22] value=2
+  body:
+    - Indexing [2:34]
+      target: Identifier [2:33] name=\"a\" typeArgs=null
+      index: Literal [2:35] value=0
+Invocation [3:2]
+  target: Identifier [3:1] name=\"f\" typeArgs=null
+  args:
+    - Argument label=\"a\"
+      value: Array [3:6]
+        items:
+          - Literal [3:7] value=1
+";
+        assert_eq!(expected, nodes_to_tree(&dump));
+
+        let expected = json!({
+            "kind": "Identifier",
+            "position": [3, 1],
+            "name": "f",
+            "typeArgs": null,
+        });
+        assert_eq!(expected, dump[2].to_json()["target"]);
+    }
+
+    #[test]
+    fn test_dump_typed_ast() {
+        let (_, project, result) = typecheck_main(&[("main.abra", "type P { x: Int }\nval p = P(x: 1)\nval y = [p.x, 2]")]);
+        let module_id = result.unwrap();
+        let dump = dump_typed_module(&project, &project.modules[module_id.0]);
+
+        let expected = json!({
+            "kind": "BindingDecl",
+            "span": { "start": [3, 1], "end": [3, 15] },
+            "isExported": false,
+            "pattern": {
+                "kind": "VariablePattern",
+                "position": [3, 5],
+                "name": "y",
+                "vars": [{ "kind": "Variable", "name": "y", "type": "Int[]" }],
+            },
+            "expr": {
+                "kind": "Array",
+                "span": { "start": [3, 9], "end": [3, 15] },
+                "type": "Int[]",
+                "items": [
+                    {
+                        "kind": "Accessor",
+                        "span": { "start": [3, 10], "end": [3, 12] },
+                        "type": "Int",
+                        "target": {
+                            "kind": "Identifier",
+                            "span": { "start": [3, 10], "end": [3, 10] },
+                            "type": "P",
+                            "name": "p",
+                            "typeArgs": [],
+                        },
+                        "member": "x",
+                        "accessorKind": "field",
+                        "isOptSafe": false,
+                    },
+                    { "kind": "Literal", "span": { "start": [3, 15], "end": [3, 15] }, "type": "Int", "value": 2 },
+                ],
+            },
+        });
+        assert_eq!(expected, dump[2].to_json());
+
+        let expected = "\
+TypeDecl [1:6-1:6] name=\"P\" typeArgs=[] methods=[]
+  fields:
+    - Field [1:10-1:10] name=\"x\" type=\"Int\" isReadonly=false defaultValue=null
+";
+        assert_eq!(expected, dump[0].to_tree());
+    }
+}
@@ -0,0 +1,931 @@
+use crate::Error;
+use crate::lexer::lexer::tokenize_with_comments;
+use crate::lexer::tokens::{Comment, CommentKind, Position, Token};
+use crate::parser::ast::{AstNode, BindingPattern, DecoratorNode, FunctionDeclNode, IfNode, ImportKind, IndexingMode, MatchCase, MatchCaseArgument, MatchCaseType, MatchNode, ModuleId, TypeDeclField, TypeIdentifier, UnaryOp};
+use crate::parser::cst::TokenSpans;
+use crate::parser::parser::parse;
+
+pub(crate) const INDENT: &str = "  ";
+
+type Param = (Token, Option<TypeIdentifier>, bool, Option<AstNode>);
+
+// Pretty-prints a module into its canonical layout. The only layout decisions taken from the source are which constructs span
+// multiple lines: a list whose first item is on a new line is printed one item per line, and a block, match or type body is kept
+// on one line only if it was written on one line. Spacing, indentation and commas are all normalized, and comments are re-emitted
+// in their original order (trailing comments stay at the end of their line). Formatting already-formatted source is a no-op.
+pub fn format_source(module_id: &ModuleId, source: &String) -> Result<String, Error> {
+    let (tokens, comments) = tokenize_with_comments(module_id, source).map_err(Error::LexerError)?;
+    let parse_result = parse(module_id.clone(), tokens.clone()).map_err(Error::ParseError)?;
+
+    let mut formatter = Formatter::new(source, tokens, comments);
+    formatter.format_items(&parse_result.nodes);
+    Ok(formatter.finish())
+}
+
+enum Member<'a> {
+    Field(&'a TypeDeclField),
+    Variant(&'a (Token, Option<Vec<Param>>)),
+    Method(&'a AstNode),
+}
+
+struct Formatter {
+    chars: Vec<char>,
+    line_starts: Vec<usize>,
+    spans: TokenSpans,
+    // The sorted start offsets of every token and comment, used to recover the original text of literals
+    boundaries: Vec<usize>,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    out: String,
+    indent: usize,
+    can_append: bool,
+    is_first_in_container: bool,
+    last_source_line: usize,
+    // Whether the node being formatted is the target of an accessor, invocation or indexing (ie. not the root of a chain)
+    in_chain: bool,
+}
+
+impl Formatter {
+    fn new(source: &String, tokens: Vec<Token>, comments: Vec<Comment>) -> Self {
+        let chars = source.chars().collect::<Vec<_>>();
+        let mut line_starts = vec![0];
+        for (idx, ch) in chars.iter().enumerate() {
+            if *ch == '\n' { line_starts.push(idx + 1); }
+        }
+
+        let offset = |pos: &Position| line_starts[pos.line - 1] + pos.col - 1;
+        let mut boundaries = tokens.iter().map(|t| offset(&t.get_position()))
+            .chain(comments.iter().map(|c| offset(&c.position)))
+            .collect::<Vec<_>>();
+        boundaries.sort();
+
+        Formatter {
+            chars,
+            line_starts,
+            spans: TokenSpans::new(tokens),
+            boundaries,
+            comments,
+            next_comment: 0,
+            out: String::new(),
+            indent: 0,
+            can_append: false,
+            is_first_in_container: true,
+            last_source_line: 0,
+            in_chain: false,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush_comments(&Position::new(usize::MAX, usize::MAX));
+
+        let len = self.out.trim_end().len();
+        self.out.truncate(len);
+        if !self.out.is_empty() { self.out.push('\n'); }
+        self.out
+    }
+
+    // Source helpers
+
+    fn offset(&self, pos: &Position) -> usize {
+        self.line_starts[pos.line - 1] + pos.col - 1
+    }
+
+    // The token's text as written in the source, which preserves the original spelling of number and string literals
+    fn text(&self, idx: usize) -> String {
+        let start = self.offset(&self.spans.pos(idx));
+        let next = self.boundaries.partition_point(|b| *b <= start);
+        let end = self.boundaries.get(next).copied().unwrap_or(self.chars.len());
+        self.chars[start..end].iter().collect::<String>().trim_end().to_string()
+    }
+
+    fn token_text(&self, token: &Token) -> String {
+        self.text(self.spans.idx(token))
+    }
+
+    fn is_blank_line(&self, line: usize) -> bool {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).copied().unwrap_or(self.chars.len());
+        self.chars[start..end].iter().all(|ch| ch.is_whitespace())
+    }
+
+    fn has_code_before(&self, pos: &Position) -> bool {
+        let start = self.line_starts[pos.line - 1];
+        self.chars[start..self.offset(pos)].iter().any(|ch| !ch.is_whitespace())
+    }
+
+    fn has_comments_between(&self, start_idx: usize, end_idx: usize) -> bool {
+        let start = self.spans.pos(start_idx);
+        let end = self.spans.pos(end_idx);
+        self.comments.iter().any(|c| c.position > start && c.position < end)
+    }
+
+    // Whether a delimited body should be printed across multiple lines; an empty body is only kept open if it contains comments
+    fn is_multiline(&self, open_idx: usize, close_idx: usize, is_empty: bool) -> bool {
+        !self.spans.is_same_line(open_idx, close_idx) && (!is_empty || self.has_comments_between(open_idx, close_idx))
+    }
+
+    // Output helpers
+
+    fn write<S: AsRef<str>>(&mut self, s: S) {
+        self.out.push_str(s.as_ref());
+        self.can_append = true;
+    }
+
+    fn newline(&mut self) {
+        let len = self.out.trim_end_matches(' ').len();
+        self.out.truncate(len);
+        if !self.out.is_empty() { self.out.push('\n'); }
+        self.out.push_str(&INDENT.repeat(self.indent));
+    }
+
+    // Begins a new output line for something which started on `line` in the source; a blank line before it is preserved, unless
+    // it's the first thing in its container
+    fn start_line(&mut self, line: usize) {
+        if !self.is_first_in_container && line != self.last_source_line && line > 1 && self.is_blank_line(line - 1) {
+            self.newline();
+        }
+        self.newline();
+        self.is_first_in_container = false;
+        self.last_source_line = line;
+    }
+
+    fn flush_comments(&mut self, before: &Position) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.position >= *before { break; }
+            let comment = comment.clone();
+            self.next_comment += 1;
+
+            if self.can_append && self.has_code_before(&comment.position) {
+                self.out.push(' ');
+                self.out.push_str(&comment.text);
+                if comment.kind == CommentKind::Line { self.can_append = false; }
+            } else {
+                self.start_line(comment.position.line);
+                self.out.push_str(&comment.text);
+                self.can_append = false;
+            }
+        }
+    }
+
+    // Block comments which sit within a construct printed on one line are kept inline, provided no earlier comment is still pending
+    fn write_inline_comments(&mut self, after: &Position, before: &Position) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.kind != CommentKind::Block || comment.position <= *after || comment.position >= *before { break; }
+            let text = format!("{} ", comment.text);
+            self.next_comment += 1;
+            self.write(text);
+        }
+    }
+
+    fn start_item(&mut self, pos: &Position) {
+        self.flush_comments(pos);
+        self.start_line(pos.line);
+    }
+
+    fn open_multiline<S: AsRef<str>>(&mut self, open: S) {
+        self.write(open);
+        self.indent += 1;
+        self.is_first_in_container = true;
+    }
+
+    fn close_multiline<S: AsRef<str>>(&mut self, close_idx: usize, close: S) {
+        self.flush_comments(&self.spans.pos(close_idx));
+        self.indent -= 1;
+        self.newline();
+        self.write(close);
+        self.is_first_in_container = false;
+    }
+
+    fn format_list<T>(
+        &mut self,
+        open_idx: usize,
+        delims: (&str, &str),
+        is_padded: bool,
+        items: &[T],
+        trailing_comma: bool,
+        item_start: fn(&Self, &T) -> Position,
+        format_item: fn(&mut Self, &T),
+    ) {
+        let close_idx = self.spans.matching(open_idx);
+        let (open, close) = delims;
+        let is_multiline = match items.first() {
+            Some(item) => item_start(self, item).line > self.spans.pos(open_idx).line,
+            None => self.is_multiline(open_idx, close_idx, true),
+        };
+
+        if !is_multiline {
+            self.write(open);
+            if is_padded && !items.is_empty() { self.write(" "); }
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 { self.write(", "); }
+                format_item(self, item);
+            }
+            if is_padded && !items.is_empty() { self.write(" "); }
+            self.write(close);
+            return;
+        }
+
+        self.open_multiline(open);
+        for (idx, item) in items.iter().enumerate() {
+            let start = item_start(self, item);
+            self.start_item(&start);
+            format_item(self, item);
+            if trailing_comma || idx < items.len() - 1 { self.write(","); }
+        }
+        self.close_multiline(close_idx, close);
+    }
+
+    // Nodes
+
+    fn format_items(&mut self, nodes: &[AstNode]) {
+        for node in nodes {
+            let start = self.spans.start_pos(node);
+            self.start_item(&start);
+            self.format_node(node);
+        }
+    }
+
+    fn format_node(&mut self, node: &AstNode) {
+        // A chain of accessors which is broken across lines is indented one level deeper, for the whole of the chain
+        let in_chain = std::mem::replace(&mut self.in_chain, false);
+        let is_indented = !in_chain && self.chain_has_break(node);
+        if is_indented { self.indent += 1; }
+        self.format_node_inner(node);
+        if is_indented { self.indent -= 1; }
+    }
+
+    fn chain_has_break(&self, node: &AstNode) -> bool {
+        match node {
+            AstNode::Accessor(token, n) => !self.spans.is_same_line(self.spans.end_idx(&n.target), self.spans.idx(token)) || self.chain_has_break(&n.target),
+            AstNode::Invocation(token, n) if !self.spans.is_interpolation(self.spans.idx(token)) => self.chain_has_break(&n.target),
+            AstNode::Indexing(_, n) => self.chain_has_break(&n.target),
+            _ => false,
+        }
+    }
+
+    fn format_chain_target(&mut self, target: &AstNode) {
+        self.in_chain = true;
+        self.format_node(target);
+    }
+
+    fn format_node_inner(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Literal(token, _) => self.write(self.token_text(token)),
+            AstNode::Unary(_, n) => {
+                self.write(match n.op { UnaryOp::Minus => "-", UnaryOp::Negate => "!" });
+                self.format_node(&n.expr);
+            }
+            AstNode::Binary(token, n) => {
+                self.format_node(&n.left);
+
+                // Line breaks on either side of the operator are preserved
+                let op_idx = self.spans.idx(token);
+                let right_start = self.spans.start_pos(&n.right);
+                if !self.spans.is_same_line(self.spans.end_idx(&n.left), op_idx) {
+                    self.indent += 1;
+                    self.start_item(&self.spans.pos(op_idx));
+                    self.write(format!("{} ", n.op.repr()));
+                    self.format_node(&n.right);
+                    self.indent -= 1;
+                } else if right_start.line > self.spans.pos(op_idx).line {
+                    self.write(format!(" {}", n.op.repr()));
+                    self.indent += 1;
+                    self.start_item(&right_start);
+                    self.format_node(&n.right);
+                    self.indent -= 1;
+                } else {
+                    self.write(format!(" {} ", n.op.repr()));
+                    self.format_node(&n.right);
+                }
+            }
+            AstNode::Grouped(_, n) => {
+                self.write("(");
+                self.format_node(&n.expr);
+                self.write(")");
+            }
+            AstNode::Array(token, n) => self.format_list(self.spans.idx(token), ("[", "]"), false, &n.items, true, Self::node_start, Self::format_node),
+            AstNode::Set(token, n) => self.format_list(self.spans.idx(token), ("#{", "}"), false, &n.items, true, Self::node_start, Self::format_node),
+            AstNode::Map(token, n) => self.format_list(self.spans.idx(token), ("{", "}"), true, &n.items, true, Self::map_item_start, Self::format_map_item),
+            AstNode::Tuple(token, items) => self.format_list(self.spans.idx(token), ("(", ")"), false, items, false, Self::node_start, Self::format_node),
+            AstNode::BindingDecl(_, n) => {
+                self.format_decorators(&n.decorators);
+                if n.export_token.is_some() { self.write("export "); }
+                self.write(if n.is_mutable { "var " } else { "val " });
+                self.format_pattern(&n.binding);
+                if let Some(type_ann) = &n.type_ann {
+                    self.write(format!(": {}", self.type_text(type_ann)));
+                }
+                if let Some(expr) = &n.expr {
+                    self.write(" = ");
+                    self.format_node(expr);
+                }
+            }
+            AstNode::FunctionDecl(_, n) => self.format_func_decl(n),
+            AstNode::TypeDecl(_, n) => {
+                self.format_decorators(&n.decorators);
+                if n.export_token.is_some() { self.write("export "); }
+                self.write("type ");
+                self.format_type_decl_name(&n.name, &n.type_args);
+
+                let members = n.fields.iter().map(Member::Field)
+                    .chain(n.methods.iter().map(Member::Method))
+                    .collect();
+                self.format_type_decl_body(self.spans.skip_type_args(self.spans.idx(&n.name) + 1), members);
+            }
+            AstNode::EnumDecl(_, n) => {
+                self.format_decorators(&n.decorators);
+                if n.export_token.is_some() { self.write("export "); }
+                self.write("enum ");
+                self.format_type_decl_name(&n.name, &n.type_args);
+
+                let members = n.variants.iter().map(Member::Variant)
+                    .chain(n.methods.iter().map(Member::Method))
+                    .collect();
+                self.format_type_decl_body(self.spans.skip_type_args(self.spans.idx(&n.name) + 1), members);
+            }
+            AstNode::Identifier(token, type_args) => {
+                self.write(self.token_text(token));
+                if let Some(type_args) = type_args {
+                    let type_args = type_args.iter().map(|t| self.type_text(t)).collect::<Vec<_>>();
+                    self.write(format!("<{}>", type_args.join(", ")));
+                }
+            }
+            AstNode::Assignment(_, n) => {
+                self.format_node(&n.target);
+                self.write(" = ");
+                self.format_node(&n.expr);
+            }
+            AstNode::Indexing(_, n) => {
+                self.format_chain_target(&n.target);
+                self.write("[");
+                match &n.index {
+                    IndexingMode::Index(idx) => self.format_node(idx),
+                    IndexingMode::Range(start, end) => {
+                        if let Some(start) = start { self.format_node(start); }
+                        self.write(":");
+                        if let Some(end) = end { self.format_node(end); }
+                    }
+                }
+                self.write("]");
+            }
+            AstNode::IfStatement(_, n) | AstNode::IfExpression(_, n) => self.format_if(n),
+            AstNode::Invocation(token, n) => {
+                let lparen_idx = self.spans.idx(token);
+                if self.spans.is_interpolation(lparen_idx) {
+                    self.write(self.text(lparen_idx));
+                    return;
+                }
+
+                match &*n.target {
+                    // `Some(x)` is desugared into `Option.Some(x)` by the parser
+                    AstNode::Accessor(Token::Ident(_, name), _) => self.write(name),
+                    target => self.format_chain_target(target),
+                }
+                self.format_list(lparen_idx, ("(", ")"), false, &n.args, true, Self::arg_start, Self::format_arg);
+            }
+            AstNode::ForLoop(_, n) => {
+                self.write("for ");
+                self.format_pattern(&n.binding);
+                if let Some(index_ident) = &n.index_ident {
+                    self.write(format!(", {}", self.token_text(index_ident)));
+                }
+                self.write(" in ");
+                self.format_node(&n.iterator);
+                self.format_body(&n.body, self.spans.end_idx(&n.iterator) + 1);
+            }
+            AstNode::WhileLoop(_, n) => {
+                self.write("while ");
+                self.format_node(&n.condition);
+                if let Some(condition_binding) = &n.condition_binding {
+                    self.write(format!(" |{}|", self.token_text(condition_binding)));
+                }
+                self.format_body(&n.body, self.spans.while_body_start_idx(&n.condition, &n.condition_binding));
+            }
+            AstNode::Break(_) => self.write("break"),
+            AstNode::Continue(_) => self.write("continue"),
+            AstNode::Accessor(token, n) => {
+                self.format_chain_target(&n.target);
+                let dot_idx = self.spans.idx(token);
+                if !self.spans.is_same_line(self.spans.end_idx(&n.target), dot_idx) {
+                    self.start_item(&self.spans.pos(dot_idx));
+                }
+                self.write(if n.is_opt_safe { "?." } else { "." });
+                self.format_node(&n.field);
+            }
+            AstNode::Try(_, n) => {
+                self.write("try ");
+                self.format_node(&n.expr);
+            }
+            AstNode::Lambda(token, n) => {
+                let arrow_idx = self.spans.idx(token);
+                match self.spans.token(arrow_idx - 1) {
+                    Token::RParen(_) => self.format_params(self.spans.start_idx(node), &n.args),
+                    _ => self.write(self.text(arrow_idx - 1)),
+                }
+                self.write(" =>");
+                self.format_body(&n.body, arrow_idx + 1);
+            }
+            AstNode::MatchStatement(_, n) | AstNode::MatchExpression(_, n) => self.format_match(n),
+            AstNode::ReturnStatement(_, expr) => {
+                self.write("return");
+                if let Some(expr) = expr {
+                    self.write(" ");
+                    self.format_node(expr);
+                }
+            }
+            AstNode::ImportStatement(_, n) => {
+                let module = self.token_text(&n.module_token);
+                match &n.kind {
+                    ImportKind::ImportAll(_) => self.write(format!("import * from {}", module)),
+                    ImportKind::ImportList(imports) => {
+                        let imports = imports.iter().map(|i| self.token_text(i)).collect::<Vec<_>>();
+                        self.write(format!("import {} from {}", imports.join(", "), module));
+                    }
+                    ImportKind::Alias(alias) => self.write(format!("import {} as {}", module, self.token_text(alias))),
+                }
+            }
+        }
+    }
+
+    fn node_start(&self, node: &AstNode) -> Position {
+        self.spans.start_pos(node)
+    }
+
+    fn map_item_start(&self, (key, _): &(AstNode, AstNode)) -> Position {
+        let key_idx = self.spans.start_idx(key);
+        match self.spans.token(key_idx - 1) {
+            Token::LParen(_, _) => self.spans.pos(key_idx - 1),
+            _ => self.spans.pos(key_idx),
+        }
+    }
+
+    fn format_map_item(&mut self, (key, value): &(AstNode, AstNode)) {
+        let key_idx = self.spans.start_idx(key);
+        if let Token::LParen(_, _) = self.spans.token(key_idx - 1) {
+            self.write("(");
+            self.format_node(key);
+            self.write(")");
+        } else {
+            self.format_node(key);
+        }
+        self.write(": ");
+        self.format_node(value);
+    }
+
+    fn arg_start(&self, (label, value): &(Option<Token>, AstNode)) -> Position {
+        match label {
+            Some(label) => label.get_position(),
+            None => self.spans.start_pos(value),
+        }
+    }
+
+    fn format_arg(&mut self, (label, value): &(Option<Token>, AstNode)) {
+        if let Some(label) = label {
+            self.write(format!("{}: ", self.token_text(label)));
+        }
+        self.format_node(value);
+    }
+
+    fn param_start(&self, (ident, _, is_vararg, _): &Param) -> Position {
+        let idx = self.spans.idx(ident);
+        if *is_vararg { self.spans.pos(idx - 1) } else { self.spans.pos(idx) }
+    }
+
+    fn format_param(&mut self, (ident, type_ident, is_vararg, default_value): &Param) {
+        if *is_vararg { self.write("*"); }
+        self.write(self.token_text(ident));
+        if let Some(type_ident) = type_ident {
+            self.write(format!(": {}", self.type_text(type_ident)));
+        }
+        if let Some(default_value) = default_value {
+            self.write(" = ");
+            self.format_node(default_value);
+        }
+    }
+
+    fn format_params(&mut self, lparen_idx: usize, params: &Vec<Param>) {
+        self.format_list(lparen_idx, ("(", ")"), false, params, true, Self::param_start, Self::format_param);
+    }
+
+    fn format_decorators(&mut self, decorators: &Vec<DecoratorNode>) {
+        for dec in decorators {
+            self.write(format!("@{}", self.token_text(&dec.name)));
+            let name_idx = self.spans.idx(&dec.name);
+            let end_idx = match self.spans.token(name_idx + 1) {
+                Token::LParen(_, _) => {
+                    self.format_list(name_idx + 1, ("(", ")"), false, &dec.args, true, Self::arg_start, Self::format_arg);
+                    self.spans.matching(name_idx + 1)
+                }
+                _ => name_idx,
+            };
+
+            if self.spans.is_same_line(end_idx, end_idx + 1) {
+                self.write(" ");
+            } else {
+                self.start_item(&self.spans.pos(end_idx + 1));
+            }
+        }
+    }
+
+    fn format_pattern(&mut self, pattern: &BindingPattern) {
+        match pattern {
+            BindingPattern::Variable(ident) => self.write(self.token_text(ident)),
+            BindingPattern::Tuple(_, patterns) => {
+                self.write("(");
+                for (idx, pattern) in patterns.iter().enumerate() {
+                    if idx > 0 { self.write(", "); }
+                    self.format_pattern(pattern);
+                }
+                self.write(")");
+            }
+            BindingPattern::Array(_, patterns, _) => {
+                self.write("[");
+                for (idx, (pattern, is_splat)) in patterns.iter().enumerate() {
+                    if idx > 0 { self.write(", "); }
+                    if *is_splat { self.write("*"); }
+                    self.format_pattern(pattern);
+                }
+                self.write("]");
+            }
+        }
+    }
+
+    fn type_text(&self, type_ident: &TypeIdentifier) -> String {
+        // Function and union types need to be parenthesized when they're nested within another type
+        let nested_type_text = |type_ident: &TypeIdentifier| match type_ident {
+            TypeIdentifier::Func { .. } | TypeIdentifier::Union { .. } => format!("({})", self.type_text(type_ident)),
+            _ => self.type_text(type_ident),
+        };
+
+        match type_ident {
+            TypeIdentifier::Normal { ident, type_args } => {
+                let name = self.token_text(ident);
+                match type_args {
+                    Some(type_args) => {
+                        let type_args = type_args.iter().map(|t| self.type_text(t)).collect::<Vec<_>>();
+                        format!("{}<{}>", name, type_args.join(", "))
+                    }
+                    None => name,
+                }
+            }
+            TypeIdentifier::Array { inner } => format!("{}[]", nested_type_text(inner)),
+            TypeIdentifier::Option { inner } => format!("{}?", nested_type_text(inner)),
+            TypeIdentifier::Tuple { types } => {
+                let types = types.iter().map(|t| self.type_text(t)).collect::<Vec<_>>();
+                format!("({})", types.join(", "))
+            }
+            TypeIdentifier::Union { left, right } => format!("{} | {}", nested_type_text(left), self.type_text(right)),
+            TypeIdentifier::Func { args, ret } => {
+                let args = args.iter().map(|t| self.type_text(t)).collect::<Vec<_>>();
+                format!("({}) => {}", args.join(", "), self.type_text(ret))
+            }
+        }
+    }
+
+    // A body is either a block, or a single expression (or return/break/continue statement) following its header; if the latter
+    // was written on the line after its header, it stays there
+    fn format_body(&mut self, body: &Vec<AstNode>, body_start_idx: usize) {
+        if !matches!(self.spans.token(body_start_idx), Token::LBrace(_)) {
+            if self.spans.is_same_line(body_start_idx - 1, body_start_idx) {
+                self.write(" ");
+                self.format_node(&body[0]);
+            } else {
+                self.indent += 1;
+                self.start_item(&self.spans.pos(body_start_idx));
+                self.format_node(&body[0]);
+                self.indent -= 1;
+            }
+            return;
+        }
+
+        self.write(" ");
+        let close_idx = self.spans.matching(body_start_idx);
+        if !self.is_multiline(body_start_idx, close_idx, body.is_empty()) && body.len() <= 1 {
+            let (open_pos, close_pos) = (self.spans.pos(body_start_idx), self.spans.pos(close_idx));
+            self.write("{ ");
+            if let Some(node) = body.first() {
+                self.write_inline_comments(&open_pos, &self.spans.start_pos(node));
+                self.format_node(node);
+                self.write(" ");
+            }
+            self.write_inline_comments(&open_pos, &close_pos);
+
+            if body.is_empty() && self.out.ends_with("{ ") { self.out.pop(); }
+            self.write("}");
+            return;
+        }
+
+        self.open_multiline("{");
+        self.format_items(body);
+        self.close_multiline(close_idx, "}");
+    }
+
+    fn format_func_decl(&mut self, node: &FunctionDeclNode) {
+        self.format_decorators(&node.decorators);
+        if node.export_token.is_some() { self.write("export "); }
+        self.write("func ");
+        self.format_type_decl_name(&node.name, &node.type_args);
+
+        let lparen_idx = self.spans.skip_type_args(self.spans.idx(&node.name) + 1);
+        self.format_params(lparen_idx, &node.args);
+
+        let mut idx = self.spans.matching(lparen_idx) + 1;
+        if let Some(ret_type) = &node.ret_type {
+            self.write(format!(": {}", self.type_text(ret_type)));
+            idx = self.spans.skip_type(idx + 1);
+        }
+
+        match self.spans.get(idx) {
+            Some(Token::Assign(_)) if !node.body.is_empty() => {
+                self.write(" =");
+                self.format_body(&node.body, idx + 1);
+            }
+            Some(Token::LBrace(_)) => self.format_body(&node.body, idx),
+            // Stub functions have no body
+            _ => {}
+        }
+    }
+
+    fn format_type_decl_name(&mut self, name: &Token, type_args: &Vec<Token>) {
+        self.write(self.token_text(name));
+        if !type_args.is_empty() {
+            let type_args = type_args.iter().map(|t| self.token_text(t)).collect::<Vec<_>>();
+            self.write(format!("<{}>", type_args.join(", ")));
+        }
+    }
+
+    fn member_start(&self, member: &Member) -> Position {
+        match member {
+            Member::Field(field) => field.ident.get_position(),
+            Member::Variant((ident, _)) => ident.get_position(),
+            Member::Method(method) => self.spans.start_pos(method),
+        }
+    }
+
+    fn format_member(&mut self, member: &Member) {
+        match member {
+            Member::Field(field) => {
+                self.write(format!("{}: {}", self.token_text(&field.ident), self.type_text(&field.type_ident)));
+                if let Some(default_value) = &field.default_value {
+                    self.write(" = ");
+                    self.format_node(default_value);
+                }
+                if field.readonly.is_some() { self.write(" readonly"); }
+            }
+            Member::Variant((ident, args)) => {
+                self.write(self.token_text(ident));
+                if let Some(args) = args {
+                    self.format_params(self.spans.idx(ident) + 1, args);
+                }
+            }
+            Member::Method(method) => self.format_node(method),
+        }
+    }
+
+    fn format_type_decl_body(&mut self, lbrace_idx: usize, mut members: Vec<Member>) {
+        members.sort_by_key(|m| self.member_start(m));
+
+        let close_idx = self.spans.matching(lbrace_idx);
+        if !self.is_multiline(lbrace_idx, close_idx, members.is_empty()) {
+            if members.is_empty() {
+                self.write(" {}");
+                return;
+            }
+
+            self.write(" { ");
+            for (idx, member) in members.iter().enumerate() {
+                if idx > 0 { self.write(", "); }
+                self.format_member(member);
+            }
+            self.write(" }");
+            return;
+        }
+
+        self.open_multiline(" {");
+        for member in &members {
+            let start = self.member_start(member);
+            self.start_item(&start);
+            self.format_member(member);
+        }
+        self.close_multiline(close_idx, "}");
+    }
+
+    fn format_if(&mut self, node: &IfNode) {
+        self.write("if ");
+        self.format_node(&node.condition);
+        if let Some(condition_binding) = &node.condition_binding {
+            self.write(" |");
+            self.format_pattern(condition_binding);
+            self.write("|");
+        }
+
+        let body_start_idx = self.spans.if_body_start_idx(node);
+        self.format_body(&node.if_block, body_start_idx);
+
+        if let Some(else_block) = &node.else_block {
+            let if_end_idx = self.spans.body_end_idx(&node.if_block, body_start_idx);
+            let is_braced = matches!(self.spans.token(body_start_idx), Token::LBrace(_));
+            if !is_braced && !self.spans.is_same_line(if_end_idx, if_end_idx + 1) {
+                self.start_item(&self.spans.pos(if_end_idx + 1));
+                self.write("else");
+            } else {
+                self.write(" else");
+            }
+            self.format_body(else_block, if_end_idx + 2);
+        }
+    }
+
+    fn case_start(&self, case: &MatchCase) -> Position {
+        match &case.match_type {
+            MatchCaseType::None(token) |
+            MatchCaseType::Wildcard(token) |
+            MatchCaseType::Ident(token, _) |
+            MatchCaseType::Tuple(token, _) => token.get_position(),
+            MatchCaseType::Compound(idents, _) => idents[0].get_position(),
+            MatchCaseType::Constant(node) => self.spans.start_pos(node),
+        }
+    }
+
+    // The index of the `=>` token following a match case
+    fn case_arrow_idx(&self, case: &MatchCase) -> usize {
+        if let Some(case_binding) = &case.case_binding {
+            return self.spans.idx(case_binding) + 1;
+        }
+
+        match &case.match_type {
+            MatchCaseType::Ident(_, Some(_)) |
+            MatchCaseType::Compound(_, Some(_)) |
+            MatchCaseType::Tuple(_, _) => self.spans.matching(self.spans.idx(&case.token)) + 1,
+            MatchCaseType::Compound(idents, None) => self.spans.idx(idents.last().unwrap()) + 1,
+            _ => self.spans.idx(&case.token) + 1,
+        }
+    }
+
+    fn format_case(&mut self, case: &MatchCase) {
+        let args = match &case.match_type {
+            MatchCaseType::None(_) => {
+                self.write("None");
+                &None
+            }
+            MatchCaseType::Wildcard(_) => {
+                self.write("_");
+                &None
+            }
+            MatchCaseType::Constant(node) => {
+                self.format_node(node);
+                &None
+            }
+            MatchCaseType::Tuple(_, nodes) => {
+                self.write("(");
+                for (idx, node) in nodes.iter().enumerate() {
+                    if idx > 0 { self.write(", "); }
+                    self.format_node(node);
+                }
+                self.write(")");
+                &None
+            }
+            MatchCaseType::Ident(ident, args) => {
+                self.write(self.token_text(ident));
+                args
+            }
+            MatchCaseType::Compound(idents, args) => {
+                // `Ok` and `Err` cases are desugared into `Result.Ok` and `Result.Err` by the parser
+                let is_desugared = idents.len() == 2 && idents[0].get_position() == idents[1].get_position();
+                let idents = if is_desugared { &idents[1..] } else { &idents[..] };
+                let idents = idents.iter().map(|i| self.token_text(i)).collect::<Vec<_>>();
+                self.write(idents.join("."));
+                args
+            }
+        };
+
+        if let Some(args) = args {
+            self.write("(");
+            for (idx, arg) in args.iter().enumerate() {
+                if idx > 0 { self.write(", "); }
+                match arg {
+                    MatchCaseArgument::Pattern(pattern) => self.format_pattern(pattern),
+                    MatchCaseArgument::Literal(node) => self.format_node(node),
+                }
+            }
+            self.write(")");
+        }
+
+        if let Some(case_binding) = &case.case_binding {
+            self.write(format!(" {}", self.token_text(case_binding)));
+        }
+    }
+
+    fn format_match(&mut self, node: &MatchNode) {
+        self.write("match ");
+        self.format_node(&node.target);
+
+        let lbrace_idx = self.spans.end_idx(&node.target) + 1;
+        let close_idx = self.spans.matching(lbrace_idx);
+        if self.spans.is_same_line(lbrace_idx, close_idx) {
+            self.write(" { ");
+            for (idx, (case, body)) in node.branches.iter().enumerate() {
+                if idx > 0 { self.write(", "); }
+                self.format_case(case);
+                self.write(" =>");
+                self.format_body(body, self.case_arrow_idx(case) + 1);
+            }
+            self.write(" }");
+            return;
+        }
+
+        self.open_multiline(" {");
+        for (case, body) in &node.branches {
+            let start = self.case_start(case);
+            self.start_item(&start);
+            self.format_case(case);
+            self.write(" =>");
+            self.format_body(body, self.case_arrow_idx(case) + 1);
+        }
+        self.close_multiline(close_idx, "}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::formatter::format_source;
+    use crate::lexer::lexer::{tokenize, tokenize_with_comments};
+    use crate::parser::ast::ModuleId;
+    use crate::parser::parser::parse;
+
+    // The debug representation of the source's imports and AST, with every position removed so that only the structure remains
+    fn ast_without_positions(module_id: &ModuleId, source: &String) -> String {
+        let parse_result = parse(module_id.clone(), tokenize(module_id, source).unwrap()).unwrap();
+        let debug = format!("{:?}\n{:?}", parse_result.imports, parse_result.nodes);
+
+        let mut stripped = String::with_capacity(debug.len());
+        let mut rest = debug.as_str();
+        while let Some(idx) = rest.find("Position { ") {
+            stripped.push_str(&rest[..idx]);
+            rest = &rest[idx..];
+            rest = &rest[rest.find('}').unwrap() + 1..];
+        }
+        stripped.push_str(rest);
+        stripped
+    }
+
+    fn format(input: &str) -> String {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        format_source(&module_id, &input.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_format_normalizes_spacing() {
+        let input = "val  x=1+ 2\nfunc  foo( a:Int,b :Int[ ]=[1,2] ):Int{\nreturn a+b\n}\n\n\n\nval m={a:1,(x):\"two\"}";
+        let expected = "val x = 1 + 2\nfunc foo(a: Int, b: Int[] = [1, 2]): Int {\n  return a + b\n}\n\nval m = { a: 1, (x): \"two\" }\n";
+        assert_eq!(expected, format(input));
+    }
+
+    #[test]
+    fn test_format_preserves_line_structure() {
+        let input = "val arr = [\n1, 2,\n  3]\nval f = (a: Int) => { a }\nmatch x { 1 => 2, _ => 3 }\nmatch x {\n  None => 1, Some(y) => y\n}";
+        let expected = "val arr = [\n  1,\n  2,\n  3,\n]\nval f = (a: Int) => { a }\nmatch x { 1 => 2, _ => 3 }\nmatch x {\n  None => 1\n  Some(y) => y\n}\n";
+        assert_eq!(expected, format(input));
+    }
+
+    #[test]
+    fn test_format_desugared_syntax() {
+        let input = "val s = \"a $b ${c+1}\"\nval o=Some(1)\nmatch r { Ok(v) => v, Err(e) => e }\ntype T{a:((Int) => Int)[] , b:(Int|String)?}";
+        let expected = "val s = \"a $b ${c+1}\"\nval o = Some(1)\nmatch r { Ok(v) => v, Err(e) => e }\ntype T { a: ((Int) => Int)[], b: (Int | String)? }\n";
+        assert_eq!(expected, format(input));
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let input = "// leading\n\n\nval x = 1 // trailing\nfunc f() {\n  /* inner */\n  x\n\n  // before close\n}\nval arr = [\n  1, // one\n  2\n]\n// end";
+        let expected = "// leading\n\nval x = 1 // trailing\nfunc f() {\n  /* inner */\n  x\n\n  // before close\n}\nval arr = [\n  1, // one\n  2,\n]\n// end\n";
+        assert_eq!(expected, format(input));
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dirs = vec![root.join("std"), root.join("../selfhost/src")];
+
+        let mut num_files = 0;
+        for dir in dirs {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().map_or(true, |ext| ext != "abra") { continue; }
+                num_files += 1;
+
+                let module_id = ModuleId::parse_module_path("./test").unwrap();
+                let source = std::fs::read_to_string(&path).unwrap();
+                let once = format_source(&module_id, &source).unwrap_or_else(|e| panic!("Failed to format {:?}: {:?}", path, e));
+                let twice = format_source(&module_id, &once).unwrap_or_else(|e| panic!("Failed to reformat {:?}: {:?}", path, e));
+                assert_eq!(once, twice, "Formatting {:?} should be idempotent", path);
+
+                let comments = |src: &String| tokenize_with_comments(&module_id, src).unwrap().1.into_iter().map(|c| c.text).collect::<Vec<_>>();
+                assert_eq!(comments(&source), comments(&once), "Formatting {:?} should preserve comments", path);
+
+                assert!(ast_without_positions(&module_id, &source) == ast_without_positions(&module_id, &once), "Formatting {:?} should not change its AST", path);
+            }
+        }
+        assert!(num_files > 0);
+    }
+}
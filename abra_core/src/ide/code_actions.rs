@@ -0,0 +1,358 @@
+use std::path::Path;
+use itertools::{Either, Itertools};
+use crate::ide::Analysis;
+use crate::lexer::lexer::tokenize;
+use crate::lexer::tokens::{Position, Range, Token};
+use crate::parser;
+use crate::parser::ast::{AstNode, BindingPattern, ImportKind, MatchCaseType};
+use crate::parser::cst::TokenSpans;
+use crate::parser::parser::{parse, ParseResult};
+use crate::typechecker::typechecker2::{EnumVariantKind, LoadModule, ModuleId, TypeError, TypeId};
+use crate::typechecker::virtual_module_loader::{normalize_path, VirtualModuleLoader};
+
+#[derive(Debug, PartialEq)]
+pub struct TextEdit {
+    // Unlike a `Range`, the end is exclusive, so an insertion is an edit whose start and end are the same
+    pub start: Position,
+    pub end: Position,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    fn insert<S: AsRef<str>>(position: Position, text: S) -> TextEdit {
+        TextEdit { start: position.clone(), end: position, new_text: text.as_ref().to_string() }
+    }
+
+    fn replace<S: AsRef<str>>(range: &Range, text: S) -> TextEdit {
+        TextEdit { start: range.start.clone(), end: position_after(&range.end), new_text: text.as_ref().to_string() }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CodeAction {
+    pub title: String,
+    // The edits are all made to the file in which the error is
+    pub edits: Vec<TextEdit>,
+    // The range and code of the error which the action fixes (which are those of its diagnostic)
+    pub fixes: Option<(Range, &'static str)>,
+}
+
+impl CodeAction {
+    fn new<S: AsRef<str>>(title: S, edits: Vec<TextEdit>) -> CodeAction {
+        CodeAction { title: title.as_ref().to_string(), edits, fixes: None }
+    }
+}
+
+// The parsed source of the module in which the error is
+struct ErrorModule<'a> {
+    module_id: ModuleId,
+    m_id: &'a parser::ast::ModuleId,
+    path: String,
+    source: String,
+    spans: TokenSpans,
+    parse_result: ParseResult,
+}
+
+impl Analysis {
+    // Fixes for the error which stopped typechecking, if it's in the file and overlaps the range. Only some errors can be fixed
+    // mechanically: unknown identifiers exported by another module of the project, non-exhaustive matches on enums, misspelled members,
+    // and constructor calls without argument labels.
+    pub fn code_actions(&self, file: &str, range: &Range) -> Vec<CodeAction> {
+        let Some(Either::Right(error)) = &self.error else { return vec![]; };
+        let span = error.span();
+        if span.range.end < range.start || range.end < span.range.start {
+            return vec![];
+        }
+        let Some(module) = self.error_module(span.module_id(), file) else { return vec![]; };
+
+        let actions = match error {
+            TypeError::UnknownIdentifier { token, .. } => self.import_actions(&module, &Token::get_ident_name(token)),
+            TypeError::NonExhaustiveMatch { span, type_id } => self.missing_cases_action(&module, &span.range.start, type_id).into_iter().collect(),
+            TypeError::UnknownMember { span, .. } => {
+                let Some(suggestion) = error.suggestion(&self.project) else { return vec![]; };
+                vec![CodeAction::new(format!("Change to '{}'", suggestion), vec![TextEdit::replace(&span.range, suggestion)])]
+            }
+            TypeError::MissingRequiredArgumentLabels { span } => self.argument_labels_action(&module, &span.range.start).into_iter().collect(),
+            _ => vec![],
+        };
+        actions.into_iter().map(|action| CodeAction { fixes: Some((span.range.clone(), error.code())), ..action }).collect()
+    }
+
+    fn error_module(&self, module_id: &ModuleId, file: &str) -> Option<ErrorModule<'_>> {
+        let path = self.loader.get_path(module_id)?;
+        if Path::new(&path) != normalize_path(file) {
+            return None;
+        }
+
+        let m_id = self.loader.get_m_id(module_id)?;
+        let source = self.source(module_id)?;
+        let tokens = tokenize(m_id, &source).ok()?;
+        let parse_result = parse(m_id.clone(), tokens.clone()).ok()?;
+
+        Some(ErrorModule { module_id: *module_id, m_id, path, source, spans: TokenSpans::new(tokens), parse_result })
+    }
+
+    // An import from each module of the project which exports the name
+    fn import_actions(&self, module: &ErrorModule, name: &String) -> Vec<CodeAction> {
+        let mut files = vec![];
+        collect_module_files(&self.loader, self.loader.program_root(), &mut files);
+
+        files.into_iter()
+            .filter(|file| *file != module.path)
+            .filter(|file| match self.loader.parse_file(module.m_id, file.clone()) {
+                Ok(Some((_, parse_result))) => exported_names(&parse_result.nodes).contains(name),
+                _ => false,
+            })
+            .filter_map(|file| {
+                let module_path = relative_module_path(&module.path, &file)?;
+                let edit = import_edit(&module.parse_result, name, &module_path);
+                Some(CodeAction::new(format!("Import '{}' from \"{}\"", name, module_path), vec![edit]))
+            })
+            .collect()
+    }
+
+    // A case for each of the enum's variants which isn't matched, with an empty body
+    fn missing_cases_action(&self, module: &ErrorModule, match_position: &Position, type_id: &TypeId) -> Option<CodeAction> {
+        let type_id = self.project.type_is_option(type_id).unwrap_or(*type_id);
+        let (enum_, _, _) = self.project.get_enum_by_type_id(&type_id)?;
+
+        let is_match = |node: &AstNode| matches!(node, AstNode::MatchStatement(token, _) | AstNode::MatchExpression(token, _) if token.get_position() == *match_position);
+        let node = find_node(&module.spans, module.parse_result.nodes.iter().collect(), &is_match)?;
+        let (AstNode::MatchStatement(_, match_node) | AstNode::MatchExpression(_, match_node)) = node else { return None; };
+
+        let matched_variants = match_node.branches.iter()
+            .filter_map(|(case, _)| match &case.match_type {
+                MatchCaseType::Ident(token, _) => Some(Token::get_ident_name(token)),
+                MatchCaseType::Compound(idents, _) => idents.last().map(Token::get_ident_name),
+                _ => None,
+            })
+            .collect_vec();
+        let cases = enum_.variants.iter()
+            .filter(|variant| !matched_variants.contains(&variant.name))
+            .map(|variant| {
+                let args = match &variant.kind {
+                    EnumVariantKind::Constant => "".to_string(),
+                    EnumVariantKind::Container(func_id) => format!("({})", self.project.get_func_by_id(func_id).params.iter().map(|param| &param.name).join(", ")),
+                };
+                format!("{}.{}{} => {{}}", enum_.name, variant.name, args)
+            })
+            .collect_vec();
+        if cases.is_empty() {
+            return None;
+        }
+
+        // Cases are added in the same layout as the existing ones: on the same line, or each on their own line
+        let close_idx = module.spans.end_idx(node);
+        let lbrace_idx = module.spans.matching(close_idx);
+        let last_token_end = position_after(&module.spans.token(close_idx - 1).get_range().end);
+        let edit = if module.spans.is_same_line(lbrace_idx, close_idx) {
+            TextEdit::insert(last_token_end, cases.iter().map(|case| format!(", {}", case)).join(""))
+        } else {
+            let indent = " ".repeat(module.spans.pos(lbrace_idx + 1).col - 1);
+            let close_pos = module.spans.pos(close_idx);
+            let close_line = module.source.lines().nth(close_pos.line - 1).unwrap_or("");
+            if close_line.chars().take(close_pos.col - 1).all(char::is_whitespace) {
+                TextEdit::insert(Position::new(close_pos.line, 1), cases.iter().map(|case| format!("{}{}\n", indent, case)).join(""))
+            } else {
+                TextEdit::insert(last_token_end, cases.iter().map(|case| format!("\n{}{}", indent, case)).join(""))
+            }
+        };
+
+        let title = if cases.len() == 1 { "Add missing match case".to_string() } else { format!("Add {} missing match cases", cases.len()) };
+        Some(CodeAction::new(title, vec![edit]))
+    }
+
+    // Each unlabeled argument is labeled with the name of the field to which it's passed
+    fn argument_labels_action(&self, module: &ErrorModule, arg_position: &Position) -> Option<CodeAction> {
+        let is_invocation = |node: &AstNode| matches!(node, AstNode::Invocation(_, n) if n.args.iter().any(|(_, arg)| arg.get_token().get_position() == *arg_position));
+        let Some(AstNode::Invocation(_, invocation)) = find_node(&module.spans, module.parse_result.nodes.iter().collect(), &is_invocation) else { return None; };
+
+        let target_position = match &*invocation.target {
+            AstNode::Identifier(token, _) => token.get_position(),
+            AstNode::Accessor(_, accessor) => accessor.field.get_token().get_position(),
+            _ => return None,
+        };
+        let reference = self.project.modules[module.module_id.0].references.get(&target_position)?;
+        let signature = self.signature(&reference.symbol)?;
+
+        let edits = invocation.args.iter().zip(&signature.params)
+            .filter(|((label, _), _)| label.is_none())
+            .filter_map(|((_, arg), param)| Some(TextEdit::insert(module.spans.start_pos(arg), format!("{}: ", param.name.as_ref()?))))
+            .collect_vec();
+        Some(CodeAction::new("Add argument labels", edits))
+    }
+}
+
+fn position_after(position: &Position) -> Position {
+    Position::new(position.line, position.col + 1)
+}
+
+// The innermost node for which the predicate holds
+fn find_node<'a, F: Fn(&AstNode) -> bool>(spans: &TokenSpans, nodes: Vec<&'a AstNode>, predicate: &F) -> Option<&'a AstNode> {
+    nodes.into_iter().find_map(|node| {
+        find_node(spans, spans.children(node), predicate).or_else(|| if predicate(node) { Some(node) } else { None })
+    })
+}
+
+// Every module within the directory and its subdirectories (other than hidden ones), outside of the std library
+fn collect_module_files(loader: &VirtualModuleLoader, dir: &Path, files: &mut Vec<String>) {
+    for entry in loader.list_dir(dir) {
+        let path = Path::new(&entry);
+        if path.extension().is_some_and(|ext| ext == "abra") {
+            if !loader.is_std_file(path) {
+                files.push(entry.clone());
+            }
+        } else if path.is_dir() && !path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.')) {
+            collect_module_files(loader, path, files);
+        }
+    }
+}
+
+fn exported_names(nodes: &Vec<AstNode>) -> Vec<String> {
+    fn pattern_names(pattern: &BindingPattern, names: &mut Vec<String>) {
+        match pattern {
+            BindingPattern::Variable(ident) => names.push(Token::get_ident_name(ident)),
+            BindingPattern::Tuple(_, patterns) => patterns.iter().for_each(|pattern| pattern_names(pattern, names)),
+            BindingPattern::Array(_, patterns, _) => patterns.iter().for_each(|(pattern, _)| pattern_names(pattern, names)),
+        }
+    }
+
+    let mut names = vec![];
+    for node in nodes {
+        match node {
+            AstNode::FunctionDecl(_, n) if n.export_token.is_some() => names.push(Token::get_ident_name(&n.name)),
+            AstNode::TypeDecl(_, n) if n.export_token.is_some() => names.push(Token::get_ident_name(&n.name)),
+            AstNode::EnumDecl(_, n) if n.export_token.is_some() => names.push(Token::get_ident_name(&n.name)),
+            AstNode::BindingDecl(_, n) if n.export_token.is_some() => pattern_names(&n.binding, &mut names),
+            _ => {}
+        }
+    }
+    names
+}
+
+// The path with which the module at `to` is imported from the module at `from` (eg. `./utils` or `../lib/math`)
+fn relative_module_path(from: &str, to: &str) -> Option<String> {
+    let from_dir = Path::new(from).parent()?.components().collect_vec();
+    let to = Path::new(to).with_extension("");
+    let to = to.components().collect_vec();
+    let num_common = from_dir.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut segments = vec![];
+    if num_common == from_dir.len() {
+        segments.push(".".to_string());
+    }
+    segments.extend(from_dir[num_common..].iter().map(|_| "..".to_string()));
+    for component in &to[num_common..] {
+        segments.push(component.as_os_str().to_str()?.to_string());
+    }
+    Some(segments.join("/"))
+}
+
+// The name is added to an existing import list from the module if there is one, and otherwise a new import follows the others
+fn import_edit(parse_result: &ParseResult, name: &String, module_path: &str) -> TextEdit {
+    let m_id = parser::ast::ModuleId::parse_module_path(module_path);
+    let import_list = parse_result.imports.iter().find_map(|(_, import)| match &import.kind {
+        ImportKind::ImportList(imports) if Some(&import.module_id) == m_id.as_ref() => imports.last(),
+        _ => None,
+    });
+    if let Some(last_import) = import_list {
+        return TextEdit::insert(position_after(&last_import.get_range().end), format!(", {}", name));
+    }
+
+    let import = format!("import {} from \"{}\"\n", name, module_path);
+    match parse_result.imports.last() {
+        Some((_, last_import)) => TextEdit::insert(Position::new(last_import.module_token.get_range().end.line + 1, 1), import),
+        None => TextEdit::insert(Position::new(1, 1), format!("{}\n", import)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::code_actions::TextEdit;
+    use crate::ide::try_analyze_files;
+    use crate::lexer::tokens::{Position, Range};
+
+    // The source with the edits applied (which mustn't overlap)
+    fn apply_edits(source: &str, edits: &Vec<TextEdit>) -> String {
+        let offset = |position: &Position| -> usize {
+            let line_start = source.split_inclusive('\n').take(position.line - 1).map(|line| line.chars().count()).sum::<usize>();
+            line_start + position.col - 1
+        };
+
+        let mut chars = source.chars().collect::<Vec<_>>();
+        let mut edits = edits.iter().collect::<Vec<_>>();
+        edits.sort_by(|a, b| b.start.cmp(&a.start));
+        for edit in edits {
+            chars.splice(offset(&edit.start)..offset(&edit.end), edit.new_text.chars());
+        }
+        chars.into_iter().collect()
+    }
+
+    fn fixes(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        let analysis = try_analyze_files(files);
+        let range = Range { start: Position::new(1, 1), end: Position::new(100, 1) };
+        analysis.code_actions("/project/main.abra", &range).into_iter()
+            .map(|action| (action.title, apply_edits(files[0].1, &action.edits)))
+            .collect()
+    }
+
+    #[test]
+    fn test_import_unknown_identifier() {
+        let utils = "export func double(i: Int): Int = i * 2\nexport val (limit, _) = (10, 1)";
+        let main = "import limit from \"./utils\"\nval x = double(limit)";
+        let expected = vec![("Import 'double' from \"./utils\"".to_string(), "import limit, double from \"./utils\"\nval x = double(limit)".to_string())];
+        assert_eq!(expected, fixes(&[("/project/main.abra", main), ("/project/utils.abra", utils)]));
+
+        let main = "import \"./utils\" as u\n\nval x = limit";
+        let expected = vec![("Import 'limit' from \"./utils\"".to_string(), "import \"./utils\" as u\nimport limit from \"./utils\"\n\nval x = limit".to_string())];
+        assert_eq!(expected, fixes(&[("/project/main.abra", main), ("/project/utils.abra", utils)]));
+
+        let expected = vec![("Import 'double' from \"./utils\"".to_string(), "import double from \"./utils\"\n\nval x = double(1)".to_string())];
+        assert_eq!(expected, fixes(&[("/project/main.abra", "val x = double(1)"), ("/project/utils.abra", utils)]));
+
+        // Names which aren't exported aren't imported
+        assert!(fixes(&[("/project/main.abra", "val x = triple(1)"), ("/project/utils.abra", "func triple(i: Int): Int = i * 3")]).is_empty());
+    }
+
+    #[test]
+    fn test_add_missing_match_cases() {
+        let shape = "enum Shape { Dot(x: Int, y: Int), Circle(r: Float), Empty }\n";
+
+        let main = format!("{}func f(s: Shape) {{\n  match s {{\n    Shape.Circle(r) => {{}}\n  }}\n}}", shape);
+        let expected = format!("{}func f(s: Shape) {{\n  match s {{\n    Shape.Circle(r) => {{}}\n    Shape.Dot(x, y) => {{}}\n    Shape.Empty => {{}}\n  }}\n}}", shape);
+        assert_eq!(vec![("Add 2 missing match cases".to_string(), expected)], fixes(&[("/project/main.abra", &main)]));
+
+        let main = format!("{}val s: Shape? = None\nmatch s {{ None => {{}}, Shape.Empty => {{}}, Shape.Dot => {{}} }}", shape);
+        let expected = format!("{}val s: Shape? = None\nmatch s {{ None => {{}}, Shape.Empty => {{}}, Shape.Dot => {{}}, Shape.Circle(r) => {{}} }}", shape);
+        assert_eq!(vec![("Add missing match case".to_string(), expected)], fixes(&[("/project/main.abra", &main)]));
+
+        // Only matches on enums can be completed
+        assert!(fixes(&[("/project/main.abra", "match 1 {\n  1 => {}\n}")]).is_empty());
+    }
+
+    #[test]
+    fn test_fix_unknown_member() {
+        let main = "type Point {\n  x: Int\n  func length(self): Int = self.x\n}\nval p = Point(x: 1)\nval l = p.lenght()";
+        let expected = vec![("Change to 'length'".to_string(), main.replace("p.lenght()", "p.length()"))];
+        assert_eq!(expected, fixes(&[("/project/main.abra", main)]));
+
+        assert!(fixes(&[("/project/main.abra", "val l = [1].foo")]).is_empty());
+    }
+
+    #[test]
+    fn test_add_argument_labels() {
+        let main = "type Point {\n  x: Int\n  y: Int = 0\n}\nval p = Point(1 + 2, 3)";
+        let expected = vec![("Add argument labels".to_string(), main.replace("Point(1 + 2, 3)", "Point(x: 1 + 2, y: 3)"))];
+        assert_eq!(expected, fixes(&[("/project/main.abra", main)]));
+
+        // Each fix records the error it targets, which is identified the same way as the error's diagnostic
+        let analysis = try_analyze_files(&[("/project/main.abra", main)]);
+        let diagnostic = analysis.diagnostic().unwrap();
+        let actions = analysis.code_actions("/project/main.abra", &Range { start: Position::new(1, 1), end: Position::new(5, 30) });
+        assert_eq!(Some((diagnostic.range, diagnostic.code.as_deref().unwrap())), actions[0].fixes.clone());
+
+        // Fixes are only offered for errors within the range
+        assert!(analysis.code_actions("/project/main.abra", &Range { start: Position::new(1, 1), end: Position::new(4, 1) }).is_empty());
+        assert!(analysis.code_actions("/project/other.abra", &Range { start: Position::new(1, 1), end: Position::new(5, 1) }).is_empty());
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashSet;
+use std::path::Path;
+use itertools::{Either, Itertools};
+use crate::ide::Analysis;
+use crate::lexer::lexer::tokenize;
+use crate::lexer::tokens::{Position, Range, Token};
+use crate::parser;
+use crate::typechecker::typechecker2::{ExportedValue, FunctionKind, LoadModule, ModuleId, Symbol, Type, TypeError, TypeId, TypeKind, Variable, VariableAlias};
+use crate::typechecker::virtual_module_loader::VirtualModuleLoader;
+
+// Inserted at the cursor, so that typechecking the source fails right there with an error which describes what's expected
+pub(crate) const PLACEHOLDER: &str = "__completion__";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Method,
+    Field,
+    Struct,
+    Enum,
+    EnumVariant,
+    TypeParameter,
+    Module,
+    Folder,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: Option<String>,
+    pub doc: Option<String>,
+}
+
+impl Analysis {
+    // The completions at a position in `source`, which is the current contents of the file at `path` (the module `entry`). Since the
+    // source is likely incomplete at the position (eg. `p.`), it's repaired with a placeholder name there, and typechecked up to the
+    // error which that name causes; that error (and the scope in which it occurred) determines which names are offered.
+    pub fn completions(mut loader: VirtualModuleLoader, entry: &parser::ast::ModuleId, path: &str, source: &str, position: &Position) -> Vec<Completion> {
+        let lines = source.lines().collect_vec();
+        let line = lines.get(position.line - 1).copied().unwrap_or("");
+        let chars = line.chars().collect_vec();
+        let col = (position.col - 1).min(chars.len());
+        let line_prefix = chars[..col].iter().collect::<String>();
+
+        if line_prefix.trim_start().starts_with("import ") {
+            // Within the import's path string
+            if line_prefix.matches('"').count() % 2 == 1 {
+                let partial_path = line_prefix.rsplit('"').next().unwrap_or("");
+                return module_path_completions(&loader, path, partial_path);
+            }
+            // Within the list of imported names
+            if !line_prefix.contains('"') {
+                let Some((imported_names, module_path)) = parse_import_list(line) else { return vec![]; };
+                let repaired = replace_line(&lines, position.line, &format!("import \"{}\" as {}", module_path, PLACEHOLDER));
+                loader.add_file(path, repaired);
+
+                let analysis = Analysis::analyze(loader, entry);
+                let Some(module_id) = analysis.placeholder_module_alias() else { return vec![]; };
+                return analysis.export_completions(&module_id).into_iter()
+                    .filter(|completion| !imported_names.contains(&completion.label))
+                    .collect();
+            }
+        }
+
+        let is_ident_char = |ch: &char| ch.is_alphanumeric() || *ch == '_';
+        let word_start = col - chars[..col].iter().rev().take_while(|ch| is_ident_char(ch)).count();
+        let word_end = col + chars[col..].iter().take_while(|ch| is_ident_char(ch)).count();
+        let before = chars[..word_start].iter().collect::<String>();
+        let after = chars[word_end..].iter().collect::<String>();
+
+        // Prefer to keep the rest of the source, but if it doesn't parse (eg. because the cursor is in an unfinished block) then
+        // discard everything after the cursor and close any unclosed brackets
+        let preceding_lines = lines.iter().take(position.line - 1).map(|line| format!("{}\n", line)).join("");
+        let candidates = [
+            replace_line(&lines, position.line, &format!("{}{}{}", before, PLACEHOLDER, after)),
+            format!("{}{}{}{}", preceding_lines, before, PLACEHOLDER, closing_brackets(&format!("{}{}", preceding_lines, before))),
+        ];
+        let Some(repaired) = candidates.iter().find(|candidate| parses(entry, candidate)) else { return vec![]; };
+        loader.add_file(path, repaired.clone());
+
+        let analysis = Analysis::analyze(loader, entry);
+        let range = Range { start: Position::new(position.line, word_start + 1), end: Position::new(position.line, word_start + 1) };
+        analysis.completions_at_error(&range)
+    }
+
+    fn completions_at_error(&self, range: &Range) -> Vec<Completion> {
+        let Some(Either::Right(error)) = &self.error else { return vec![]; };
+
+        match error {
+            TypeError::UnknownMember { field_name, type_id, .. } if field_name == PLACEHOLDER => self.member_completions(type_id, range),
+            TypeError::UnknownExport { module_id, import_name, .. } if import_name == PLACEHOLDER => self.export_completions(module_id),
+            TypeError::UnknownIdentifier { token, .. } if Token::get_ident_name(token) == PLACEHOLDER => self.scope_completions(false, range),
+            TypeError::UnknownType { name, .. } if name == PLACEHOLDER => self.scope_completions(true, range),
+            _ => vec![],
+        }
+    }
+
+    // The names visible from the scope in which typechecking stopped (inner declarations shadow outer ones)
+    fn scope_completions(&self, types_only: bool, range: &Range) -> Vec<Completion> {
+        let Some(scope_id) = &self.error_scope_id else { return vec![]; };
+
+        let mut seen_names = HashSet::new();
+        let mut completions = self.project.visible_variables(scope_id).into_iter()
+            .filter(|var| !types_only || matches!(self.project.get_type_by_id(&var.type_id), Type::Type(_)))
+            .filter(|var| seen_names.insert(&var.name))
+            .map(|var| self.variable_completion(var, range))
+            .collect_vec();
+        if types_only {
+            let generic_names = self.project.visible_type_names(scope_id).into_iter().filter(|name| seen_names.insert(name)).collect_vec();
+            completions.extend(generic_names.into_iter().map(|name| Completion { label: name.clone(), kind: CompletionKind::TypeParameter, detail: None, doc: None }));
+        }
+
+        completions
+    }
+
+    // The members of a value (or, for types, the static members) of the given type
+    fn member_completions(&self, type_id: &TypeId, range: &Range) -> Vec<Completion> {
+        let mut symbols = vec![];
+        match self.project.get_type_by_id(type_id) {
+            Type::Type(TypeKind::Struct(struct_id)) => {
+                let struct_ = self.project.get_struct_by_id(struct_id);
+                symbols.extend(struct_.static_methods.iter().map(|func_id| Symbol::Function(*func_id)));
+            }
+            Type::Type(TypeKind::Enum(enum_id)) => {
+                let enum_ = self.project.get_enum_by_id(enum_id);
+                symbols.extend((0..enum_.variants.len()).map(|idx| Symbol::EnumVariant(*enum_id, idx)));
+                symbols.extend(enum_.static_methods.iter().map(|func_id| Symbol::Function(*func_id)));
+            }
+            Type::GenericEnumInstance(enum_id, _, _) => {
+                let enum_ = self.project.get_enum_by_id(enum_id);
+                symbols.extend(enum_.methods.iter().map(|func_id| Symbol::Function(*func_id)));
+            }
+            _ => {
+                if let Some((struct_, _)) = self.project.get_struct_by_type_id(type_id) {
+                    symbols.extend((0..struct_.fields.len()).map(|idx| Symbol::Field(struct_.id, idx)));
+                    symbols.extend(struct_.methods.iter().map(|func_id| Symbol::Function(*func_id)));
+                }
+            }
+        }
+
+        symbols.iter().map(|symbol| self.symbol_completion(symbol, range)).collect()
+    }
+
+    fn export_completions(&self, module_id: &ModuleId) -> Vec<Completion> {
+        let module = &self.project.modules[module_id.0];
+        module.exports.iter()
+            .sorted_by_key(|(name, _)| *name)
+            .map(|(name, exported_value)| {
+                let symbol = match exported_value {
+                    ExportedValue::Function(func_id) => Symbol::Function(*func_id),
+                    ExportedValue::Type(type_kind) => Symbol::Type(*type_kind),
+                    ExportedValue::Variable(var_id) => Symbol::Variable(*var_id),
+                };
+                let range = self.project.symbol_defined_span(&symbol).map(|span| span.range.clone()).unwrap_or(Range { start: Position::new(1, 1), end: Position::new(1, 1) });
+                Completion { label: name.clone(), ..self.symbol_completion(&symbol, &range) }
+            })
+            .collect()
+    }
+
+    fn variable_completion(&self, var: &Variable, range: &Range) -> Completion {
+        if var.alias == VariableAlias::None && var.type_id.as_module_type_alias().is_some() {
+            return Completion { label: var.name.clone(), kind: CompletionKind::Module, detail: None, doc: None };
+        }
+
+        Completion { label: var.name.clone(), ..self.symbol_completion(&self.project.symbol_for_var(&var.id), range) }
+    }
+
+    fn symbol_completion(&self, symbol: &Symbol, range: &Range) -> Completion {
+        let kind = match symbol {
+            Symbol::Variable(_) => CompletionKind::Variable,
+            Symbol::Function(func_id) => match self.project.get_func_by_id(func_id).kind {
+                FunctionKind::Freestanding => CompletionKind::Function,
+                FunctionKind::Method(_) | FunctionKind::StaticMethod(_) => CompletionKind::Method,
+            },
+            Symbol::Type(TypeKind::Struct(_)) => CompletionKind::Struct,
+            Symbol::Type(TypeKind::Enum(_)) => CompletionKind::Enum,
+            Symbol::Field(_, _) => CompletionKind::Field,
+            Symbol::EnumVariant(_, _) => CompletionKind::EnumVariant,
+        };
+        let hover = self.symbol_hover(symbol, range.clone());
+
+        Completion { label: self.project.symbol_name(symbol).clone(), kind, detail: Some(hover.signature), doc: hover.doc }
+    }
+
+    fn placeholder_module_alias(&self) -> Option<ModuleId> {
+        self.project.modules.iter()
+            .filter_map(|module| module.scopes.first())
+            .flat_map(|scope| &scope.vars)
+            .find(|var| var.name == PLACEHOLDER)
+            .and_then(|var| var.type_id.as_module_type_alias())
+    }
+}
+
+// The names already imported, and the path of the module, for an import statement like `import a, b from "./module"`
+fn parse_import_list(line: &str) -> Option<(Vec<String>, String)> {
+    let (names, module_path) = line.trim_start().strip_prefix("import")?.split_once(" from ")?;
+    let module_path = module_path.trim().strip_prefix('"')?.split('"').next()?;
+    let names = names.split(',').map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).collect();
+
+    Some((names, module_path.to_string()))
+}
+
+// Relative paths are resolved against the file's directory, and all others against the std library. Only the last segment of the
+// path is completed, so nothing is offered until a relative path has a `/`.
+fn module_path_completions(loader: &VirtualModuleLoader, path: &str, partial_path: &str) -> Vec<Completion> {
+    let (dir, is_relative) = match partial_path.rsplit_once('/') {
+        Some((dir, _)) if partial_path.starts_with('.') => (Path::new(path).parent().map(|parent| parent.join(dir)), true),
+        Some((dir, _)) => (Some(loader.std_path().join(dir)), false),
+        None if partial_path.starts_with('.') => (None, true),
+        None => (Some(loader.std_path().to_path_buf()), false),
+    };
+    let Some(dir) = dir else { return vec![]; };
+
+    loader.list_dir(&dir).into_iter()
+        .filter(|entry| entry != path)
+        .filter_map(|entry| {
+            let entry = Path::new(&entry);
+            let name = entry.file_stem()?.to_str()?.to_string();
+            if entry.extension().is_some_and(|ext| ext == "abra") {
+                // Modules whose names start with `_` are private to the std library
+                let is_private = !is_relative && (name.starts_with('_') || name == "prelude");
+                if is_private { None } else { Some(Completion { label: name, kind: CompletionKind::Module, detail: None, doc: None }) }
+            } else if entry.is_dir() {
+                Some(Completion { label: name, kind: CompletionKind::Folder, detail: None, doc: None })
+            } else {
+                None
+            }
+        })
+        .unique_by(|completion| completion.label.clone())
+        .collect()
+}
+
+fn replace_line(lines: &Vec<&str>, line_num: usize, replacement: &str) -> String {
+    let mut lines = lines.iter().map(|line| line.to_string()).collect_vec();
+    while lines.len() < line_num {
+        lines.push("".to_string());
+    }
+    lines[line_num - 1] = replacement.to_string();
+    lines.join("\n")
+}
+
+pub(crate) fn parses(entry: &parser::ast::ModuleId, source: &str) -> bool {
+    let Ok(tokens) = tokenize(entry, &source.to_string()) else { return false; };
+    parser::parser::parse(entry.clone(), tokens).is_ok()
+}
+
+// A bracket left open in the source, and the offsets (in chars) of the commas directly within it
+pub(crate) struct OpenBracket {
+    pub ch: char,
+    pub offset: usize,
+    pub comma_offsets: Vec<usize>,
+}
+
+// The brackets left open in the source, outermost first (ignoring any within strings and comments)
+pub(crate) fn open_brackets(source: &str) -> Vec<OpenBracket> {
+    let mut open_brackets: Vec<OpenBracket> = vec![];
+    let mut in_string = false;
+    let mut chars = source.chars().enumerate().peekable();
+    while let Some((offset, ch)) = chars.next() {
+        if in_string {
+            match ch {
+                '\\' => { chars.next(); }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => in_string = true,
+            '/' if chars.peek().is_some_and(|(_, ch)| *ch == '/') => {
+                while chars.next_if(|(_, ch)| *ch != '\n').is_some() {}
+            }
+            '(' | '[' | '{' => open_brackets.push(OpenBracket { ch, offset, comma_offsets: vec![] }),
+            ')' | ']' | '}' => { open_brackets.pop(); }
+            ',' => {
+                if let Some(bracket) = open_brackets.last_mut() { bracket.comma_offsets.push(offset); }
+            }
+            _ => {}
+        }
+    }
+
+    open_brackets
+}
+
+// The brackets which would close those left open in the source
+pub(crate) fn closing_brackets(source: &str) -> String {
+    open_brackets(source).iter().rev()
+        .map(|bracket| match bracket.ch {
+            '(' => ")",
+            '[' => "]",
+            _ => "\n}",
+        })
+        .join("")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::completion::{Completion, CompletionKind};
+    use crate::ide::{extract_cursor, Analysis, TEST_UTILS};
+    use crate::parser;
+    use crate::typechecker::test_helpers::std_loader;
+
+    // The cursor is at `<|>`
+    fn complete(source: &str) -> Vec<(String, CompletionKind, Option<String>)> {
+        let (source, position) = extract_cursor(source);
+        let loader = std_loader(&[("/project/utils.abra", TEST_UTILS)]);
+        let entry = parser::ast::ModuleId::parse_module_path("./main").unwrap();
+
+        Analysis::completions(loader, &entry, "/project/main.abra", &source, &position).into_iter()
+            .map(|Completion { label, kind, detail, .. }| (label, kind, detail))
+            .collect()
+    }
+
+    fn labels(completions: &Vec<(String, CompletionKind, Option<String>)>) -> Vec<&str> {
+        completions.iter().map(|(label, _, _)| label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_complete_members() {
+        let source = "\
+import Point, Shape from \"./utils\"
+func f(p: Point?) {
+  val x = p?.<|>
+  val y = 1
+";
+        let completions = complete(source);
+        assert_eq!(Some(&("x".to_string(), CompletionKind::Field, Some("x: Int".to_string()))), completions.first());
+        assert!(labels(&completions).contains(&"dist"));
+
+        let completions = complete("import Point, Shape from \"./utils\"\nval s = Shape.D<|>\nval t = 1");
+        assert_eq!(vec!["Dot", "Empty"], labels(&completions));
+        assert_eq!(CompletionKind::EnumVariant, completions[0].1);
+
+        let completions = complete("import \"./utils\" as u\nval o = u.<|>");
+        assert_eq!(vec!["Point", "Shape", "origin", "sum"], labels(&completions));
+    }
+
+    #[test]
+    fn test_complete_scope() {
+        let source = "\
+import Point from \"./utils\"
+val a = 1
+func f(count: Int) {
+  val b = 2
+  if true {
+    val c = co<|>
+  }
+  val d = 3
+}
+";
+        let completions = complete(source);
+        let names = labels(&completions);
+        assert!(["count", "b", "a", "f", "Point", "println"].iter().all(|name| names.contains(name)));
+        assert!(!names.contains(&"c") && !names.contains(&"d"));
+        assert!(completions.contains(&("count".to_string(), CompletionKind::Variable, Some("count: Int".to_string()))));
+
+        // Only types are offered in type positions
+        let completions = complete("import Point from \"./utils\"\nval a = 1\nval p: Po<|> = Point(x: 1)");
+        let names = labels(&completions);
+        assert!(names.contains(&"Point") && names.contains(&"Int") && !names.contains(&"a"));
+    }
+
+    #[test]
+    fn test_complete_imports() {
+        let completions = complete("import Point, <|> from \"./utils\"\nval a = 1");
+        assert_eq!(vec![("Shape".to_string(), CompletionKind::Enum, Some("enum Shape".to_string())), ("origin".to_string(), CompletionKind::Function, Some("func origin(): Point".to_string()))], completions[..2]);
+        assert_eq!(vec!["Shape", "origin", "sum"], labels(&completions));
+
+        let completions = complete("import Point from \"./<|>");
+        assert_eq!(vec![("utils".to_string(), CompletionKind::Module, None)], completions);
+        let completions = complete("import \"<|>");
+        assert_eq!(vec![("libc".to_string(), CompletionKind::Module, None)], completions);
+    }
+}
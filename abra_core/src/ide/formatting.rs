@@ -0,0 +1,173 @@
+use itertools::Itertools;
+use crate::Error;
+use crate::formatter::{format_source, INDENT};
+use crate::ide::code_actions::TextEdit;
+use crate::lexer::lexer::tokenize;
+use crate::lexer::tokens::{Position, Range};
+use crate::parser::ast::ModuleId;
+use crate::parser::cst::TokenSpans;
+use crate::parser::parser::parse;
+
+// An edit which replaces the whole source with its canonical formatting, unless it's already formatted
+pub fn format_document(module_id: &ModuleId, source: &String) -> Result<Option<TextEdit>, Error> {
+    let formatted = format_source(module_id, source)?;
+    if formatted == *source {
+        return Ok(None);
+    }
+
+    Ok(Some(TextEdit { start: Position::new(1, 1), end: end_position(source), new_text: formatted }))
+}
+
+// Only the lines of the top-level items which overlap the range are reformatted. Formatting preserves the top-level items, so the
+// lines of the nth item in the source are replaced by those of the nth item in the formatted output.
+pub fn format_range(module_id: &ModuleId, source: &String, range: &Range) -> Result<Option<TextEdit>, Error> {
+    let formatted = format_source(module_id, source)?;
+    let source_items = item_lines(module_id, source)?;
+    let formatted_items = item_lines(module_id, &formatted)?;
+
+    let overlapping = source_items.iter()
+        .positions(|(start_line, end_line)| *start_line <= range.end.line && range.start.line <= *end_line)
+        .collect_vec();
+    let (Some(first), Some(last)) = (overlapping.first(), overlapping.last()) else { return Ok(None); };
+
+    let source_lines = source.lines().collect_vec();
+    let formatted_lines = formatted.lines().collect_vec();
+    let (start_line, end_line) = (source_items[*first].0, source_items[*last].1);
+    let new_lines = &formatted_lines[formatted_items[*first].0 - 1..formatted_items[*last].1];
+    if source_lines[start_line - 1..end_line] == *new_lines {
+        return Ok(None);
+    }
+
+    // The replaced lines include their trailing newline, if the last of them has one
+    let start = Position::new(start_line, 1);
+    let edit = if end_line < source_lines.len() || source.ends_with('\n') {
+        TextEdit { start, end: Position::new(end_line + 1, 1), new_text: format!("{}\n", new_lines.join("\n")) }
+    } else {
+        TextEdit { start, end: end_position(source), new_text: new_lines.join("\n") }
+    };
+    Ok(Some(edit))
+}
+
+// Re-indents the line on which a newline was typed, or on which a `}` was typed if it begins the line, to the depth of the brackets
+// left open by the lines before it
+pub fn on_type_formatting(source: &str, position: &Position, ch: char) -> Option<TextEdit> {
+    let line = source.lines().nth(position.line - 1).unwrap_or("");
+    let content = line.trim_start();
+    match ch {
+        '\n' => {}
+        '}' if content.starts_with('}') => {}
+        _ => return None,
+    }
+
+    let num_closers = content.chars().take_while(|ch| matches!(ch, '}' | ')' | ']')).count();
+    let indent = INDENT.repeat(open_brackets(source.lines().take(position.line - 1)).saturating_sub(num_closers));
+    let current_indent = &line[..line.len() - content.len()];
+    if current_indent == indent {
+        return None;
+    }
+
+    let end = Position::new(position.line, current_indent.chars().count() + 1);
+    Some(TextEdit { start: Position::new(position.line, 1), end, new_text: indent })
+}
+
+// The number of brackets which the lines leave open, not counting those within strings or comments. Like the repl's continuation
+// logic this is only an approximation (eg. it assumes strings don't span lines), but it doesn't need the code to parse.
+fn open_brackets<'a, I: Iterator<Item = &'a str>>(lines: I) -> usize {
+    let mut open = vec![];
+    for line in lines {
+        let mut chars = line.chars().peekable();
+        let mut in_string = false;
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' if in_string => { chars.next(); }
+                '"' => in_string = !in_string,
+                '/' if !in_string && chars.peek() == Some(&'/') => break,
+                '{' | '(' | '[' if !in_string => open.push(ch),
+                '}' | ')' | ']' if !in_string => {
+                    let opener = match ch { '}' => '{', ')' => '(', _ => '[' };
+                    if open.last() == Some(&opener) {
+                        open.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    open.len()
+}
+
+// The first and last lines of each top-level item
+fn item_lines(module_id: &ModuleId, source: &String) -> Result<Vec<(usize, usize)>, Error> {
+    let tokens = tokenize(module_id, source).map_err(Error::LexerError)?;
+    let parse_result = parse(module_id.clone(), tokens.clone()).map_err(Error::ParseError)?;
+    let spans = TokenSpans::new(tokens);
+
+    let lines = parse_result.nodes.iter()
+        .map(|node| (spans.start_pos(node).line, spans.token(spans.end_idx(node)).get_range().end.line))
+        .collect();
+    Ok(lines)
+}
+
+fn end_position(source: &str) -> Position {
+    let last_line = source.rsplit('\n').next().unwrap_or("");
+    Position::new(source.matches('\n').count() + 1, last_line.chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::code_actions::TextEdit;
+    use crate::ide::formatting::{format_document, format_range, on_type_formatting};
+    use crate::lexer::tokens::{Position, Range};
+    use crate::parser::ast::ModuleId;
+
+    fn edit(start: (usize, usize), end: (usize, usize), new_text: &str) -> TextEdit {
+        TextEdit { start: Position::new(start.0, start.1), end: Position::new(end.0, end.1), new_text: new_text.to_string() }
+    }
+
+    fn lines(start: usize, end: usize) -> Range {
+        Range { start: Position::new(start, 1), end: Position::new(end, 1) }
+    }
+
+    #[test]
+    fn test_format_document() {
+        let module_id = ModuleId::parse_module_path("./main").unwrap();
+
+        let source = "val  x=1\nfunc f( a:Int ):Int{\na}".to_string();
+        let expected = edit((1, 1), (3, 3), "val x = 1\nfunc f(a: Int): Int {\n  a\n}\n");
+        assert_eq!(Some(expected), format_document(&module_id, &source).unwrap());
+
+        assert_eq!(None, format_document(&module_id, &"val x = 1\n".to_string()).unwrap());
+        assert!(format_document(&module_id, &"val x = (".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_format_range() {
+        let module_id = ModuleId::parse_module_path("./main").unwrap();
+        let source = "val  a=1\n\n@Foo\nfunc f( a:Int ):Int{\na}\nval  b=[\n1,2]\n// comment\nval  c=3".to_string();
+
+        // Only the items which overlap the range are formatted, along with the comments between them
+        let expected = edit((3, 1), (6, 1), "@Foo\nfunc f(a: Int): Int {\n  a\n}\n");
+        assert_eq!(Some(expected), format_range(&module_id, &source, &lines(4, 4)).unwrap());
+        let expected = edit((6, 1), (9, 9), "val b = [\n  1,\n  2,\n]\n// comment\nval c = 3");
+        assert_eq!(Some(expected), format_range(&module_id, &source, &lines(7, 9)).unwrap());
+
+        assert_eq!(None, format_range(&module_id, &source, &lines(2, 2)).unwrap());
+        assert_eq!(None, format_range(&module_id, &"val a = 1\nval  b=2".to_string(), &lines(1, 1)).unwrap());
+    }
+
+    #[test]
+    fn test_on_type_formatting() {
+        // After a newline, the new line is indented to the depth of the open brackets
+        let source = "func f() {\n  val x = [\n1";
+        assert_eq!(Some(edit((3, 1), (3, 1), "    ")), on_type_formatting(source, &Position::new(3, 1), '\n'));
+        let source = "func f() {\n  val s = \"{(\" // [\n    ";
+        assert_eq!(Some(edit((3, 1), (3, 5), "  ")), on_type_formatting(source, &Position::new(3, 1), '\n'));
+        assert_eq!(None, on_type_formatting("func f() {\n  ", &Position::new(2, 3), '\n'));
+
+        // A closing brace which begins a line is dedented
+        let source = "func f() {\n  if true {\n    1\n    }";
+        assert_eq!(Some(edit((4, 1), (4, 5), "  ")), on_type_formatting(source, &Position::new(4, 6), '}'));
+        let source = "func f() {\n  val x = {\n    a: 1 }";
+        assert_eq!(None, on_type_formatting(source, &Position::new(3, 11), '}'));
+    }
+}
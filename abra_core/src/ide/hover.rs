@@ -0,0 +1,263 @@
+use itertools::Itertools;
+use crate::ide::{source_text, walk_module, Analysis};
+use crate::lexer::tokens::{Position, Range};
+use crate::typechecker::typechecker2::{AccessorKind, Enum, EnumVariant, EnumVariantKind, Function, FunctionKind, ModuleId, Struct, StructField, Symbol, Type, TypeId, TypeKind, TypedNode, Variable, VariableAlias, PRELUDE_UNIT_TYPE_ID};
+
+#[derive(Debug, PartialEq)]
+pub struct Hover {
+    pub range: Range,
+    // A declaration-like rendering of the item under the cursor (eg. `func double(i: Int, times: Int = 2): Int`, or `val x: Int`)
+    pub signature: String,
+    pub doc: Option<String>,
+}
+
+impl Analysis {
+    // Hovering over a declaration describes the declared item; hovering over an expression describes the item it refers to (for
+    // identifiers and accessors), or otherwise the expression's type.
+    pub fn hover(&self, position: &Position) -> Option<Hover> {
+        let module_id = self.module_id?;
+        self.hover_declaration(&module_id, position).or_else(|| self.hover_expression(&module_id, position))
+    }
+
+    fn hover_declaration(&self, module_id: &ModuleId, position: &Position) -> Option<Hover> {
+        let (symbol, range) = self.declaration_at(module_id, position)?;
+        Some(self.symbol_hover(&symbol, range))
+    }
+
+    pub(crate) fn symbol_hover(&self, symbol: &Symbol, range: Range) -> Hover {
+        match *symbol {
+            Symbol::Variable(var_id) => {
+                let var = self.project.get_var_by_id(&var_id);
+                self.variable_hover(var, &var.type_id, range)
+            }
+            Symbol::Function(func_id) => self.function_hover(self.project.get_func_by_id(&func_id), range),
+            Symbol::Type(TypeKind::Struct(struct_id)) => self.struct_hover(self.project.get_struct_by_id(&struct_id), range),
+            Symbol::Type(TypeKind::Enum(enum_id)) => self.enum_hover(self.project.get_enum_by_id(&enum_id), range),
+            Symbol::Field(struct_id, field_idx) => {
+                let field = &self.project.get_struct_by_id(&struct_id).fields[field_idx];
+                self.field_hover(field, &field.type_id, range)
+            }
+            Symbol::EnumVariant(enum_id, variant_idx) => {
+                let enum_ = self.project.get_enum_by_id(&enum_id);
+                self.variant_hover(enum_, &enum_.variants[variant_idx], range)
+            }
+        }
+    }
+
+    fn hover_expression(&self, module_id: &ModuleId, position: &Position) -> Option<Hover> {
+        let mut innermost = None;
+        walk_module(&self.project, module_id, &mut |node| {
+            if is_expression(node) && node.span().contains(position) {
+                innermost = Some(node);
+            }
+        });
+        let node = innermost?;
+
+        match node {
+            TypedNode::Identifier { token, var_id, type_id, .. } => {
+                let var = self.project.get_var_by_id(var_id);
+                let range = token.get_range();
+                return Some(match &var.alias {
+                    VariableAlias::None => self.variable_hover(var, type_id, range),
+                    VariableAlias::Function(func_id) => self.function_hover(self.project.get_func_by_id(func_id), range),
+                    VariableAlias::Type(TypeKind::Struct(struct_id)) => self.struct_hover(self.project.get_struct_by_id(struct_id), range),
+                    VariableAlias::Type(TypeKind::Enum(enum_id)) => self.enum_hover(self.project.get_enum_by_id(enum_id), range),
+                });
+            }
+            TypedNode::Accessor { target, kind, is_opt_safe, member_idx, member_span, type_id, .. } if member_span.contains(position) => {
+                if let Some(hover) = self.member_hover(target, kind, *is_opt_safe, *member_idx, type_id, member_span.clone()) {
+                    return Some(hover);
+                }
+            }
+            _ => {}
+        }
+
+        Some(Hover { range: node.span(), signature: self.project.type_repr(node.type_id()), doc: None })
+    }
+
+    fn member_hover(&self, target: &TypedNode, kind: &AccessorKind, is_opt_safe: bool, member_idx: usize, type_id: &TypeId, range: Range) -> Option<Hover> {
+        let mut target_type_id = *target.type_id();
+        if is_opt_safe {
+            target_type_id = self.project.type_is_option(&target_type_id).unwrap_or(target_type_id);
+        }
+        let target_type = self.project.get_type_by_id(&target_type_id);
+
+        match kind {
+            AccessorKind::Field => target_type.get_field(&self.project, member_idx).map(|field| self.field_hover(field, type_id, range)),
+            AccessorKind::Method => target_type.get_method(&self.project, member_idx).map(|func_id| self.function_hover(self.project.get_func_by_id(&func_id), range)),
+            AccessorKind::StaticMethod => target_type.get_static_method(&self.project, member_idx).map(|func_id| self.function_hover(self.project.get_func_by_id(&func_id), range)),
+            AccessorKind::EnumVariant => {
+                let Type::Type(TypeKind::Enum(enum_id)) = target_type else { return None; };
+                let enum_ = self.project.get_enum_by_id(enum_id);
+                enum_.variants.get(member_idx).map(|variant| self.variant_hover(enum_, variant, range))
+            }
+        }
+    }
+
+    fn variable_hover(&self, var: &Variable, type_id: &TypeId, range: Range) -> Hover {
+        let keyword = if var.is_parameter { "" } else if var.is_mutable { "var " } else { "val " };
+        let signature = format!("{keyword}{}: {}", var.name, self.project.type_repr(type_id));
+        Hover { range, signature, doc: None }
+    }
+
+    fn function_hover(&self, func: &Function, range: Range) -> Hover {
+        Hover { range, signature: self.function_signature(func), doc: func.doc_comment.clone() }
+    }
+
+    fn struct_hover(&self, struct_: &Struct, range: Range) -> Hover {
+        let signature = format!("type {}{}", struct_.name, self.generics(&struct_.generic_ids));
+        Hover { range, signature, doc: struct_.doc_comment.clone() }
+    }
+
+    fn enum_hover(&self, enum_: &Enum, range: Range) -> Hover {
+        let signature = format!("enum {}{}", enum_.name, self.generics(&enum_.generic_ids));
+        Hover { range, signature, doc: enum_.doc_comment.clone() }
+    }
+
+    fn field_hover(&self, field: &StructField, type_id: &TypeId, range: Range) -> Hover {
+        let readonly = if field.is_readonly { " readonly" } else { "" };
+        let signature = format!("{}: {}{readonly}", field.name, self.project.type_repr(type_id));
+        Hover { range, signature, doc: field.doc_comment.clone() }
+    }
+
+    fn variant_hover(&self, enum_: &Enum, variant: &EnumVariant, range: Range) -> Hover {
+        let signature = match &variant.kind {
+            EnumVariantKind::Constant => format!("{}.{}", enum_.name, variant.name),
+            EnumVariantKind::Container(func_id) => format!("{}.{}({})", enum_.name, variant.name, self.params(self.project.get_func_by_id(func_id))),
+        };
+        Hover { range, signature, doc: None }
+    }
+
+    fn function_signature(&self, func: &Function) -> String {
+        let (prefix, suffix) = self.function_signature_parts(func);
+        format!("{prefix}{}{suffix}", self.params(func))
+    }
+
+    // The signature before and after the list of params (eg. `func double(` and `): Int`)
+    pub(crate) fn function_signature_parts(&self, func: &Function) -> (String, String) {
+        let owner = match &func.kind {
+            FunctionKind::Freestanding => "".to_string(),
+            FunctionKind::Method(type_id) | FunctionKind::StaticMethod(type_id) => format!("{}.", self.project.type_repr(type_id)),
+        };
+        let prefix = format!("func {owner}{}{}(", func.name, self.generics(&func.generic_ids));
+        let suffix = if func.return_type_id != PRELUDE_UNIT_TYPE_ID {
+            format!("): {}", self.project.type_repr(&func.return_type_id))
+        } else {
+            ")".to_string()
+        };
+        (prefix, suffix)
+    }
+
+    fn params(&self, func: &Function) -> String {
+        self.param_reprs(func).join(", ")
+    }
+
+    pub(crate) fn param_reprs(&self, func: &Function) -> Vec<String> {
+        let source = func.defined_span.as_ref().and_then(|span| self.source(span.module_id()));
+
+        func.params.iter()
+            .map(|param| {
+                if func.has_self() && param.name == "self" { return "self".to_string(); }
+
+                // A variadic param's type is its element type, so its array suffix is re-added to match the source
+                let (vararg, array) = if param.is_variadic { ("*", "[]") } else { ("", "") };
+                let default_value = self.default_value_repr(param.default_value.as_ref(), source.as_deref());
+                format!("{vararg}{}: {}{array}{default_value}", param.name, self.project.type_repr(&param.type_id))
+            })
+            .collect()
+    }
+
+    // The ` = <value>` suffix of a param or field with a default value, as written in the source
+    pub(crate) fn default_value_repr(&self, default_value: Option<&TypedNode>, source: Option<&str>) -> String {
+        match (default_value, source) {
+            (Some(default_value), Some(source)) => format!(" = {}", source_text(source, &default_value.span())),
+            (Some(_), None) => " = ...".to_string(),
+            (None, _) => "".to_string(),
+        }
+    }
+
+    fn generics(&self, generic_ids: &Vec<TypeId>) -> String {
+        if generic_ids.is_empty() { return "".to_string(); }
+
+        format!("<{}>", generic_ids.iter().map(|type_id| self.project.type_repr(type_id)).join(", "))
+    }
+}
+
+// Statements (and if/match expressions, whose blocks may span many lines) aren't described on hover
+fn is_expression(node: &TypedNode) -> bool {
+    !matches!(
+        node,
+        TypedNode::If { .. } | TypedNode::Match { .. } | TypedNode::Assignment { .. } | TypedNode::FuncDeclaration(_) |
+        TypedNode::TypeDeclaration(_) | TypedNode::EnumDeclaration(_) | TypedNode::BindingDeclaration { .. } |
+        TypedNode::ForLoop { .. } | TypedNode::WhileLoop { .. } | TypedNode::Break { .. } | TypedNode::Continue { .. } |
+        TypedNode::Return { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::analyze_files;
+    use crate::lexer::tokens::Position;
+
+    const SOURCE: &str = "\
+/// Doubles a number
+func double(i: Int, times = 2): Int = i * times
+val x = double(3)
+var names = [\"a\", \"b\"].map(n => n.length)
+
+/// A point in space
+type Point {
+  /// The horizontal position
+  x: Int
+  func dist(self, other: Point): Int = self.x - other.x
+}
+val p = Point(x: 1)
+val d = p.dist(p)
+
+enum Shape { Dot(p: Point), Empty }
+val s = Shape.Dot(p: p)
+val o: Point? = None
+val ox = o?.x
+";
+
+    fn hover(line: usize, col: usize) -> Option<(String, Option<String>, (usize, usize, usize, usize))> {
+        let analysis = analyze_files(&[("main.abra", SOURCE)]);
+        analysis.hover(&Position::new(line, col))
+            .map(|hover| (hover.signature, hover.doc, (hover.range.start.line, hover.range.start.col, hover.range.end.line, hover.range.end.col)))
+    }
+
+    #[test]
+    fn test_hover_declarations() {
+        let expected = ("func double(i: Int, times: Int = 2): Int".to_string(), Some("Doubles a number".to_string()), (2, 6, 2, 11));
+        assert_eq!(Some(expected), hover(2, 8));
+        assert_eq!(Some(("i: Int".to_string(), None, (2, 13, 2, 13))), hover(2, 13));
+        assert_eq!(Some(("var names: Int[]".to_string(), None, (4, 5, 4, 9))), hover(4, 5));
+        assert_eq!(Some(("type Point".to_string(), Some("A point in space".to_string()), (7, 6, 7, 10))), hover(7, 6));
+        assert_eq!(Some(("x: Int".to_string(), Some("The horizontal position".to_string()), (9, 3, 9, 3))), hover(9, 3));
+        assert_eq!(Some(("func Point.dist(self, other: Point): Int".to_string(), None, (10, 8, 10, 11))), hover(10, 9));
+        assert_eq!(Some(("Shape.Dot(p: Point)".to_string(), None, (15, 14, 15, 16))), hover(15, 14));
+    }
+
+    #[test]
+    fn test_hover_references() {
+        let expected = ("func double(i: Int, times: Int = 2): Int".to_string(), Some("Doubles a number".to_string()), (3, 9, 3, 14));
+        assert_eq!(Some(expected), hover(3, 10));
+        // Lambda parameters, with their inferred types
+        assert_eq!(Some(("n: String".to_string(), None, (4, 28, 4, 28))), hover(4, 28));
+        assert_eq!(Some(("length: Int".to_string(), None, (4, 35, 4, 40))), hover(4, 37));
+        assert_eq!(Some(("type Point".to_string(), Some("A point in space".to_string()), (12, 9, 12, 13))), hover(12, 9));
+        assert_eq!(Some(("func Point.dist(self, other: Point): Int".to_string(), None, (13, 11, 13, 14))), hover(13, 12));
+        assert_eq!(Some(("x: Int".to_string(), Some("The horizontal position".to_string()), (10, 45, 10, 45))), hover(10, 45));
+        assert_eq!(Some(("Shape.Dot(p: Point)".to_string(), None, (16, 15, 16, 17))), hover(16, 16));
+        // Fields accessed via `?.` are optional
+        assert_eq!(Some(("x: Int?".to_string(), Some("The horizontal position".to_string()), (18, 13, 18, 13))), hover(18, 13));
+    }
+
+    #[test]
+    fn test_hover_expressions() {
+        // The type of the innermost expression containing the position
+        assert_eq!(Some(("String[]".to_string(), None, (4, 13, 4, 21))), hover(4, 13));
+        assert_eq!(Some(("Int[]".to_string(), None, (4, 13, 4, 40))), hover(4, 27));
+        assert_eq!(None, hover(5, 1));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::ide::{source_text, walk_module, Analysis};
+use crate::lexer::tokens::{Position, Range, Token};
+use crate::typechecker::typechecker2::{ModuleId, TypeId, TypedNode};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InlayHintKind {
+    Type,
+    Parameter,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InlayHint {
+    // The hint is displayed before the character at this position
+    pub position: Position,
+    pub label: String,
+    pub kind: InlayHintKind,
+}
+
+impl Analysis {
+    // The hints on the lines of the range: the inferred types of `val`/`var` bindings and lambda params which have no type annotation,
+    // and the names of the params to which positional arguments are passed
+    pub fn inlay_hints(&self, range: &Range) -> Vec<InlayHint> {
+        let Some(module_id) = self.module_id else { return vec![]; };
+        let Some(source) = self.source(&module_id) else { return vec![]; };
+        let chars = SourceChars::new(&source);
+
+        let mut hints = vec![];
+        walk_module(&self.project, &module_id, &mut |node| {
+            match node {
+                TypedNode::BindingDeclaration { token, vars, expr: Some(expr), .. } => {
+                    let is_annotated = source_text(&source, &Range { start: token.get_range().end, end: expr.span().start }).contains(':');
+                    if is_annotated { return; }
+
+                    for var_id in vars {
+                        let var = self.project.get_var_by_id(var_id);
+                        if let Some(span) = &var.defined_span {
+                            hints.push(self.type_hint(&span.range.end, &var.type_id));
+                        }
+                    }
+                }
+                TypedNode::Lambda { func_id, .. } => {
+                    for param in &self.project.get_func_by_id(func_id).params {
+                        let Some(span) = &param.defined_span else { continue; };
+                        if chars.next_char(&span.range.end) != Some(':') {
+                            hints.push(self.type_hint(&span.range.end, &param.type_id));
+                        }
+                    }
+                }
+                TypedNode::Invocation { target, arguments, .. } => hints.extend(self.param_name_hints(&module_id, &chars, target, arguments)),
+                _ => {}
+            }
+        });
+
+        hints.retain(|hint| (range.start.line..=range.end.line).contains(&hint.position.line));
+        hints.sort_by(|a, b| a.position.cmp(&b.position));
+        hints
+    }
+
+    fn type_hint(&self, name_end: &Position, type_id: &TypeId) -> InlayHint {
+        let position = Position::new(name_end.line, name_end.col + 1);
+        InlayHint { position, label: format!(": {}", self.project.type_repr(type_id)), kind: InlayHintKind::Type }
+    }
+
+    // Arguments which are identifiers with the same name as their params don't need hints
+    fn param_name_hints(&self, module_id: &ModuleId, chars: &SourceChars, target: &TypedNode, arguments: &Vec<Option<TypedNode>>) -> Vec<InlayHint> {
+        let target_range = match target {
+            TypedNode::Identifier { token, .. } => token.get_range(),
+            TypedNode::Accessor { member_span, .. } => member_span.clone(),
+            _ => return vec![],
+        };
+        let module = &self.project.modules[module_id.0];
+        let Some(signature) = module.references.get(&target_range.start).and_then(|reference| self.signature(&reference.symbol)) else { return vec![]; };
+
+        signature.params.iter().zip(arguments)
+            .filter_map(|(param, arg)| {
+                let (Some(name), Some(arg)) = (&param.name, arg) else { return None; };
+                if param.is_variadic || matches!(arg, TypedNode::Identifier { token, .. } if Token::get_ident_name(token) == *name) {
+                    return None;
+                }
+
+                let position = positional_argument_start(chars, arg)?;
+                Some(InlayHint { position, label: format!("{}: ", name), kind: InlayHintKind::Parameter })
+            })
+            .collect()
+    }
+}
+
+// The start of the argument, if it's not labeled. The span of a lambda starts at its first param (or at its `=>` if it has none), so
+// it's moved back to any opening paren.
+fn positional_argument_start(chars: &SourceChars, arg: &TypedNode) -> Option<Position> {
+    let mut start = arg.span().start;
+    if let TypedNode::Lambda { .. } = arg {
+        if chars.char_at(&start) == Some('=') {
+            start = chars.prev_char(&start, &[')'])?.0;
+        } else if chars.next_char(&start) != Some('=') {
+            start = chars.prev_char(&start, &[])?.0;
+        }
+    }
+
+    match chars.prev_char(&start, &['(']) {
+        Some((_, ':')) => None,
+        _ => Some(start),
+    }
+}
+
+struct SourceChars(Vec<Vec<char>>);
+
+impl SourceChars {
+    fn new(source: &str) -> SourceChars {
+        SourceChars(source.lines().map(|line| line.chars().collect()).collect())
+    }
+
+    fn char_at(&self, position: &Position) -> Option<char> {
+        self.0.get(position.line - 1).and_then(|line| line.get(position.col - 1)).copied()
+    }
+
+    // The first non-whitespace char after the position
+    fn next_char(&self, position: &Position) -> Option<char> {
+        let mut line_num = position.line;
+        let mut skip = position.col;
+        loop {
+            let line = self.0.get(line_num - 1)?;
+            if let Some(ch) = line.iter().skip(skip).find(|ch| !ch.is_whitespace()) {
+                return Some(*ch);
+            }
+            line_num += 1;
+            skip = 0;
+        }
+    }
+
+    // The last char before the position which is neither whitespace nor one of the skipped chars, and its position
+    fn prev_char(&self, position: &Position, skipped: &[char]) -> Option<(Position, char)> {
+        let mut line_num = position.line;
+        let mut end = position.col - 1;
+        loop {
+            let line = self.0.get(line_num - 1)?;
+            let end_idx = end.min(line.len());
+            if let Some(idx) = line[..end_idx].iter().rposition(|ch| !ch.is_whitespace() && !skipped.contains(ch)) {
+                return Some((Position::new(line_num, idx + 1), line[idx]));
+            }
+            if line_num == 1 {
+                return None;
+            }
+            line_num -= 1;
+            end = usize::MAX;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::analyze_files;
+    use crate::ide::inlay_hints::InlayHintKind;
+    use crate::lexer::tokens::{Position, Range};
+
+    const SOURCE: &str = "\
+func add(a: Int, b: Int, *rest: Int[]): Int = a + b
+val x = add(1, 3)
+var (y, z) = (\"a\", [true])
+val w: Int = add(a: 4, b: 5)
+val b = 5
+val sums = [1, 2].map((i, idx) => add(i, b, 6, 7))
+val lengths = [\"a\"].map(s => s.length)
+type Point {
+  x: Int
+  func dist(self, other: Point): Int = self.x - other.x
+}
+val p = Point(x: 1)
+val d = p.dist(p)
+";
+
+    fn hints(start_line: usize, end_line: usize) -> Vec<((usize, usize), String, InlayHintKind)> {
+        let analysis = analyze_files(&[("main.abra", SOURCE)]);
+        let range = Range { start: Position::new(start_line, 1), end: Position::new(end_line, 1) };
+        analysis.inlay_hints(&range).into_iter()
+            .map(|hint| ((hint.position.line, hint.position.col), hint.label, hint.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_type_hints() {
+        let expected = vec![
+            ((2, 6), ": Int".to_string(), InlayHintKind::Type),
+            ((2, 13), "a: ".to_string(), InlayHintKind::Parameter),
+            ((2, 16), "b: ".to_string(), InlayHintKind::Parameter),
+            ((3, 7), ": String".to_string(), InlayHintKind::Type),
+            ((3, 10), ": Bool[]".to_string(), InlayHintKind::Type),
+        ];
+        assert_eq!(expected, hints(1, 4));
+
+        // Lambda params are hinted, as are positional arguments (except those named like their params, and those gathered into a
+        // variadic param)
+        let expected = vec![
+            ((6, 9), ": Int[]".to_string(), InlayHintKind::Type),
+            ((6, 23), "fn: ".to_string(), InlayHintKind::Parameter),
+            ((6, 25), ": Int".to_string(), InlayHintKind::Type),
+            ((6, 30), ": Int".to_string(), InlayHintKind::Type),
+            ((6, 39), "a: ".to_string(), InlayHintKind::Parameter),
+            ((7, 12), ": Int[]".to_string(), InlayHintKind::Type),
+            ((7, 25), "fn: ".to_string(), InlayHintKind::Parameter),
+            ((7, 26), ": String".to_string(), InlayHintKind::Type),
+        ];
+        assert_eq!(expected, hints(6, 7));
+    }
+
+    #[test]
+    fn test_param_name_hints() {
+        let expected = vec![
+            ((12, 6), ": Point".to_string(), InlayHintKind::Type),
+            ((13, 6), ": Int".to_string(), InlayHintKind::Type),
+            ((13, 16), "other: ".to_string(), InlayHintKind::Parameter),
+        ];
+        assert_eq!(expected, hints(12, 13));
+
+        let analysis = analyze_files(&[("main.abra", "val f = (s: String) => s.length\nval xs = [1].map(x => x)")]);
+        let hints = analysis.inlay_hints(&Range { start: Position::new(1, 1), end: Position::new(2, 1) });
+        let labels = hints.iter().map(|hint| hint.label.as_str()).collect::<Vec<_>>();
+        // Lambdas passed positionally are hinted before their params
+        assert_eq!(vec![": (String) => Int", ": Int[]", "fn: ", ": Int"], labels);
+        assert_eq!(Position::new(2, 18), hints[2].position);
+    }
+}
@@ -0,0 +1,318 @@
+use std::path::Path;
+use crate::common::diagnostic::Diagnostic;
+use crate::lexer::tokens::Range;
+use crate::parser;
+use crate::parser::ast::IndexingMode;
+use crate::typechecker::typechecker2::{AssignmentKind, Function, LoadModule, ModuleId, Project, ScopeId, TypecheckError, Typechecker2, TypedMatchCaseKind, TypedNode, PRELUDE_MODULE_ID};
+use crate::typechecker::virtual_module_loader::{normalize_path, VirtualModuleLoader};
+
+pub mod code_actions;
+pub mod completion;
+pub mod formatting;
+pub mod hover;
+pub mod inlay_hints;
+pub mod references;
+pub mod rename;
+pub mod semantic_tokens;
+pub mod signature_help;
+pub mod symbols;
+
+// The result of typechecking a file on behalf of an editor, against which queries (hover, go-to-definition, etc) are answered.
+// Typechecking stops at the first error, in which case the project only contains what was typechecked before it, and queries
+// aren't answered.
+pub struct Analysis {
+    pub loader: VirtualModuleLoader,
+    pub project: Project,
+    pub module_id: Option<ModuleId>,
+    pub error: Option<TypecheckError>,
+    // The scope in which typechecking stopped, if it failed
+    pub error_scope_id: Option<ScopeId>,
+}
+
+impl Analysis {
+    pub fn analyze(loader: VirtualModuleLoader, entry: &parser::ast::ModuleId) -> Analysis {
+        let mut analysis = Analysis { loader, project: Project::default(), module_id: None, error: None, error_scope_id: None };
+        analysis.typecheck(entry);
+        analysis
+    }
+
+    // Typechecks the entry again after some files have changed (their new contents having already been given to the loader), reusing
+    // every module which doesn't depend on them. Returns whether the analysis was affected at all, which it isn't if none of the files
+    // is part of the project (unless typechecking had previously failed, in which case it's always retried).
+    pub fn reanalyze<P: AsRef<Path>>(&mut self, entry: &parser::ast::ModuleId, changed_files: &[P]) -> bool {
+        let changed_files = changed_files.iter().map(normalize_path).collect::<Vec<_>>();
+        let changed_module_ids = self.project.modules.iter()
+            .filter(|m| changed_files.iter().any(|file| file.as_path() == Path::new(&m.name)))
+            .map(|m| m.id)
+            .collect::<Vec<_>>();
+        if changed_module_ids.is_empty() && self.error.is_none() {
+            return false;
+        }
+
+        // Changes to the std library (or to any module which it imports) invalidate everything
+        let invalidated_modules = self.project.invalidated_modules(&changed_module_ids);
+        let entry_module_id = self.loader.get_module_id(entry).copied();
+        let is_reusable = !invalidated_modules.contains(&PRELUDE_MODULE_ID) &&
+            invalidated_modules.iter().all(|module_id| self.source(module_id).is_some());
+        let Some(entry_module_id) = entry_module_id.filter(|_| is_reusable) else {
+            self.typecheck(entry);
+            return true;
+        };
+
+        let mut tc = Typechecker2::new(&mut self.loader, &mut self.project);
+        let result = tc.retypecheck_module(&entry_module_id, &invalidated_modules).map(|_| entry_module_id);
+        let scope_id = tc.current_scope_id();
+        self.set_result(result, scope_id);
+        true
+    }
+
+    fn typecheck(&mut self, entry: &parser::ast::ModuleId) {
+        self.loader.reset_modules();
+        self.project = Project::default();
+        let mut tc = Typechecker2::new(&mut self.loader, &mut self.project);
+        let result = tc.typecheck_prelude().and_then(|_| tc.typecheck_module(entry, None));
+        let scope_id = tc.current_scope_id();
+        self.set_result(result, scope_id);
+    }
+
+    fn set_result(&mut self, result: Result<ModuleId, TypecheckError>, current_scope_id: ScopeId) {
+        match result {
+            Ok(module_id) => {
+                self.module_id = Some(module_id);
+                self.error = None;
+                self.error_scope_id = None;
+            }
+            Err(e) => {
+                self.module_id = None;
+                self.error = Some(e);
+                self.error_scope_id = Some(current_scope_id);
+            }
+        }
+    }
+
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        self.error.as_ref().map(|e| Diagnostic::from_typecheck_error(e, &self.loader, &self.project))
+    }
+
+    pub fn source(&self, module_id: &ModuleId) -> Option<String> {
+        self.loader.get_path(module_id).and_then(|path| self.loader.load_file(&path))
+    }
+}
+
+// The text of the source within the range (which is inclusive of its end position)
+pub fn source_text(source: &str, range: &Range) -> String {
+    source.lines()
+        .enumerate()
+        .filter(|(idx, _)| (range.start.line..=range.end.line).contains(&(idx + 1)))
+        .map(|(idx, line)| {
+            let line_num = idx + 1;
+            let start = if line_num == range.start.line { range.start.col - 1 } else { 0 };
+            let end = if line_num == range.end.line { range.end.col } else { line.chars().count() };
+            line.chars().skip(start).take(end.saturating_sub(start)).collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Calls `f` with every node in a module's typed tree, descending into function and method bodies, lambdas, and the default values
+// of parameters and fields. Parents are visited before their children, so the last node visited which contains a position is the
+// innermost one.
+pub fn walk_module<'a, F: FnMut(&'a TypedNode)>(project: &'a Project, module_id: &ModuleId, f: &mut F) {
+    walk_nodes(project, &project.modules[module_id.0].code, f);
+}
+
+fn walk_nodes<'a, F: FnMut(&'a TypedNode)>(project: &'a Project, nodes: &'a Vec<TypedNode>, f: &mut F) {
+    for node in nodes {
+        walk_node(project, node, f);
+    }
+}
+
+fn walk_function<'a, F: FnMut(&'a TypedNode)>(project: &'a Project, func: &'a Function, f: &mut F) {
+    for param in &func.params {
+        if let Some(default_value) = &param.default_value { walk_node(project, default_value, f); }
+    }
+    walk_nodes(project, &func.body, f);
+}
+
+fn walk_node<'a, F: FnMut(&'a TypedNode)>(project: &'a Project, node: &'a TypedNode, f: &mut F) {
+    f(node);
+
+    match node {
+        TypedNode::Literal { .. } |
+        TypedNode::Identifier { .. } |
+        TypedNode::NoneValue { .. } |
+        TypedNode::Break { .. } |
+        TypedNode::Continue { .. } => {}
+        TypedNode::Unary { expr, .. } |
+        TypedNode::Grouped { expr, .. } => walk_node(project, expr, f),
+        TypedNode::Binary { left, right, .. } => {
+            walk_node(project, left, f);
+            walk_node(project, right, f);
+        }
+        TypedNode::Array { items, .. } |
+        TypedNode::Tuple { items, .. } |
+        TypedNode::Set { items, .. } => walk_nodes(project, items, f),
+        TypedNode::Map { items, .. } => {
+            for (key, value) in items {
+                walk_node(project, key, f);
+                walk_node(project, value, f);
+            }
+        }
+        TypedNode::Invocation { target, arguments, .. } => {
+            walk_node(project, target, f);
+            for arg in arguments.iter().flatten() {
+                walk_node(project, arg, f);
+            }
+        }
+        TypedNode::Accessor { target, .. } => walk_node(project, target, f),
+        TypedNode::Indexing { target, index, .. } => {
+            walk_node(project, target, f);
+            match index {
+                IndexingMode::Index(index) => walk_node(project, index, f),
+                IndexingMode::Range(start, end) => {
+                    if let Some(start) = start { walk_node(project, start, f); }
+                    if let Some(end) = end { walk_node(project, end, f); }
+                }
+            }
+        }
+        TypedNode::Lambda { func_id, .. } => walk_function(project, project.get_func_by_id(func_id), f),
+        TypedNode::Assignment { kind, expr, .. } => {
+            match kind {
+                AssignmentKind::Identifier { .. } => {}
+                AssignmentKind::Accessor { target, .. } => walk_node(project, target, f),
+                AssignmentKind::Indexing { target, index } => {
+                    walk_node(project, target, f);
+                    walk_node(project, index, f);
+                }
+            }
+            walk_node(project, expr, f);
+        }
+        TypedNode::If { condition, if_block, else_block, .. } => {
+            walk_node(project, condition, f);
+            walk_nodes(project, if_block, f);
+            walk_nodes(project, else_block, f);
+        }
+        TypedNode::Match { target, cases, .. } => {
+            walk_node(project, target, f);
+            for case in cases {
+                if let TypedMatchCaseKind::Constant(_, value) = &case.kind { walk_node(project, value, f); }
+                walk_nodes(project, &case.body, f);
+            }
+        }
+        TypedNode::FuncDeclaration(func_id) => walk_function(project, project.get_func_by_id(func_id), f),
+        TypedNode::TypeDeclaration(struct_id) => {
+            let struct_ = project.get_struct_by_id(struct_id);
+            for field in &struct_.fields {
+                if let Some(default_value) = &field.default_value { walk_node(project, default_value, f); }
+            }
+            for func_id in struct_.static_methods.iter().chain(&struct_.methods) {
+                walk_function(project, project.get_func_by_id(func_id), f);
+            }
+        }
+        TypedNode::EnumDeclaration(enum_id) => {
+            let enum_ = project.get_enum_by_id(enum_id);
+            for func_id in enum_.static_methods.iter().chain(&enum_.methods) {
+                walk_function(project, project.get_func_by_id(func_id), f);
+            }
+        }
+        TypedNode::BindingDeclaration { expr, .. } => {
+            if let Some(expr) = expr { walk_node(project, expr, f); }
+        }
+        TypedNode::ForLoop { iterator, body, .. } => {
+            walk_node(project, iterator, f);
+            walk_nodes(project, body, f);
+        }
+        TypedNode::WhileLoop { condition, body, .. } => {
+            walk_node(project, condition, f);
+            walk_nodes(project, body, f);
+        }
+        TypedNode::Return { expr, .. } => {
+            if let Some(expr) = expr { walk_node(project, expr, f); }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn analyze_files(files: &[(&str, &str)]) -> Analysis {
+    let analysis = try_analyze_files(files);
+    if let Some(diagnostic) = analysis.diagnostic() {
+        panic!("{}", diagnostic.rendered);
+    }
+    analysis
+}
+
+#[cfg(test)]
+pub(crate) fn try_analyze_files(files: &[(&str, &str)]) -> Analysis {
+    let loader = crate::typechecker::test_helpers::std_loader(files);
+    Analysis::analyze(loader, &parser::ast::ModuleId::parse_module_path("./main").unwrap())
+}
+
+// A module for tests to import from, at `/project/utils.abra`
+#[cfg(test)]
+pub(crate) const TEST_UTILS: &str = "\
+export type Point {
+  x: Int
+  y: Int = 0
+  func dist(self, other: Point): Int = self.x - other.x
+}
+export enum Shape { Dot(p: Point), Empty }
+export func origin(): Point = Point(x: 0)
+/// Sums some numbers
+export func sum(a: Int, b: Int, *rest: Int[]): Int = a + b
+";
+
+// Removes the `<|>` marker from the source, returning the source and the position of the cursor which it marks
+#[cfg(test)]
+pub(crate) fn extract_cursor(source: &str) -> (String, crate::lexer::tokens::Position) {
+    let (line_idx, line) = source.lines().enumerate().find(|(_, line)| line.contains("<|>")).expect("There should be a cursor");
+    let position = crate::lexer::tokens::Position::new(line_idx + 1, line.find("<|>").unwrap() + 1);
+    (source.replace("<|>", ""), position)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::{analyze_files, source_text};
+    use crate::lexer::tokens::{Position, Range};
+    use crate::parser;
+
+    #[test]
+    fn test_source_text() {
+        let source = "val x = 1\nval y = [\n  1,\n  2\n]";
+        assert_eq!("x", source_text(source, &Range { start: Position::new(1, 5), end: Position::new(1, 5) }));
+        assert_eq!("[\n  1,\n  2\n]", source_text(source, &Range { start: Position::new(2, 9), end: Position::new(5, 1) }));
+    }
+
+    #[test]
+    fn test_reanalyze() {
+        let main = "import double from \"./utils\"\nimport triple from \"./math\"\nval x = double(triple(1))";
+        let mut analysis = analyze_files(&[("/project/main.abra", main), ("/project/utils.abra", "export func double(i: Int): Int = i * 2"), ("/project/math.abra", "export func triple(i: Int): Int = i * 3")]);
+        let entry = parser::ast::ModuleId::parse_module_path("./main").unwrap();
+        let num_modules = analysis.project.modules.len();
+        let x_position = Position::new(3, 5);
+        assert_eq!("val x: Int", analysis.hover(&x_position).unwrap().signature);
+
+        // Only the changed module and the modules which depend on it are typechecked again
+        analysis.loader.add_file("/project/utils.abra", "export func double(i: Int): Float = i * 2.0");
+        assert!(analysis.reanalyze(&entry, &["/project/utils.abra"]));
+        assert!(analysis.error.is_none());
+        assert_eq!("val x: Float", analysis.hover(&x_position).unwrap().signature);
+        assert_eq!(num_modules, analysis.project.modules.len());
+        let module_names = analysis.project.module_order.iter().rev().take(3).map(|module_id| analysis.project.modules[module_id.0].name.clone()).collect::<Vec<_>>();
+        assert_eq!(vec!["/project/main.abra", "/project/utils.abra", "/project/math.abra"], module_names);
+
+        // Files which aren't part of the project don't affect it
+        analysis.loader.add_file("/project/other.abra", "val y = 1");
+        assert!(!analysis.reanalyze(&entry, &["/project/other.abra"]));
+
+        analysis.loader.add_file("/project/math.abra", "export func triple(i: Int): Int = i *");
+        assert!(analysis.reanalyze(&entry, &["/project/math.abra"]));
+        assert_eq!("/project/math.abra", analysis.diagnostic().unwrap().file);
+        assert!(analysis.hover(&x_position).is_none());
+
+        // Once the broken module is no longer imported, it's no longer typechecked
+        analysis.loader.add_file("/project/main.abra", "import double from \"./utils\"\nval x = double(1)");
+        assert!(analysis.reanalyze(&entry, &["/project/main.abra"]));
+        assert!(analysis.error.is_none());
+        assert_eq!("val x: Float", analysis.hover(&Position::new(2, 5)).unwrap().signature);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::ide::Analysis;
+use crate::lexer::tokens::{Position, Range};
+use crate::typechecker::typechecker2::{LoadModule, ModuleId, Span, Symbol, TypeKind, VariableAlias};
+
+// A range within a file, which may be outside of the analyzed module (eg. in an imported module, or in the std library)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub file: String,
+    pub range: Range,
+}
+
+impl Analysis {
+    // The symbol under the cursor (either a usage of a name, or its declaration), and the range of the name
+    pub fn symbol_at(&self, position: &Position) -> Option<(Symbol, Range)> {
+        let module_id = self.module_id?;
+        let module = &self.project.modules[module_id.0];
+
+        // References are keyed by their start position, so the only candidate is the last one which starts before the position
+        if let Some((_, reference)) = module.references.range(..=position.clone()).next_back() {
+            if reference.range.contains(position) {
+                return Some((reference.symbol, reference.range.clone()));
+            }
+        }
+
+        self.declaration_at(&module_id, position)
+    }
+
+    pub(crate) fn declaration_at(&self, module_id: &ModuleId, position: &Position) -> Option<(Symbol, Range)> {
+        let declared_at = |span: &Span| span.module_id() == module_id && span.range.contains(position);
+        let module = &self.project.modules[module_id.0];
+
+        for struct_ in &module.structs {
+            if let Some(span) = struct_.defined_span.as_ref().filter(|span| declared_at(span)) {
+                return Some((Symbol::Type(TypeKind::Struct(struct_.id)), span.range.clone()));
+            }
+            if let Some((idx, field)) = struct_.fields.iter().enumerate().find(|(_, f)| declared_at(&f.defined_span)) {
+                return Some((Symbol::Field(struct_.id, idx), field.defined_span.range.clone()));
+            }
+        }
+        for enum_ in &module.enums {
+            if declared_at(&enum_.defined_span) {
+                return Some((Symbol::Type(TypeKind::Enum(enum_.id)), enum_.defined_span.range.clone()));
+            }
+            if let Some((idx, variant)) = enum_.variants.iter().enumerate().find(|(_, v)| declared_at(&v.defined_span)) {
+                return Some((Symbol::EnumVariant(enum_.id, idx), variant.defined_span.range.clone()));
+            }
+        }
+        for scope in &module.scopes {
+            if let Some(func) = scope.funcs.iter().find(|f| f.defined_span.as_ref().is_some_and(declared_at)) {
+                return Some((Symbol::Function(func.id), func.defined_span.as_ref().unwrap().range.clone()));
+            }
+            // Variables which alias functions and types share their declarations' spans, so they've been handled above
+            let var = scope.vars.iter().find(|v| v.alias == VariableAlias::None && v.defined_span.as_ref().is_some_and(declared_at));
+            if let Some(var) = var {
+                return Some((Symbol::Variable(var.id), var.defined_span.as_ref().unwrap().range.clone()));
+            }
+        }
+
+        None
+    }
+
+    // Builtins (eg. `Int`) have no declaration to go to
+    pub fn definition(&self, position: &Position) -> Option<Location> {
+        let (symbol, _) = self.symbol_at(position)?;
+        let span = self.project.symbol_defined_span(&symbol)?;
+
+        self.location(span.module_id(), &span.range)
+    }
+
+    // Every usage of the symbol under the cursor in any module of the project (which includes only the modules reachable from the
+    // analyzed module, so usages in modules which import it aren't found)
+    pub fn references(&self, position: &Position, include_declaration: bool) -> Vec<Location> {
+        let Some((symbol, _)) = self.symbol_at(position) else { return vec![]; };
+
+        self.references_to(&symbol, include_declaration)
+    }
+
+    pub(crate) fn references_to(&self, symbol: &Symbol, include_declaration: bool) -> Vec<Location> {
+        let mut locations = vec![];
+        if include_declaration {
+            if let Some(span) = self.project.symbol_defined_span(symbol) {
+                locations.extend(self.location(span.module_id(), &span.range));
+            }
+        }
+        for module in &self.project.modules {
+            for reference in module.references.values().filter(|r| r.symbol == *symbol) {
+                locations.extend(self.location(&module.id, &reference.range));
+            }
+        }
+
+        locations
+    }
+
+    fn location(&self, module_id: &ModuleId, range: &Range) -> Option<Location> {
+        let file = self.loader.get_path(module_id)?;
+        Some(Location { file, range: range.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::analyze_files;
+    use crate::ide::references::Location;
+    use crate::lexer::tokens::{Position, Range};
+
+    const UTILS: &str = "\
+export type Point {
+  x: Int
+  func dist(self, other: Point): Int = self.x - other.x
+}
+export enum Shape { Dot(p: Point), Empty }
+export func origin(): Point = Point(x: 0)
+";
+
+    const MAIN: &str = "\
+import Point, Shape, origin from \"./utils\"
+val p = Point(x: 1)
+val d = p.dist(origin())
+val s = Shape.Dot(p: p)
+match s {
+  Shape.Dot(p) => p.x
+  _ => 0
+}
+val l = [1, 2].length
+";
+
+    fn loc(file: &str, start: (usize, usize), end: (usize, usize)) -> Location {
+        Location { file: file.to_string(), range: Range { start: Position::new(start.0, start.1), end: Position::new(end.0, end.1) } }
+    }
+
+    #[test]
+    fn test_definition() {
+        let analysis = analyze_files(&[("/project/main.abra", MAIN), ("/project/utils.abra", UTILS)]);
+
+        // Into another module, from an import list, a type reference, a method call, and an argument label
+        assert_eq!(Some(loc("/project/utils.abra", (1, 13), (1, 17))), analysis.definition(&Position::new(1, 8)));
+        assert_eq!(Some(loc("/project/utils.abra", (1, 13), (1, 17))), analysis.definition(&Position::new(2, 9)));
+        assert_eq!(Some(loc("/project/utils.abra", (3, 8), (3, 11))), analysis.definition(&Position::new(3, 12)));
+        assert_eq!(Some(loc("/project/utils.abra", (2, 3), (2, 3))), analysis.definition(&Position::new(2, 15)));
+        // Enum variants, in expressions and in match cases
+        assert_eq!(Some(loc("/project/utils.abra", (5, 21), (5, 23))), analysis.definition(&Position::new(4, 15)));
+        assert_eq!(Some(loc("/project/utils.abra", (5, 21), (5, 23))), analysis.definition(&Position::new(6, 9)));
+        // Into the std library
+        let Some(Location { file, .. }) = analysis.definition(&Position::new(9, 17)) else { panic!("Expected a definition for `length`") };
+        assert_eq!("/std/prelude.abra", file);
+        // Literals have no declarations
+        assert_eq!(None, analysis.definition(&Position::new(9, 10)));
+    }
+
+    #[test]
+    fn test_references() {
+        let analysis = analyze_files(&[("/project/main.abra", MAIN), ("/project/utils.abra", UTILS)]);
+
+        let expected = vec![
+            loc("/project/main.abra", (2, 5), (2, 5)),
+            loc("/project/main.abra", (3, 9), (3, 9)),
+            loc("/project/main.abra", (4, 22), (4, 22)),
+        ];
+        assert_eq!(expected, analysis.references(&Position::new(3, 9), true));
+
+        // From an argument label, across modules
+        let expected = vec![
+            loc("/project/utils.abra", (2, 3), (2, 3)),
+            loc("/project/main.abra", (2, 15), (2, 15)),
+            loc("/project/main.abra", (6, 21), (6, 21)),
+            loc("/project/utils.abra", (3, 45), (3, 45)),
+            loc("/project/utils.abra", (3, 55), (3, 55)),
+            loc("/project/utils.abra", (6, 37), (6, 37)),
+        ];
+        assert_eq!(expected, analysis.references(&Position::new(2, 15), true));
+        assert_eq!(expected[1..].to_vec(), analysis.references(&Position::new(2, 15), false));
+    }
+}
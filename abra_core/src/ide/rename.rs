@@ -0,0 +1,218 @@
+use crate::ide::references::Location;
+use crate::ide::Analysis;
+use crate::lexer::lexer::tokenize;
+use crate::lexer::tokens::{Position, Range, Token};
+use crate::parser;
+use crate::typechecker::typechecker2::{FunctionKind, ImportedValue, LoadModule, ScopeId, Span, Symbol, TypeId, TypeKind};
+
+#[derive(Debug, PartialEq)]
+pub enum RenameError {
+    // Builtins and items declared in the std library can't be renamed
+    NotRenameable,
+    InvalidName(String),
+    DuplicateName { name: String, original: Option<Location> },
+}
+
+impl RenameError {
+    pub fn message(&self) -> String {
+        match self {
+            RenameError::NotRenameable => "This element can't be renamed".to_string(),
+            RenameError::InvalidName(name) => format!("'{}' is not a valid identifier", name),
+            RenameError::DuplicateName { name, original: Some(Location { file, range }) } => {
+                format!("Cannot rename to '{}', since that name is already declared at {}:{}:{}", name, file, range.start.line, range.start.col)
+            }
+            RenameError::DuplicateName { name, original: None } => format!("Cannot rename to '{}', since that name is already declared", name),
+        }
+    }
+}
+
+impl Analysis {
+    // The range of the name under the cursor, if it can be renamed
+    pub fn prepare_rename(&self, position: &Position) -> Result<Range, RenameError> {
+        let (symbol, range) = self.symbol_at(position).ok_or(RenameError::NotRenameable)?;
+        self.assert_renameable(&symbol)?;
+
+        Ok(range)
+    }
+
+    // The locations of the declaration and every usage of the symbol under the cursor (in any loaded module, including import lists
+    // and accesses through module aliases), each of which is to be replaced with the new name.
+    pub fn rename(&self, position: &Position, new_name: &str) -> Result<Vec<Location>, RenameError> {
+        let (symbol, _) = self.symbol_at(position).ok_or(RenameError::NotRenameable)?;
+        self.assert_renameable(&symbol)?;
+
+        let is_identifier = matches!(tokenize(&parser::ast::ModuleId::External("_".to_string()), &new_name.to_string()).as_deref(), Ok([Token::Ident(_, name)]) if name == new_name);
+        if !is_identifier || new_name == "_" {
+            return Err(RenameError::InvalidName(new_name.to_string()));
+        }
+        if self.project.symbol_name(&symbol) != new_name {
+            self.assert_no_conflicts(&symbol, new_name)?;
+        }
+
+        Ok(self.references_to(&symbol, true))
+    }
+
+    fn assert_renameable(&self, symbol: &Symbol) -> Result<(), RenameError> {
+        let span = self.project.symbol_defined_span(symbol).ok_or(RenameError::NotRenameable)?;
+        let Some(path) = self.loader.get_path(span.module_id()) else { return Err(RenameError::NotRenameable); };
+        if self.loader.is_std_file(&path) || self.project.symbol_name(symbol) == "self" {
+            return Err(RenameError::NotRenameable);
+        }
+
+        Ok(())
+    }
+
+    // The new name must not collide with another name declared alongside the symbol (which, for exported symbols, includes the root
+    // scope of every module which imports them)
+    fn assert_no_conflicts(&self, symbol: &Symbol, new_name: &str) -> Result<(), RenameError> {
+        match symbol {
+            Symbol::Variable(var_id) => self.assert_no_conflicts_in_scope(&var_id.0, new_name)?,
+            Symbol::Function(func_id) => match &self.project.get_func_by_id(func_id).kind {
+                FunctionKind::Freestanding => self.assert_no_conflicts_in_scope(&func_id.0, new_name)?,
+                FunctionKind::Method(type_id) | FunctionKind::StaticMethod(type_id) => self.assert_no_conflicting_members(type_id, new_name)?,
+            },
+            Symbol::Type(TypeKind::Struct(struct_id)) => self.assert_no_conflicts_in_scope(&ScopeId(struct_id.0, 0), new_name)?,
+            Symbol::Type(TypeKind::Enum(enum_id)) => self.assert_no_conflicts_in_scope(&ScopeId(enum_id.0, 0), new_name)?,
+            Symbol::Field(struct_id, _) => self.assert_no_conflicting_members(&self.project.get_struct_by_id(struct_id).self_type_id, new_name)?,
+            Symbol::EnumVariant(enum_id, _) => self.assert_no_conflicting_members(&self.project.get_enum_by_id(enum_id).self_type_id, new_name)?,
+        }
+
+        for module in &self.project.modules {
+            let imports_symbol = module.imports.values().flatten().any(|imported_value| {
+                match imported_value {
+                    ImportedValue::Function(_, func_id) => *symbol == Symbol::Function(*func_id),
+                    ImportedValue::Type(_, type_kind) => *symbol == Symbol::Type(*type_kind),
+                    ImportedValue::Variable(_, var_id) => *symbol == Symbol::Variable(*var_id),
+                }
+            });
+            if imports_symbol {
+                self.assert_no_conflicts_in_scope(&ScopeId(module.id, 0), new_name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn assert_no_conflicts_in_scope(&self, scope_id: &ScopeId, new_name: &str) -> Result<(), RenameError> {
+        let name = new_name.to_string();
+        let scope = &self.project.modules[scope_id.0.0].scopes[scope_id.1];
+        if let Some(var) = scope.vars.iter().find(|var| var.name == name) {
+            return Err(self.duplicate_name_error(new_name, var.defined_span.as_ref()));
+        }
+
+        // Imported variables aren't declared in the importing module's root scope
+        if scope_id.1 == 0 {
+            if let Some((token, _)) = self.project.find_imported_var_by_name(&scope_id.0, &name) {
+                let original = self.loader.get_path(&scope_id.0).map(|file| Location { file, range: token.get_range() });
+                return Err(RenameError::DuplicateName { name, original });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn assert_no_conflicting_members(&self, type_id: &TypeId, new_name: &str) -> Result<(), RenameError> {
+        let mut members = vec![];
+        let method_ids = if let Some((struct_, _)) = self.project.get_struct_by_type_id(type_id) {
+            members.extend(struct_.fields.iter().map(|field| (&field.name, Some(&field.defined_span))));
+            struct_.methods.iter().chain(&struct_.static_methods)
+        } else if let Some((enum_, _, _)) = self.project.get_enum_by_type_id(type_id) {
+            members.extend(enum_.variants.iter().map(|variant| (&variant.name, Some(&variant.defined_span))));
+            enum_.methods.iter().chain(&enum_.static_methods)
+        } else {
+            return Ok(());
+        };
+        for func_id in method_ids {
+            let func = self.project.get_func_by_id(func_id);
+            members.push((&func.name, func.defined_span.as_ref()));
+        }
+
+        match members.into_iter().find(|(name, _)| *name == new_name) {
+            Some((_, span)) => Err(self.duplicate_name_error(new_name, span)),
+            None => Ok(()),
+        }
+    }
+
+    fn duplicate_name_error(&self, new_name: &str, span: Option<&Span>) -> RenameError {
+        let original = span.and_then(|span| {
+            self.loader.get_path(span.module_id()).map(|file| Location { file, range: span.range.clone() })
+        });
+        RenameError::DuplicateName { name: new_name.to_string(), original }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::analyze_files;
+    use crate::ide::references::Location;
+    use crate::ide::rename::RenameError;
+    use crate::lexer::tokens::{Position, Range};
+
+    const UTILS: &str = "\
+export type Point {
+  x: Int
+  y: Int
+  func dist(self, other: Point): Int = self.x - other.x
+}
+export func origin(): Point = Point(x: 0, y: 0)
+";
+
+    const MAIN: &str = "\
+import Point, origin from \"./utils\"
+import \"./utils\" as u
+val p = Point(x: 1, y: 2)
+val o = u.origin()
+val d = p.dist(other: o)
+func double(i: Int): Int = i * 2
+val l = [1, 2].length
+val q = u.Point(x: 3, y: 4)
+";
+
+    fn loc(file: &str, start: (usize, usize), end: (usize, usize)) -> Location {
+        Location { file: file.to_string(), range: Range { start: Position::new(start.0, start.1), end: Position::new(end.0, end.1) } }
+    }
+
+    #[test]
+    fn test_rename() {
+        let analysis = analyze_files(&[("/project/main.abra", MAIN), ("/project/utils.abra", UTILS)]);
+
+        // The declaration, the import list, references via the module alias, and usages in both modules
+        let expected = vec![
+            loc("/project/utils.abra", (1, 13), (1, 17)),
+            loc("/project/main.abra", (1, 8), (1, 12)),
+            loc("/project/main.abra", (3, 9), (3, 13)),
+            loc("/project/main.abra", (8, 11), (8, 15)),
+            loc("/project/utils.abra", (4, 26), (4, 30)),
+            loc("/project/utils.abra", (6, 23), (6, 27)),
+            loc("/project/utils.abra", (6, 31), (6, 35)),
+        ];
+        assert_eq!(Ok(expected), analysis.rename(&Position::new(3, 10), "Vec"));
+
+        let expected = vec![
+            loc("/project/utils.abra", (4, 19), (4, 23)),
+            loc("/project/main.abra", (5, 16), (5, 20)),
+            loc("/project/utils.abra", (4, 49), (4, 53)),
+        ];
+        assert_eq!(Ok(expected), analysis.rename(&Position::new(5, 17), "p2"));
+    }
+
+    #[test]
+    fn test_rename_refused() {
+        let analysis = analyze_files(&[("/project/main.abra", MAIN), ("/project/utils.abra", UTILS)]);
+
+        assert_eq!(Err(RenameError::NotRenameable), analysis.prepare_rename(&Position::new(7, 17)));
+        assert_eq!(Err(RenameError::NotRenameable), analysis.rename(&Position::new(7, 9), "x"));
+        assert_eq!(Err(RenameError::InvalidName("val".to_string())), analysis.rename(&Position::new(3, 5), "val"));
+        assert_eq!(Err(RenameError::InvalidName("a b".to_string())), analysis.rename(&Position::new(3, 5), "a b"));
+
+        // Conflicts with other variables, with fields, and with names in modules which import the symbol
+        let expected = RenameError::DuplicateName { name: "d".to_string(), original: Some(loc("/project/main.abra", (5, 5), (5, 5))) };
+        assert_eq!(Err(expected), analysis.rename(&Position::new(3, 5), "d"));
+        let expected = RenameError::DuplicateName { name: "y".to_string(), original: Some(loc("/project/utils.abra", (3, 3), (3, 3))) };
+        assert_eq!(Err(expected), analysis.rename(&Position::new(3, 15), "y"));
+        let expected = RenameError::DuplicateName { name: "double".to_string(), original: Some(loc("/project/main.abra", (6, 6), (6, 11))) };
+        assert_eq!(Err(expected), analysis.rename(&Position::new(4, 12), "double"));
+
+        assert_eq!(Ok(Range { start: Position::new(6, 13), end: Position::new(6, 13) }), analysis.prepare_rename(&Position::new(6, 13)));
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use crate::ide::Analysis;
+use crate::lexer::tokens::Range;
+use crate::typechecker::typechecker2::{FunctionKind, Symbol, TypeKind, VariableAlias};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SemanticTokenKind {
+    Namespace,
+    Type,
+    Enum,
+    EnumMember,
+    Property,
+    Function,
+    Method,
+    Parameter,
+    Variable,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SemanticToken {
+    pub range: Range,
+    pub kind: SemanticTokenKind,
+    // Variables declared with `var`
+    pub is_mutable: bool,
+}
+
+impl Analysis {
+    // Every name in the analyzed module (both usages and declarations), classified by what it resolves to, in order of position
+    pub fn semantic_tokens(&self) -> Vec<SemanticToken> {
+        let Some(module_id) = self.module_id else { return vec![]; };
+        let module = &self.project.modules[module_id.0];
+
+        // Declarations are added from the most general (eg. the function which constructs an enum variant) to the most specific
+        let mut symbols = BTreeMap::new();
+        for scope in &module.scopes {
+            for func in &scope.funcs {
+                if let Some(span) = &func.defined_span {
+                    symbols.insert(span.range.start.clone(), (span.range.clone(), Symbol::Function(func.id)));
+                }
+            }
+            // Variables which alias functions and types share their declarations' spans, so they've been handled above
+            for var in scope.vars.iter().filter(|var| var.alias == VariableAlias::None) {
+                if let Some(span) = &var.defined_span {
+                    symbols.insert(span.range.start.clone(), (span.range.clone(), Symbol::Variable(var.id)));
+                }
+            }
+        }
+
+        for struct_ in &module.structs {
+            if let Some(span) = &struct_.defined_span {
+                symbols.insert(span.range.start.clone(), (span.range.clone(), Symbol::Type(TypeKind::Struct(struct_.id))));
+            }
+            for (idx, field) in struct_.fields.iter().enumerate() {
+                symbols.insert(field.defined_span.range.start.clone(), (field.defined_span.range.clone(), Symbol::Field(struct_.id, idx)));
+            }
+        }
+        for enum_ in &module.enums {
+            symbols.insert(enum_.defined_span.range.start.clone(), (enum_.defined_span.range.clone(), Symbol::Type(TypeKind::Enum(enum_.id))));
+            for (idx, variant) in enum_.variants.iter().enumerate() {
+                symbols.insert(variant.defined_span.range.start.clone(), (variant.defined_span.range.clone(), Symbol::EnumVariant(enum_.id, idx)));
+            }
+        }
+        for reference in module.references.values() {
+            symbols.insert(reference.range.start.clone(), (reference.range.clone(), reference.symbol));
+        }
+
+        symbols.into_values()
+            .filter(|(range, _)| range.start.line == range.end.line)
+            .map(|(range, symbol)| self.semantic_token(range, &symbol))
+            .collect()
+    }
+
+    fn semantic_token(&self, range: Range, symbol: &Symbol) -> SemanticToken {
+        let mut is_mutable = false;
+        let kind = match symbol {
+            Symbol::Variable(var_id) => {
+                let var = self.project.get_var_by_id(var_id);
+                is_mutable = var.is_mutable;
+                if var.type_id.as_module_type_alias().is_some() {
+                    SemanticTokenKind::Namespace
+                } else if var.is_parameter {
+                    SemanticTokenKind::Parameter
+                } else {
+                    SemanticTokenKind::Variable
+                }
+            }
+            Symbol::Function(func_id) => match self.project.get_func_by_id(func_id).kind {
+                FunctionKind::Freestanding => SemanticTokenKind::Function,
+                FunctionKind::Method(_) | FunctionKind::StaticMethod(_) => SemanticTokenKind::Method,
+            },
+            Symbol::Type(TypeKind::Struct(_)) => SemanticTokenKind::Type,
+            Symbol::Type(TypeKind::Enum(_)) => SemanticTokenKind::Enum,
+            Symbol::Field(_, _) => SemanticTokenKind::Property,
+            Symbol::EnumVariant(_, _) => SemanticTokenKind::EnumMember,
+        };
+
+        SemanticToken { range, kind, is_mutable }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::analyze_files;
+    use crate::ide::semantic_tokens::SemanticTokenKind;
+
+    const UTILS: &str = "\
+export type Point {
+  x: Int
+  func dist(self, other: Point): Int = self.x - other.x
+}
+";
+
+    const MAIN: &str = "\
+import Point from \"./utils\"
+import \"./utils\" as u
+enum Shape { Dot(p: Point), Empty }
+var count = 0
+func f(p: Point): Int {
+  count += 1
+  p.dist(Point(x: 1)) + p.x
+}
+val s = Shape.Empty
+val q = u.Point(x: 2)
+";
+
+    #[test]
+    fn test_semantic_tokens() {
+        let analysis = analyze_files(&[("/project/main.abra", MAIN), ("/project/utils.abra", UTILS)]);
+        let tokens = analysis.semantic_tokens().into_iter()
+            .map(|token| (token.range.start.line, token.range.start.col, token.range.end.col - token.range.start.col + 1, token.kind, token.is_mutable))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            (1, 8, 5, SemanticTokenKind::Type, false),
+            (2, 21, 1, SemanticTokenKind::Namespace, false),
+            (3, 6, 5, SemanticTokenKind::Enum, false),
+            (3, 14, 3, SemanticTokenKind::EnumMember, false),
+            (3, 18, 1, SemanticTokenKind::Parameter, false),
+            (3, 21, 5, SemanticTokenKind::Type, false),
+            (3, 29, 5, SemanticTokenKind::EnumMember, false),
+            (4, 5, 5, SemanticTokenKind::Variable, true),
+            (5, 6, 1, SemanticTokenKind::Function, false),
+            (5, 8, 1, SemanticTokenKind::Parameter, false),
+            (5, 11, 5, SemanticTokenKind::Type, false),
+            (6, 3, 5, SemanticTokenKind::Variable, true),
+            (7, 3, 1, SemanticTokenKind::Parameter, false),
+            (7, 5, 4, SemanticTokenKind::Method, false),
+            (7, 10, 5, SemanticTokenKind::Type, false),
+            (7, 16, 1, SemanticTokenKind::Property, false),
+            (7, 25, 1, SemanticTokenKind::Parameter, false),
+            (7, 27, 1, SemanticTokenKind::Property, false),
+            (9, 5, 1, SemanticTokenKind::Variable, false),
+            (9, 9, 5, SemanticTokenKind::Enum, false),
+            (9, 15, 5, SemanticTokenKind::EnumMember, false),
+            (10, 5, 1, SemanticTokenKind::Variable, false),
+            (10, 9, 1, SemanticTokenKind::Namespace, false),
+            (10, 11, 5, SemanticTokenKind::Type, false),
+            (10, 17, 1, SemanticTokenKind::Property, false),
+        ];
+        assert_eq!(expected, tokens);
+    }
+}
@@ -0,0 +1,270 @@
+use itertools::Itertools;
+use crate::ide::completion::{closing_brackets, open_brackets, parses, PLACEHOLDER};
+use crate::ide::Analysis;
+use crate::lexer::tokens::Position;
+use crate::parser;
+use crate::typechecker::typechecker2::{EnumVariantKind, LoadModule, Symbol, Type, TypeId, TypeKind, PRELUDE_UNIT_TYPE_ID};
+use crate::typechecker::virtual_module_loader::VirtualModuleLoader;
+
+#[derive(Debug, PartialEq)]
+pub struct Signature {
+    // A declaration-like rendering of the invoked item (eg. `func range(start: Int, end: Int, stepBy: Int = 1): Int[]`)
+    pub label: String,
+    pub doc: Option<String>,
+    // The params to which arguments can be passed (so not including a method's `self`)
+    pub params: Vec<SignatureParam>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SignatureParam {
+    // The params of function-typed values have no names
+    pub name: Option<String>,
+    // The start and end offsets (in chars) of the part of the label which describes the param
+    pub label_offsets: (usize, usize),
+    pub is_variadic: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SignatureHelp {
+    pub signature: Signature,
+    pub active_param: Option<usize>,
+}
+
+impl Analysis {
+    // The signature of the invocation enclosing a position in `source`, which is the current contents of the file at `path` (the module
+    // `entry`), and the param to which the argument at the position is passed. Like with completions, the source is likely incomplete
+    // (eg. `range(1, `), so the invocation's target is resolved by repairing the source with a placeholder member access on the target
+    // (eg. `range.__completion__`), and typechecking up to it.
+    pub fn signature_help(mut loader: VirtualModuleLoader, entry: &parser::ast::ModuleId, path: &str, source: &str, position: &Position) -> Option<SignatureHelp> {
+        let chars = source.chars().collect_vec();
+        let cursor = char_offset(source, position);
+
+        // The innermost invocation, unless the position is within a block passed to it (eg. the body of a lambda argument)
+        let open_brackets = open_brackets(&chars[..cursor].iter().collect::<String>());
+        let paren_idx = open_brackets.iter().rposition(|bracket| bracket.ch == '(')?;
+        if open_brackets[paren_idx..].iter().any(|bracket| bracket.ch == '{') {
+            return None;
+        }
+        let paren = &open_brackets[paren_idx];
+        let target_start = paren.offset - chars[..paren.offset].iter().rev().take_while(|ch| is_ident_char(ch)).count();
+        if target_start == paren.offset {
+            return None;
+        }
+
+        let before_paren = chars[..paren.offset].iter().collect::<String>();
+        let candidates = [
+            format!("{}.{}{}", before_paren, PLACEHOLDER, chars[paren.offset..].iter().collect::<String>()),
+            format!("{}.{}{}", before_paren, PLACEHOLDER, closing_brackets(&before_paren)),
+        ];
+        let repaired = candidates.iter().find(|candidate| parses(entry, candidate))?;
+        loader.add_file(path, repaired.clone());
+
+        let analysis = Analysis::analyze(loader, entry);
+        let module = analysis.project.modules.iter().find(|module| analysis.loader.get_path(&module.id).as_deref() == Some(path))?;
+        let reference = module.references.get(&position_at(source, target_start))?;
+        let signature = analysis.signature(&reference.symbol)?;
+
+        // A labeled argument is passed to the param with that name; otherwise arguments are passed in order, with any extra arguments
+        // gathered into a trailing variadic param
+        let arg_start = paren.comma_offsets.last().unwrap_or(&paren.offset) + 1;
+        let arg = chars[arg_start..cursor].iter().collect::<String>();
+        let active_param = match arg.split_once(':') {
+            Some((label, _)) if is_identifier(label.trim()) => {
+                signature.params.iter().position(|param| param.name.as_deref() == Some(label.trim()))
+            }
+            _ => {
+                let arg_idx = paren.comma_offsets.len();
+                match signature.params.last() {
+                    _ if arg_idx < signature.params.len() => Some(arg_idx),
+                    Some(last_param) if last_param.is_variadic => Some(signature.params.len() - 1),
+                    _ => None,
+                }
+            }
+        };
+
+        Some(SignatureHelp { signature, active_param })
+    }
+
+    // The signature of an invocable symbol: a function, a struct (which is invoked to instantiate it), an enum variant with values,
+    // or a function-typed variable or field
+    pub(crate) fn signature(&self, symbol: &Symbol) -> Option<Signature> {
+        match *symbol {
+            Symbol::Function(func_id) => {
+                let func = self.project.get_func_by_id(&func_id);
+                let (prefix, suffix) = self.function_signature_parts(func);
+                let params = func.params.iter().zip(self.param_reprs(func))
+                    .map(|(param, repr)| (Some(param.name.clone()), param.is_variadic, repr))
+                    .collect();
+                let num_hidden_params = if func.has_self() { 1 } else { 0 };
+
+                Some(build_signature(prefix, params, num_hidden_params, suffix, func.doc_comment.clone()))
+            }
+            Symbol::Type(TypeKind::Struct(struct_id)) => {
+                let struct_ = self.project.get_struct_by_id(&struct_id);
+                let source = self.source(struct_.defined_span.as_ref()?.module_id());
+                let params = struct_.fields.iter()
+                    .map(|field| {
+                        let default_value = self.default_value_repr(field.default_value.as_ref(), source.as_deref());
+                        (Some(field.name.clone()), false, format!("{}: {}{}", field.name, self.project.type_repr(&field.type_id), default_value))
+                    })
+                    .collect();
+
+                Some(build_signature(format!("{}(", struct_.name), params, 0, ")".to_string(), struct_.doc_comment.clone()))
+            }
+            Symbol::Type(TypeKind::Enum(_)) => None,
+            Symbol::EnumVariant(enum_id, variant_idx) => {
+                let enum_ = self.project.get_enum_by_id(&enum_id);
+                let variant = &enum_.variants[variant_idx];
+                let EnumVariantKind::Container(func_id) = &variant.kind else { return None; };
+                let func = self.project.get_func_by_id(func_id);
+                let params = func.params.iter().zip(self.param_reprs(func))
+                    .map(|(param, repr)| (Some(param.name.clone()), param.is_variadic, repr))
+                    .collect();
+
+                Some(build_signature(format!("{}.{}(", enum_.name, variant.name), params, 0, ")".to_string(), None))
+            }
+            Symbol::Variable(var_id) => {
+                let var = self.project.get_var_by_id(&var_id);
+                self.function_type_signature(&var.name, &var.type_id)
+            }
+            Symbol::Field(struct_id, field_idx) => {
+                let field = &self.project.get_struct_by_id(&struct_id).fields[field_idx];
+                self.function_type_signature(&field.name, &field.type_id)
+            }
+        }
+    }
+
+    fn function_type_signature(&self, name: &String, type_id: &TypeId) -> Option<Signature> {
+        let Type::Function(param_type_ids, _, is_variadic, return_type_id) = self.project.get_type_by_id(type_id) else { return None; };
+
+        let num_params = param_type_ids.len();
+        let params = param_type_ids.iter().enumerate()
+            .map(|(idx, param_type_id)| {
+                let is_variadic = *is_variadic && idx == num_params - 1;
+                (None, is_variadic, self.project.type_repr(param_type_id))
+            })
+            .collect();
+        let suffix = if *return_type_id != PRELUDE_UNIT_TYPE_ID {
+            format!("): {}", self.project.type_repr(return_type_id))
+        } else {
+            ")".to_string()
+        };
+
+        Some(build_signature(format!("{}(", name), params, 0, suffix, None))
+    }
+}
+
+// Hidden params (ie. a method's `self`) are rendered in the label, but can't be passed arguments
+fn build_signature(prefix: String, params: Vec<(Option<String>, bool, String)>, num_hidden_params: usize, suffix: String, doc: Option<String>) -> Signature {
+    let mut label = prefix;
+    let mut signature_params = vec![];
+    for (idx, (name, is_variadic, repr)) in params.into_iter().enumerate() {
+        if idx > 0 {
+            label.push_str(", ");
+        }
+        let start = label.chars().count();
+        label.push_str(&repr);
+        if idx >= num_hidden_params {
+            signature_params.push(SignatureParam { name, label_offsets: (start, label.chars().count()), is_variadic });
+        }
+    }
+    label.push_str(&suffix);
+
+    Signature { label, doc, params: signature_params }
+}
+
+fn is_ident_char(ch: &char) -> bool {
+    ch.is_alphanumeric() || *ch == '_'
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.chars().next().is_some_and(|ch| !ch.is_numeric()) && s.chars().all(|ch| is_ident_char(&ch))
+}
+
+// The offset (in chars) of the position within the source; positions past the end of a line are clamped to it
+fn char_offset(source: &str, position: &Position) -> usize {
+    let mut offset = 0;
+    for (idx, line) in source.split('\n').enumerate() {
+        let line_len = line.chars().count();
+        if idx + 1 == position.line {
+            return offset + (position.col - 1).min(line_len);
+        }
+        offset += line_len + 1;
+    }
+
+    offset.saturating_sub(1)
+}
+
+fn position_at(source: &str, offset: usize) -> Position {
+    let preceding = source.chars().take(offset).collect::<String>();
+    let line = preceding.matches('\n').count() + 1;
+    let col = preceding.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+
+    Position::new(line, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::{extract_cursor, Analysis, TEST_UTILS};
+    use crate::ide::signature_help::SignatureHelp;
+    use crate::parser;
+    use crate::typechecker::test_helpers::std_loader;
+
+    // The cursor is at `<|>`
+    fn signature_help(source: &str) -> Option<SignatureHelp> {
+        let (source, position) = extract_cursor(source);
+        let loader = std_loader(&[("/project/utils.abra", TEST_UTILS)]);
+        let entry = parser::ast::ModuleId::parse_module_path("./main").unwrap();
+
+        Analysis::signature_help(loader, &entry, "/project/main.abra", &source, &position)
+    }
+
+    // The label, the part of it for the active param, and the doc
+    fn describe(help: Option<SignatureHelp>) -> Option<(String, Option<String>, Option<String>)> {
+        help.map(|SignatureHelp { signature, active_param }| {
+            let active = active_param.map(|idx| {
+                let (start, end) = signature.params[idx].label_offsets;
+                signature.label.chars().skip(start).take(end - start).collect()
+            });
+            (signature.label, active, signature.doc)
+        })
+    }
+
+    #[test]
+    fn test_signature_help_functions() {
+        let label = "func sum(a: Int, b: Int, *rest: Int[]): Int".to_string();
+        let doc = Some("Sums some numbers".to_string());
+
+        let help = describe(signature_help("import sum from \"./utils\"\nval x = sum(<|>"));
+        assert_eq!(Some((label.clone(), Some("a: Int".to_string()), doc.clone())), help);
+        let help = describe(signature_help("import sum from \"./utils\"\nval x = sum(1, <|>)\nval y = 1"));
+        assert_eq!(Some((label.clone(), Some("b: Int".to_string()), doc.clone())), help);
+        // Extra arguments are passed to the variadic param, and labeled arguments to the param with that name
+        let help = describe(signature_help("import sum from \"./utils\"\nval x = sum(1, 2, 3, [4, <|>"));
+        assert_eq!(Some((label.clone(), Some("*rest: Int[]".to_string()), doc.clone())), help);
+        let help = describe(signature_help("import sum from \"./utils\"\nval x = sum(a: 1, b: <|>"));
+        assert_eq!(Some((label.clone(), Some("b: Int".to_string()), doc.clone())), help);
+
+        // A method's `self` can't be passed an argument
+        let help = describe(signature_help("import Point from \"./utils\"\nval p = Point(x: 1)\nval d = p.dist(<|>"));
+        assert_eq!(Some(("func Point.dist(self, other: Point): Int".to_string(), Some("other: Point".to_string()), None)), help);
+
+        let help = describe(signature_help("val f = (i: Int) => i + 1\nval x = f(1, <|>"));
+        assert_eq!(Some(("f(Int): Int".to_string(), None, None)), help);
+    }
+
+    #[test]
+    fn test_signature_help_instantiations() {
+        let help = describe(signature_help("import Point from \"./utils\"\nval p = Point(x: 1, y: <|>)"));
+        assert_eq!(Some(("Point(x: Int, y: Int = 0)".to_string(), Some("y: Int = 0".to_string()), None)), help);
+        let help = describe(signature_help("import Point, Shape from \"./utils\"\nval s = Shape.Dot(<|>"));
+        assert_eq!(Some(("Shape.Dot(p: Point)".to_string(), Some("p: Point".to_string()), None)), help);
+    }
+
+    #[test]
+    fn test_signature_help_outside_invocations() {
+        assert_eq!(None, signature_help("val x = (1 + <|>"));
+        assert_eq!(None, signature_help("val x = [1, 2].map(i => {\n  val y = <|>\n  y\n})"));
+        assert_eq!(None, signature_help("func f(a: Int, <|>"));
+    }
+}
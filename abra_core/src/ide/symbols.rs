@@ -0,0 +1,235 @@
+use itertools::Itertools;
+use crate::ide::references::Location;
+use crate::ide::Analysis;
+use crate::lexer::tokens::Range;
+use crate::typechecker::typechecker2::{FuncId, FunctionKind, LoadModule, ModuleId, Symbol, TypeKind, TypedNode};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Struct,
+    Enum,
+    EnumVariant,
+    Field,
+    Function,
+    Method,
+    Variable,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // A declaration-like rendering of the symbol, as on hover
+    pub detail: String,
+    // The extent of the declaration (eg. including a function's body), and the range of its name within it
+    pub range: Range,
+    pub selection_range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WorkspaceSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub location: Location,
+    // The name of the type or enum which declares the symbol, if any
+    pub container_name: Option<String>,
+}
+
+impl Analysis {
+    // The outline of the analyzed module: its top-level functions and bindings, and its types and enums along with their members
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        let Some(module_id) = self.module_id else { return vec![]; };
+        self.module_symbols(&module_id)
+    }
+
+    // The symbols of every loaded module outside of the std library whose names contain the query (ignoring case)
+    pub fn workspace_symbols(&self, query: &str) -> Vec<WorkspaceSymbol> {
+        let query = query.to_lowercase();
+        let mut symbols = vec![];
+        for module in &self.project.modules {
+            let Some(file) = self.loader.get_path(&module.id) else { continue; };
+            if self.loader.is_std_file(&file) { continue; }
+
+            for symbol in self.module_symbols(&module.id) {
+                collect_workspace_symbols(&mut symbols, symbol, None, &file, &query);
+            }
+        }
+
+        symbols
+    }
+
+    fn module_symbols(&self, module_id: &ModuleId) -> Vec<DocumentSymbol> {
+        self.project.modules[module_id.0].code.iter().flat_map(|node| self.node_symbols(node)).collect()
+    }
+
+    fn node_symbols(&self, node: &TypedNode) -> Vec<DocumentSymbol> {
+        match node {
+            TypedNode::FuncDeclaration(func_id) => self.function_symbol(func_id).into_iter().collect(),
+            TypedNode::TypeDeclaration(struct_id) => {
+                let struct_ = self.project.get_struct_by_id(struct_id);
+                let Some(span) = &struct_.defined_span else { return vec![]; };
+
+                let mut children = struct_.fields.iter().enumerate()
+                    .map(|(idx, field)| self.symbol(Symbol::Field(*struct_id, idx), SymbolKind::Field, field.defined_span.range.clone(), vec![]))
+                    .collect_vec();
+                children.extend(struct_.static_methods.iter().chain(&struct_.methods).filter_map(|func_id| self.function_symbol(func_id)));
+                vec![self.container_symbol(Symbol::Type(TypeKind::Struct(*struct_id)), SymbolKind::Struct, &span.range, children)]
+            }
+            TypedNode::EnumDeclaration(enum_id) => {
+                let enum_ = self.project.get_enum_by_id(enum_id);
+
+                let mut children = enum_.variants.iter().enumerate()
+                    .map(|(idx, variant)| self.symbol(Symbol::EnumVariant(*enum_id, idx), SymbolKind::EnumVariant, variant.defined_span.range.clone(), vec![]))
+                    .collect_vec();
+                children.extend(enum_.static_methods.iter().chain(&enum_.methods).filter_map(|func_id| self.function_symbol(func_id)));
+                vec![self.container_symbol(Symbol::Type(TypeKind::Enum(*enum_id)), SymbolKind::Enum, &enum_.defined_span.range, children)]
+            }
+            TypedNode::BindingDeclaration { token, vars, expr, .. } => {
+                vars.iter()
+                    .filter_map(|var_id| {
+                        let span = self.project.get_var_by_id(var_id).defined_span.as_ref()?;
+                        let mut range = token.get_range().expand(&span.range);
+                        if let Some(expr) = expr {
+                            range = range.expand(&expr.span());
+                        }
+                        Some(DocumentSymbol { range, ..self.symbol(Symbol::Variable(*var_id), SymbolKind::Variable, span.range.clone(), vec![]) })
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    // A function's extent ends with its body (though declarations within it have no span of their own)
+    fn function_symbol(&self, func_id: &FuncId) -> Option<DocumentSymbol> {
+        let func = self.project.get_func_by_id(func_id);
+        let span = func.defined_span.as_ref()?;
+        let kind = match func.kind {
+            FunctionKind::Freestanding => SymbolKind::Function,
+            FunctionKind::Method(_) | FunctionKind::StaticMethod(_) => SymbolKind::Method,
+        };
+
+        let mut symbol = self.symbol(Symbol::Function(*func_id), kind, span.range.clone(), vec![]);
+        let body_end = func.body.iter().rev().find(|node| !is_declaration(node)).map(|node| node.span());
+        if let Some(body_end) = body_end {
+            symbol.range = symbol.range.expand(&body_end);
+        }
+        Some(symbol)
+    }
+
+    fn container_symbol(&self, symbol: Symbol, kind: SymbolKind, name_range: &Range, mut children: Vec<DocumentSymbol>) -> DocumentSymbol {
+        children.sort_by(|a, b| a.selection_range.start.cmp(&b.selection_range.start));
+        let range = children.iter().fold(name_range.clone(), |range, child| range.expand(&child.range));
+
+        DocumentSymbol { range, ..self.symbol(symbol, kind, name_range.clone(), children) }
+    }
+
+    fn symbol(&self, symbol: Symbol, kind: SymbolKind, name_range: Range, children: Vec<DocumentSymbol>) -> DocumentSymbol {
+        let name = self.project.symbol_name(&symbol).clone();
+        let detail = self.symbol_hover(&symbol, name_range.clone()).signature;
+
+        DocumentSymbol { name, kind, detail, range: name_range.clone(), selection_range: name_range, children }
+    }
+}
+
+fn is_declaration(node: &TypedNode) -> bool {
+    matches!(node, TypedNode::FuncDeclaration(_) | TypedNode::TypeDeclaration(_) | TypedNode::EnumDeclaration(_))
+}
+
+fn collect_workspace_symbols(symbols: &mut Vec<WorkspaceSymbol>, symbol: DocumentSymbol, container_name: Option<&String>, file: &String, query: &str) {
+    if symbol.name.to_lowercase().contains(query) {
+        let location = Location { file: file.clone(), range: symbol.selection_range.clone() };
+        symbols.push(WorkspaceSymbol { name: symbol.name.clone(), kind: symbol.kind, location, container_name: container_name.cloned() });
+    }
+    for child in symbol.children {
+        collect_workspace_symbols(symbols, child, Some(&symbol.name), file, query);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::analyze_files;
+    use crate::ide::references::Location;
+    use crate::ide::symbols::{DocumentSymbol, SymbolKind, WorkspaceSymbol};
+    use crate::lexer::tokens::{Position, Range};
+
+    const UTILS: &str = "\
+export type Point {
+  x: Int
+  func origin(): Point = Point(x: 0)
+  func dist(self, other: Point): Int {
+    val dx = self.x - other.x
+    dx
+  }
+}
+export enum Shape {
+  Dot(p: Point)
+  Empty
+  func isEmpty(self): Bool = self == Shape.Empty
+}
+";
+
+    const MAIN: &str = "\
+import Point from \"./utils\"
+func double(i: Int): Int = i * 2
+var (a, b) = (1, [2])
+val p = Point(x: double(a))
+";
+
+    fn range(start: (usize, usize), end: (usize, usize)) -> Range {
+        Range { start: Position::new(start.0, start.1), end: Position::new(end.0, end.1) }
+    }
+
+    fn outline(symbols: &Vec<DocumentSymbol>) -> Vec<(String, SymbolKind, Vec<(String, SymbolKind)>)> {
+        symbols.iter()
+            .map(|symbol| {
+                let children = symbol.children.iter().map(|child| (child.name.clone(), child.kind)).collect();
+                (symbol.name.clone(), symbol.kind, children)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let analysis = analyze_files(&[("/project/main.abra", UTILS)]);
+        let symbols = analysis.document_symbols();
+        let expected = vec![
+            ("Point".to_string(), SymbolKind::Struct, vec![("x".to_string(), SymbolKind::Field), ("origin".to_string(), SymbolKind::Method), ("dist".to_string(), SymbolKind::Method)]),
+            ("Shape".to_string(), SymbolKind::Enum, vec![("Dot".to_string(), SymbolKind::EnumVariant), ("Empty".to_string(), SymbolKind::EnumVariant), ("isEmpty".to_string(), SymbolKind::Method)]),
+        ];
+        assert_eq!(expected, outline(&symbols));
+
+        // Declarations span their members and bodies
+        assert_eq!(range((1, 13), (6, 6)), symbols[0].range);
+        assert_eq!(range((1, 13), (1, 17)), symbols[0].selection_range);
+        assert_eq!(range((4, 8), (6, 6)), symbols[0].children[2].range);
+        assert_eq!("func Point.dist(self, other: Point): Int", symbols[0].children[2].detail);
+
+        let analysis = analyze_files(&[("/project/main.abra", MAIN), ("/project/utils.abra", UTILS)]);
+        let symbols = analysis.document_symbols();
+        let expected = vec![
+            ("double".to_string(), SymbolKind::Function, vec![]),
+            ("a".to_string(), SymbolKind::Variable, vec![]),
+            ("b".to_string(), SymbolKind::Variable, vec![]),
+            ("p".to_string(), SymbolKind::Variable, vec![]),
+        ];
+        assert_eq!(expected, outline(&symbols));
+        assert_eq!("var b: Int[]", symbols[2].detail);
+        // Bindings' extents start at their keyword
+        assert_eq!(Position::new(3, 1), symbols[2].range.start);
+    }
+
+    #[test]
+    fn test_workspace_symbols() {
+        let analysis = analyze_files(&[("/project/main.abra", MAIN), ("/project/utils.abra", UTILS)]);
+
+        let expected = vec![
+            WorkspaceSymbol { name: "isEmpty".to_string(), kind: SymbolKind::Method, location: Location { file: "/project/utils.abra".to_string(), range: range((12, 8), (12, 14)) }, container_name: Some("Shape".to_string()) },
+        ];
+        assert_eq!(expected, analysis.workspace_symbols("EMPTY").into_iter().filter(|s| s.kind == SymbolKind::Method).collect::<Vec<_>>());
+
+        let names = analysis.workspace_symbols("o").into_iter().map(|symbol| symbol.name).collect::<Vec<_>>();
+        assert_eq!(vec!["double", "Point", "origin", "Dot"], names);
+    }
+}
@@ -1,48 +1,133 @@
 use peekmore::{PeekMore, PeekMoreIterator};
 use std::str::Chars;
-use crate::lexer::tokens::{Token, Position};
+use crate::lexer::tokens::{Comment, CommentKind, Token, Position, Trivia, TriviaToken};
 use crate::lexer::lexer_error::{LexerErrorKind, LexerError};
 use crate::parser::ast::ModuleId;
 use itertools::Itertools;
 
 pub fn tokenize(module_id: &ModuleId, input: &String) -> Result<Vec<Token>, LexerError> {
+    tokenize_with_comments(module_id, input).map(|(tokens, _)| tokens)
+}
+
+// Comments are usually discarded, but tooling which needs to reproduce them (eg. the formatter) can get them here, in source order.
+pub fn tokenize_with_comments(module_id: &ModuleId, input: &String) -> Result<(Vec<Token>, Vec<Comment>), LexerError> {
+    let (tokens, comments) = lex(module_id, input)?;
+    Ok((tokens.into_iter().map(|(token, _)| token).collect(), comments))
+}
+
+// Lossless tokenization: every character of the input is accounted for, either as part of a token's text or as trivia (whitespace
+// and comments) attached to a token. Any trivia after the last token is returned separately.
+pub fn tokenize_with_trivia(module_id: &ModuleId, input: &String) -> Result<(Vec<TriviaToken>, Vec<Trivia>), LexerError> {
+    let (tokens, comments) = lex(module_id, input)?;
+
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut line_starts = vec![0];
+    line_starts.extend(chars.iter().enumerate().filter(|(_, ch)| **ch == '\n').map(|(idx, _)| idx + 1));
+    let offset = |pos: &Position| line_starts[pos.line - 1] + pos.col - 1;
+    let text = |start: usize, end: usize| chars[start..end].iter().collect::<String>();
+
+    // The trivia before the first token, between each pair of tokens, and after the last token
+    let mut comments = comments.into_iter().peekable();
+    let mut gaps = Vec::with_capacity(tokens.len() + 1);
+    for idx in 0..=tokens.len() {
+        let gap_start = if idx == 0 { 0 } else { tokens[idx - 1].1 };
+        let gap_end = tokens.get(idx).map(|(token, _)| offset(&token.get_position())).unwrap_or(chars.len());
+
+        let mut trivia = vec![];
+        let mut cursor = gap_start;
+        while let Some(comment) = comments.next_if(|c| offset(&c.position) < gap_end) {
+            let comment_start = offset(&comment.position);
+            if comment_start > cursor {
+                trivia.push(Trivia::Whitespace(text(cursor, comment_start)));
+            }
+            cursor = comment_start + comment.text.chars().count();
+            trivia.push(Trivia::Comment(comment));
+        }
+        if gap_end > cursor {
+            trivia.push(Trivia::Whitespace(text(cursor, gap_end)));
+        }
+        gaps.push(trivia);
+    }
+
+    let mut gaps = gaps.into_iter();
+    let mut leading_trivia = gaps.next().unwrap();
+    let mut trivia_tokens = Vec::with_capacity(tokens.len());
+    for ((token, end), gap) in tokens.into_iter().zip(gaps) {
+        let text = text(offset(&token.get_position()), end);
+        let (trailing_trivia, next_leading_trivia) = split_trailing_trivia(gap);
+        trivia_tokens.push(TriviaToken { leading_trivia, token, text, trailing_trivia });
+        leading_trivia = next_leading_trivia;
+    }
+
+    Ok((trivia_tokens, leading_trivia))
+}
+
+// A token's trailing trivia is everything up to the next newline; the rest belongs to the following token
+fn split_trailing_trivia(gap: Vec<Trivia>) -> (Vec<Trivia>, Vec<Trivia>) {
+    let mut trailing = vec![];
+    let mut gap = gap.into_iter();
+    while let Some(trivia) = gap.next() {
+        match trivia {
+            Trivia::Whitespace(ws) if ws.contains('\n') => {
+                let newline_idx = ws.find('\n').unwrap();
+                if newline_idx > 0 {
+                    trailing.push(Trivia::Whitespace(ws[..newline_idx].to_string()));
+                }
+                let leading = vec![Trivia::Whitespace(ws[newline_idx..].to_string())].into_iter().chain(gap).collect();
+                return (trailing, leading);
+            }
+            trivia => trailing.push(trivia),
+        }
+    }
+
+    (trailing, vec![])
+}
+
+// Each token is paired with the (char) offset just past its end
+fn lex(module_id: &ModuleId, input: &String) -> Result<(Vec<(Token, usize)>, Vec<Comment>), LexerError> {
     let mut lexer = Lexer::new(input);
 
-    let mut tokens: Vec<Token> = vec![];
+    let mut tokens = vec![];
 
     loop {
         match lexer.next_token() {
             Err(kind) => return Err(LexerError { module_id: module_id.clone(), kind }),
             Ok(tok) => match tok {
-                Some(tok) => tokens.push(tok),
+                Some(tok) => tokens.push((tok, lexer.offset)),
                 None => break
             }
         }
     };
 
-    Ok(tokens)
+    Ok((tokens, lexer.comments))
 }
 
 struct Lexer<'a> {
     input: PeekMoreIterator<Chars<'a>>,
     line: usize,
     col: usize,
+    // The number of chars consumed so far
+    offset: usize,
+    comments: Vec<Comment>,
+    interpolation_depth: usize,
 }
 
 impl<'a> Lexer<'a> {
     fn new(input: &'a String) -> Self {
         let input = input.chars().peekmore();
 
-        Lexer { input, line: 1, col: 0 }
+        Lexer { input, line: 1, col: 0, offset: 0, comments: vec![], interpolation_depth: 0 }
     }
 
     fn advance(&mut self) -> Option<char> {
         self.col += 1;
+        self.offset += 1;
         self.input.next()
     }
 
     fn expect_next(&mut self) -> Result<char, LexerErrorKind> {
         self.col += 1;
+        self.offset += 1;
         self.input.next().ok_or(LexerErrorKind::UnexpectedEof(Position::new(self.line, self.col)))
     }
 
@@ -50,6 +135,13 @@ impl<'a> Lexer<'a> {
         self.input.peek()
     }
 
+    fn record_comment(&mut self, kind: CommentKind, position: Position, text: String) {
+        // Comments within an interpolated string's expressions are part of the string token itself
+        if self.interpolation_depth == 0 {
+            self.comments.push(Comment { kind, position, text });
+        }
+    }
+
     fn skip_whitespace(&mut self) -> bool {
         let mut saw_newline = false;
         loop {
@@ -258,7 +350,9 @@ impl<'a> Lexer<'a> {
                             chars = Vec::new();
 
                             self.expect_next()?; // Consume '$'
+                            self.interpolation_depth += 1;
                             let next_tok = self.next_token()?.expect("There is at least 1 alphabetic character there");
+                            self.interpolation_depth -= 1;
                             chunks.push(next_tok);
 
                             pos = Position::new(self.line, self.col + 1);
@@ -272,6 +366,7 @@ impl<'a> Lexer<'a> {
                             self.expect_next()?; // Consume '{'
                             let mut num_braces = 1;
 
+                            self.interpolation_depth += 1;
                             loop {
                                 let next_tok = self.next_token()?;
                                 let next_tok = next_tok.ok_or(LexerErrorKind::UnexpectedEof(Position::new(self.line, self.col)))?;
@@ -286,6 +381,7 @@ impl<'a> Lexer<'a> {
                                     chunks.push(next_tok);
                                 }
                             }
+                            self.interpolation_depth -= 1;
 
                             pos = Position::new(self.line, self.col + 1);
                             continue;
@@ -390,28 +486,37 @@ impl<'a> Lexer<'a> {
             '/' => {
                 if let Some('/') = self.peek() {
                     self.expect_next()?; // Consume '/' token
-                    while let Some(ch) = self.peek() {
-                        if ch == &'\n' {
+                    let mut text = "//".to_string();
+                    while let Some(&ch) = self.peek() {
+                        if ch == '\n' {
                             break; // The \n will get picked up later on, to increment the line
                         }
-                        self.expect_next()?; // Consume next token
+                        text.push(self.expect_next()?); // Consume next token
                     }
+                    self.record_comment(CommentKind::Line, pos, text.trim_end().to_string());
                     self.next_token()
                 } else if let Some('*') = self.peek() {
                     self.expect_next()?; // Consume '*' token
-                    while let Some(ch) = self.peek() {
-                        if ch == &'*' {
-                            self.expect_next()?; // Consume '*' token
+                    let mut text = "/*".to_string();
+                    while let Some(&ch) = self.peek() {
+                        if ch == '*' {
+                            text.push(self.expect_next()?); // Consume '*' token
                             if let Some('/') = self.peek() {
-                                self.expect_next()?; // Consume '/' token
+                                text.push(self.expect_next()?); // Consume '/' token
                                 break;
                             }
-                        } else if ch.is_whitespace() {
-                            self.skip_whitespace(); // Use skip_whitespace to ensure newlines get counted
+                        } else if ch == '\n' {
+                            // Consume newlines by hand (rather than with skip_whitespace), so they're kept in the comment's text
+                            self.line += 1;
+                            self.col = 0;
+                            self.offset += 1;
+                            self.input.next();
+                            text.push(ch);
                         } else {
-                            self.expect_next()?; // Consume next token
+                            text.push(self.expect_next()?); // Consume next token
                         }
                     }
+                    self.record_comment(CommentKind::Block, pos, text);
                     self.next_token()
                 } else if let Some('=') = self.peek() {
                     self.expect_next()?; // Consume '=' token
@@ -954,4 +1059,43 @@ mod tests {
         ];
         assert_eq!(expected, tokens);
     }
+
+    #[test]
+    fn test_tokenize_with_comments() {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let input = "// leading\nval x = 1 // trailing  \n/* multi\n  line */ val s = \"${x /* not a comment */}\"";
+        let (tokens, comments) = tokenize_with_comments(&module_id, &input.to_string()).unwrap();
+        assert_eq!(tokenize(input).unwrap(), tokens);
+
+        let expected = vec![
+            Comment { kind: CommentKind::Line, position: Position::new(1, 1), text: "// leading".to_string() },
+            Comment { kind: CommentKind::Line, position: Position::new(2, 11), text: "// trailing".to_string() },
+            Comment { kind: CommentKind::Block, position: Position::new(3, 1), text: "/* multi\n  line */".to_string() },
+        ];
+        assert_eq!(expected, comments);
+    }
+
+    #[test]
+    fn test_tokenize_with_trivia() {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let input = "// leading\nval x = 1.50 // trailing  \n\n  /* a */ x /* b */\n";
+        let (tokens, eof_trivia) = tokenize_with_trivia(&module_id, &input.to_string()).unwrap();
+
+        let reproduced = tokens.iter().map(|t| t.full_text()).collect::<String>() + &eof_trivia.iter().map(|t| t.text().clone()).collect::<String>();
+        assert_eq!(input, reproduced);
+
+        let texts = tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec!["val", "x", "=", "1.50", "x"], texts);
+
+        let line_comment = Comment { kind: CommentKind::Line, position: Position::new(1, 1), text: "// leading".to_string() };
+        assert_eq!(vec![Trivia::Comment(line_comment), Trivia::Whitespace("\n".to_string())], tokens[0].leading_trivia);
+        assert_eq!(vec![Trivia::Whitespace(" ".to_string())], tokens[0].trailing_trivia);
+
+        let trailing_comment = Comment { kind: CommentKind::Line, position: Position::new(2, 14), text: "// trailing".to_string() };
+        assert_eq!(vec![Trivia::Whitespace(" ".to_string()), Trivia::Comment(trailing_comment), Trivia::Whitespace("  ".to_string())], tokens[3].trailing_trivia);
+
+        let block_comment = Comment { kind: CommentKind::Block, position: Position::new(4, 3), text: "/* a */".to_string() };
+        assert_eq!(vec![Trivia::Whitespace("\n\n  ".to_string()), Trivia::Comment(block_comment), Trivia::Whitespace(" ".to_string())], tokens[4].leading_trivia);
+        assert_eq!(vec![Trivia::Whitespace("\n".to_string())], eof_trivia);
+    }
 }
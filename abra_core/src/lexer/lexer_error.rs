@@ -1,5 +1,5 @@
 use crate::lexer::tokens::{Position, Range};
-use crate::common::display_error::{DisplayError, IND_AMT};
+use crate::common::display_error::{DisplayError, ErrorDescription, IND_AMT};
 use crate::parser::ast::ModuleId;
 
 #[derive(Debug, PartialEq)]
@@ -26,6 +26,16 @@ impl LexerError {
             LexerErrorKind::UnsupportedEscapeSequence(pos, s, _) => Range::with_length(pos, s.len() - 1)
         }
     }
+
+    // See common::error_codes for the explanation of each code
+    pub fn code(&self) -> &'static str {
+        match &self.kind {
+            LexerErrorKind::UnexpectedChar(_, _) => "E0001",
+            LexerErrorKind::UnterminatedString(_, _) => "E0002",
+            LexerErrorKind::UnexpectedEof(_) => "E0003",
+            LexerErrorKind::UnsupportedEscapeSequence(_, _, _) => "E0004",
+        }
+    }
 }
 
 fn get_cursor(left_padding: usize) -> String {
@@ -33,56 +43,54 @@ fn get_cursor(left_padding: usize) -> String {
 }
 
 impl DisplayError for LexerError {
-    fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String {
+    fn describe(&self) -> ErrorDescription<Range> {
         match &self.kind {
-            LexerErrorKind::UnexpectedChar(pos, string) => {
-                let cursor_line = Self::get_underlined_line_no_token(lines, pos, &self.get_range());
-
-                format!(
-                    "Error at {}:{}:{}\nUnexpected character '{}':\n{}",
-                    file_name, pos.line, pos.col, string, cursor_line
-                )
-            }
+            LexerErrorKind::UnexpectedChar(_, string) => ErrorDescription::new(format!("Unexpected character '{}'", string)),
             LexerErrorKind::UnterminatedString(start_pos, end_pos) => {
-                let start_line = lines.get(start_pos.line - 1).expect("There should be a line");
-                let end_line = lines.get(end_pos.line - 1).expect("There should be a line");
-
-                let indent = Self::indent();
-                let start_message = {
-                    let cursor = get_cursor(2 * IND_AMT + start_pos.col);
-                    format!("{}String begins at ({}:{})\n{}|{}{}\n{}", indent, start_pos.line, start_pos.col, indent, indent, start_line, cursor)
-                };
-                let end_message = {
-                    let cursor = get_cursor(2 * IND_AMT + end_pos.col);
-                    format!("{}String is terminated at ({}:{})\n{}|{}{}\n{}", indent, end_pos.line, end_pos.col, indent, indent, end_line, cursor)
-                };
-
-                format!(
-                    "Error at {}:{}:{}\nUnterminated string:\n{}\n{}",
-                    file_name, end_pos.line, end_pos.col, start_message, end_message
-                )
+                ErrorDescription::new("Unterminated string")
+                    .note_at(format!("String begins at ({}:{})", start_pos.line, start_pos.col), Range::with_length(start_pos, 0))
+                    .note_at(format!("String is terminated at ({}:{})", end_pos.line, end_pos.col), Range::with_length(end_pos, 0))
             }
-            LexerErrorKind::UnexpectedEof(pos) => {
-                let cursor_line = Self::get_underlined_line_no_token(lines, pos, &self.get_range());
-
-                format!(
-                    "Error at {}:{}:{}\nUnexpected end of file:\n{}",
-                    file_name, pos.line, pos.col, cursor_line
-                )
+            LexerErrorKind::UnexpectedEof(_) => ErrorDescription::new("Unexpected end of file"),
+            LexerErrorKind::UnsupportedEscapeSequence(_, _, is_unicode) => {
+                let description = ErrorDescription::new("Unsupported escape sequence");
+                if *is_unicode {
+                    description.note("Unicode escape sequences must be \\u followed by 4 hexadecimal characters (between 0000 and 7FFF)")
+                } else {
+                    description
+                }
             }
-            LexerErrorKind::UnsupportedEscapeSequence(pos, _, is_unicode) => {
-                let cursor_line = Self::get_underlined_line_no_token(lines, pos, &self.get_range());
-
-                let msg = if *is_unicode {
-                    "\nUnicode escape sequences must be \\u followed by 4 hexadecimal characters (between 0000 and 7FFF)"
-                } else { "" };
+        }
+    }
 
-                format!(
-                    "Error at {}:{}:{}\nUnsupported escape sequence:\n{}{}",
-                    file_name, pos.line, pos.col, cursor_line, msg
-                )
-            }
+    fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String {
+        let description = self.describe();
+        let range = self.get_range();
+
+        // An unterminated string is reported at its end, and both its start and end are underlined by its notes
+        let (pos, cursor_line) = match &self.kind {
+            LexerErrorKind::UnterminatedString(_, end_pos) => (end_pos, None),
+            _ => (&range.start, Some(Self::get_underlined_line_no_token(lines, &range.start, &range))),
+        };
+
+        let mut out = vec![
+            format!("Error at {}:{}:{}", file_name, pos.line, pos.col),
+            format!("{}:", description.message),
+        ];
+        out.extend(cursor_line);
+        for (note, location) in description.notes {
+            let Some(location) = location else {
+                out.push(note);
+                continue;
+            };
+
+            let line = lines.get(location.start.line - 1).expect("There should be a line");
+            let cursor = get_cursor(2 * IND_AMT + location.start.col);
+            let indent = Self::indent();
+            out.push(format!("{}{}\n{}|{}{}\n{}", indent, note, indent, indent, line, cursor));
         }
+
+        out.join("\n")
     }
 }
 
@@ -54,6 +54,60 @@ impl Range {
 
         Self { start, end }
     }
+
+    // Ranges are inclusive of their end position
+    pub fn contains(&self, position: &Position) -> bool {
+        &self.start <= position && position <= &self.end
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommentKind {
+    Line,
+    Block,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    pub position: Position,
+    // The full text of the comment, including its `//` or `/* */` delimiters
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trivia {
+    Whitespace(String),
+    Comment(Comment),
+}
+
+impl Trivia {
+    pub fn text(&self) -> &String {
+        match self {
+            Trivia::Whitespace(text) => text,
+            Trivia::Comment(comment) => &comment.text,
+        }
+    }
+}
+
+// A token along with its exact source text and the trivia surrounding it. Trailing trivia runs up to (but not including) the end
+// of the token's line; anything after that is leading trivia of the next token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TriviaToken {
+    pub leading_trivia: Vec<Trivia>,
+    pub token: Token,
+    pub text: String,
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+impl TriviaToken {
+    pub fn full_text(&self) -> String {
+        let mut text = String::new();
+        self.leading_trivia.iter().for_each(|t| text.push_str(t.text()));
+        text.push_str(&self.text);
+        self.trailing_trivia.iter().for_each(|t| text.push_str(t.text()));
+        text
+    }
 }
 
 #[derive(Debug, Display, Clone, PartialEq, EnumString, EnumDiscriminants)]
@@ -7,7 +7,7 @@ extern crate strum_macros;
 use std::path::PathBuf;
 use crate::vm::compiler::{Module, Metadata};
 use crate::typechecker::typechecker::TypedModule;
-use crate::common::display_error::DisplayError;
+use crate::common::display_error::{DisplayError, ErrorDescription};
 use crate::lexer::tokens::Range;
 use crate::parser::parser::ParseResult;
 use crate::typechecker::typechecker_error::{TypecheckerErrorKind, TypecheckerError};
@@ -16,9 +16,16 @@ use crate::parser::ast::ModuleId;
 use crate::transpile::clang::clang;
 use crate::transpile::genc::{CCompiler, normalize_module_name};
 
+pub mod build_cache;
 pub mod builtins;
 pub mod common;
+pub mod docgen;
+pub mod dump;
+pub mod formatter;
+pub mod ide;
 pub mod lexer;
+pub mod linter;
+pub mod manifest;
 pub mod module_loader;
 pub mod parser;
 pub mod transpile;
@@ -54,6 +61,15 @@ impl Error {
 }
 
 impl DisplayError for Error {
+    fn describe(&self) -> ErrorDescription<Range> {
+        match self {
+            Error::LexerError(e) => e.describe(),
+            Error::ParseError(e) => e.describe(),
+            Error::TypecheckerError(e) => e.describe(),
+            Error::InterpretError(_) => ErrorDescription::new("Runtime error!"),
+        }
+    }
+
     fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String {
         match self {
             Error::LexerError(e) => e.message_for_error(file_name, lines),
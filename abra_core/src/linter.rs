@@ -0,0 +1,587 @@
+use std::collections::{BTreeMap, HashSet};
+use crate::lexer::tokens::Range;
+use crate::parser::ast::{BinaryOp, IndexingMode};
+use crate::typechecker::typechecker2::{AssignmentKind, FuncId, Function, ModuleId, Project, TerminatorKind, Type, TypedLiteral, TypedMatchCase, TypedMatchCaseArgument, TypedMatchCaseKind, TypedNode, VariableAlias, PRELUDE_BOOL_TYPE_ID};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LintRule {
+    ShadowedBinding,
+    ConstantComparison,
+    BoolComparison,
+    EmptyMatchCase,
+    RedundantElse,
+    UnreachableMatchCase,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 6] = [
+        LintRule::ShadowedBinding,
+        LintRule::ConstantComparison,
+        LintRule::BoolComparison,
+        LintRule::EmptyMatchCase,
+        LintRule::RedundantElse,
+        LintRule::UnreachableMatchCase,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::ShadowedBinding => "shadowed-binding",
+            LintRule::ConstantComparison => "constant-comparison",
+            LintRule::BoolComparison => "bool-comparison",
+            LintRule::EmptyMatchCase => "empty-match-case",
+            LintRule::RedundantElse => "redundant-else",
+            LintRule::UnreachableMatchCase => "unreachable-match-case",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LintRule> {
+        LintRule::ALL.iter().find(|rule| rule.name() == name).copied()
+    }
+}
+
+// Which rules are enabled; every rule is enabled by default. Rules can be toggled from the `[lint]` section of an `abra.toml`, eg.
+//
+//   [lint]
+//   shadowed-binding = false
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LintConfig {
+    disabled_rules: HashSet<LintRule>,
+}
+
+impl LintConfig {
+    pub fn from_settings(settings: &BTreeMap<String, bool>) -> Result<LintConfig, String> {
+        let mut config = LintConfig::default();
+        for (name, enabled) in settings {
+            let Some(rule) = LintRule::from_name(name) else {
+                let known_rules = LintRule::ALL.iter().map(|rule| rule.name()).collect::<Vec<_>>().join(", ");
+                return Err(format!("Unknown lint rule '{}', expected one of: {}", name, known_rules));
+            };
+            if !enabled {
+                config.disabled_rules.insert(rule);
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        !self.disabled_rules.contains(&rule)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub rule: LintRule,
+    pub range: Range,
+    pub message: String,
+    pub help: Option<String>,
+    // Other locations within the same module which are relevant to the lint
+    pub related: Vec<(Range, String)>,
+}
+
+// Lints within a module can be suppressed by `// abra-lint-disable` comments, optionally followed by the names of the rules to
+// disable (otherwise all rules are disabled). A comment at the end of a line of code applies to that line; a comment on a line
+// of its own applies to the following line. An `// abra-lint-disable-file` comment applies to the entire module.
+struct Suppressions {
+    file: Option<HashSet<String>>,
+    lines: BTreeMap<usize, Option<HashSet<String>>>,
+}
+
+const DISABLE_DIRECTIVE: &str = "abra-lint-disable";
+const DISABLE_FILE_DIRECTIVE: &str = "abra-lint-disable-file";
+
+impl Suppressions {
+    fn parse(source: &str) -> Suppressions {
+        let mut suppressions = Suppressions { file: None, lines: BTreeMap::new() };
+
+        for (idx, line) in source.lines().enumerate() {
+            let Some(comment_start) = line.find("//") else { continue; };
+            let comment = line[comment_start + 2..].trim();
+            let (is_file_directive, rest) = if let Some(rest) = comment.strip_prefix(DISABLE_FILE_DIRECTIVE) {
+                (true, rest)
+            } else if let Some(rest) = comment.strip_prefix(DISABLE_DIRECTIVE) {
+                (false, rest)
+            } else {
+                continue;
+            };
+            // Guard against eg. `// abra-lint-disabled`
+            if !rest.is_empty() && !rest.starts_with(char::is_whitespace) { continue; }
+
+            let rules = rest.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect::<HashSet<_>>();
+            let rules = if rules.is_empty() { None } else { Some(rules) };
+
+            if is_file_directive {
+                // An empty set represents all rules
+                suppressions.file = Some(match (suppressions.file.take(), rules) {
+                    (None, Some(rules)) => rules,
+                    (Some(mut existing), Some(rules)) if !existing.is_empty() => {
+                        existing.extend(rules);
+                        existing
+                    }
+                    _ => HashSet::new(),
+                });
+                continue;
+            }
+
+            let is_own_line = line[..comment_start].trim().is_empty();
+            let line_num = if is_own_line { idx + 2 } else { idx + 1 };
+            suppressions.lines.insert(line_num, rules);
+        }
+
+        suppressions
+    }
+
+    fn is_suppressed(&self, lint: &Lint) -> bool {
+        let name = lint.rule.name();
+        if let Some(rules) = &self.file {
+            if rules.is_empty() || rules.contains(name) { return true; }
+        }
+
+        match self.lines.get(&lint.range.start.line) {
+            Some(None) => true,
+            Some(Some(rules)) => rules.contains(name),
+            None => false,
+        }
+    }
+}
+
+// Lints a module which has been successfully typechecked. The module's source is needed in order to find any
+// `// abra-lint-disable` comments. Lints are returned in the order in which they appear in the module.
+pub fn lint_module(project: &Project, module_id: &ModuleId, source: &str, config: &LintConfig) -> Vec<Lint> {
+    let mut linter = Linter { project, lints: vec![] };
+    linter.check_shadowed_bindings(module_id);
+    linter.nodes(&project.modules[module_id.0].code);
+
+    let suppressions = Suppressions::parse(source);
+    let mut lints = linter.lints.into_iter()
+        .filter(|lint| config.is_enabled(lint.rule) && !suppressions.is_suppressed(lint))
+        .collect::<Vec<_>>();
+    lints.sort_by_key(|lint| (lint.range.start.line, lint.range.start.col));
+    lints
+}
+
+struct Linter<'a> {
+    project: &'a Project,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    fn lint(&mut self, rule: LintRule, range: Range, message: String, help: Option<&str>, related: Vec<(Range, String)>) {
+        self.lints.push(Lint { rule, range, message, help: help.map(|h| h.to_string()), related });
+    }
+
+    // A binding shadows another if a variable with the same name was declared earlier in an enclosing scope of the same
+    // module. Names starting with `_` are exempt, as are declarations of functions and types.
+    fn check_shadowed_bindings(&mut self, module_id: &ModuleId) {
+        let module = &self.project.modules[module_id.0];
+        for scope in &module.scopes {
+            for var in &scope.vars {
+                if var.alias != VariableAlias::None || var.name.starts_with('_') || var.name == "self" { continue; }
+                let Some(span) = &var.defined_span else { continue; };
+
+                let mut parent = scope.parent;
+                while let Some(parent_scope_id) = parent {
+                    if parent_scope_id.0 != *module_id { break; }
+                    let parent_scope = self.project.get_scope_by_id(&parent_scope_id);
+
+                    let shadowed = parent_scope.vars.iter().find(|outer| {
+                        outer.name == var.name && matches!(&outer.defined_span, Some(outer_span) if outer_span.range.start < span.range.start)
+                    });
+                    if let Some(outer_span) = shadowed.and_then(|outer| outer.defined_span.as_ref()) {
+                        let message = format!("'{}' shadows an earlier binding of the same name", var.name);
+                        let related = vec![(outer_span.range.clone(), "shadowed binding declared here".to_string())];
+                        self.lint(LintRule::ShadowedBinding, span.range.clone(), message, Some("consider giving this binding a different name"), related);
+                        break;
+                    }
+                    parent = parent_scope.parent;
+                }
+            }
+        }
+    }
+
+    fn nodes(&mut self, nodes: &Vec<TypedNode>) {
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn function(&mut self, func: &Function) {
+        for param in &func.params {
+            if let Some(default_value) = &param.default_value { self.node(default_value); }
+        }
+        self.nodes(&func.body);
+    }
+
+    fn functions(&mut self, func_ids: &Vec<FuncId>) {
+        for func_id in func_ids {
+            let func = self.project.get_func_by_id(func_id);
+            // Skip generated methods (eg. `toString`), which have no source
+            if func.defined_span.is_some() {
+                self.function(func);
+            }
+        }
+    }
+
+    fn node(&mut self, node: &TypedNode) {
+        match node {
+            TypedNode::Literal { .. } |
+            TypedNode::Identifier { .. } |
+            TypedNode::NoneValue { .. } |
+            TypedNode::Break { .. } |
+            TypedNode::Continue { .. } => {}
+            TypedNode::Unary { expr, .. } => self.node(expr),
+            TypedNode::Binary { op, left, right, .. } => {
+                self.check_comparison(node, op, left, right);
+                self.node(left);
+                self.node(right);
+            }
+            TypedNode::Grouped { expr, .. } => self.node(expr),
+            TypedNode::Array { items, .. } |
+            TypedNode::Tuple { items, .. } |
+            TypedNode::Set { items, .. } => self.nodes(items),
+            TypedNode::Map { items, .. } => {
+                for (key, value) in items {
+                    self.node(key);
+                    self.node(value);
+                }
+            }
+            TypedNode::Invocation { target, arguments, .. } => {
+                self.node(target);
+                for arg in arguments.iter().flatten() {
+                    self.node(arg);
+                }
+            }
+            TypedNode::Accessor { target, .. } => self.node(target),
+            TypedNode::Indexing { target, index, .. } => {
+                self.node(target);
+                match index {
+                    IndexingMode::Index(index) => self.node(index),
+                    IndexingMode::Range(start, end) => {
+                        if let Some(start) = start { self.node(start); }
+                        if let Some(end) = end { self.node(end); }
+                    }
+                }
+            }
+            TypedNode::Lambda { func_id, .. } => self.function(self.project.get_func_by_id(func_id)),
+            TypedNode::Assignment { kind, expr, .. } => {
+                match kind {
+                    AssignmentKind::Identifier { .. } => {}
+                    AssignmentKind::Accessor { target, .. } => self.node(target),
+                    AssignmentKind::Indexing { target, index } => {
+                        self.node(target);
+                        self.node(index);
+                    }
+                }
+                self.node(expr);
+            }
+            TypedNode::If { condition, if_block, if_block_terminator, else_block, is_statement, .. } => {
+                // An `else` following an `if` block which always exits (ie. via return, break or continue) can be flattened
+                if *is_statement && if_block_terminator.is_some() {
+                    if let Some(first_else_node) = else_block.first() {
+                        let exit = if *if_block_terminator == Some(TerminatorKind::Returning) { "return" } else { "break or continue" };
+                        let message = format!("Unnecessary else block after {}", exit);
+                        let help = "remove the else and move its contents after the if statement";
+                        self.lint(LintRule::RedundantElse, first_else_node.span(), message, Some(help), vec![]);
+                    }
+                }
+
+                self.node(condition);
+                self.nodes(if_block);
+                self.nodes(else_block);
+            }
+            TypedNode::Match { target, cases, is_statement, .. } => {
+                self.check_match_cases(target, cases, *is_statement);
+
+                self.node(target);
+                for case in cases {
+                    if let TypedMatchCaseKind::Constant(_, value) = &case.kind { self.node(value); }
+                    self.nodes(&case.body);
+                }
+            }
+            TypedNode::FuncDeclaration(func_id) => self.function(self.project.get_func_by_id(func_id)),
+            TypedNode::TypeDeclaration(struct_id) => {
+                let struct_ = self.project.get_struct_by_id(struct_id);
+                for field in &struct_.fields {
+                    if let Some(default_value) = &field.default_value { self.node(default_value); }
+                }
+                self.functions(&struct_.static_methods);
+                self.functions(&struct_.methods);
+            }
+            TypedNode::EnumDeclaration(enum_id) => {
+                let enum_ = self.project.get_enum_by_id(enum_id);
+                self.functions(&enum_.static_methods);
+                self.functions(&enum_.methods);
+            }
+            TypedNode::BindingDeclaration { expr, .. } => {
+                if let Some(expr) = expr { self.node(expr); }
+            }
+            TypedNode::ForLoop { iterator, body, .. } => {
+                self.node(iterator);
+                self.nodes(body);
+            }
+            TypedNode::WhileLoop { condition, body, .. } => {
+                self.node(condition);
+                self.nodes(body);
+            }
+            TypedNode::Return { expr, .. } => {
+                if let Some(expr) = expr { self.node(expr); }
+            }
+        }
+    }
+
+    fn check_comparison(&mut self, node: &TypedNode, op: &BinaryOp, left: &TypedNode, right: &TypedNode) {
+        if !matches!(op, BinaryOp::Eq | BinaryOp::Neq | BinaryOp::Lt | BinaryOp::Lte | BinaryOp::Gt | BinaryOp::Gte) { return; }
+
+        let left = unwrap_grouped(left);
+        let right = unwrap_grouped(right);
+        match (left, right) {
+            (TypedNode::Literal { .. }, TypedNode::Literal { .. }) => {
+                let message = "Comparison between two constant values always has the same result".to_string();
+                self.lint(LintRule::ConstantComparison, node.span(), message, None, vec![]);
+            }
+            (TypedNode::Identifier { var_id: v1, .. }, TypedNode::Identifier { var_id: v2, .. }) if v1 == v2 => {
+                let message = "Comparison of a value with itself always has the same result".to_string();
+                self.lint(LintRule::ConstantComparison, node.span(), message, None, vec![]);
+            }
+            (TypedNode::Literal { value: TypedLiteral::Bool(b), .. }, _) | (_, TypedNode::Literal { value: TypedLiteral::Bool(b), .. }) => {
+                if !matches!(op, BinaryOp::Eq | BinaryOp::Neq) { return; }
+
+                let is_negated = (*op == BinaryOp::Eq) != *b;
+                let message = format!("Unnecessary comparison to `{}`", b);
+                let help = if is_negated { "use the negated value directly, eg. `!x`" } else { "use the value directly" };
+                self.lint(LintRule::BoolComparison, node.span(), message, Some(help), vec![]);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_match_cases(&mut self, target: &TypedNode, cases: &Vec<TypedMatchCase>, is_statement: bool) {
+        // Match expressions can't have empty cases, which is already a type error
+        if is_statement {
+            for case in cases {
+                if case.body.is_empty() {
+                    let message = "Empty match case".to_string();
+                    let help = "if this case is meant to be ignored, add a comment explaining why";
+                    self.lint(LintRule::EmptyMatchCase, case.token.get_range(), message, Some(help), vec![]);
+                }
+            }
+        }
+
+        // The typechecker rejects cases which follow a wildcard, but not cases which follow a set of cases which are already
+        // exhaustive (eg. a wildcard after every variant of an enum has been handled)
+        let target_type_id = *target.type_id();
+        let option_inner_type_id = self.project.type_is_option(&target_type_id);
+        let inner_type_id = option_inner_type_id.unwrap_or(target_type_id);
+        let inner_type = self.project.get_type_by_id(&inner_type_id);
+
+        let mut none_covered = option_inner_type_id.is_none();
+        let mut type_covered = false;
+        let mut seen_variants = HashSet::new();
+        let mut seen_bools = HashSet::new();
+        let num_variants = match inner_type {
+            Type::GenericEnumInstance(enum_id, _, _) => Some(self.project.get_enum_by_id(enum_id).variants.len()),
+            _ => None,
+        };
+
+        for case in cases {
+            let all_variants_covered = num_variants.is_some_and(|num_variants| seen_variants.len() == num_variants);
+            if none_covered && (type_covered || all_variants_covered) {
+                let message = "Unreachable match case, since the cases before it are exhaustive".to_string();
+                self.lint(LintRule::UnreachableMatchCase, case.token.get_range(), message, Some("remove this case"), vec![]);
+                break;
+            }
+
+            match &case.kind {
+                TypedMatchCaseKind::None => none_covered = true,
+                TypedMatchCaseKind::Wildcard(_) => break,
+                TypedMatchCaseKind::Type(case_type_id, args) => {
+                    match self.project.get_type_by_id(case_type_id) {
+                        Type::GenericEnumInstance(_, _, Some(variant_idx)) => {
+                            // A case which destructures a variant's values into literals only handles some instances of that variant
+                            if args.iter().all(|arg| matches!(arg, TypedMatchCaseArgument::Pattern(_, _))) {
+                                seen_variants.insert(*variant_idx);
+                            }
+                        }
+                        _ => if *case_type_id == inner_type_id { type_covered = true },
+                    }
+                }
+                TypedMatchCaseKind::Constant(_, TypedNode::Literal { value: TypedLiteral::Bool(b), .. }) if inner_type_id == PRELUDE_BOOL_TYPE_ID => {
+                    seen_bools.insert(*b);
+                    type_covered = seen_bools.len() == 2;
+                }
+                TypedMatchCaseKind::Constant(_, _) => {}
+            }
+        }
+    }
+}
+
+fn unwrap_grouped(node: &TypedNode) -> &TypedNode {
+    match node {
+        TypedNode::Grouped { expr, .. } => unwrap_grouped(expr),
+        _ => node,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::linter::{lint_module, LintConfig, LintRule};
+    use crate::typechecker::test_helpers::typecheck_main;
+
+    fn lint(source: &str, config: &LintConfig) -> Vec<(LintRule, (usize, usize))> {
+        let (_, project, result) = typecheck_main(&[("main.abra", source)]);
+        let module_id = result.unwrap();
+
+        lint_module(&project, &module_id, source, config).into_iter()
+            .map(|lint| (lint.rule, (lint.range.start.line, lint.range.start.col)))
+            .collect()
+    }
+
+    #[test]
+    fn test_shadowed_binding() {
+        let lints = lint("\
+            val x = 1\n\
+            func f(x: Int): Int {\n\
+              val _y = 1\n\
+              if x > 0 { val _y = 2 }\n\
+              x\n\
+            }\n\
+            func g(): Int {\n\
+              val z = 1\n\
+              z\n\
+            }\n\
+            val z = 2\n\
+            func h(a: Int): Int {\n\
+              if a > 0 {\n\
+                val a = 3\n\
+                return a\n\
+              }\n\
+              a\n\
+            }\
+        ", &LintConfig::default());
+        let expected = vec![
+            (LintRule::ShadowedBinding, (2, 8)),
+            (LintRule::ShadowedBinding, (14, 5)),
+        ];
+        assert_eq!(expected, lints);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let lints = lint("\
+            val x = 1\n\
+            val b = x > 0\n\
+            val c1 = 1 == 2\n\
+            val c2 = x <= (x)\n\
+            val c3 = b == true\n\
+            val c4 = false != b\n\
+            val c5 = b == false\n\
+            val c6 = true == false\
+        ", &LintConfig::default());
+        let expected = vec![
+            (LintRule::ConstantComparison, (3, 10)),
+            (LintRule::ConstantComparison, (4, 10)),
+            (LintRule::BoolComparison, (5, 10)),
+            (LintRule::BoolComparison, (6, 10)),
+            (LintRule::BoolComparison, (7, 10)),
+            (LintRule::ConstantComparison, (8, 10)),
+        ];
+        assert_eq!(expected, lints);
+    }
+
+    #[test]
+    fn test_match_cases() {
+        let lints = lint("\
+            enum Color { Red, Green }\n\
+            val c = Color.Red\n\
+            match c {\n\
+              Color.Red => {}\n\
+              Color.Green => println(\"green\")\n\
+              _ => println(\"other\")\n\
+            }\n\
+            val arr = [1, 2]\n\
+            match arr[0] {\n\
+              None => println(\"none\")\n\
+              Int => println(\"int\")\n\
+              _ => println(\"other\")\n\
+            }\n\
+            match arr[1] {\n\
+              Int => println(\"int\")\n\
+              _ => println(\"none\")\n\
+            }\
+        ", &LintConfig::default());
+        let expected = vec![
+            (LintRule::EmptyMatchCase, (4, 1)),
+            (LintRule::UnreachableMatchCase, (6, 1)),
+            (LintRule::UnreachableMatchCase, (12, 1)),
+        ];
+        assert_eq!(expected, lints);
+    }
+
+    #[test]
+    fn test_redundant_else() {
+        let lints = lint("\
+            func f(a: Int): Int {\n\
+              if a > 0 {\n\
+                return 1\n\
+              } else {\n\
+                println(a)\n\
+              }\n\
+              for i in [1, 2] {\n\
+                if i > a { break } else { println(i) }\n\
+                if i > a { println(i) } else { continue }\n\
+              }\n\
+              val x = if a > 1 { 1 } else { 2 }\n\
+              x\n\
+            }\
+        ", &LintConfig::default());
+        let expected = vec![
+            (LintRule::RedundantElse, (5, 1)),
+            (LintRule::RedundantElse, (8, 27)),
+        ];
+        assert_eq!(expected, lints);
+    }
+
+    #[test]
+    fn test_suppressions() {
+        let source = "\
+            val a = 1 == 1 // abra-lint-disable\n\
+            // abra-lint-disable constant-comparison\n\
+            val b = 1 == 1\n\
+            // abra-lint-disable shadowed-binding\n\
+            val c = 1 == 1\n\
+            val d = (a == true) // abra-lint-disable constant-comparison, bool-comparison\n\
+            val e = b == true\
+        ";
+        let expected = vec![
+            (LintRule::ConstantComparison, (5, 9)),
+            (LintRule::BoolComparison, (7, 9)),
+        ];
+        assert_eq!(expected, lint(source, &LintConfig::default()));
+
+        let source = format!("// abra-lint-disable-file bool-comparison\n{}", source);
+        assert_eq!(vec![(LintRule::ConstantComparison, (6, 9))], lint(&source, &LintConfig::default()));
+
+        let source = format!("// abra-lint-disable-file\n{}", source);
+        assert!(lint(&source, &LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_lint_config() {
+        let settings = vec![("constant-comparison".to_string(), false), ("bool-comparison".to_string(), true)].into_iter().collect::<BTreeMap<_, _>>();
+        let config = LintConfig::from_settings(&settings).unwrap();
+        assert!(!config.is_enabled(LintRule::ConstantComparison));
+        assert!(config.is_enabled(LintRule::BoolComparison));
+        assert!(config.is_enabled(LintRule::ShadowedBinding));
+
+        let lints = lint("val a = 1 == 1\nval b = a == true", &config);
+        assert_eq!(vec![(LintRule::BoolComparison, (2, 9))], lints);
+
+        let settings = vec![("shadowed-bindings".to_string(), false)].into_iter().collect::<BTreeMap<_, _>>();
+        let err = LintConfig::from_settings(&settings).unwrap_err();
+        assert!(err.starts_with("Unknown lint rule 'shadowed-bindings'"));
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Component, Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+pub const MANIFEST_FILE_NAME: &str = "abra.toml";
+pub const LOCK_FILE_NAME: &str = "abra.lock";
+const DEFAULT_SOURCE_ROOT: &str = "src";
+
+// The contents of an `abra.toml` file, eg.
+//
+//   [package]
+//   name = "myapp"
+//   entry = ["src/main.abra"]
+//   lib = "src/lib.abra"
+//   source-roots = ["src", "vendor"]
+//
+//   [dependencies]
+//   mylib = { path = "../mylib" }
+//
+//   [lint]
+//   shadowed-binding = false
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: PackageSection,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, DependencySection>,
+    #[serde(default)]
+    pub lint: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageSection {
+    pub name: String,
+    #[serde(default)]
+    pub entry: Vec<String>,
+    pub lib: Option<String>,
+    pub source_roots: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DependencySection {
+    pub path: String,
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io { path: PathBuf, message: String },
+    Parse { path: PathBuf, message: String },
+    MissingDependency { manifest_path: PathBuf, name: String, path: PathBuf },
+    ConflictingDependency { name: String, first_path: PathBuf, second_path: PathBuf },
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Io { path, message } => write!(f, "Could not read {}: {}", path.display(), message),
+            ManifestError::Parse { path, message } => write!(f, "Invalid manifest at {}:\n  {}", path.display(), message),
+            ManifestError::MissingDependency { manifest_path, name, path } => {
+                write!(f, "Could not resolve dependency '{}' (declared in {}): no such directory {}", name, manifest_path.display(), path.display())
+            }
+            ManifestError::ConflictingDependency { name, first_path, second_path } => {
+                write!(f, "Dependency '{}' refers to two different packages:\n  {}\n  {}", name, first_path.display(), second_path.display())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedPackage {
+    pub name: String,
+    pub root: PathBuf,
+    pub entry: Vec<PathBuf>,
+    pub lib: Option<PathBuf>,
+    pub source_roots: Vec<PathBuf>,
+}
+
+impl ResolvedPackage {
+    // Returns the path (without the `.abra` extension) of the first source root which contains the module
+    fn find_module(&self, module_path: &str) -> Option<PathBuf> {
+        self.source_roots.iter()
+            .map(|root| root.join(module_path))
+            .find(|path| path.with_extension("abra").is_file())
+    }
+
+    fn resolve_module_path(&self, module_path: Option<&str>) -> Option<PathBuf> {
+        match module_path {
+            None => self.lib.as_ref().map(|lib| lib.with_extension("")),
+            Some(module_path) => self.find_module(module_path).or_else(|| {
+                // Even if the module doesn't exist, resolve it within the package so that the error points at the right place
+                self.source_roots.first().map(|root| root.join(module_path))
+            }),
+        }
+    }
+}
+
+// A manifest whose source roots and dependencies have been resolved to absolute paths. Dependencies are flattened, so a module
+// within any package in the graph can import any other package by name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedManifest {
+    pub manifest_path: PathBuf,
+    pub package: ResolvedPackage,
+    pub dependencies: Vec<ResolvedPackage>,
+    // The settings of the root package's `[lint]` section; dependencies' settings don't apply
+    pub lint: BTreeMap<String, bool>,
+}
+
+// The contents of an `abra.lock` file. Paths are relative to the directory of the manifest, so that the lock file is the same on every
+// checkout of the project (and can be committed).
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct LockFile {
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct LockedPackage {
+    name: String,
+    root: PathBuf,
+    lib: Option<PathBuf>,
+    source_roots: Vec<PathBuf>,
+}
+
+impl ResolvedManifest {
+    pub fn root(&self) -> &PathBuf {
+        &self.package.root
+    }
+
+    // Searches the given directory and its ancestors for an `abra.toml` file
+    pub fn find<P: AsRef<Path>>(start_dir: P) -> Result<Option<ResolvedManifest>, ManifestError> {
+        for dir in start_dir.as_ref().ancestors() {
+            let manifest_path = dir.join(MANIFEST_FILE_NAME);
+            if manifest_path.is_file() {
+                return Self::load(manifest_path).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn load<P: AsRef<Path>>(manifest_path: P) -> Result<ResolvedManifest, ManifestError> {
+        let manifest_path = canonicalize(manifest_path.as_ref())?;
+        let (package, dependency_decls, lint) = resolve_package(&manifest_path)?;
+
+        let mut dependencies: Vec<ResolvedPackage> = vec![];
+        let mut queue = dependency_decls.into_iter().map(|(name, root)| (manifest_path.clone(), name, root)).collect::<Vec<_>>();
+        while let Some((declared_in, name, root)) = queue.pop() {
+            if let Some(existing) = dependencies.iter().find(|dep| dep.name == name) {
+                if existing.root != root {
+                    return Err(ManifestError::ConflictingDependency { name, first_path: existing.root.clone(), second_path: root });
+                }
+                continue;
+            }
+            if root == package.root { continue; }
+            if !root.is_dir() {
+                return Err(ManifestError::MissingDependency { manifest_path: declared_in, name, path: root });
+            }
+
+            let dep_manifest_path = root.join(MANIFEST_FILE_NAME);
+            let dependency = if dep_manifest_path.is_file() {
+                let (mut dependency, transitive_decls, _) = resolve_package(&dep_manifest_path)?;
+                for (transitive_name, transitive_root) in transitive_decls {
+                    queue.push((dep_manifest_path.clone(), transitive_name, transitive_root));
+                }
+                // A dependency is always imported by the name under which it was declared
+                dependency.name = name;
+                dependency
+            } else {
+                // A directory without a manifest is treated as a package whose only source root is itself
+                ResolvedPackage { name, root: root.clone(), entry: vec![], lib: None, source_roots: vec![root] }
+            };
+            dependencies.push(dependency);
+        }
+        dependencies.sort_by(|d1, d2| d1.name.cmp(&d2.name));
+
+        Ok(ResolvedManifest { manifest_path, package, dependencies, lint })
+    }
+
+    pub fn lock_file_path(&self) -> PathBuf {
+        self.package.root.join(LOCK_FILE_NAME)
+    }
+
+    fn lock_file(&self) -> LockFile {
+        let root = &self.package.root;
+        let package = self.dependencies.iter()
+            .map(|dep| LockedPackage {
+                name: dep.name.clone(),
+                root: relative_path(root, &dep.root),
+                lib: dep.lib.as_ref().map(|lib| relative_path(root, lib)),
+                source_roots: dep.source_roots.iter().map(|source_root| relative_path(root, source_root)).collect(),
+            })
+            .collect();
+        LockFile { package }
+    }
+
+    pub fn lock_file_contents(&self) -> String {
+        let contents = toml::to_string(&self.lock_file()).expect("A lock file should always be serializable");
+        format!("# This file is generated by abra and records how dependencies were resolved; it should not be edited by hand.\n\n{}", contents)
+    }
+
+    // Writes the lock file next to the manifest, but only if the dependencies' resolution differs from the one it records (so that
+    // commands which merely read the manifest don't rewrite it). Returns whether the lock file was written.
+    pub fn write_lock_file(&self) -> Result<bool, ManifestError> {
+        let lock_file_path = self.lock_file_path();
+        let existing = std::fs::read_to_string(&lock_file_path).ok().and_then(|contents| toml::from_str::<LockFile>(&contents).ok());
+        if existing.as_ref() == Some(&self.lock_file()) {
+            return Ok(false);
+        }
+
+        std::fs::write(&lock_file_path, self.lock_file_contents()).map_err(|e| ManifestError::Io { path: lock_file_path, message: e.to_string() })?;
+        Ok(true)
+    }
+
+    // Resolves a non-relative module path (ie. `import "mylib/strings"`) to a file path (without the `.abra` extension). A path whose
+    // first segment is the name of a dependency (or of this package) is resolved within that package's source roots; otherwise, the
+    // module is searched for in this package's source roots. Module loaders only consult the manifest for modules which aren't in the
+    // std library, so that the std library's modules can't be shadowed.
+    pub fn resolve_module_path(&self, module_name: &str) -> Option<PathBuf> {
+        let (package_name, rest) = match module_name.split_once('/') {
+            Some((package_name, rest)) => (package_name, Some(rest)),
+            None => (module_name, None),
+        };
+
+        if let Some(dependency) = self.dependencies.iter().find(|dep| dep.name == package_name) {
+            return dependency.resolve_module_path(rest);
+        }
+        if self.package.name == package_name {
+            if let Some(path) = self.package.resolve_module_path(rest) {
+                return Some(path);
+            }
+        }
+
+        self.package.find_module(module_name)
+    }
+
+    // The files which, if they were created, would take precedence over the module at `path` when resolving non-relative imports:
+    // the same module within each of its package's source roots which come before the one containing it.
+    pub fn shadowing_paths(&self, path: &Path) -> Vec<PathBuf> {
+        for package in std::iter::once(&self.package).chain(&self.dependencies) {
+            for (idx, source_root) in package.source_roots.iter().enumerate() {
+                if let Ok(relative) = path.strip_prefix(source_root) {
+                    return package.source_roots[..idx].iter().map(|earlier_root| earlier_root.join(relative)).collect();
+                }
+            }
+        }
+
+        vec![]
+    }
+
+    pub fn default_entrypoint(&self) -> Option<&PathBuf> {
+        self.package.entry.first()
+    }
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf, ManifestError> {
+    path.canonicalize().map_err(|e| ManifestError::Io { path: path.to_path_buf(), message: e.to_string() })
+}
+
+// The path relative to the base directory (eg. `../mylib/src`); both are absolute
+fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    let base = base.components().collect::<Vec<_>>();
+    let path = path.components().collect::<Vec<_>>();
+    let num_common = base.iter().zip(&path).take_while(|(a, b)| a == b).count();
+
+    let mut relative = base[num_common..].iter().map(|_| Component::ParentDir).collect::<PathBuf>();
+    relative.extend(&path[num_common..]);
+    if relative.as_os_str().is_empty() { PathBuf::from(".") } else { relative }
+}
+
+fn resolve_package(manifest_path: &PathBuf) -> Result<(ResolvedPackage, Vec<(String, PathBuf)>, BTreeMap<String, bool>), ManifestError> {
+    let contents = std::fs::read_to_string(manifest_path)
+        .map_err(|e| ManifestError::Io { path: manifest_path.clone(), message: e.to_string() })?;
+    let manifest: Manifest = toml::from_str(&contents)
+        .map_err(|e| ManifestError::Parse { path: manifest_path.clone(), message: e.to_string() })?;
+
+    let root = manifest_path.parent().expect("A manifest file is always within a directory").to_path_buf();
+    let source_roots = manifest.package.source_roots
+        .unwrap_or_else(|| vec![DEFAULT_SOURCE_ROOT.to_string()])
+        .into_iter()
+        .map(|source_root| root.join(source_root))
+        .collect();
+    let entry = manifest.package.entry.into_iter().map(|entry| root.join(entry)).collect();
+    let lib = manifest.package.lib.map(|lib| root.join(lib));
+    let package = ResolvedPackage { name: manifest.package.name, root: root.clone(), entry, lib, source_roots };
+
+    let mut dependencies = Vec::with_capacity(manifest.dependencies.len());
+    for (name, DependencySection { path }) in manifest.dependencies {
+        let dep_root = root.join(&path);
+        let dep_root = dep_root.canonicalize().unwrap_or(dep_root);
+        dependencies.push((name, dep_root));
+    }
+
+    Ok((package, dependencies, manifest.lint))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::test_utils::make_temp_dir;
+    use crate::manifest::{ManifestError, ResolvedManifest};
+
+    #[test]
+    fn test_resolve_module_paths() {
+        let dir = make_temp_dir("abra_manifest", &[
+            ("app/abra.toml", "[package]\nname = \"app\"\nentry = [\"src/main.abra\"]\n\n[dependencies]\nmylib = { path = \"../mylib\" }\n\n[lint]\nshadowed-binding = false\n"),
+            ("app/src/main.abra", ""),
+            ("app/src/utils/strings.abra", ""),
+            ("mylib/abra.toml", "[package]\nname = \"mylib\"\nlib = \"src/lib.abra\"\n\n[dependencies]\nother = { path = \"../other\" }\n"),
+            ("mylib/src/lib.abra", ""),
+            ("mylib/src/strings.abra", ""),
+            ("other/other.abra", ""),
+        ]);
+
+        let manifest = ResolvedManifest::find(dir.join("app/src/utils")).unwrap().unwrap();
+        assert_eq!(dir.join("app"), *manifest.root());
+        assert_eq!(Some(&dir.join("app/src/main.abra")), manifest.default_entrypoint());
+        assert_eq!(vec!["mylib", "other"], manifest.dependencies.iter().map(|d| d.name.as_str()).collect::<Vec<_>>());
+        assert_eq!(Some(&false), manifest.lint.get("shadowed-binding"));
+
+        assert_eq!(Some(dir.join("mylib/src/lib")), manifest.resolve_module_path("mylib"));
+        assert_eq!(Some(dir.join("mylib/src/strings")), manifest.resolve_module_path("mylib/strings"));
+        assert_eq!(Some(dir.join("other/other")), manifest.resolve_module_path("other/other"));
+        assert_eq!(Some(dir.join("app/src/utils/strings")), manifest.resolve_module_path("utils/strings"));
+        assert_eq!(Some(dir.join("app/src/utils/strings")), manifest.resolve_module_path("app/utils/strings"));
+        assert_eq!(None, manifest.resolve_module_path("libc"));
+
+        assert!(manifest.write_lock_file().unwrap());
+        let lock_file = std::fs::read_to_string(dir.join("app/abra.lock")).unwrap();
+        assert!(lock_file.contains("name = \"mylib\""));
+        assert!(lock_file.contains("root = \"../other\""));
+        assert!(lock_file.contains("source-roots = [\"../mylib/src\"]"));
+        assert!(!lock_file.contains(dir.to_str().unwrap()));
+
+        // The lock file is only rewritten once the resolution changes
+        assert!(!manifest.write_lock_file().unwrap());
+        std::fs::write(dir.join("app/abra.lock"), lock_file.replace("# This file", "# Edited: this file")).unwrap();
+        assert!(!manifest.write_lock_file().unwrap());
+        std::fs::write(dir.join("app/abra.lock"), lock_file.replace("../other", "../elsewhere")).unwrap();
+        assert!(manifest.write_lock_file().unwrap());
+        assert_eq!(lock_file, std::fs::read_to_string(dir.join("app/abra.lock")).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_manifests() {
+        let dir = make_temp_dir("abra_manifest", &[
+            ("app/abra.toml", "[package]\nname = \"app\"\n\n[dependencies]\nmylib = { path = \"../mylib\" }\n"),
+            ("bad/abra.toml", "[package]\nnmae = \"bad\"\n"),
+        ]);
+
+        let err = ResolvedManifest::load(dir.join("app/abra.toml")).unwrap_err();
+        assert!(matches!(err, ManifestError::MissingDependency { name, .. } if name == "mylib"));
+        let err = ResolvedManifest::load(dir.join("bad/abra.toml")).unwrap_err();
+        assert!(matches!(err, ManifestError::Parse { .. }));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
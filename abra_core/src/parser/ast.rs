@@ -2,7 +2,8 @@ use crate::lexer::tokens::{Range, Token};
 use std::path::Path;
 use std::hash::{Hash, Hasher};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, EnumDiscriminants)]
+#[strum_discriminants(name(AstNodeKind))]
 pub enum AstNode {
     Literal(Token, AstLiteralNode),
     Unary(Token, UnaryNode),
@@ -260,6 +261,8 @@ pub fn args_to_parameters(raw_arg_tuple: &(Token, Option<TypeIdentifier>, bool,
 pub struct FunctionDeclNode {
     pub decorators: Vec<DecoratorNode>,
     pub export_token: Option<Token>,
+    // The text of the declaration's doc comment, if any
+    pub doc_comment: Option<String>,
     // Must be a Token::Ident
     pub name: Token,
     // Must be a Token::Idents
@@ -292,6 +295,8 @@ impl LambdaNode {
 pub struct TypeDeclNode {
     pub decorators: Vec<DecoratorNode>,
     pub export_token: Option<Token>,
+    // The text of the declaration's doc comment, if any
+    pub doc_comment: Option<String>,
     // Must be a Token::Ident
     pub name: Token,
     // Must be Token::Idents
@@ -306,12 +311,15 @@ pub struct TypeDeclField {
     pub type_ident: TypeIdentifier,
     pub default_value: Option<AstNode>,
     pub readonly: Option<Token>,
+    pub doc_comment: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct EnumDeclNode {
     pub decorators: Vec<DecoratorNode>,
     pub export_token: Option<Token>,
+    // The text of the declaration's doc comment, if any
+    pub doc_comment: Option<String>,
     // Must be a Token::Ident
     pub name: Token,
     // Must be Token::Idents
@@ -535,14 +543,17 @@ impl ModuleId {
         let m = match path.first() {
             None => return None,
             Some(ModulePathSegment::UpDir | ModulePathSegment::CurrentDir) => ModuleId::Internal(path),
-            Some(ModulePathSegment::Directory(name) | ModulePathSegment::Module(name)) => {
-                if path.len() != 1 {
-                    // TODO: Support nested external/builtin imports (ie. time/date)?
-                    // Right now, if the first segment is a directory, the rest of the path is discarded
-                    unimplemented!()
+            Some(ModulePathSegment::Directory(_) | ModulePathSegment::Module(_)) => {
+                // Nested external imports (ie. `mylib/strings`) are resolved relative to a named dependency or a module search path
+                let mut names = Vec::with_capacity(path.len());
+                for seg in &path {
+                    match seg {
+                        ModulePathSegment::Directory(name) | ModulePathSegment::Module(name) => names.push(name.as_str()),
+                        ModulePathSegment::CurrentDir | ModulePathSegment::UpDir => return None,
+                    }
                 }
 
-                ModuleId::External(name.clone())
+                ModuleId::External(names.join("/"))
             }
         };
 
@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+use crate::Error;
+use crate::lexer::lexer::tokenize_with_trivia;
+use crate::lexer::tokens::{Position, Token, Trivia, TriviaToken};
+use crate::parser::ast::{AstNode, AstNodeKind, BindingPattern, DecoratorNode, IfNode, ImportKind, IndexingMode, MatchCaseArgument, MatchCaseType, ModuleId};
+use crate::parser::parser::{parse, ParseResult};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyntaxKind {
+    Module,
+    Node(AstNodeKind),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(TriviaToken),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    // The node's exact source text, including the trivia attached to its tokens
+    pub fn text(&self) -> String {
+        self.tokens().iter().map(|t| t.full_text()).collect()
+    }
+
+    pub fn tokens(&self) -> Vec<&TriviaToken> {
+        let mut tokens = vec![];
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item=&SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+}
+
+// A lossless view of a module: every token keeps its surrounding whitespace and comments, so the tree reproduces the source
+// byte-for-byte. The AST is parsed from the tree's tokens, and each of its nodes has a corresponding node in the tree.
+#[derive(Debug)]
+pub struct ConcreteSyntaxTree {
+    pub root: SyntaxNode,
+    // Any whitespace and comments after the last token
+    pub eof_trivia: Vec<Trivia>,
+    pub ast: ParseResult,
+}
+
+impl ConcreteSyntaxTree {
+    pub fn to_source(&self) -> String {
+        let mut source = self.root.text();
+        self.eof_trivia.iter().for_each(|t| source.push_str(t.text()));
+        source
+    }
+}
+
+pub fn parse_lossless(module_id: &ModuleId, source: &String) -> Result<ConcreteSyntaxTree, Error> {
+    let (trivia_tokens, eof_trivia) = tokenize_with_trivia(module_id, source).map_err(Error::LexerError)?;
+    let tokens = trivia_tokens.iter().map(|t| t.token.clone()).collect::<Vec<_>>();
+    let ast = parse(module_id.clone(), tokens.clone()).map_err(Error::ParseError)?;
+
+    let spans = TokenSpans::new(tokens);
+    let mut builder = TreeBuilder { spans: &spans, tokens: trivia_tokens.into_iter().map(Some).collect(), cursor: 0 };
+    let mut children = vec![];
+    for node in &ast.nodes {
+        builder.build_child(node, &mut children);
+    }
+    builder.take_until(builder.tokens.len(), &mut children);
+
+    let root = SyntaxNode { kind: SyntaxKind::Module, children };
+    Ok(ConcreteSyntaxTree { root, eof_trivia, ast })
+}
+
+struct TreeBuilder<'a> {
+    spans: &'a TokenSpans,
+    tokens: Vec<Option<TriviaToken>>,
+    cursor: usize,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn take_until(&mut self, idx: usize, children: &mut Vec<SyntaxElement>) {
+        while self.cursor < idx {
+            let token = self.tokens[self.cursor].take().expect("Each token is taken once");
+            children.push(SyntaxElement::Token(token));
+            self.cursor += 1;
+        }
+    }
+
+    // Tokens are always consumed in order, so even if a node's span is off the tree stays lossless
+    fn build_child(&mut self, node: &AstNode, children: &mut Vec<SyntaxElement>) {
+        let start_idx = self.spans.start_idx(node);
+        if start_idx < self.cursor { return; }
+        self.take_until(start_idx, children);
+
+        let mut child_nodes = self.spans.children(node);
+        child_nodes.sort_by_key(|n| self.spans.start_idx(n));
+        let mut node_children = vec![];
+        for child in child_nodes {
+            self.build_child(child, &mut node_children);
+        }
+        self.take_until(self.spans.end_idx(node) + 1, &mut node_children);
+
+        children.push(SyntaxElement::Node(SyntaxNode { kind: SyntaxKind::Node(AstNodeKind::from(node)), children: node_children }));
+    }
+}
+
+// Maps AST nodes onto the range of tokens they were parsed from. AST nodes only hold onto some of their tokens (and
+// `AstNode::get_token` is not necessarily the first), so the range is recovered by re-walking the token stream.
+pub struct TokenSpans {
+    tokens: Vec<Token>,
+    token_idxs: HashMap<(usize, usize), usize>,
+    // The index of the matching delimiter for each opening and closing delimiter
+    matching: HashMap<usize, usize>,
+}
+
+impl TokenSpans {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let mut token_idxs = HashMap::new();
+        let mut matching = HashMap::new();
+        let mut stack = vec![];
+        for (idx, token) in tokens.iter().enumerate() {
+            let pos = token.get_position();
+            token_idxs.insert((pos.line, pos.col), idx);
+
+            match token {
+                Token::LParen(_, _) | Token::LBrack(_, _) | Token::LBrace(_) | Token::LBraceHash(_) => stack.push(idx),
+                Token::RParen(_) | Token::RBrack(_) | Token::RBrace(_) => {
+                    if let Some(open_idx) = stack.pop() {
+                        matching.insert(open_idx, idx);
+                        matching.insert(idx, open_idx);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        TokenSpans { tokens, token_idxs, matching }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn token(&self, idx: usize) -> &Token {
+        &self.tokens[idx]
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&Token> {
+        self.tokens.get(idx)
+    }
+
+    pub fn idx(&self, token: &Token) -> usize {
+        let pos = token.get_position();
+        *self.token_idxs.get(&(pos.line, pos.col)).expect("Every ast token should be present in the token stream")
+    }
+
+    pub fn pos(&self, idx: usize) -> Position {
+        self.tokens[idx].get_position()
+    }
+
+    pub fn is_same_line(&self, idx1: usize, idx2: usize) -> bool {
+        self.pos(idx1).line == self.pos(idx2).line
+    }
+
+    pub fn matching(&self, idx: usize) -> usize {
+        self.matching[&idx]
+    }
+
+    pub fn find_next(&self, mut idx: usize, pred: fn(&Token) -> bool) -> usize {
+        while !pred(&self.tokens[idx]) { idx += 1; }
+        idx
+    }
+
+    // The inclusive range of token indices that the node was parsed from
+    pub fn span(&self, node: &AstNode) -> (usize, usize) {
+        (self.start_idx(node), self.end_idx(node))
+    }
+
+    pub fn start_idx(&self, node: &AstNode) -> usize {
+        match node {
+            AstNode::Binary(_, n) => self.start_idx(&n.left),
+            AstNode::Assignment(_, n) => self.start_idx(&n.target),
+            AstNode::Indexing(_, n) => self.start_idx(&n.target),
+            AstNode::Accessor(_, n) => self.start_idx(&n.target),
+            AstNode::Invocation(token, n) => {
+                let idx = self.idx(token);
+                if self.is_interpolation(idx) { idx } else { self.start_idx(&n.target) }
+            }
+            AstNode::Lambda(token, _) => {
+                let arrow_idx = self.idx(token);
+                match self.tokens[arrow_idx - 1] {
+                    Token::RParen(_) => self.matching[&(arrow_idx - 1)],
+                    _ => arrow_idx - 1,
+                }
+            }
+            AstNode::BindingDecl(token, n) => self.decl_start_idx(token, &n.decorators, &n.export_token),
+            AstNode::FunctionDecl(token, n) => self.decl_start_idx(token, &n.decorators, &n.export_token),
+            AstNode::TypeDecl(token, n) => self.decl_start_idx(token, &n.decorators, &n.export_token),
+            AstNode::EnumDecl(token, n) => self.decl_start_idx(token, &n.decorators, &n.export_token),
+            node => self.idx(node.get_token()),
+        }
+    }
+
+    pub fn start_pos(&self, node: &AstNode) -> Position {
+        self.pos(self.start_idx(node))
+    }
+
+    fn decl_start_idx(&self, token: &Token, decorators: &Vec<DecoratorNode>, export_token: &Option<Token>) -> usize {
+        match (decorators.first(), export_token) {
+            (Some(dec), _) => self.idx(&dec.at_token),
+            (None, Some(export_token)) => self.idx(export_token),
+            (None, None) => self.idx(token),
+        }
+    }
+
+    pub fn end_idx(&self, node: &AstNode) -> usize {
+        match node {
+            AstNode::Unary(_, n) => self.end_idx(&n.expr),
+            AstNode::Binary(_, n) => self.end_idx(&n.right),
+            AstNode::Assignment(_, n) => self.end_idx(&n.expr),
+            AstNode::Try(_, n) => self.end_idx(&n.expr),
+            AstNode::Accessor(_, n) => self.end_idx(&n.field),
+            AstNode::Grouped(token, _) |
+            AstNode::Array(token, _) |
+            AstNode::Set(token, _) |
+            AstNode::Map(token, _) |
+            AstNode::Tuple(token, _) |
+            AstNode::Indexing(token, _) => self.matching[&self.idx(token)],
+            AstNode::Invocation(token, _) => {
+                let idx = self.idx(token);
+                if self.is_interpolation(idx) { idx } else { self.matching[&idx] }
+            }
+            AstNode::Identifier(token, Some(_)) => {
+                let mut idx = self.idx(token) + 1;
+                let mut depth = 0;
+                loop {
+                    match self.tokens[idx] {
+                        Token::LT(_) => depth += 1,
+                        Token::GT(_) => {
+                            depth -= 1;
+                            if depth == 0 { break idx; }
+                        }
+                        _ => {}
+                    }
+                    idx += 1;
+                }
+            }
+            AstNode::BindingDecl(_, n) => {
+                let pattern_end_idx = self.pattern_end_idx(&n.binding);
+                match (&n.expr, &n.type_ann) {
+                    (Some(expr), _) => self.end_idx(expr),
+                    (None, Some(_)) => self.skip_type(pattern_end_idx + 2) - 1,
+                    (None, None) => pattern_end_idx,
+                }
+            }
+            AstNode::FunctionDecl(_, n) => {
+                let lparen_idx = self.skip_type_args(self.idx(&n.name) + 1);
+                let mut idx = self.matching[&lparen_idx] + 1;
+                if n.ret_type.is_some() {
+                    idx = self.skip_type(idx + 1);
+                }
+
+                match self.tokens.get(idx) {
+                    Some(Token::Assign(_)) if !n.body.is_empty() => self.body_end_idx(&n.body, idx + 1),
+                    Some(Token::LBrace(_)) => self.matching[&idx],
+                    // Stub functions have no body
+                    _ => idx - 1,
+                }
+            }
+            AstNode::TypeDecl(_, n) => self.matching[&self.skip_type_args(self.idx(&n.name) + 1)],
+            AstNode::EnumDecl(_, n) => self.matching[&self.skip_type_args(self.idx(&n.name) + 1)],
+            AstNode::Lambda(token, n) => self.body_end_idx(&n.body, self.idx(token) + 1),
+            AstNode::IfStatement(_, n) | AstNode::IfExpression(_, n) => {
+                let body_start_idx = self.if_body_start_idx(n);
+                let if_end_idx = self.body_end_idx(&n.if_block, body_start_idx);
+                match &n.else_block {
+                    None => if_end_idx,
+                    Some(else_block) => self.body_end_idx(else_block, if_end_idx + 2),
+                }
+            }
+            AstNode::MatchStatement(_, n) | AstNode::MatchExpression(_, n) => self.matching[&(self.end_idx(&n.target) + 1)],
+            AstNode::ForLoop(_, n) => self.body_end_idx(&n.body, self.end_idx(&n.iterator) + 1),
+            AstNode::WhileLoop(_, n) => self.body_end_idx(&n.body, self.while_body_start_idx(&n.condition, &n.condition_binding)),
+            AstNode::ReturnStatement(token, expr) => expr.as_ref().map(|e| self.end_idx(e)).unwrap_or(self.idx(token)),
+            AstNode::ImportStatement(_, n) => match &n.kind {
+                ImportKind::Alias(alias_token) => self.idx(alias_token),
+                _ => self.idx(&n.module_token),
+            },
+            node => self.idx(node.get_token()),
+        }
+    }
+
+    fn pattern_end_idx(&self, pattern: &BindingPattern) -> usize {
+        match pattern {
+            BindingPattern::Variable(token) => self.idx(token),
+            BindingPattern::Tuple(token, _) | BindingPattern::Array(token, _, _) => self.matching[&self.idx(token)],
+        }
+    }
+
+    // An `else if` is parsed as an else-block containing a single if-expression whose `if` token directly follows the `else`
+    pub fn body_end_idx(&self, body: &Vec<AstNode>, body_start_idx: usize) -> usize {
+        match &self.tokens[body_start_idx] {
+            Token::LBrace(_) => self.matching[&body_start_idx],
+            _ => self.end_idx(&body[0]),
+        }
+    }
+
+    pub fn if_body_start_idx(&self, node: &IfNode) -> usize {
+        let idx = self.end_idx(&node.condition) + 1;
+        if node.condition_binding.is_none() { return idx; }
+
+        self.find_next(idx + 1, |t| matches!(t, Token::Pipe(_))) + 1
+    }
+
+    pub fn while_body_start_idx(&self, condition: &AstNode, condition_binding: &Option<Token>) -> usize {
+        let idx = self.end_idx(condition) + 1;
+        if condition_binding.is_some() { idx + 3 } else { idx }
+    }
+
+    pub fn is_interpolation(&self, idx: usize) -> bool {
+        matches!(self.tokens[idx], Token::StringInterp(_, _))
+    }
+
+    pub fn skip_type_args(&self, idx: usize) -> usize {
+        match self.tokens[idx] {
+            Token::LT(_) => self.find_next(idx, |t| matches!(t, Token::GT(_))) + 1,
+            _ => idx,
+        }
+    }
+
+    // Mirrors the parser's handling of type identifiers, returning the index of the token after the type
+    pub fn skip_type(&self, idx: usize) -> usize {
+        let mut idx = match self.tokens[idx] {
+            Token::LParen(_, _) => {
+                let idx = self.matching[&idx] + 1;
+                match self.tokens.get(idx) {
+                    Some(Token::Arrow(_)) => return self.skip_type(idx + 1),
+                    _ => idx,
+                }
+            }
+            _ => {
+                let mut idx = idx + 1;
+                if let Some(Token::LT(_)) = self.tokens.get(idx) {
+                    let mut depth = 0;
+                    loop {
+                        match self.tokens[idx] {
+                            Token::LT(_) => depth += 1,
+                            Token::GT(_) => depth -= 1,
+                            _ => {}
+                        }
+                        idx += 1;
+                        if depth == 0 { break; }
+                    }
+                }
+                idx
+            }
+        };
+
+        loop {
+            match (self.tokens.get(idx), self.tokens.get(idx + 1)) {
+                (Some(Token::LBrack(_, _)), Some(Token::RBrack(_))) => idx += 2,
+                (Some(Token::Question(_)), _) => idx += 1,
+                _ => break,
+            }
+        }
+        match self.tokens.get(idx) {
+            Some(Token::Pipe(_)) => self.skip_type(idx + 1),
+            _ => idx,
+        }
+    }
+
+    // The node's direct children which were parsed from its tokens. Interpolated strings are treated as leaves, since their
+    // chunks are lexed from within a single token.
+    pub fn children<'a>(&self, node: &'a AstNode) -> Vec<&'a AstNode> {
+        fn decorator_args(decorators: &Vec<DecoratorNode>) -> impl Iterator<Item=&AstNode> {
+            decorators.iter().flat_map(|dec| dec.args.iter().map(|(_, arg)| arg))
+        }
+
+        match node {
+            AstNode::Literal(_, _) | AstNode::Identifier(_, _) | AstNode::Break(_) | AstNode::Continue(_) | AstNode::ImportStatement(_, _) => vec![],
+            AstNode::Unary(_, n) => vec![&n.expr],
+            AstNode::Binary(_, n) => vec![&n.left, &n.right],
+            AstNode::Grouped(_, n) => vec![&n.expr],
+            AstNode::Array(_, n) => n.items.iter().collect(),
+            AstNode::Set(_, n) => n.items.iter().collect(),
+            AstNode::Map(_, n) => n.items.iter().flat_map(|(k, v)| vec![k, v]).collect(),
+            AstNode::Tuple(_, items) => items.iter().collect(),
+            AstNode::BindingDecl(_, n) => decorator_args(&n.decorators).chain(n.expr.iter().map(|e| &**e)).collect(),
+            AstNode::FunctionDecl(_, n) => decorator_args(&n.decorators)
+                .chain(n.args.iter().filter_map(|(_, _, _, default_value)| default_value.as_ref()))
+                .chain(n.body.iter())
+                .collect(),
+            AstNode::TypeDecl(_, n) => decorator_args(&n.decorators)
+                .chain(n.fields.iter().filter_map(|f| f.default_value.as_ref()))
+                .chain(n.methods.iter())
+                .collect(),
+            AstNode::EnumDecl(_, n) => decorator_args(&n.decorators)
+                .chain(n.variants.iter().flat_map(|(_, args)| args.iter().flatten().filter_map(|(_, _, _, default_value)| default_value.as_ref())))
+                .chain(n.methods.iter())
+                .collect(),
+            AstNode::Assignment(_, n) => vec![&n.target, &n.expr],
+            AstNode::Indexing(_, n) => {
+                let mut children = vec![&*n.target];
+                match &n.index {
+                    IndexingMode::Index(idx) => children.push(idx),
+                    IndexingMode::Range(start, end) => children.extend(start.iter().chain(end.iter()).map(|e| &**e)),
+                }
+                children
+            }
+            AstNode::IfStatement(_, n) | AstNode::IfExpression(_, n) => {
+                std::iter::once(&*n.condition).chain(n.if_block.iter()).chain(n.else_block.iter().flatten()).collect()
+            }
+            AstNode::Invocation(token, n) => {
+                if self.is_interpolation(self.idx(token)) { return vec![]; }
+                std::iter::once(&*n.target).chain(n.args.iter().map(|(_, arg)| arg)).collect()
+            }
+            AstNode::ForLoop(_, n) => std::iter::once(&*n.iterator).chain(n.body.iter()).collect(),
+            AstNode::WhileLoop(_, n) => std::iter::once(&*n.condition).chain(n.body.iter()).collect(),
+            // `Some(x)` is desugared to `Option.Some(x)`, where the `Option` identifier has no token of its own
+            AstNode::Accessor(Token::Ident(_, _), n) => vec![&n.field],
+            AstNode::Accessor(_, n) => vec![&n.target, &n.field],
+            AstNode::Try(_, n) => vec![&n.expr],
+            AstNode::Lambda(_, n) => n.args.iter().filter_map(|(_, _, _, default_value)| default_value.as_ref()).chain(n.body.iter()).collect(),
+            AstNode::MatchStatement(_, n) | AstNode::MatchExpression(_, n) => {
+                let mut children = vec![&*n.target];
+                for (case, body) in &n.branches {
+                    match &case.match_type {
+                        MatchCaseType::Constant(node) => children.push(node),
+                        MatchCaseType::Tuple(_, nodes) => children.extend(nodes),
+                        MatchCaseType::Ident(_, Some(args)) | MatchCaseType::Compound(_, Some(args)) => {
+                            children.extend(args.iter().filter_map(|arg| match arg {
+                                MatchCaseArgument::Literal(node) => Some(node),
+                                MatchCaseArgument::Pattern(_) => None,
+                            }))
+                        }
+                        _ => {}
+                    }
+                    children.extend(body);
+                }
+                children
+            }
+            AstNode::ReturnStatement(_, expr) => expr.iter().map(|e| &**e).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::parser::ast::{AstNode, AstNodeKind, ModuleId};
+    use crate::parser::cst::{parse_lossless, SyntaxElement, SyntaxKind, SyntaxNode, TokenSpans};
+
+    fn count_nodes(node: &SyntaxNode) -> usize {
+        1 + node.child_nodes().map(count_nodes).sum::<usize>()
+    }
+
+    fn count_ast_nodes(spans: &TokenSpans, node: &AstNode) -> usize {
+        1 + spans.children(node).into_iter().map(|n| count_ast_nodes(spans, n)).sum::<usize>()
+    }
+
+    #[test]
+    fn test_parse_lossless() {
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let source = "// Header\nval x = [1, 2] // Trailing\n\nfunc f(a = 3) = a + 1\n".to_string();
+        let cst = parse_lossless(&module_id, &source).unwrap();
+        assert_eq!(source, cst.to_source());
+
+        let decls = cst.root.child_nodes().collect::<Vec<_>>();
+        assert_eq!(2, decls.len());
+        assert_eq!(SyntaxKind::Node(AstNodeKind::BindingDecl), decls[0].kind);
+        assert_eq!("// Header\nval x = [1, 2] // Trailing", decls[0].text());
+        assert_eq!(SyntaxKind::Node(AstNodeKind::FunctionDecl), decls[1].kind);
+        assert_eq!("\n\nfunc f(a = 3) = a + 1", decls[1].text());
+
+        let array = decls[0].child_nodes().next().unwrap();
+        assert_eq!(SyntaxKind::Node(AstNodeKind::Array), array.kind);
+        assert_eq!("[1, 2] // Trailing", array.text());
+        assert_eq!(2, array.child_nodes().count());
+
+        let func_children = decls[1].child_nodes().map(|n| n.kind).collect::<Vec<_>>();
+        assert_eq!(vec![SyntaxKind::Node(AstNodeKind::Literal), SyntaxKind::Node(AstNodeKind::Binary)], func_children);
+        match &decls[1].children[0] {
+            SyntaxElement::Token(token) => assert_eq!("func", token.text),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_lossless_round_trips() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dirs = vec![root.join("std"), root.join("../selfhost/src")];
+
+        let mut num_files = 0;
+        for dir in dirs {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().map_or(true, |ext| ext != "abra") { continue; }
+                num_files += 1;
+
+                let module_id = ModuleId::parse_module_path("./test").unwrap();
+                let source = std::fs::read_to_string(&path).unwrap();
+                let cst = parse_lossless(&module_id, &source).unwrap_or_else(|e| panic!("Failed to parse {:?}: {:?}", path, e));
+                assert_eq!(source, cst.to_source(), "Parsing {:?} should be lossless", path);
+
+                // Every ast node should have its own node in the tree; a node whose span overruns its next sibling would swallow it
+                let spans = TokenSpans::new(cst.root.tokens().into_iter().map(|t| t.token.clone()).collect());
+                let num_ast_nodes = cst.ast.nodes.iter().map(|n| count_ast_nodes(&spans, n)).sum::<usize>();
+                assert_eq!(num_ast_nodes + 1, count_nodes(&cst.root), "Every node in {:?} should be in the tree", path);
+            }
+        }
+        assert!(num_files > 0);
+    }
+}
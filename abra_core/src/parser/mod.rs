@@ -3,6 +3,7 @@
 mod test_helpers;
 
 pub mod ast;
+pub mod cst;
 pub mod parser;
 pub mod parse_error;
 pub mod precedence;
\ No newline at end of file
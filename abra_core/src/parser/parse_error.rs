@@ -1,6 +1,6 @@
 use std::str::FromStr;
 use crate::lexer::tokens::{Token, TokenType, Position, Range};
-use crate::common::display_error::{DisplayError, IND_AMT};
+use crate::common::display_error::{DisplayError, ErrorDescription, IND_AMT};
 use crate::parser::ast::ModuleId;
 use itertools::Itertools;
 
@@ -29,67 +29,60 @@ impl ParseError {
             ParseErrorKind::InvalidImportPath(tok) => tok.get_range()
         }
     }
+
+    // See common::error_codes for the explanation of each code
+    pub fn code(&self) -> &'static str {
+        match &self.kind {
+            ParseErrorKind::UnexpectedEof(_) => "E0100",
+            ParseErrorKind::UnexpectedToken(_) => "E0101",
+            ParseErrorKind::ExpectedToken(_, _) => "E0102",
+            ParseErrorKind::ExpectedOneOf(_, _) => "E0103",
+            ParseErrorKind::InvalidImportPath(_) => "E0104",
+        }
+    }
 }
 
 impl DisplayError for ParseError {
-    fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String {
+    fn describe(&self) -> ErrorDescription<Range> {
         match &self.kind {
-            ParseErrorKind::UnexpectedToken(token) => {
-                let pos = token.get_position();
-                let message = Self::get_underlined_line(lines, token);
-
-                format!(
-                    "Error at {}:{}:{}\nUnexpected token '{}'\n{}",
-                    file_name, pos.line, pos.col, token.to_string(), message
-                )
-            }
-            ParseErrorKind::UnexpectedEof(range) => {
-                let Position { line, col } = range.end;
-                let last_line = lines.get(line - 1).expect("There should be a last line");
-
-                let cursor = format!("{}^", " ".repeat(2 * IND_AMT + col));
-                let indent = Self::indent();
-                let message = format!("{}|{}{}\n{}", indent, indent, last_line, cursor);
-
-                format!(
-                    "Error at {}:{}:{}\nUnexpected end of file\n{}",
-                    file_name, line, col, message
-                )
-            }
+            ParseErrorKind::UnexpectedToken(token) => ErrorDescription::new(format!("Unexpected token '{}'", token.to_string())),
+            ParseErrorKind::UnexpectedEof(_) => ErrorDescription::new("Unexpected end of file"),
             ParseErrorKind::ExpectedToken(expected, actual) => {
-                let pos = actual.get_position();
-                let message = Self::get_underlined_line(lines, actual);
-
                 // Convert from TokenType to Token, to make use of the #[strum(to_string)] meta,
                 // since strum doesn't apply the #[strum(to_string)] to the discriminants.
                 let expected: Token = Token::from_str(&expected.to_string()).unwrap();
-                format!(
-                    "Error at {}:{}:{}\nExpected token '{}', saw '{}'\n{}",
-                    file_name, pos.line, pos.col, expected.to_string(), actual.to_string(), message
-                )
+                ErrorDescription::new(format!("Expected token '{}', saw '{}'", expected.to_string(), actual.to_string()))
             }
             ParseErrorKind::ExpectedOneOf(expected, actual) => {
-                let pos = actual.get_position();
-                let message = Self::get_underlined_line(lines, actual);
-
                 // Convert from TokenTypes to Tokens, to make use of the #[strum(to_string)] meta,
                 // since strum doesn't apply the #[strum(to_string)] to the discriminants.
                 let expecteds = expected.iter()
                     .map(|token_type| format!("'{}'", Token::from_str(&token_type.to_string()).unwrap()))
                     .join(" | ");
-                format!(
-                    "Error at {}:{}:{}\nExpected one of {}, saw '{}'\n{}",
-                    file_name, pos.line, pos.col, expecteds, actual.to_string(), message
-                )
+                ErrorDescription::new(format!("Expected one of {}, saw '{}'", expecteds, actual.to_string()))
             }
-            ParseErrorKind::InvalidImportPath(token) => {
-                let pos = token.get_position();
-                let message = Self::get_underlined_line(lines, token);
+            ParseErrorKind::InvalidImportPath(_) => ErrorDescription::new("Invalid import path"),
+        }
+    }
+
+    fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String {
+        let description = self.describe();
+        match &self.kind {
+            // The end of the file is reported just past the last character
+            ParseErrorKind::UnexpectedEof(range) => {
+                let Position { line, col } = range.end;
+                let last_line = lines.get(line - 1).expect("There should be a last line");
+
+                let cursor = format!("{}^", " ".repeat(2 * IND_AMT + col));
+                let indent = Self::indent();
+                let message = format!("{}|{}{}\n{}", indent, indent, last_line, cursor);
+
                 format!(
-                    "Error at {}:{}:{}\nInvalid import path\n{}",
-                    file_name, pos.line, pos.col, message
+                    "Error at {}:{}:{}\n{}\n{}",
+                    file_name, line, col, description.message, message
                 )
             }
+            _ => Self::render_description(file_name, lines, &self.get_range(), &description),
         }
     }
 }
@@ -1,11 +1,13 @@
 use peekmore::{PeekMore, PeekMoreIterator};
 use std::vec::IntoIter;
 use itertools::Itertools;
-use crate::lexer::tokens::{Token, TokenType, Position, Range};
+use std::collections::HashMap;
+use crate::lexer::tokens::{Comment, CommentKind, Token, TokenType, Position, Range};
 use crate::parser::ast::{ArrayNode, AssignmentNode, AstLiteralNode, AstNode, BinaryNode, BinaryOp, BindingDeclNode, ForLoopNode, FunctionDeclNode, GroupedNode, IfNode, IndexingMode, IndexingNode, InvocationNode, TypeIdentifier, UnaryNode, UnaryOp, WhileLoopNode, TypeDeclNode, MapNode, AccessorNode, LambdaNode, EnumDeclNode, MatchNode, MatchCase, MatchCaseType, SetNode, BindingPattern, TypeDeclField, ImportNode, ModuleId, MatchCaseArgument, ImportKind, TryNode, DecoratorNode};
 use crate::parser::parse_error::{ParseErrorKind, ParseError};
 use crate::parser::precedence::Precedence;
 
+#[derive(Debug)]
 pub struct ParseResult {
     pub imports: Vec<(Token, ImportNode)>,
     pub nodes: Vec<AstNode>,
@@ -44,6 +46,72 @@ pub fn parse(module_id: ModuleId, tokens: Vec<Token>) -> Result<ParseResult, Par
     Ok(ParseResult { imports, nodes })
 }
 
+// Comments aren't part of the token stream, so doc comments are attached after parsing. A doc comment is a run of `///` line
+// comments on consecutive lines (each on a line of its own) ending on the line directly above a function, type or enum declaration
+// (or its first decorator), or above a type's field. The `///` and the following space are stripped from each line.
+pub fn parse_with_doc_comments(module_id: ModuleId, tokens: Vec<Token>, comments: &Vec<Comment>) -> Result<ParseResult, ParseError> {
+    let mut first_token_cols = HashMap::new();
+    for token in &tokens {
+        let pos = token.get_position();
+        first_token_cols.entry(pos.line).or_insert(pos.col);
+    }
+
+    let mut doc_lines = HashMap::new();
+    for comment in comments {
+        if comment.kind != CommentKind::Line || !comment.text.starts_with("///") || comment.text.starts_with("////") { continue; }
+        let is_own_line = first_token_cols.get(&comment.position.line).map_or(true, |col| *col > comment.position.col);
+        if !is_own_line { continue; }
+
+        let text = &comment.text[3..];
+        doc_lines.insert(comment.position.line, text.strip_prefix(' ').unwrap_or(text).to_string());
+    }
+
+    let mut result = parse(module_id, tokens)?;
+    if !doc_lines.is_empty() {
+        attach_doc_comments(&mut result.nodes, &doc_lines);
+    }
+    Ok(result)
+}
+
+fn attach_doc_comments(nodes: &mut Vec<AstNode>, doc_lines: &HashMap<usize, String>) {
+    fn doc_comment(doc_lines: &HashMap<usize, String>, decl_line: usize) -> Option<String> {
+        let mut lines = vec![];
+        let mut line = decl_line - 1;
+        while let Some(text) = doc_lines.get(&line) {
+            lines.push(text.as_str());
+            line -= 1;
+        }
+        if lines.is_empty() { return None; }
+
+        lines.reverse();
+        Some(lines.join("\n"))
+    }
+
+    fn decl_line(token: &Token, decorators: &Vec<DecoratorNode>, export_token: &Option<Token>) -> usize {
+        decorators.first().map(|dec| &dec.at_token).or(export_token.as_ref()).unwrap_or(token).get_position().line
+    }
+
+    for node in nodes {
+        match node {
+            AstNode::FunctionDecl(token, n) => {
+                n.doc_comment = doc_comment(doc_lines, decl_line(token, &n.decorators, &n.export_token));
+            }
+            AstNode::TypeDecl(token, n) => {
+                n.doc_comment = doc_comment(doc_lines, decl_line(token, &n.decorators, &n.export_token));
+                for field in &mut n.fields {
+                    field.doc_comment = doc_comment(doc_lines, field.ident.get_position().line);
+                }
+                attach_doc_comments(&mut n.methods, doc_lines);
+            }
+            AstNode::EnumDecl(token, n) => {
+                n.doc_comment = doc_comment(doc_lines, decl_line(token, &n.decorators, &n.export_token));
+                attach_doc_comments(&mut n.methods, doc_lines);
+            }
+            _ => {}
+        }
+    }
+}
+
 #[derive(PartialEq)]
 enum Context {
     ParsingExpr,
@@ -585,7 +653,7 @@ impl Parser {
             Some(t) => Err(ParseErrorKind::UnexpectedToken(t.clone())),
         }?;
 
-        Ok(AstNode::FunctionDecl(token, FunctionDeclNode { decorators, export_token, name, type_args, args, ret_type, body }))
+        Ok(AstNode::FunctionDecl(token, FunctionDeclNode { decorators, export_token, doc_comment: None, name, type_args, args, ret_type, body }))
     }
 
     fn parse_binding_decl(&mut self, export_token: Option<Token>) -> Result<AstNode, ParseErrorKind> {
@@ -684,7 +752,7 @@ impl Parser {
                             Some(self.expect_next()?)
                         } else { None };
 
-                        let field = TypeDeclField { ident, type_ident, default_value, readonly };
+                        let field = TypeDeclField { ident, type_ident, default_value, readonly, doc_comment: None };
                         fields.push(field);
                     }
                 }
@@ -702,9 +770,9 @@ impl Parser {
         self.expect_next_token(TokenType::RBrace)?;
 
         if is_enum {
-            Ok(AstNode::EnumDecl(keyword_tok, EnumDeclNode { decorators, export_token, name, variants, methods, type_args }))
+            Ok(AstNode::EnumDecl(keyword_tok, EnumDeclNode { decorators, export_token, doc_comment: None, name, variants, methods, type_args }))
         } else {
-            Ok(AstNode::TypeDecl(keyword_tok, TypeDeclNode { decorators, export_token, name, fields, methods, type_args }))
+            Ok(AstNode::TypeDecl(keyword_tok, TypeDeclNode { decorators, export_token, doc_comment: None, name, fields, methods, type_args }))
         }
     }
 
@@ -1668,7 +1736,7 @@ impl Parser {
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::lexer::tokenize;
+    use crate::lexer::lexer::{tokenize, tokenize_with_comments};
     use crate::lexer::tokens::{Position, Token};
     use crate::parser::ast::AstNode::*;
     use crate::parser::ast::ModulePathSegment;
@@ -3013,6 +3081,7 @@ mod tests {
                 body: vec![
                     int_literal!((1, 14), 123)
                 ],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3041,6 +3110,7 @@ mod tests {
                     ),
                     identifier!((1, 26), "a"),
                 ],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3397,6 +3467,7 @@ mod tests {
                 type_args: vec![],
                 fields: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3415,9 +3486,11 @@ mod tests {
                         type_ident: TypeIdentifier::Normal { ident: ident_token!((1, 21), "String"), type_args: None },
                         default_value: None,
                         readonly: None,
+                        doc_comment: None,
                     },
                 ],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3438,15 +3511,18 @@ mod tests {
                         type_ident: TypeIdentifier::Normal { ident: ident_token!((1, 21), "String"), type_args: None },
                         default_value: None,
                         readonly: None,
+                        doc_comment: None,
                     },
                     TypeDeclField {
                         ident: ident_token!((1, 29), "age"),
                         type_ident: TypeIdentifier::Normal { ident: ident_token!((1, 34), "Int"), type_args: None },
                         default_value: None,
                         readonly: None,
+                        doc_comment: None,
                     },
                 ],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3466,15 +3542,18 @@ mod tests {
                         type_ident: TypeIdentifier::Normal { ident: ident_token!((1, 21), "String"), type_args: None },
                         default_value: None,
                         readonly: None,
+                        doc_comment: None,
                     },
                     TypeDeclField {
                         ident: ident_token!((1, 29), "isHappy"),
                         type_ident: TypeIdentifier::Normal { ident: ident_token!((1, 38), "Bool"), type_args: None },
                         default_value: Some(bool_literal!((1, 45), true)),
                         readonly: None,
+                        doc_comment: None,
                     },
                 ],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3494,9 +3573,11 @@ mod tests {
                         type_ident: TypeIdentifier::Normal { ident: ident_token!((1, 21), "String"), type_args: None },
                         default_value: None,
                         readonly: Some(Token::Readonly(Position::new(1, 28))),
+                        doc_comment: None,
                     },
                 ],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3517,6 +3598,7 @@ mod tests {
                 type_args: vec![ident_token!((1, 11), "T")],
                 fields: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3535,6 +3617,7 @@ mod tests {
                 ],
                 fields: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3594,9 +3677,11 @@ mod tests {
                             body: vec![
                                 string_literal!((2, 20), "hello"),
                             ],
+                            doc_comment: None,
                         },
                     ),
                 ],
+                doc_comment: None,
             },
         );
         Ok(assert_eq!(expected, ast[0]))
@@ -3654,6 +3739,7 @@ mod tests {
                     (ident_token!((3, 1), "Blue"), None),
                 ],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3672,6 +3758,7 @@ mod tests {
                     (ident_token!((1, 23), "Blue"), None),
                 ],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -3695,6 +3782,7 @@ mod tests {
                     ])),
                 ],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5330,6 +5418,7 @@ mod tests {
                 args: vec![],
                 ret_type: None,
                 body: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5345,6 +5434,7 @@ mod tests {
                 type_args: vec![],
                 fields: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5360,6 +5450,7 @@ mod tests {
                 type_args: vec![],
                 variants: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5525,6 +5616,7 @@ mod tests {
                 args: vec![],
                 ret_type: None,
                 body: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5540,6 +5632,7 @@ mod tests {
                 args: vec![],
                 ret_type: None,
                 body: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5564,9 +5657,11 @@ mod tests {
                             args: vec![],
                             ret_type: None,
                             body: vec![],
+                            doc_comment: None,
                         },
                     ),
                 ],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5582,6 +5677,7 @@ mod tests {
                 type_args: vec![],
                 fields: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5596,6 +5692,7 @@ mod tests {
                 type_args: vec![],
                 fields: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5611,6 +5708,7 @@ mod tests {
                 type_args: vec![],
                 variants: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
@@ -5625,10 +5723,34 @@ mod tests {
                 type_args: vec![],
                 variants: vec![],
                 methods: vec![],
+                doc_comment: None,
             },
         );
         assert_eq!(expected, ast[0]);
 
         Ok(())
     }
+
+    #[test]
+    fn parse_doc_comments() {
+        let input = "/// Adds one\n/// to a number\nfunc inc(i: Int): Int = i + 1\n\n/// Not attached (there's a gap)\n\nfunc f() {}\n\nval x = 1 /// Not a doc comment\n/// A person\n@Foo\nexport type Person {\n  /// Their name\n  name: String\n  // Just a comment\n  age: Int\n  /// Says hi\n  func greet(self) {}\n}\n//// Not a doc comment either\nenum Color { Red }";
+        let module_id = ModuleId::parse_module_path("./test").unwrap();
+        let (tokens, comments) = tokenize_with_comments(&module_id, &input.to_string()).unwrap();
+        let ParseResult { nodes, .. } = parse_with_doc_comments(module_id, tokens, &comments).unwrap();
+
+        let AstNode::FunctionDecl(_, inc) = &nodes[0] else { unreachable!() };
+        assert_eq!(Some("Adds one\nto a number".to_string()), inc.doc_comment);
+        let AstNode::FunctionDecl(_, f) = &nodes[1] else { unreachable!() };
+        assert_eq!(None, f.doc_comment);
+
+        let AstNode::TypeDecl(_, person) = &nodes[3] else { unreachable!() };
+        assert_eq!(Some("A person".to_string()), person.doc_comment);
+        assert_eq!(Some("Their name".to_string()), person.fields[0].doc_comment);
+        assert_eq!(None, person.fields[1].doc_comment);
+        let AstNode::FunctionDecl(_, greet) = &person.methods[0] else { unreachable!() };
+        assert_eq!(Some("Says hi".to_string()), greet.doc_comment);
+
+        let AstNode::EnumDecl(_, color) = &nodes[4] else { unreachable!() };
+        assert_eq!(None, color.doc_comment);
+    }
 }
@@ -317,7 +317,8 @@ impl<W: std::io::Write> CCompiler2<W> {
 
         self.emit_newline();
         self.emit_comment("Load and execute module code");
-        for module in &project.modules {
+        for module_id in &project.module_order {
+            let module = &project.modules[module_id.0];
             self.emit_comment(&module.name);
             writeln!(self.output, "{}();", self.get_module_entrypoint_name(&module)).expect("Output should be able to be written to");
         }
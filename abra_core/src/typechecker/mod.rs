@@ -1,6 +1,6 @@
 #[macro_use]
 #[cfg(test)]
-mod test_helpers;
+pub(crate) mod test_helpers;
 
 #[cfg(test)]
 mod typechecker_tests;
@@ -12,3 +12,4 @@ pub mod typechecker;
 pub mod typechecker2;
 pub mod typechecker_error;
 pub mod types;
+pub mod virtual_module_loader;
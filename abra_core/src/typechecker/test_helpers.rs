@@ -1,3 +1,7 @@
+use crate::parser;
+use crate::typechecker::typechecker2::{ModuleId, Project, Typechecker2, TypecheckError};
+use crate::typechecker::virtual_module_loader::VirtualModuleLoader;
+
 macro_rules! ident_token {
     ($pos: expr, $i: expr) => (
         match $pos {
@@ -85,3 +89,30 @@ macro_rules! string_literal {
         }
     );
 }
+
+// A loader rooted at `/project` with the given files, along with the std modules which are needed to typecheck the prelude
+pub(crate) fn std_loader(files: &[(&str, &str)]) -> VirtualModuleLoader {
+    let mut loader = VirtualModuleLoader::new("/project", "/std");
+    add_std(&mut loader);
+    for (path, source) in files {
+        loader.add_file(path, source);
+    }
+    loader
+}
+
+pub(crate) fn add_std(loader: &mut VirtualModuleLoader) {
+    loader.add_file("/std/prelude.abra", include_str!("../../std/prelude.abra"));
+    loader.add_file("/std/_intrinsics.abra", include_str!("../../std/_intrinsics.abra"));
+    loader.add_file("/std/libc.abra", include_str!("../../std/libc.abra"));
+}
+
+// Typechecks the prelude and then `./main`, given the files of a project rooted at `/project`
+pub(crate) fn typecheck_main(files: &[(&str, &str)]) -> (VirtualModuleLoader, Project, Result<ModuleId, TypecheckError>) {
+    let mut loader = std_loader(files);
+    let mut project = Project::default();
+    let mut tc = Typechecker2::new(&mut loader, &mut project);
+    tc.typecheck_prelude().unwrap();
+    let result = tc.typecheck_module(&parser::ast::ModuleId::parse_module_path("./main").unwrap(), None);
+
+    (loader, project, result)
+}
@@ -1847,7 +1847,7 @@ impl<'a, R: ModuleReader> AstVisitor<TypedAstNode, TypecheckerErrorKind> for Typ
 
         let mut field_names = HashMap::<String, Token>::new();
         let fields = fields.into_iter()
-            .map(|TypeDeclField { ident, type_ident, default_value, readonly }| {
+            .map(|TypeDeclField { ident, type_ident, default_value, readonly, .. }| {
                 let field_type = self.type_from_type_ident(&type_ident, false)?;
                 let field_name_str = Token::get_ident_name(&ident);
                 if let Some(orig_ident) = field_names.get(&field_name_str) {
@@ -1,32 +1,40 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
-use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use itertools::{Either, EitherOrBoth, Itertools};
 use crate::parser;
+use crate::common::display_error::ErrorDescription;
+use crate::common::suggestions::find_similar_name;
 use crate::common::util::integer_decode;
 use crate::parser::parser::{ParseResult};
 use crate::lexer::lexer_error::LexerError;
-use crate::lexer::tokens::{POSITION_BOGUS, Range, Token};
+use crate::lexer::tokens::{POSITION_BOGUS, Position, Range, Token};
 use crate::parser::ast::{AccessorNode, args_to_parameters, AssignmentNode, AstLiteralNode, AstNode, BinaryNode, BinaryOp, BindingDeclNode, BindingPattern, EnumDeclNode, ForLoopNode, FunctionDeclNode, IfNode, ImportKind, ImportNode, IndexingMode, IndexingNode, InvocationNode, MatchCase, MatchCaseArgument, MatchCaseType, MatchNode, Parameter, TypeDeclField, TypeDeclNode, TypeIdentifier, UnaryNode, UnaryOp, WhileLoopNode};
 use crate::parser::parse_error::ParseError;
+use crate::manifest::ResolvedManifest;
 
 pub trait LoadModule {
     fn get_path(&self, module_id: &ModuleId) -> Option<String>;
     fn calculate_path_wrt_other(&self, m_id: &parser::ast::ModuleId, other: Option<&ModuleId>) -> String;
     fn register(&mut self, m_id: &parser::ast::ModuleId, module_id: &ModuleId, with_respect_to: Option<&ModuleId>);
     fn get_module_id(&self, m_id: &parser::ast::ModuleId) -> Option<&ModuleId>;
+    fn get_m_id(&self, module_id: &ModuleId) -> Option<&parser::ast::ModuleId>;
     fn module_exists(&self, m_id: &parser::ast::ModuleId, with_respect_to: Option<&ModuleId>) -> bool;
     fn load_file(&self, file_name: &String) -> Option<String>;
+    // The paths of the files within a directory, used to suggest alternatives for modules which can't be found
+    fn list_dir(&self, _dir: &Path) -> Vec<String> { vec![] }
     fn load_untyped_ast(&self, module_id: &parser::ast::ModuleId, with_respect_to: Option<&ModuleId>) -> Result<Option<(String, ParseResult)>, Either<LexerError, ParseError>> {
+        let file_name = self.calculate_path_wrt_other(module_id, with_respect_to);
+        self.parse_file(module_id, file_name)
+    }
+    fn parse_file(&self, module_id: &parser::ast::ModuleId, file_name: String) -> Result<Option<(String, ParseResult)>, Either<LexerError, ParseError>> {
         use crate::{lexer::lexer, parser::parser};
 
-        let file_name = self.calculate_path_wrt_other(module_id, with_respect_to);
         let Some(file_contents) = self.load_file(&file_name) else { return Ok(None); };
 
-        match lexer::tokenize(module_id, &file_contents) {
+        match lexer::tokenize_with_comments(module_id, &file_contents) {
             Err(e) => Err(Either::Left(e)),
-            Ok(tokens) => match parser::parse(module_id.clone(), tokens) {
+            Ok((tokens, comments)) => match parser::parse_with_doc_comments(module_id.clone(), tokens, &comments) {
                 Err(e) => Err(Either::Right(e)),
                 Ok(nodes) => Ok(Some((file_name, nodes)))
             }
@@ -37,6 +45,7 @@ pub trait LoadModule {
 pub struct ModuleLoader<'a> {
     program_root: &'a PathBuf,
     std_path: &'a PathBuf,
+    manifest: Option<&'a ResolvedManifest>,
     module_id_map: HashMap<ModuleId, parser::ast::ModuleId>,
     module_id_map_rev: HashMap<parser::ast::ModuleId, ModuleId>,
     module_id_paths: HashMap<ModuleId, String>,
@@ -47,11 +56,57 @@ impl<'a> ModuleLoader<'a> {
         ModuleLoader {
             program_root,
             std_path,
+            manifest: None,
             module_id_map: HashMap::new(),
             module_id_map_rev: HashMap::new(),
             module_id_paths: HashMap::new(),
         }
     }
+
+    pub fn with_manifest(program_root: &'a PathBuf, std_path: &'a PathBuf, manifest: &'a ResolvedManifest) -> ModuleLoader<'a> {
+        ModuleLoader { manifest: Some(manifest), ..ModuleLoader::new(program_root, std_path) }
+    }
+
+    // The paths of every module which has been registered so far, including any which failed to typecheck
+    pub fn module_paths(&self) -> Vec<String> {
+        self.module_id_paths.values().cloned().sorted().collect()
+    }
+}
+
+// Computes the file path of a module, for LoadModule implementations which resolve modules relative to a program root, the std
+// library, and (optionally) a project manifest. Relative imports are resolved with respect to the importing module's path.
+// Whether a file exists is determined by `file_exists`, since that depends on where the LoadModule implementation reads files from.
+pub fn calculate_module_path<F: Fn(&str) -> bool>(
+    program_root: &Path,
+    std_path: &Path,
+    manifest: Option<&ResolvedManifest>,
+    module_id_paths: &HashMap<ModuleId, String>,
+    m_id: &parser::ast::ModuleId,
+    other: Option<&ModuleId>,
+    file_exists: F,
+) -> String {
+    let path = if let parser::ast::ModuleId::External(module_name) = &m_id {
+        // Modules within the std library (and the prelude itself) can never be shadowed by modules in the project, nor can the std
+        // library's own modules be shadowed by a project module or dependency of the same name
+        let std_module_path = m_id.get_path(std_path);
+        let is_std_import = m_id.is_prelude() || file_exists(&format!("{std_module_path}.abra")) || other
+            .and_then(|wrt| module_id_paths.get(wrt))
+            .map(|wrt_path| Path::new(wrt_path).starts_with(std_path))
+            .unwrap_or(false);
+        let manifest_path = if is_std_import { None } else { manifest.and_then(|manifest| manifest.resolve_module_path(module_name)) };
+
+        match manifest_path {
+            Some(path) => path.to_str().unwrap().to_string(),
+            None => std_module_path,
+        }
+    } else if let Some(wrt) = other {
+        let wrt_path = module_id_paths.get(wrt)
+            .expect("Attempting to register a module with respect to other which has not yet been registered");
+        m_id.get_path(PathBuf::from(wrt_path).parent().unwrap())
+    } else {
+        m_id.get_path(program_root)
+    };
+    format!("{path}.abra")
 }
 
 impl<'a> LoadModule for ModuleLoader<'a> {
@@ -60,16 +115,7 @@ impl<'a> LoadModule for ModuleLoader<'a> {
     }
 
     fn calculate_path_wrt_other(&self, m_id: &parser::ast::ModuleId, other: Option<&ModuleId>) -> String {
-        let path = if let parser::ast::ModuleId::External(_) = &m_id {
-            m_id.get_path(self.std_path)
-        } else if let Some(wrt) = other {
-            let wrt_path = self.module_id_paths.get(wrt)
-                .expect("Attempting to register a module with respect to other which has not yet been registered");
-            m_id.get_path(PathBuf::from(wrt_path).parent().unwrap())
-        } else {
-            m_id.get_path(self.program_root)
-        };
-        format!("{path}.abra")
+        calculate_module_path(self.program_root, self.std_path, self.manifest, &self.module_id_paths, m_id, other, |path| Path::new(path).is_file())
     }
 
     fn register(&mut self, m_id: &parser::ast::ModuleId, module_id: &ModuleId, with_respect_to: Option<&ModuleId>) {
@@ -84,6 +130,10 @@ impl<'a> LoadModule for ModuleLoader<'a> {
         self.module_id_map_rev.get(m_id)
     }
 
+    fn get_m_id(&self, module_id: &ModuleId) -> Option<&parser::ast::ModuleId> {
+        self.module_id_map.get(module_id)
+    }
+
     fn module_exists(&self, m_id: &parser::ast::ModuleId, with_respect_to: Option<&ModuleId>) -> bool {
         let path = self.calculate_path_wrt_other(m_id, with_respect_to);
         Path::try_exists(Path::new(&path)).unwrap_or(false)
@@ -92,11 +142,22 @@ impl<'a> LoadModule for ModuleLoader<'a> {
     fn load_file(&self, file_name: &String) -> Option<String> {
         std::fs::read_to_string(file_name).ok()
     }
+
+    fn list_dir(&self, dir: &Path) -> Vec<String> {
+        list_dir_on_disk(dir)
+    }
+}
+
+pub fn list_dir_on_disk(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return vec![]; };
+    entries.filter_map(|entry| entry.ok()?.path().to_str().map(|p| p.to_string())).collect()
 }
 
 #[derive(Debug)]
 pub struct Project {
     pub modules: Vec<TypedModule>,
+    // The order in which modules' top-level code was typechecked, which is also the order in which it must be run
+    pub module_order: Vec<ModuleId>,
 
     // cached values
     pub prelude_option_enum_id: EnumId,
@@ -118,6 +179,7 @@ impl Default for Project {
     fn default() -> Self {
         Self {
             modules: vec![],
+            module_order: vec![],
             prelude_option_enum_id: PLACEHOLDER_ENUM_ID,
             prelude_int_struct_id: PLACEHOLDER_STRUCT_ID,
             prelude_float_struct_id: PLACEHOLDER_STRUCT_ID,
@@ -137,6 +199,24 @@ impl Project {
         &self.modules[PRELUDE_MODULE_ID.0]
     }
 
+    // The modules which must be typechecked again if the given modules change: those modules and every module which imports any of them
+    // (directly or transitively), as well as any module which isn't complete (whose imports may not even be known yet)
+    pub fn invalidated_modules(&self, module_ids: &[ModuleId]) -> HashSet<ModuleId> {
+        let mut invalidated = module_ids.iter().copied().collect::<HashSet<_>>();
+        invalidated.extend(self.modules.iter().filter(|m| !m.completed).map(|m| m.id));
+
+        let mut queue = invalidated.iter().copied().collect_vec();
+        while let Some(module_id) = queue.pop() {
+            for module in &self.modules {
+                if module.imports.contains_key(&module_id) && invalidated.insert(module.id) {
+                    queue.push(module.id);
+                }
+            }
+        }
+
+        invalidated
+    }
+
     pub fn get_scope_by_id(&self, scope_id: &ScopeId) -> &Scope {
         let ScopeId(ModuleId(module_idx), idx) = scope_id;
         &self.modules[*module_idx].scopes[*idx]
@@ -205,6 +285,52 @@ impl Project {
         &mut scope.vars[*idx]
     }
 
+    pub fn symbol_for_var(&self, var_id: &VarId) -> Symbol {
+        match self.get_var_by_id(var_id).alias {
+            VariableAlias::None => Symbol::Variable(*var_id),
+            VariableAlias::Function(func_id) => Symbol::Function(func_id),
+            VariableAlias::Type(type_kind) => Symbol::Type(type_kind),
+        }
+    }
+
+    // The member of the target's type which an accessor refers to (if any; the fields of enum variants and the members of generics
+    // don't have declarations of their own)
+    pub fn accessor_symbol(&self, target_type_id: &TypeId, kind: &AccessorKind, member_idx: usize) -> Option<Symbol> {
+        let ty = self.get_type_by_id(target_type_id);
+        match kind {
+            AccessorKind::Field => ty.get_struct_id(self).map(|struct_id| Symbol::Field(struct_id, member_idx)),
+            AccessorKind::Method => ty.get_method(self, member_idx).map(Symbol::Function),
+            AccessorKind::StaticMethod => ty.get_static_method(self, member_idx).map(Symbol::Function),
+            AccessorKind::EnumVariant => match ty {
+                Type::Type(TypeKind::Enum(enum_id)) => Some(Symbol::EnumVariant(*enum_id, member_idx)),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn symbol_name(&self, symbol: &Symbol) -> &String {
+        match symbol {
+            Symbol::Variable(var_id) => &self.get_var_by_id(var_id).name,
+            Symbol::Function(func_id) => &self.get_func_by_id(func_id).name,
+            Symbol::Type(TypeKind::Struct(struct_id)) => &self.get_struct_by_id(struct_id).name,
+            Symbol::Type(TypeKind::Enum(enum_id)) => &self.get_enum_by_id(enum_id).name,
+            Symbol::Field(struct_id, field_idx) => &self.get_struct_by_id(struct_id).fields[*field_idx].name,
+            Symbol::EnumVariant(enum_id, variant_idx) => &self.get_enum_by_id(enum_id).variants[*variant_idx].name,
+        }
+    }
+
+    // The span at which the symbol is declared, if it's not a builtin
+    pub fn symbol_defined_span(&self, symbol: &Symbol) -> Option<&Span> {
+        match symbol {
+            Symbol::Variable(var_id) => self.get_var_by_id(var_id).defined_span.as_ref(),
+            Symbol::Function(func_id) => self.get_func_by_id(func_id).defined_span.as_ref(),
+            Symbol::Type(TypeKind::Struct(struct_id)) => self.get_struct_by_id(struct_id).defined_span.as_ref(),
+            Symbol::Type(TypeKind::Enum(enum_id)) => Some(&self.get_enum_by_id(enum_id).defined_span),
+            Symbol::Field(struct_id, field_idx) => Some(&self.get_struct_by_id(struct_id).fields[*field_idx].defined_span),
+            Symbol::EnumVariant(enum_id, variant_idx) => Some(&self.get_enum_by_id(enum_id).variants[*variant_idx].defined_span),
+        }
+    }
+
     pub fn get_struct_by_type_id(&self, type_id: &TypeId) -> Option<(&Struct, HashMap<TypeId, TypeId>)> {
         let mut generic_substitutions = HashMap::new();
         let ty = self.get_type_by_id(type_id);
@@ -391,6 +517,44 @@ impl Project {
         )
     }
 
+    // The variables (which includes functions and types) visible from the given scope, used to suggest alternatives for unknown names
+    pub fn visible_variables(&self, scope_id: &ScopeId) -> Vec<&Variable> {
+        let mut vars = vec![];
+        let mut cur_scope_id = Some(*scope_id);
+        while let Some(ScopeId(ModuleId(module_idx), scope_idx)) = cur_scope_id {
+            let scope = &self.modules[module_idx].scopes[scope_idx];
+            vars.extend(scope.vars.iter());
+            cur_scope_id = scope.parent;
+        }
+
+        for imported_values in self.modules[scope_id.0.0].imports.values() {
+            for import in imported_values {
+                if let ImportedValue::Variable(_, var_id) = import {
+                    vars.push(self.get_var_by_id(var_id));
+                }
+            }
+        }
+
+        vars
+    }
+
+    // The names of the types (including generics) visible from the given scope, used to suggest alternatives for unknown types
+    pub fn visible_type_names(&self, scope_id: &ScopeId) -> Vec<&String> {
+        let mut names = self.visible_variables(scope_id).into_iter()
+            .filter(|var| matches!(self.get_type_by_id(&var.type_id), Type::Type(_)))
+            .map(|var| &var.name)
+            .collect_vec();
+
+        let mut cur_scope_id = Some(*scope_id);
+        while let Some(ScopeId(ModuleId(module_idx), scope_idx)) = cur_scope_id {
+            let scope = &self.modules[module_idx].scopes[scope_idx];
+            names.extend(scope.types.iter().filter_map(|ty| if let Type::Generic(_, name) = ty { Some(name) } else { None }));
+            cur_scope_id = scope.parent;
+        }
+
+        names
+    }
+
     pub fn find_var_id_by_alias(&self, alias: VariableAlias) -> Option<VarId> {
         let scope_id = match alias {
             VariableAlias::None => return None,
@@ -597,6 +761,10 @@ impl Span {
         Span { module_id, range }
     }
 
+    pub fn module_id(&self) -> &ModuleId {
+        &self.module_id
+    }
+
     pub fn expand(&self, other: &Span) -> Span {
         self.expand_range(&other.range)
     }
@@ -626,6 +794,7 @@ pub struct Struct {
     pub name: String,
     // Structs with no defined_span are builtins
     pub defined_span: Option<Span>,
+    pub doc_comment: Option<String>,
     pub generic_ids: Vec<TypeId>,
     pub self_type_id: TypeId,
     pub fields: Vec<StructField>,
@@ -640,6 +809,7 @@ pub struct StructField {
     pub defined_span: Span,
     pub is_readonly: bool,
     pub default_value: Option<TypedNode>,
+    pub doc_comment: Option<String>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -648,6 +818,7 @@ pub struct Enum {
     pub enum_scope_id: ScopeId,
     pub name: String,
     pub defined_span: Span,
+    pub doc_comment: Option<String>,
     pub generic_ids: Vec<TypeId>,
     pub self_type_id: TypeId,
     pub variants: Vec<EnumVariant>,
@@ -691,7 +862,7 @@ impl TypeId {
         TypeId(Self::MODULE_ALIAS_MARKER, *module_idx)
     }
 
-    fn as_module_type_alias(&self) -> Option<ModuleId> {
+    pub fn as_module_type_alias(&self) -> Option<ModuleId> {
         if self.0 == Self::MODULE_ALIAS_MARKER { Some(ModuleId(self.1)) } else { None }
     }
 }
@@ -706,7 +877,7 @@ pub enum PrimitiveType {
     String,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum TypeKind {
     Struct(StructId),
     Enum(EnumId),
@@ -769,6 +940,27 @@ impl Type {
         methods.iter().enumerate().find(|(_, m)| &project.get_func_by_id(m).name == method_name)
     }
 
+    // The names of the members which can be accessed on a value of this type, used to suggest alternatives for unknown members
+    pub fn member_names<'a>(&self, project: &'a Project) -> Vec<&'a String> {
+        let func_names = |func_ids: &'a Vec<FuncId>| func_ids.iter().map(|func_id| &project.get_func_by_id(func_id).name);
+
+        match self {
+            Type::Type(TypeKind::Struct(struct_id)) => func_names(&project.get_struct_by_id(struct_id).static_methods).collect(),
+            Type::Type(TypeKind::Enum(enum_id)) => {
+                let enum_ = project.get_enum_by_id(enum_id);
+                enum_.variants.iter().map(|v| &v.name).chain(func_names(&enum_.static_methods)).collect()
+            }
+            Type::GenericEnumInstance(enum_id, _, _) => func_names(&project.get_enum_by_id(enum_id).methods).collect(),
+            _ => match self.get_struct_id(project) {
+                Some(struct_id) => {
+                    let struct_ = project.get_struct_by_id(&struct_id);
+                    struct_.fields.iter().map(|f| &f.name).chain(func_names(&struct_.methods)).collect()
+                }
+                None => vec![],
+            }
+        }
+    }
+
     fn get_struct_id(&self, project: &Project) -> Option<StructId> {
         match self {
             Type::Primitive(PrimitiveType::Int) => Some(project.prelude_int_struct_id),
@@ -905,6 +1097,7 @@ pub struct Function {
     pub return_type_id: TypeId,
     // Functions with no defined_span are builtins or lambdas (since they can't have name collisions anyway)
     pub defined_span: Option<Span>,
+    pub doc_comment: Option<String>,
     pub body: Vec<TypedNode>,
     pub captured_vars: Vec<VarId>,
     pub captured_closures: Vec<FuncId>,
@@ -950,6 +1143,23 @@ pub enum ImportedValue {
     Variable(Token, VarId),
 }
 
+// The declaration which a name refers to. Imported functions and types are declared as aliasing variables in the importing module,
+// but references to them point to the original declaration.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Symbol {
+    Variable(VarId),
+    Function(FuncId),
+    Type(TypeKind),
+    Field(StructId, /* field_idx: */ usize),
+    EnumVariant(EnumId, /* variant_idx: */ usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    pub range: Range,
+    pub symbol: Symbol,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct TypedModule {
     pub id: ModuleId,
@@ -963,9 +1173,34 @@ pub struct TypedModule {
     pub code: Vec<TypedNode>,
     pub scopes: Vec<Scope>,
     pub exports: HashMap<String, ExportedValue>,
+    // Every usage of a name in the module, keyed by its start position (some code is typechecked more than once)
+    pub references: BTreeMap<Position, Reference>,
     pub completed: bool,
 }
 
+impl TypedModule {
+    // An empty module, which has only its root scope
+    fn new(id: ModuleId, name: String) -> TypedModule {
+        let label = format!("{:?}.root", &id);
+        let root_scope = Scope { label, kind: ScopeKind::Module(id), terminator: None, id: ScopeId(id, 0), parent: Some(PRELUDE_SCOPE_ID), types: vec![], vars: vec![], funcs: vec![] };
+
+        TypedModule {
+            id,
+            name,
+            imports: HashMap::new(),
+            type_ids: vec![],
+            functions: vec![],
+            structs: vec![],
+            enums: vec![],
+            code: vec![],
+            scopes: vec![root_scope],
+            exports: HashMap::new(),
+            references: BTreeMap::new(),
+            completed: false,
+        }
+    }
+}
+
 // TODO: AccessorKind should come with func_id when appropriate, why do func_id lookup again later?
 #[derive(Clone, Debug, PartialEq)]
 pub enum AccessorKind {
@@ -998,6 +1233,7 @@ pub enum TypedMatchCaseKind {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypedMatchCase {
+    pub token: Token,
     pub body: Vec<TypedNode>,
     pub kind: TypedMatchCaseKind,
     pub case_binding: Option<VarId>,
@@ -1427,8 +1663,8 @@ pub enum TypeError {
     TypeMismatch { span: Span, expected: Vec<TypeId>, received: TypeId },
     BranchTypeMismatch { span: Span, orig_span: Span, expected: TypeId, received: TypeId },
     IllegalOperator { span: Span, op: BinaryOp, left: TypeId, right: TypeId },
-    UnknownType { span: Span, name: String },
-    UnknownIdentifier { span: Span, token: Token },
+    UnknownType { span: Span, name: String, suggestion: Option<String> },
+    UnknownIdentifier { span: Span, token: Token, suggestion: Option<String> },
     MissingBindingInitializer { span: Span, is_mutable: bool },
     DuplicateName { span: Span, name: String, original_span: Option<Span>, kind: DuplicateNameKind },
     ForbiddenAssignment { span: Span, type_id: TypeId, purpose: &'static str },
@@ -1466,7 +1702,7 @@ pub enum TypeError {
     UnreachableCode { span: Span },
     InvalidExportScope { span: Span },
     CircularModuleImport { span: Span },
-    UnknownModule { span: Span, module_path: String },
+    UnknownModule { span: Span, module_path: String, suggestion: Option<String> },
     UnknownExport { span: Span, module_id: ModuleId, import_name: String, is_aliased: bool },
 }
 
@@ -1481,16 +1717,16 @@ impl TypeError {
         " ".repeat(Self::INDENT_AMOUNT)
     }
 
-    fn get_underlined_line(loader: &ModuleLoader, span: &Span) -> String {
+    fn get_underlined_line<L: LoadModule>(loader: &L, span: &Span) -> String {
         let file_name = loader.get_path(&span.module_id)
             .expect("Internal error: cannot report on errors in a file that never existed in the first place");
-        let file = std::fs::File::open(&file_name).unwrap();
-        let lines = std::io::BufReader::new(file)
+        let contents = loader.load_file(&file_name).unwrap();
+        let lines = contents
             .lines()
             .skip(span.range.start.line - 1)
             .take(span.range.end.line - span.range.start.line + 1)
-            .collect::<Result<Vec<_>, _>>()
-            .unwrap();
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
 
         let num_lines = lines.len();
         if num_lines == 1 {
@@ -1521,8 +1757,8 @@ impl TypeError {
         }
     }
 
-    pub fn message(&self, loader: &ModuleLoader, project: &Project) -> String {
-        let span = match self {
+    pub fn span(&self) -> &Span {
+        match self {
             TypeError::UnimplementedFeature { span, .. } |
             TypeError::TypeMismatch { span, .. } |
             TypeError::BranchTypeMismatch { span, .. } |
@@ -1568,78 +1804,129 @@ impl TypeError {
             TypeError::CircularModuleImport { span } |
             TypeError::UnknownModule { span, .. } |
             TypeError::UnknownExport { span, .. } => span
-        };
-        let cursor_line = Self::get_underlined_line(loader, span);
+        }
+    }
+
+    // A similarly-named alternative to an unknown name, if there is one
+    pub fn suggestion(&self, project: &Project) -> Option<String> {
+        match self {
+            TypeError::UnknownType { suggestion, .. } |
+            TypeError::UnknownIdentifier { suggestion, .. } |
+            TypeError::UnknownModule { suggestion, .. } => suggestion.clone(),
+            TypeError::UnknownMember { field_name, type_id, .. } => {
+                let member_names = project.get_type_by_id(type_id).member_names(project);
+                find_similar_name(field_name, member_names.into_iter().map(|name| name.as_str()))
+            }
+            TypeError::UnknownExport { module_id, import_name, .. } => {
+                find_similar_name(import_name, project.modules[module_id.0].exports.keys().map(|name| name.as_str()))
+            }
+            _ => None,
+        }
+    }
 
-        let msg = match self {
+    // See common::error_codes for the explanation of each code
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeError::UnimplementedFeature { .. } => "E0200",
+            TypeError::TypeMismatch { .. } => "E0201",
+            TypeError::BranchTypeMismatch { .. } => "E0202",
+            TypeError::IllegalOperator { .. } => "E0203",
+            TypeError::UnknownType { .. } => "E0204",
+            TypeError::UnknownIdentifier { .. } => "E0205",
+            TypeError::MissingBindingInitializer { .. } => "E0206",
+            TypeError::DuplicateName { .. } => "E0207",
+            TypeError::ForbiddenAssignment { .. } => "E0208",
+            TypeError::DestructuringMismatch { .. } => "E0209",
+            TypeError::DuplicateSplat { .. } => "E0210",
+            TypeError::DuplicateParameter { .. } => "E0211",
+            TypeError::ReturnTypeMismatch { .. } => "E0212",
+            TypeError::IllegalInvocation { .. } => "E0213",
+            TypeError::IllegalEnumVariantConstruction { .. } => "E0214",
+            TypeError::UnexpectedArgumentName { .. } => "E0215",
+            TypeError::MixedArgumentType { .. } => "E0216",
+            TypeError::DuplicateArgumentLabel { .. } => "E0217",
+            TypeError::InvalidArity { .. } => "E0218",
+            TypeError::InvalidSelfParam { .. } => "E0219",
+            TypeError::InvalidSelfParamPosition { .. } => "E0220",
+            TypeError::InvalidRequiredParamPosition { .. } => "E0221",
+            TypeError::InvalidVarargPosition { .. } => "E0222",
+            TypeError::InvalidVarargType { .. } => "E0223",
+            TypeError::InvalidTypeArgumentArity { .. } => "E0224",
+            TypeError::UnknownMember { .. } => "E0225",
+            TypeError::MissingRequiredArgumentLabels { .. } => "E0226",
+            TypeError::UnknownTypeForParameter { .. } => "E0227",
+            TypeError::AssignmentToImmutable { .. } => "E0228",
+            TypeError::InvalidIndexableType { .. } => "E0229",
+            TypeError::InvalidIndexType { .. } => "E0230",
+            TypeError::InvalidTupleIndex { .. } => "E0231",
+            TypeError::InvalidAssignmentTarget { .. } => "E0232",
+            TypeError::EmptyIfElseBlock { .. } => "E0233",
+            TypeError::DuplicateMatchCase { .. } => "E0234",
+            TypeError::EmptyMatchBlock { .. } => "E0235",
+            TypeError::UnreachableMatchCase { .. } => "E0236",
+            TypeError::NonExhaustiveMatch { .. } => "E0237",
+            TypeError::InvalidControlFlowTarget { .. } => "E0238",
+            TypeError::InvalidControlFlowTerminator { .. } => "E0239",
+            TypeError::UnreachableCode { .. } => "E0240",
+            TypeError::InvalidExportScope { .. } => "E0241",
+            TypeError::CircularModuleImport { .. } => "E0242",
+            TypeError::UnknownModule { .. } => "E0243",
+            TypeError::UnknownExport { .. } => "E0244",
+        }
+    }
+
+    // Secondary locations referred to by the error (along with a description of each), which the message also underlines
+    pub fn related_spans(&self) -> Vec<(&Span, &'static str)> {
+        match self {
+            TypeError::BranchTypeMismatch { orig_span, .. } => vec![(orig_span, "type determined by prior branch")],
+            TypeError::DuplicateName { original_span: Some(original_span), .. } => vec![(original_span, "originally declared here")],
+            TypeError::AssignmentToImmutable { defined_span: Some(defined_span), .. } => vec![(defined_span, "declared here")],
+            TypeError::DuplicateMatchCase { orig_span, .. } => vec![(orig_span, "first handled here")],
+            _ => vec![],
+        }
+    }
+
+    pub fn describe(&self, project: &Project) -> ErrorDescription<&Span> {
+        let description = match self {
             TypeError::UnimplementedFeature { desc, .. } => {
-                format!("Unimplemented feature: {}\n{}", desc, cursor_line)
+                ErrorDescription::new(format!("Unimplemented feature: {}", desc))
             }
             TypeError::TypeMismatch { expected, received, .. } => {
+                let description = ErrorDescription::new("Type mismatch");
                 if *received == PRELUDE_UNIT_TYPE_ID {
-                    format!(
-                        "Type mismatch\n{}\n\
-                        Cannot use instance of type {} as value",
-                        cursor_line, project.type_repr(&PRELUDE_UNIT_TYPE_ID),
-                    )
+                    description.note(format!("Cannot use instance of type {} as value", project.type_repr(&PRELUDE_UNIT_TYPE_ID)))
                 } else if matches!(project.get_type_by_id(received), Type::Function(_, _, true, _)) {
-                    format!(
-                        "Type mismatch\n{}\nCannot pass variadic function as value",
-                        cursor_line,
-                    )
+                    description.note("Cannot pass variadic function as value")
                 } else {
                     let multiple_expected = expected.len() > 1;
                     let expected = expected.iter().map(|type_id| project.type_repr(type_id)).join(", ");
-                    let received = project.type_repr(received);
-
-                    format!(
-                        "Type mismatch\n{}\n\
-                        Expected{}{}\n\
-                        but instead found: {}",
-                        cursor_line,
-                        if multiple_expected { " one of: " } else { ": " }, expected,
-                        received
-                    )
+
+                    description
+                        .note(format!("Expected{}{}", if multiple_expected { " one of: " } else { ": " }, expected))
+                        .note(format!("but instead found: {}", project.type_repr(received)))
                 }
             }
             TypeError::BranchTypeMismatch { orig_span, expected, received, .. } => {
-                format!(
-                    "Type mismatch between branches\n{}\n\
-                    Found type {}, but expected type {} because of prior branch\n{}",
-                    cursor_line,
-                    project.type_repr(received), project.type_repr(expected),
-                    Self::get_underlined_line(loader, orig_span)
+                ErrorDescription::new("Type mismatch between branches").note_at(
+                    format!("Found type {}, but expected type {} because of prior branch", project.type_repr(received), project.type_repr(expected)),
+                    orig_span,
                 )
             }
             TypeError::IllegalOperator { op, left, right, .. } => {
-                format!(
-                    "Illegal operator\n{}\n\
-                    No operator '{}' exists between types {} and {}",
-                    cursor_line,
-                    op.repr(), project.type_repr(left), project.type_repr(right),
-                )
+                ErrorDescription::new("Illegal operator")
+                    .note(format!("No operator '{}' exists between types {} and {}", op.repr(), project.type_repr(left), project.type_repr(right)))
             }
             TypeError::UnknownType { name, .. } => {
-                format!(
-                    "Unknown type '{}'\n{}\n\
-                    No type with that name is visible in current scope",
-                    name, cursor_line
-                )
+                ErrorDescription::new(format!("Unknown type '{}'", name))
+                    .note("No type with that name is visible in current scope")
             }
             TypeError::UnknownIdentifier { token, .. } => {
                 let ident = Token::get_ident_name(token);
+                let description = ErrorDescription::new(format!("Unknown identifier '{}'", ident));
                 if &ident == "_" {
-                    format!(
-                        "Unknown identifier '{}'\n{}\n\
-                        The _ represents an anonymous identifier; please give the variable a name if you want to reference it",
-                        ident, cursor_line
-                    )
+                    description.note("The _ represents an anonymous identifier; please give the variable a name if you want to reference it")
                 } else {
-                    format!(
-                        "Unknown identifier '{}'\n{}\n\
-                        No variable with that name is visible in current scope",
-                        ident, cursor_line
-                    )
+                    description.note("No variable with that name is visible in current scope")
                 }
             }
             TypeError::MissingBindingInitializer { is_mutable, .. } => {
@@ -1649,24 +1936,16 @@ impl TypeError {
                     "Since 'val' was used, you must provide an initial value"
                 };
 
-                format!(
-                    "Could not determine type of {} variable\n{}\n{}",
-                    if *is_mutable { "mutable" } else { "immutable" },
-                    cursor_line, msg
-                )
+                ErrorDescription::new(format!("Could not determine type of {} variable", if *is_mutable { "mutable" } else { "immutable" }))
+                    .note(msg)
             }
             TypeError::DuplicateName { name, original_span, kind, .. } => {
                 if *kind == DuplicateNameKind::StaticMethodOrVariant {
-                    let first_msg = format!("Duplicate member '{}'\n{}", &name, cursor_line);
-
                     let Some(original_span) = original_span else { unreachable!() };
                     let pos = &original_span.range.start;
-                    let cursor_line = Self::get_underlined_line(loader, original_span);
-                    format!(
-                        "{}\n\
-                        There is already a variant declared in this enum with that name at ({}:{})\n{}",
-                        first_msg,
-                        pos.line, pos.col, cursor_line
+                    ErrorDescription::new(format!("Duplicate member '{}'", &name)).note_at(
+                        format!("There is already a variant declared in this enum with that name at ({}:{})", pos.line, pos.col),
+                        original_span,
                     )
                 } else {
                     let kind = match kind {
@@ -1681,44 +1960,32 @@ impl TypeError {
                         DuplicateNameKind::StaticMethodOrVariant => unreachable!("Handled as a special case above"),
                     };
 
-                    let first_msg = format!("Duplicate {} '{}'\n{}", &kind, &name, cursor_line);
-
-                    let second_msg = if let Some(original_span) = original_span {
+                    let description = ErrorDescription::new(format!("Duplicate {} '{}'", &kind, &name));
+                    if let Some(original_span) = original_span {
                         let pos = &original_span.range.start;
-                        let cursor_line = Self::get_underlined_line(loader, original_span);
-                        format!("This {} is already declared at ({}:{})\n{}", kind, pos.line, pos.col, cursor_line)
+                        description.note_at(format!("This {} is already declared at ({}:{})", kind, pos.line, pos.col), original_span)
                     } else {
-                        format!("This {} is already declared as built-in value", kind)
-                    };
-
-                    format!("{}\n{}", first_msg, second_msg)
+                        description.note(format!("This {} is already declared as built-in value", kind))
+                    }
                 }
             }
             TypeError::ForbiddenAssignment { type_id, purpose, .. } => {
                 let type_repr = project.type_repr(type_id);
 
                 if *type_id == PRELUDE_UNIT_TYPE_ID {
-                    format!(
-                        "Forbidden type for variable\n{}\n\
-                        Instances of type {} cannot be used as {} values",
-                        cursor_line, type_repr, purpose
-                    )
+                    ErrorDescription::new("Forbidden type for variable")
+                        .note(format!("Instances of type {} cannot be used as {} values", type_repr, purpose))
                 } else if let Type::Function(param_type_ids, num_required_params, _, _) = project.get_type_by_id(type_id) {
                     let num_optional_params = param_type_ids.len() - *num_required_params;
                     debug_assert!(num_optional_params > 0, "We shouldn't reach this error case otherwise");
 
-                    format!(
-                        "Cannot use function as value in this context\n{}\n\
-                        Expression is a function which has optional parameters. It will not be possible to \
-                        obtain enough information to call this variable as a function later on.",
-                        cursor_line
+                    ErrorDescription::new("Cannot use function as value in this context").note(
+                        "Expression is a function which has optional parameters. It will not be possible to \
+                        obtain enough information to call this variable as a function later on."
                     )
                 } else {
-                    format!(
-                        "Could not determine type\n{}\n\
-                        Type {} has unbound generics. Please use an explicit type annotation to denote the type",
-                        cursor_line, type_repr
-                    )
+                    ErrorDescription::new("Could not determine type")
+                        .note(format!("Type {} has unbound generics. Please use an explicit type annotation to denote the type", type_repr))
                 }
             }
             TypeError::DestructuringMismatch { kind, type_id, .. } => {
@@ -1740,39 +2007,25 @@ impl TypeError {
                     }
                 };
 
-                format!(
-                    "Invalid destructuring pattern\n{}\n{}",
-                    cursor_line, msg
-                )
+                ErrorDescription::new("Invalid destructuring pattern").note(msg)
             }
             TypeError::DuplicateSplat { .. } => {
-                format!(
-                    "Invalid destructuring pattern for assignment\n{}\n\
-                    Cannot have more than one splat (*) instance in an array destructuring",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid destructuring pattern for assignment")
+                    .note("Cannot have more than one splat (*) instance in an array destructuring")
             }
             TypeError::DuplicateParameter { name, .. } => {
-                format!("Duplicate parameter '{}'\n{}", &name, cursor_line)
+                ErrorDescription::new(format!("Duplicate parameter '{}'", &name))
             }
             TypeError::ReturnTypeMismatch { expected, received, func_name, .. } => {
-                let expected_repr = project.type_repr(expected);
-                let received_repr = project.type_repr(received);
-
-                let first_line = if is_lambda_fn(&func_name) {
+                let message = if is_lambda_fn(&func_name) {
                     "Return type mismatch for lambda function".to_string()
                 } else {
                     format!("Return type mismatch for function '{}'", func_name)
                 };
 
-                format!(
-                    "{}\n{}\n\
-                    Expected: {}\n\
-                    but instead saw: {}",
-                    first_line, cursor_line,
-                    expected_repr,
-                    received_repr,
-                )
+                ErrorDescription::new(message)
+                    .note(format!("Expected: {}", project.type_repr(expected)))
+                    .note(format!("but instead saw: {}", project.type_repr(received)))
             }
             TypeError::IllegalInvocation { type_id, .. } => {
                 let type_repr = project.type_repr(type_id);
@@ -1787,138 +2040,87 @@ impl TypeError {
                     format!("Type '{}' is not callable", type_repr)
                 };
 
-                format!(
-                    "Cannot invoke target as function\n{}\n{}",
-                    cursor_line, hint
-                )
+                ErrorDescription::new("Cannot invoke target as function").note(hint)
             }
             TypeError::IllegalEnumVariantConstruction { enum_id, variant_idx, .. } => {
                 let enum_ = project.get_enum_by_id(enum_id);
                 let enum_name = &enum_.name;
                 let variant_name = &enum_.variants[*variant_idx].name;
-                format!(
-                    "Cannot invoke target as function\n{}\n\
-                    Variant {} of enum {} cannot be constructed",
-                    cursor_line,
-                    variant_name, enum_name,
-                )
+                ErrorDescription::new("Cannot invoke target as function")
+                    .note(format!("Variant {} of enum {} cannot be constructed", variant_name, enum_name))
             }
             TypeError::UnexpectedArgumentName { arg_name, is_instantiation, .. } => {
-                let second_line = if *is_instantiation {
+                let note = if *is_instantiation {
                     format!("This constructor doesn't have a field called '{}'", arg_name)
                 } else {
                     format!("This function doesn't have a parameter called '{}'", arg_name)
                 };
-                format!("Unexpected argument label '{}'\n{}\n{}", arg_name, cursor_line, second_line)
+                ErrorDescription::new(format!("Unexpected argument label '{}'", arg_name)).note(note)
             }
             TypeError::MixedArgumentType { .. } => {
-                format!(
-                    "Invalid function call\n{}\n\
-                    Cannot mix named and positional arguments.",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid function call").note("Cannot mix named and positional arguments.")
             }
             TypeError::DuplicateArgumentLabel { name, .. } => {
-                format!(
-                    "Duplicate parameter name '{}'\n{}\n\
-                    A value has already been passed for this parameter",
-                    name, cursor_line,
-                )
+                ErrorDescription::new(format!("Duplicate parameter name '{}'", name))
+                    .note("A value has already been passed for this parameter")
             }
             TypeError::InvalidArity { num_possible_args, num_required_args, num_provided_args, .. } => {
                 if num_provided_args < num_required_args {
-                    format!(
-                        "Not enough arguments for invocation\n{}\n\
-                         {} argument{} required, but {} {} provided",
-                        cursor_line,
+                    ErrorDescription::new("Not enough arguments for invocation").note(format!(
+                        "{} argument{} required, but {} {} provided",
                         num_required_args, if *num_required_args == 1 { "" } else { "s" },
                         num_provided_args, if *num_provided_args == 1 { "was" } else { "were" },
-                    )
+                    ))
                 } else if num_provided_args > num_possible_args {
-                    format!(
-                        "Too many arguments for invocation\n{}\n\
-                         Expected no more than {} argument{}, but {} {} passed",
-                        cursor_line,
+                    ErrorDescription::new("Too many arguments for invocation").note(format!(
+                        "Expected no more than {} argument{}, but {} {} passed",
                         num_possible_args, if *num_possible_args == 1 { "" } else { "s" },
                         num_provided_args, if *num_provided_args == 1 { "was" } else { "were" },
-                    )
+                    ))
                 } else {
                     unreachable!()
                 }
             }
             TypeError::InvalidSelfParam { .. } => {
-                format!(
-                    "Invalid usage of `self` parameter\n{}\n\
-                    `self` can only appear within methods on types",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid usage of `self` parameter").note("`self` can only appear within methods on types")
             }
             TypeError::InvalidSelfParamPosition { .. } => {
-                format!(
-                    "Invalid position for `self`\n{}\n\
-                    `self` must appear as the first parameter",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid position for `self`").note("`self` must appear as the first parameter")
             }
             TypeError::InvalidRequiredParamPosition { is_variadic, .. } => {
                 if *is_variadic {
-                    format!(
-                        "Invalid usage of variable-length parameter\n{}\n\
-                        Functions with optional parameters cannot have a variadic parameter",
-                        cursor_line,
-                    )
+                    ErrorDescription::new("Invalid usage of variable-length parameter")
+                        .note("Functions with optional parameters cannot have a variadic parameter")
                 } else {
-                    format!(
-                        "Invalid position for required parameter\n{}\n\
-                        Required parameters must all be listed before any optional parameters",
-                        cursor_line,
-                    )
+                    ErrorDescription::new("Invalid position for required parameter")
+                        .note("Required parameters must all be listed before any optional parameters")
                 }
             }
             TypeError::InvalidVarargPosition { .. } => {
-                format!(
-                    "Invalid position for vararg parameter\n{}\n\
-                    Vararg parameters must be the last in the parameter list",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid position for vararg parameter")
+                    .note("Vararg parameters must be the last in the parameter list")
             }
             TypeError::InvalidVarargType { type_id, .. } => {
-                format!(
-                    "Invalid type for vararg parameter\n{}\n\
-                    Vararg parameters must be an Array type, but got {}",
-                    cursor_line, project.type_repr(type_id)
-                )
+                ErrorDescription::new("Invalid type for vararg parameter")
+                    .note(format!("Vararg parameters must be an Array type, but got {}", project.type_repr(type_id)))
             }
             TypeError::InvalidTypeArgumentArity { num_required_args, num_provided_args, .. } => {
-                format!(
-                    "Incorrect number of type arguments\n{}\n\
-                    Expected {}, but {} {} passed",
-                    cursor_line,
+                ErrorDescription::new("Incorrect number of type arguments").note(format!(
+                    "Expected {}, but {} {} passed",
                     num_required_args,
                     num_provided_args, if *num_provided_args == 1 { "was" } else { "were" },
-                )
+                ))
             }
             TypeError::UnknownMember { field_name, type_id, .. } => {
-                format!(
-                    "Unknown member '{}'\n{}\n\
-                    Type {} does not have a member with name '{}'",
-                    field_name, cursor_line,
-                    project.type_repr(type_id), field_name
-                )
+                ErrorDescription::new(format!("Unknown member '{}'", field_name))
+                    .note(format!("Type {} does not have a member with name '{}'", project.type_repr(type_id), field_name))
             }
             TypeError::MissingRequiredArgumentLabels { .. } => {
-                format!(
-                    "Invalid instantiation call\n{}\n\
-                    Constructor functions must be called with argument labels",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid instantiation call").note("Constructor functions must be called with argument labels")
             }
             TypeError::UnknownTypeForParameter { param_name, .. } => {
-                format!(
-                    "Could not determine type for parameter '{}'\n{}\n\
-                    Consider adding a type annotation",
-                    param_name, cursor_line
-                )
+                ErrorDescription::new(format!("Could not determine type for parameter '{}'", param_name))
+                    .note("Consider adding a type annotation")
             }
             TypeError::AssignmentToImmutable { var_name, defined_span, kind, .. } => {
                 let kind_name = match kind {
@@ -1930,7 +2132,7 @@ impl TypeError {
                     ImmutableAssignmentKind::EnumVariant(_) => "enum variant",
                 };
 
-                let second_line = match kind {
+                let note = match kind {
                     ImmutableAssignmentKind::Parameter => "Function parameters are automatically declared as immutable".to_string(),
                     ImmutableAssignmentKind::Variable => "Variable is declared as immutable".to_string(),
                     ImmutableAssignmentKind::Field(type_name) => format!("Field '{}' is marked readonly in type '{}'", var_name, type_name),
@@ -1938,33 +2140,21 @@ impl TypeError {
                     ImmutableAssignmentKind::StaticMethod(type_name) => format!("Function '{}' is a static method on type '{}'", var_name, type_name),
                     ImmutableAssignmentKind::EnumVariant(enum_name) => format!("'{}' is a variant of enum '{}'", var_name, enum_name),
                 };
-                let second_line = format!(
-                    "{}{}",
-                    second_line,
-                    defined_span.as_ref().map(|span| format!("\n{}", Self::get_underlined_line(loader, span))).unwrap_or("".to_string())
-                );
-
-                format!(
-                    "Cannot assign to {} '{}'\n{}\n{}",
-                    kind_name, var_name, cursor_line,
-                    second_line
-                )
+
+                let description = ErrorDescription::new(format!("Cannot assign to {} '{}'", kind_name, var_name));
+                match defined_span {
+                    Some(defined_span) => description.note_at(note, defined_span),
+                    None => description.note(note),
+                }
             }
             TypeError::InvalidIndexableType { type_id, is_range, .. } => {
-                format!(
-                    "Unsupported indexing operation\n{}\n\
-                    Type '{}' is not indexable{}",
-                    cursor_line, project.type_repr(type_id),
-                    if *is_range { " as a range" } else { "" }
-                )
+                ErrorDescription::new("Unsupported indexing operation")
+                    .note(format!("Type '{}' is not indexable{}", project.type_repr(type_id), if *is_range { " as a range" } else { "" }))
             }
             TypeError::InvalidIndexType { required_type_id, provided_type_id, .. } => {
-                format!(
-                    "Invalid type for index argument\n{}\n\
-                    Expected: {}\n\
-                    but instead saw: {}",
-                    cursor_line, project.type_repr(required_type_id), project.type_repr(provided_type_id),
-                )
+                ErrorDescription::new("Invalid type for index argument")
+                    .note(format!("Expected: {}", project.type_repr(required_type_id)))
+                    .note(format!("but instead saw: {}", project.type_repr(provided_type_id)))
             }
             TypeError::InvalidTupleIndex { kind, type_id, .. } => {
                 let message = match kind {
@@ -1972,10 +2162,7 @@ impl TypeError {
                     InvalidTupleIndexKind::NonConstant => "Index values for tuples must be constant non-negative integers".to_string(),
                 };
 
-                format!(
-                    "Unsupported indexing into tuple\n{}\n{}",
-                    cursor_line, message
-                )
+                ErrorDescription::new("Unsupported indexing into tuple").note(message)
             }
             TypeError::InvalidAssignmentTarget { kind, .. } => {
                 let message = match kind {
@@ -1985,64 +2172,38 @@ impl TypeError {
                     InvalidAssignmentTargetKind::UnsupportedAssignmentTarget => "Unsupported expression for left-hand side of assignment",
                 };
 
-                format!(
-                    "Cannot perform assignment\n{}\n{}",
-                    cursor_line, message
-                )
+                ErrorDescription::new("Cannot perform assignment").note(message)
             }
             TypeError::EmptyIfElseBlock { kind, ..}=> {
-                format!(
-                    "Empty {}-block in if-expression\n{}\n\
-                    If-expressions require both a then- and an else-block",
-                    kind, cursor_line
-                )
+                ErrorDescription::new(format!("Empty {}-block in if-expression", kind))
+                    .note("If-expressions require both a then- and an else-block")
             }
             TypeError::DuplicateMatchCase { orig_span, .. } => {
-                let orig_line = Self::get_underlined_line(loader, orig_span);
-
-                format!(
-                    "Duplicate match case\n{}\n\
-                    Match case already handled here\n{}",
-                    cursor_line, orig_line
-                )
+                ErrorDescription::new("Duplicate match case").note_at("Match case already handled here", orig_span)
             }
             TypeError::EmptyMatchBlock { .. } => {
-                format!(
-                    "Empty block for match case\n{}\n\
-                    Each case in a match must result in a value",
-                    cursor_line,
-                )
+                ErrorDescription::new("Empty block for match case").note("Each case in a match must result in a value")
             }
             TypeError::UnreachableMatchCase { kind, .. } => {
-                let message = match kind {
-                    UnreachableMatchCaseKind::AlreadyCovered => "This case has already been covered by a previous case".to_string(),
+                let description = ErrorDescription::new("Unreachable match case");
+                match kind {
+                    UnreachableMatchCaseKind::AlreadyCovered => description.note("This case has already been covered by a previous case"),
                     UnreachableMatchCaseKind::NoTypeOverlap { case_type, target_type, target_span } => {
                         let target_type_repr = project.type_repr(target_type);
-                        let target_underline = Self::get_underlined_line(loader, target_span);
-
-                        if let Some(case_type_id) = case_type {
-                            format!(
-                                "No overlap between case type '{}' and match target type '{}'\n{}",
-                                project.type_repr(case_type_id), target_type_repr,
-                                target_underline
-                            )
+
+                        let note = if let Some(case_type_id) = case_type {
+                            format!("No overlap between case type '{}' and match target type '{}'", project.type_repr(case_type_id), target_type_repr)
                         } else {
-                            format!("Match target type '{}' can never be None\n{}", target_type_repr, target_underline)
-                        }
+                            format!("Match target type '{}' can never be None", target_type_repr)
+                        };
+                        description.note_at(note, target_span)
                     }
-                };
-                format!(
-                    "Unreachable match case\n{}\n{}",
-                    cursor_line, message
-                )
+                }
             }
             TypeError::NonExhaustiveMatch { type_id, .. } => {
-                format!(
-                    "Non-exhaustive match\n{}\n\
-                    Match target type '{}' is not covered by all match cases.\n\
-                    You can use a wildcard to capture remaining cases.",
-                    cursor_line, project.type_repr(type_id),
-                )
+                ErrorDescription::new("Non-exhaustive match")
+                    .note(format!("Match target type '{}' is not covered by all match cases.", project.type_repr(type_id)))
+                    .note("You can use a wildcard to capture remaining cases.")
             }
             TypeError::InvalidControlFlowTarget { type_id, kind, .. } => {
                 let type_repr = project.type_repr(type_id);
@@ -2052,11 +2213,7 @@ impl TypeError {
                     InvalidControlFlowTargetKind::IfCondition => ("if-condition value", format!("Expected Bool or Option type, got '{}'", type_repr)),
                 };
 
-                format!(
-                    "Invalid type for {}\n{}\n{}",
-                    loop_type, cursor_line,
-                    message
-                )
+                ErrorDescription::new(format!("Invalid type for {}", loop_type)).note(message)
             }
             TypeError::InvalidControlFlowTerminator { terminator, .. } => {
                 let (keyword, msg) = match terminator {
@@ -2065,48 +2222,55 @@ impl TypeError {
                     ControlFlowTerminator::Return => ("return", "A return keyword cannot appear outside of a function"),
                 };
 
-                format!(
-                    "Unexpected {} keyword\n{}\n{}",
-                    keyword, cursor_line, msg
-                )
-            }
-            TypeError::UnreachableCode { .. } => {
-                format!("Unreachable code\n{}", cursor_line)
+                ErrorDescription::new(format!("Unexpected {} keyword", keyword)).note(msg)
             }
+            TypeError::UnreachableCode { .. } => ErrorDescription::new("Unreachable code"),
             TypeError::InvalidExportScope { .. } => {
-                format!(
-                    "Invalid export modifier\n{}\n\
-                    Exported values may only appear at the top level scope",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid export modifier").note("Exported values may only appear at the top level scope")
             }
             TypeError::CircularModuleImport { .. } => {
-                format!(
-                    "Could not import module due to circular dependency\n{}\n\
-                    The current module is imported by the desired module (or one of its imports), resulting in a cycle",
-                    cursor_line
+                ErrorDescription::new("Could not import module due to circular dependency").note(
+                    "The current module imports variables from the desired module, whose top-level code (or one of its imports') depends on the current module, resulting in a cycle"
                 )
             }
             TypeError::UnknownModule { module_path, .. } => {
-                format!(
-                    "Could not import module\n{}\nNo such module exists at '{}'",
-                    cursor_line, module_path
-                )
+                ErrorDescription::new("Could not import module").note(format!("No such module exists at '{}'", module_path))
             }
             TypeError::UnknownExport { module_id, import_name, is_aliased, .. } => {
-                let first_line = if *is_aliased { "Unknown member" } else { "Invalid import" };
-
-                format!(
-                    "{}\n{}\nThere's no exported value named '{}' in module '{}'",
-                    first_line, cursor_line, import_name, project.modules[module_id.0].name,
-                )
+                let message = if *is_aliased { "Unknown member" } else { "Invalid import" };
+                ErrorDescription::new(message)
+                    .note(format!("There's no exported value named '{}' in module '{}'", import_name, project.modules[module_id.0].name))
             }
         };
 
+        match self.suggestion(project) {
+            Some(suggestion) => description.help(format!("did you mean '{}'?", suggestion)),
+            None => description,
+        }
+    }
+
+    pub fn message<L: LoadModule>(&self, loader: &L, project: &Project) -> String {
+        let span = self.span();
+        let description = self.describe(project);
+
         let file_name = loader.get_path(&span.module_id)
             .expect("Internal error: cannot report on errors in a file that never existed in the first place");
-        let error_line = format!("Error at {}:{}:{}", file_name, span.range.start.line, span.range.start.col);
-        format!("{}\n{}", error_line, msg)
+        let mut lines = vec![
+            format!("Error at {}:{}:{}", file_name, span.range.start.line, span.range.start.col),
+            description.message,
+            Self::get_underlined_line(loader, span),
+        ];
+        for (note, span) in description.notes {
+            lines.push(note);
+            if let Some(span) = span {
+                lines.push(Self::get_underlined_line(loader, span));
+            }
+        }
+        if let Some(help) = description.help {
+            lines.push(format!("help: {}", help));
+        }
+
+        lines.join("\n")
     }
 }
 
@@ -2118,6 +2282,90 @@ enum FunctionPass {
     Pass2,
 }
 
+// A module which has been loaded and parsed, but whose imports and declarations have not yet been typechecked
+struct PendingModule {
+    module_id: ModuleId,
+    imports: Vec<(ModuleId, ImportNode)>,
+    nodes: Vec<AstNode>,
+}
+
+// The ids allocated in pass 0 for a block's type, enum, and function declarations, in the order in which they appear
+struct BlockDeclarations {
+    struct_ids: VecDeque<StructId>,
+    enum_ids: VecDeque<EnumId>,
+    func_ids: VecDeque<(FuncId, VarId)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ImportStage {
+    All,
+    // Within an import cycle, types can be imported as soon as they're declared, which must happen before any functions are declared
+    // (since a function's signature may refer to an imported type)...
+    Types,
+    // ...and functions can be imported once they've been declared...
+    Functions,
+    // ...but variables can only be imported once the exporting module's top-level code has been typechecked
+    Variables,
+}
+
+// Tarjan's algorithm; components are returned in reverse topological order, so a component only ever depends on components which
+// come before it. Within each component, nodes are sorted in ascending order.
+fn strongly_connected_components(edges: &Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        edges: &'a Vec<Vec<usize>>,
+        next_index: usize,
+        indices: Vec<Option<usize>>,
+        lowlinks: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State, v: usize) {
+        state.indices[v] = Some(state.next_index);
+        state.lowlinks[v] = state.next_index;
+        state.next_index += 1;
+        state.stack.push(v);
+        state.on_stack[v] = true;
+
+        let edges = state.edges;
+        for &w in &edges[v] {
+            match state.indices[w] {
+                None => {
+                    visit(state, w);
+                    state.lowlinks[v] = state.lowlinks[v].min(state.lowlinks[w]);
+                }
+                Some(w_index) if state.on_stack[w] => {
+                    state.lowlinks[v] = state.lowlinks[v].min(w_index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if state.indices[v] == Some(state.lowlinks[v]) {
+            let mut component = vec![];
+            loop {
+                let w = state.stack.pop().expect("v is on the stack, so it cannot be empty");
+                state.on_stack[w] = false;
+                component.push(w);
+                if w == v { break; }
+            }
+            component.sort();
+            state.components.push(component);
+        }
+    }
+
+    let num_nodes = edges.len();
+    let mut state = State { edges, next_index: 0, indices: vec![None; num_nodes], lowlinks: vec![0; num_nodes], stack: vec![], on_stack: vec![false; num_nodes], components: vec![] };
+    for v in 0..num_nodes {
+        if state.indices[v].is_none() {
+            visit(&mut state, v);
+        }
+    }
+
+    state.components
+}
+
 const LAMBDA_FN_NAME_PREFIX: &str = "lambda_";
 
 fn is_lambda_fn(name: &String) -> bool {
@@ -2131,15 +2379,22 @@ pub struct Typechecker2<'a, L: LoadModule> {
     current_type_decl: Option<TypeId>,
     current_function: Option<FuncId>,
     function_pass: FunctionPass,
+    // Modules which must be loaded again when they're next imported (see `retypecheck_module`)
+    stale_modules: HashSet<ModuleId>,
 }
 
 impl<'a, L: LoadModule> Typechecker2<'a, L> {
     pub fn new(module_loader: &'a mut L, project: &'a mut Project) -> Typechecker2<'a, L> {
-        Typechecker2 { module_loader, project, current_scope_id: PRELUDE_SCOPE_ID, current_type_decl: None, current_function: None, function_pass: FunctionPass::NotStarted }
+        Typechecker2 { module_loader, project, current_scope_id: PRELUDE_SCOPE_ID, current_type_decl: None, current_function: None, function_pass: FunctionPass::NotStarted, stale_modules: HashSet::new() }
     }
 
     /* UTILITIES */
 
+    // After a failure, this is the scope in which typechecking stopped
+    pub fn current_scope_id(&self) -> ScopeId {
+        self.current_scope_id
+    }
+
     fn current_module_mut(&mut self) -> &mut TypedModule {
         &mut self.project.modules[self.current_scope_id.0.0]
     }
@@ -2148,10 +2403,36 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         &self.project.modules[self.current_scope_id.0.0]
     }
 
+    fn add_reference(&mut self, range: &Range, symbol: Symbol) {
+        self.current_module_mut().references.insert(range.start.clone(), Reference { range: range.clone(), symbol });
+    }
+
     fn make_span(&self, range: &Range) -> Span {
         Span::from_range(self.current_module().id, range.clone())
     }
 
+    fn suggest_type_name(&self, name: &String) -> Option<String> {
+        find_similar_name(name, self.project.visible_type_names(&self.current_scope_id).into_iter().map(|name| name.as_str()))
+    }
+
+    // Suggests a replacement for the import path in `module_token`, based on the files alongside the module which couldn't be found
+    fn suggest_module_path(&self, module_token: &Token, module_path: &String) -> Option<String> {
+        let Token::String(_, import_path) = module_token else { return None; };
+        let module_path = Path::new(module_path);
+        let module_name = module_path.file_stem()?.to_str()?;
+
+        let sibling_files = self.module_loader.list_dir(module_path.parent()?);
+        let sibling_module_names = sibling_files.iter()
+            .filter_map(|path| Path::new(path).file_name()?.to_str()?.strip_suffix(".abra"))
+            .filter(|name| !name.starts_with('_'));
+        let suggestion = find_similar_name(module_name, sibling_module_names)?;
+
+        match import_path.rsplit_once('/') {
+            Some((dir, _)) => Some(format!("{}/{}", dir, suggestion)),
+            None => Some(suggestion),
+        }
+    }
+
     fn current_scope_mut(&mut self) -> &mut Scope {
         let ScopeId(ModuleId(module_idx), scope_idx) = self.current_scope_id;
         &mut self.project.modules[module_idx].scopes[scope_idx]
@@ -2525,14 +2806,17 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                             assert_expected_type_args(struct_.generic_ids.len())?;
 
                             let struct_id = struct_.id;
+                            self.add_reference(&ident.get_range(), Symbol::Type(TypeKind::Struct(struct_id)));
                             Ok(self.add_or_find_type_id(Type::GenericInstance(struct_id, generic_ids)))
                         } else if let Some(enum_) = self.get_enum_by_name(&ident_name) {
                             assert_expected_type_args(enum_.generic_ids.len())?;
 
                             let enum_id = enum_.id;
+                            self.add_reference(&ident.get_range(), Symbol::Type(TypeKind::Enum(enum_id)));
                             Ok(self.add_or_find_type_id(Type::GenericEnumInstance(enum_id, generic_ids, None)))
                         } else {
-                            Err(TypeError::UnknownType { span: self.make_span(&ident.get_range()), name: ident_name })
+                            let suggestion = self.suggest_type_name(&ident_name);
+                            Err(TypeError::UnknownType { span: self.make_span(&ident.get_range()), name: ident_name, suggestion })
                         }
                     }
                 }
@@ -2626,7 +2910,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             FunctionKind::Freestanding
         };
         let func_id = FuncId(current_scope.id, current_scope.funcs.len());
-        let func = Function { id: func_id, fn_scope_id, fn_type_id: PRELUDE_ANY_TYPE_ID, decorators: vec![], name: name.clone(), generic_ids, kind, params, return_type_id, defined_span: Some(span.clone()), body: vec![], captured_vars: vec![], captured_closures: vec![] };
+        let func = Function { id: func_id, fn_scope_id, fn_type_id: PRELUDE_ANY_TYPE_ID, decorators: vec![], name: name.clone(), generic_ids, kind, params, return_type_id, defined_span: Some(span.clone()), doc_comment: None, body: vec![], captured_vars: vec![], captured_closures: vec![] };
 
         self.current_scope_mut().funcs.push(func);
 
@@ -2657,7 +2941,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         let kind = FunctionKind::Freestanding;
         let func_id = FuncId(fn_decl_scope.id, fn_decl_scope.funcs.len());
         let return_type_id = return_type_hint.unwrap_or(PRELUDE_ANY_TYPE_ID);
-        let func = Function { id: func_id, fn_scope_id, fn_type_id: PRELUDE_ANY_TYPE_ID, decorators: vec![], name, generic_ids: vec![], kind, params, return_type_id, defined_span: None, body: vec![], captured_vars: vec![], captured_closures: vec![] };
+        let func = Function { id: func_id, fn_scope_id, fn_type_id: PRELUDE_ANY_TYPE_ID, decorators: vec![], name, generic_ids: vec![], kind, params, return_type_id, defined_span: None, doc_comment: None, body: vec![], captured_vars: vec![], captured_closures: vec![] };
 
         fn_decl_scope.funcs.push(func);
 
@@ -2682,7 +2966,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         return Ok(());
     }
 
-    fn add_struct_to_current_module(&mut self, struct_scope_id: ScopeId, name_token: &Token, generic_ids: Vec<TypeId>) -> Result<StructId, TypeError> {
+    fn add_struct_to_current_module(&mut self, struct_scope_id: ScopeId, name_token: &Token, generic_ids: Vec<TypeId>, doc_comment: Option<String>) -> Result<StructId, TypeError> {
         let current_module = self.current_module();
 
         let name = Token::get_ident_name(name_token);
@@ -2697,6 +2981,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             struct_scope_id,
             name: name.clone(),
             defined_span: Some(span.clone()),
+            doc_comment,
             generic_ids,
             self_type_id,
             fields: vec![],
@@ -2713,7 +2998,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         Ok(struct_id)
     }
 
-    fn add_enum_to_current_module(&mut self, enum_scope_id: ScopeId, name_token: &Token, generic_ids: Vec<TypeId>) -> Result<EnumId, TypeError> {
+    fn add_enum_to_current_module(&mut self, enum_scope_id: ScopeId, name_token: &Token, generic_ids: Vec<TypeId>, doc_comment: Option<String>) -> Result<EnumId, TypeError> {
         let current_module = self.current_module();
 
         let name = Token::get_ident_name(name_token);
@@ -2728,6 +3013,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             enum_scope_id,
             name: name.clone(),
             defined_span: span.clone(),
+            doc_comment,
             generic_ids,
             self_type_id,
             variants: vec![],
@@ -2826,7 +3112,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         debug_assert!(self.project.modules.is_empty());
 
         self.module_loader.register(&parser::ast::ModuleId::prelude(), &PRELUDE_MODULE_ID, None);
-        let mut prelude_module = TypedModule { id: PRELUDE_MODULE_ID, name: "prelude".to_string(), imports: HashMap::new(), type_ids: vec![], functions: vec![], structs: vec![], enums: vec![], code: vec![], scopes: vec![], exports: HashMap::new(), completed: false };
+        let mut prelude_module = TypedModule { id: PRELUDE_MODULE_ID, name: "prelude".to_string(), imports: HashMap::new(), type_ids: vec![], functions: vec![], structs: vec![], enums: vec![], code: vec![], scopes: vec![], exports: HashMap::new(), references: BTreeMap::new(), completed: false };
         let mut prelude_scope = Scope { label: "prelude.root".to_string(), kind: ScopeKind::Module(PRELUDE_MODULE_ID), terminator: None, id: PRELUDE_SCOPE_ID, parent: None, types: vec![], vars: vec![], funcs: vec![] };
 
         let primitives = [
@@ -2852,7 +3138,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             let tuple_struct_id = StructId(PRELUDE_MODULE_ID, prelude_module.structs.len());
             let self_type_id = self.project.add_type_id(&PRELUDE_SCOPE_ID, Type::GenericInstance(tuple_struct_id, vec![]));
             let prelude_module = &mut self.project.modules[PRELUDE_MODULE_ID.0];
-            prelude_module.structs.push(Struct { id: tuple_struct_id, self_type_id, struct_scope_id: PRELUDE_SCOPE_ID, name: "Tuple".to_string(), defined_span: None, generic_ids: vec![], fields: vec![], methods: vec![], static_methods: vec![] });
+            prelude_module.structs.push(Struct { id: tuple_struct_id, self_type_id, struct_scope_id: PRELUDE_SCOPE_ID, name: "Tuple".to_string(), defined_span: None, doc_comment: None, generic_ids: vec![], fields: vec![], methods: vec![], static_methods: vec![] });
             self.project.prelude_tuple_struct_id = tuple_struct_id;
         }
 
@@ -2867,7 +3153,8 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             if !self.module_loader.module_exists(&import_m_id, Some(&PRELUDE_MODULE_ID)) {
                 let span = self.make_span(&import_node.module_token.get_range());
                 let module_path = self.module_loader.calculate_path_wrt_other(&import_m_id, Some(&self.current_module().id));
-                return Err(Either::Right(TypeError::UnknownModule { span, module_path }));
+                let suggestion = self.suggest_module_path(&import_node.module_token, &module_path);
+                return Err(Either::Right(TypeError::UnknownModule { span, module_path, suggestion }));
             }
             let completed_module_id = if let Some(m) = self.module_loader.get_module_id(&import_m_id).and_then(|module_id| self.project.modules.get(module_id.0)) {
                 if !m.completed {
@@ -2887,10 +3174,13 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                 tc.typecheck_module(&import_m_id, Some(&PRELUDE_MODULE_ID))?
             };
             self.current_module_mut().imports.entry(imported_module_id).or_default();
-            self.typecheck_import(&imported_module_id, import_node).map_err(Either::Right)?;
+            self.typecheck_import(&imported_module_id, import_node, ImportStage::All).map_err(Either::Right)?;
         }
         self.typecheck_block(parse_result.nodes).map_err(Either::Right)?;
 
+        self.current_module_mut().completed = true;
+        self.project.module_order.push(PRELUDE_MODULE_ID);
+
         debug_assert_ne!(self.project.prelude_int_struct_id, PLACEHOLDER_STRUCT_ID);
         debug_assert_ne!(self.project.prelude_float_struct_id, PLACEHOLDER_STRUCT_ID);
         debug_assert_ne!(self.project.prelude_bool_struct_id, PLACEHOLDER_STRUCT_ID);
@@ -2906,87 +3196,272 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
     pub fn typecheck_module(&mut self, m_id: &parser::ast::ModuleId, with_respect_to_module: Option<&ModuleId>) -> Result<ModuleId, TypecheckError> {
         debug_assert!(self.project.modules.len() >= 1 && self.project.modules[0].name == "prelude", "Prelude must be loaded in order to typecheck further modules");
 
+        let mut pending_modules = vec![];
+        let module_id = self.load_module_graph(m_id, with_respect_to_module, &mut pending_modules)?;
+        self.typecheck_pending_modules(pending_modules)?;
+
+        Ok(module_id)
+    }
+
+    // Typechecks a module again in place (eg. after its source, or that of one of its dependencies, has changed), along with any of the
+    // invalidated modules which it imports. Invalidated modules keep their ids, and must include every module which depends on any of
+    // them (see `Project::invalidated_modules`), so that the rest of the project can be reused as it is.
+    pub fn retypecheck_module(&mut self, module_id: &ModuleId, invalidated_modules: &HashSet<ModuleId>) -> Result<(), TypecheckError> {
+        debug_assert!(!invalidated_modules.contains(&PRELUDE_MODULE_ID), "Prelude cannot be typechecked again");
+
+        // Invalidated modules which are no longer imported are left empty (and incomplete)
+        self.project.module_order.retain(|module_id| !invalidated_modules.contains(module_id));
+        for module_id in invalidated_modules {
+            let file_name = self.project.modules[module_id.0].name.clone();
+            self.project.modules[module_id.0] = TypedModule::new(*module_id, file_name);
+        }
+        self.stale_modules = invalidated_modules.clone();
+
+        let mut pending_modules = vec![];
+        if self.stale_modules.contains(module_id) {
+            self.reload_module_graph(module_id, &mut pending_modules)?;
+        }
+        self.typecheck_pending_modules(pending_modules)
+    }
+
+    fn typecheck_pending_modules(&mut self, pending_modules: Vec<PendingModule>) -> Result<(), TypecheckError> {
+        // Modules which import each other (directly or transitively) form a strongly-connected component of the import graph, and must be
+        // typechecked together. Components are visited such that a component's dependencies have always been fully typechecked beforehand.
+        let pending_idxs = pending_modules.iter().enumerate().map(|(idx, m)| (m.module_id, idx)).collect::<HashMap<_, _>>();
+        let edges: Vec<Vec<usize>> = pending_modules.iter()
+            .map(|m| m.imports.iter().filter_map(|(imported_module_id, _)| pending_idxs.get(imported_module_id).copied()).collect())
+            .collect();
+        let components = strongly_connected_components(&edges);
+
+        let mut pending_modules = pending_modules.into_iter().map(Some).collect_vec();
+        for component in components {
+            let modules: Vec<PendingModule> = component.into_iter()
+                .map(|idx| pending_modules[idx].take().expect("Each module belongs to exactly one component"))
+                .collect();
+            self.typecheck_module_component(modules).map_err(Either::Right)?;
+        }
+
+        Ok(())
+    }
+
+    // The module is added to the project before it's parsed, so that its id remains valid (and can be typechecked again) even if it fails
+    fn load_module_graph(&mut self, m_id: &parser::ast::ModuleId, with_respect_to_module: Option<&ModuleId>, pending_modules: &mut Vec<PendingModule>) -> Result<ModuleId, TypecheckError> {
         let module_id = ModuleId(self.project.modules.len());
         self.module_loader.register(m_id, &module_id, with_respect_to_module);
+        let file_name = self.module_loader.calculate_path_wrt_other(m_id, with_respect_to_module);
+        self.project.modules.push(TypedModule::new(module_id, file_name.clone()));
 
-        let (file_name, parse_result) = self.module_loader.load_untyped_ast(&m_id, with_respect_to_module)
+        let (file_name, parse_result) = self.module_loader.parse_file(m_id, file_name)
             .map_err(|e| Either::Left((e, m_id.clone())))?
             .expect("Internal error");
         if file_name.ends_with("/_intrinsics.abra") {
             self.project.intrinsics_module_id = module_id;
         }
 
+        let pending_idx = pending_modules.len();
+        pending_modules.push(PendingModule { module_id, imports: vec![], nodes: parse_result.nodes });
+        self.load_module_imports(pending_idx, parse_result.imports, pending_modules)?;
+
+        Ok(module_id)
+    }
+
+    fn reload_module_graph(&mut self, module_id: &ModuleId, pending_modules: &mut Vec<PendingModule>) -> Result<(), TypecheckError> {
+        self.stale_modules.remove(module_id);
+
+        let m_id = self.module_loader.get_m_id(module_id).cloned().expect("Internal error: a loaded module must have been registered");
+        let file_name = self.project.modules[module_id.0].name.clone();
+        let (_, parse_result) = self.module_loader.parse_file(&m_id, file_name)
+            .map_err(|e| Either::Left((e, m_id.clone())))?
+            .expect("Internal error");
+
+        let pending_idx = pending_modules.len();
+        pending_modules.push(PendingModule { module_id: *module_id, imports: vec![], nodes: parse_result.nodes });
+        self.load_module_imports(pending_idx, parse_result.imports, pending_modules)
+    }
+
+    fn load_module_imports(&mut self, pending_idx: usize, import_nodes: Vec<(Token, ImportNode)>, pending_modules: &mut Vec<PendingModule>) -> Result<(), TypecheckError> {
+        let module_id = pending_modules[pending_idx].module_id;
         let scope_id = ScopeId(module_id, 0);
-        let label = format!("{:?}.root", &module_id);
-        let root_scope = Scope { label, kind: ScopeKind::Module(module_id), terminator: None, id: scope_id, parent: Some(PRELUDE_SCOPE_ID), types: vec![], vars: vec![], funcs: vec![] };
-        self.project.modules.push(TypedModule {
-            id: module_id,
-            name: file_name,
-            imports: HashMap::new(),
-            type_ids: vec![],
-            functions: vec![],
-            structs: vec![],
-            enums: vec![],
-            code: vec![],
-            scopes: vec![root_scope],
-            exports: HashMap::new(),
-            completed: false,
-        });
 
-        self.current_scope_id = scope_id;
+        let mut imports = Vec::with_capacity(import_nodes.len());
+        for (_, import_node) in import_nodes {
+            self.current_scope_id = scope_id;
 
-        for (_, import_node) in parse_result.imports {
             let import_m_id = &import_node.module_id;
             if !self.module_loader.module_exists(&import_m_id, Some(&module_id)) {
                 let span = self.make_span(&import_node.module_token.get_range());
                 let module_path = self.module_loader.calculate_path_wrt_other(&import_m_id, Some(&self.current_module().id));
-                return Err(Either::Right(TypeError::UnknownModule { span, module_path }));
+                let suggestion = self.suggest_module_path(&import_node.module_token, &module_path);
+                return Err(Either::Right(TypeError::UnknownModule { span, module_path, suggestion }));
             }
 
-            let completed_module_id = if let Some(m) = self.module_loader.get_module_id(&import_m_id).and_then(|module_id| self.project.modules.get(module_id.0)) {
-                if !m.completed {
+            let imported_module_id = if let Some(imported_module_id) = self.module_loader.get_module_id(&import_m_id).copied() {
+                // A module which has been loaded but not completed is only valid here if it will be completed alongside this one (or if
+                // it's being typechecked again)
+                let m = &self.project.modules[imported_module_id.0];
+                if self.stale_modules.contains(&imported_module_id) {
+                    self.reload_module_graph(&imported_module_id, pending_modules)?;
+                } else if !m.completed && !pending_modules.iter().any(|p| p.module_id == imported_module_id) {
                     let span = self.make_span(&import_node.module_token.get_range());
                     return Err(Either::Right(TypeError::CircularModuleImport { span }));
                 }
 
-                Some(m.id)
+                imported_module_id
             } else {
-                None
+                self.load_module_graph(&import_m_id, Some(&module_id), pending_modules)?
             };
+            imports.push((imported_module_id, import_node));
+        }
+        pending_modules[pending_idx].imports = imports;
 
-            let imported_module_id = if let Some(module_id) = completed_module_id {
-                module_id
-            } else {
-                let mut tc = Typechecker2::new(self.module_loader, self.project);
-                tc.typecheck_module(&import_m_id, Some(&module_id))?
-            };
-            self.current_module_mut().imports.entry(imported_module_id).or_default();
-            self.typecheck_import(&imported_module_id, import_node).map_err(Either::Right)?;
+        Ok(())
+    }
+
+    fn typecheck_module_component(&mut self, modules: Vec<PendingModule>) -> Result<(), TypeError> {
+        let component_module_ids = modules.iter().map(|m| m.module_id).collect::<HashSet<_>>();
+
+        // Imports from modules outside of this component can be handled exactly as if there were no cycle, since those modules are
+        // already complete. Imports from modules within the component must wait until those modules' declarations have been collected.
+        let mut pending = Vec::with_capacity(modules.len());
+        for PendingModule { module_id, imports, nodes } in modules {
+            self.current_scope_id = ScopeId(module_id, 0);
+
+            let mut cyclic_imports = vec![];
+            for (imported_module_id, import_node) in imports {
+                self.current_module_mut().imports.entry(imported_module_id).or_default();
+                if component_module_ids.contains(&imported_module_id) {
+                    cyclic_imports.push((imported_module_id, import_node));
+                } else {
+                    self.typecheck_import(&imported_module_id, import_node, ImportStage::All)?;
+                }
+            }
+
+            let declarations = self.typecheck_block_pass_0_types(&nodes)?;
+            pending.push((module_id, cyclic_imports, nodes, declarations));
         }
 
-        self.current_scope_id = scope_id;
-        self.typecheck_block(parse_result.nodes).map_err(Either::Right)?;
+        for (module_id, cyclic_imports, _, _) in &pending {
+            self.current_scope_id = ScopeId(*module_id, 0);
+            for (imported_module_id, import_node) in cyclic_imports {
+                self.typecheck_import(imported_module_id, import_node.clone(), ImportStage::Types)?;
+            }
+        }
 
-        self.current_module_mut().completed = true;
+        for (module_id, _, nodes, declarations) in &mut pending {
+            self.current_scope_id = ScopeId(*module_id, 0);
+            self.typecheck_block_pass_0_functions(nodes, declarations)?;
+        }
 
-        Ok(module_id)
+        for (module_id, cyclic_imports, _, _) in &pending {
+            self.current_scope_id = ScopeId(*module_id, 0);
+            for (imported_module_id, import_node) in cyclic_imports {
+                self.typecheck_import(imported_module_id, import_node.clone(), ImportStage::Functions)?;
+            }
+        }
+
+        for (module_id, _, nodes, declarations) in &pending {
+            self.current_scope_id = ScopeId(*module_id, 0);
+            self.typecheck_block_pass_1(nodes, declarations)?;
+        }
+
+        // Top-level code must run in an order such that any exported variable is initialized before it's imported into another module,
+        // so a cycle is only an error if it'd require a module's variables to be available before its top-level code has been run.
+        let order = self.order_module_component_bodies(&pending)?;
+        let mut pending = pending.into_iter().map(Some).collect_vec();
+        for idx in order {
+            let (module_id, cyclic_imports, nodes, declarations) = pending[idx].take().expect("Each module's body is typechecked once");
+            self.current_scope_id = ScopeId(module_id, 0);
+            for (imported_module_id, import_node) in cyclic_imports {
+                self.typecheck_import(&imported_module_id, import_node, ImportStage::Variables)?;
+            }
+
+            self.typecheck_block_bodies(nodes, declarations)?;
+
+            self.current_module_mut().completed = true;
+            self.project.module_order.push(module_id);
+        }
+
+        Ok(())
+    }
+
+    fn order_module_component_bodies(&self, pending: &Vec<(ModuleId, Vec<(ModuleId, ImportNode)>, Vec<AstNode>, BlockDeclarations)>) -> Result<Vec<usize>, TypeError> {
+        let exports_variables = |module_id: &ModuleId| {
+            let Some((_, _, nodes, _)) = pending.iter().find(|(m_id, _, _, _)| m_id == module_id) else { return false; };
+            nodes.iter().any(|node| matches!(node, AstNode::BindingDecl(_, BindingDeclNode { export_token: Some(_), .. })))
+        };
+
+        let mut dependencies = Vec::with_capacity(pending.len());
+        for (module_id, cyclic_imports, _, _) in pending {
+            let mut deps = vec![];
+            for (imported_module_id, import_node) in cyclic_imports {
+                let depends_on_variables = match &import_node.kind {
+                    // At this point, the only missing exports are variables (or names which don't exist, which will fail later on)
+                    ImportKind::ImportList(imports) => {
+                        let exports = &self.project.modules[imported_module_id.0].exports;
+                        imports.iter().any(|tok| !exports.contains_key(&Token::get_ident_name(tok)))
+                    }
+                    ImportKind::ImportAll(_) | ImportKind::Alias(_) => exports_variables(imported_module_id),
+                };
+                if depends_on_variables {
+                    let dep_idx = pending.iter().position(|(m_id, _, _, _)| m_id == imported_module_id).expect("Cyclic imports are within the component");
+                    let span = Span::from_range(*module_id, import_node.module_token.get_range());
+                    deps.push((dep_idx, span));
+                }
+            }
+            dependencies.push(deps);
+        }
+
+        fn visit(idx: usize, dependencies: &Vec<Vec<(usize, Span)>>, visited: &mut Vec<Option<bool>>, order: &mut Vec<usize>) -> Result<(), TypeError> {
+            visited[idx] = Some(false);
+            for (dep_idx, span) in &dependencies[idx] {
+                match visited[*dep_idx] {
+                    None => visit(*dep_idx, dependencies, visited, order)?,
+                    Some(false) => return Err(TypeError::CircularModuleImport { span: span.clone() }),
+                    Some(true) => {}
+                }
+            }
+            visited[idx] = Some(true);
+            order.push(idx);
+
+            Ok(())
+        }
+
+        let mut visited = vec![None; pending.len()];
+        let mut order = Vec::with_capacity(pending.len());
+        for idx in 0..pending.len() {
+            if visited[idx].is_none() {
+                visit(idx, &dependencies, &mut visited, &mut order)?;
+            }
+        }
+
+        Ok(order)
     }
 
     fn typecheck_block(&mut self, nodes: Vec<AstNode>) -> Result<(), TypeError> {
-        let mut func_decls = Vec::new();
+        let declarations = self.typecheck_block_pass_0(&nodes)?;
+        self.typecheck_block_pass_1(&nodes, &declarations)?;
+        self.typecheck_block_bodies(nodes, declarations)
+    }
+
+    fn typecheck_block_pass_0(&mut self, nodes: &Vec<AstNode>) -> Result<BlockDeclarations, TypeError> {
+        let mut declarations = self.typecheck_block_pass_0_types(nodes)?;
+        self.typecheck_block_pass_0_functions(nodes, &mut declarations)?;
+        Ok(declarations)
+    }
+
+    fn typecheck_block_pass_0_types(&mut self, nodes: &Vec<AstNode>) -> Result<BlockDeclarations, TypeError> {
         let mut type_decls = Vec::new();
         let mut enum_decls = Vec::new();
 
-        for node in &nodes {
+        for node in nodes {
             match node {
-                AstNode::FunctionDecl(_, node) => func_decls.push(node),
                 AstNode::TypeDecl(_, node) => type_decls.push(node),
                 AstNode::EnumDecl(_, node) => enum_decls.push(node),
                 _ => {}
             }
         }
 
-        // --- BEGIN PASS 0 for types, enums, and functions
+        // --- BEGIN PASS 0 for types and enums
 
         let num_type_decls = type_decls.len();
         let mut struct_ids = Vec::with_capacity(num_type_decls);
@@ -3026,35 +3501,60 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             enum_ids.push(enum_id);
         }
 
-        let mut func_ids = Vec::with_capacity(func_decls.len());
-        for node in &func_decls {
+        // --- END PASS 0 for types and enums
+
+        Ok(BlockDeclarations { struct_ids: VecDeque::from(struct_ids), enum_ids: VecDeque::from(enum_ids), func_ids: VecDeque::new() })
+    }
+
+    fn typecheck_block_pass_0_functions(&mut self, nodes: &Vec<AstNode>, declarations: &mut BlockDeclarations) -> Result<(), TypeError> {
+        // --- BEGIN PASS 0 for functions
+
+        for node in nodes {
+            let AstNode::FunctionDecl(_, node) = node else { continue; };
             let func_id = self.typecheck_function_pass_0(node)?;
             let func_var_id = self.add_function_variable_alias_to_current_scope(&node.name, &func_id)?;
-            func_ids.push((func_id, func_var_id));
+            declarations.func_ids.push_back((func_id, func_var_id));
+        }
+
+        // --- END PASS 0 for functions
+
+        Ok(())
+    }
+
+    fn typecheck_block_pass_1(&mut self, nodes: &Vec<AstNode>, declarations: &BlockDeclarations) -> Result<(), TypeError> {
+        let BlockDeclarations { struct_ids, enum_ids, func_ids } = declarations;
+
+        let mut func_decls = Vec::new();
+        let mut type_decls = Vec::new();
+        let mut enum_decls = Vec::new();
+
+        for node in nodes {
+            match node {
+                AstNode::FunctionDecl(_, node) => func_decls.push(node),
+                AstNode::TypeDecl(_, node) => type_decls.push(node),
+                AstNode::EnumDecl(_, node) => enum_decls.push(node),
+                _ => {}
+            }
         }
 
-        // --- END PASS 0 for types, enums, and functions
         // --- BEGIN PASS 1 for types, enums, and functions
 
-        let mut struct_ids = VecDeque::from(struct_ids);
-        debug_assert!(num_type_decls == struct_ids.len());
-        for (node, struct_id) in type_decls.iter().zip(&struct_ids) {
+        debug_assert!(type_decls.len() == struct_ids.len());
+        for (node, struct_id) in type_decls.iter().zip(struct_ids) {
             self.typecheck_struct_pass_1(node, &struct_id)?;
         }
 
-        let mut enum_ids = VecDeque::from(enum_ids);
-        debug_assert!(num_enum_decls == enum_ids.len());
-        for (node, enum_id) in enum_decls.iter().zip(&enum_ids) {
+        debug_assert!(enum_decls.len() == enum_ids.len());
+        for (node, enum_id) in enum_decls.iter().zip(enum_ids) {
             self.typecheck_enum_pass_1(node, &enum_id)?;
         }
 
-        for (node, struct_id) in type_decls.iter().zip(&struct_ids) {
+        for (node, struct_id) in type_decls.iter().zip(struct_ids) {
             self.typecheck_struct_pass_2(node, &struct_id)?;
         }
 
-        let mut func_ids = VecDeque::from(func_ids);
         debug_assert!(func_decls.len() == func_ids.len());
-        for (node, (func_id, func_var_id)) in func_decls.iter().zip(&func_ids) {
+        for (node, (func_id, func_var_id)) in func_decls.iter().zip(func_ids) {
             self.typecheck_function_pass_1(func_id, node, false)?;
 
             let func = self.project.get_func_by_id(func_id);
@@ -3062,6 +3562,13 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         }
 
         // --- END PASS 1 for types, enums, and functions
+
+        Ok(())
+    }
+
+    fn typecheck_block_bodies(&mut self, nodes: Vec<AstNode>, declarations: BlockDeclarations) -> Result<(), TypeError> {
+        let BlockDeclarations { mut struct_ids, mut enum_ids, mut func_ids } = declarations;
+
         self.function_pass = FunctionPass::Pass2;
 
         for node in nodes {
@@ -3351,6 +3858,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
 
         let func_name = Token::get_ident_name(name);
         let func_id = self.add_function_to_current_scope(fn_scope_id, name, generic_ids, has_self, vec![], return_type_id)?;
+        self.project.get_func_by_id_mut(&func_id).doc_comment = node.doc_comment.clone();
         self.current_module_mut().functions.push(func_id);
         let ScopeKind::Function(id) = &mut self.project.get_scope_by_id_mut(&fn_scope_id).kind else { unreachable!() };
         *id = func_id;
@@ -3455,7 +3963,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
 
         // A struct's generics are scoped to the struct declaration, but the instance type should be scoped to the outer scope.
         let generic_ids = self.add_generics_to_scope(&struct_scope_id, type_args, false)?;
-        let struct_id = self.add_struct_to_current_module(struct_scope_id, name, generic_ids)?;
+        let struct_id = self.add_struct_to_current_module(struct_scope_id, name, generic_ids, node.doc_comment.clone())?;
 
         if is_exported {
             self.current_module_mut().exports.insert(struct_name, ExportedValue::Type(TypeKind::Struct(struct_id)));
@@ -3471,7 +3979,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         self.current_scope_id = struct_.struct_scope_id;
 
         let mut seen_fields: HashMap<String, Token> = HashMap::new();
-        for TypeDeclField { ident, type_ident, readonly, .. } in &node.fields {
+        for TypeDeclField { ident, type_ident, readonly, doc_comment, .. } in &node.fields {
             let is_readonly = readonly.is_some();
 
             let field_name = Token::get_ident_name(&ident);
@@ -3489,6 +3997,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                 defined_span: self.make_span(&ident.get_range()),
                 is_readonly,
                 default_value: None,
+                doc_comment: doc_comment.clone(),
             };
             self.project.get_struct_by_id_mut(&struct_id).fields.push(field);
         }
@@ -3683,7 +4192,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
 
         // An enum's generics are scoped to the enum declaration, but the instance type should be scoped to the outer scope.
         let generic_ids = self.add_generics_to_scope(&enum_scope_id, type_args, false)?;
-        let enum_id = self.add_enum_to_current_module(enum_scope_id, name, generic_ids)?;
+        let enum_id = self.add_enum_to_current_module(enum_scope_id, name, generic_ids, node.doc_comment.clone())?;
         debug_assert!(self.current_type_decl.is_none(), "At the moment, types cannot be nested within other types");
 
         if is_exported {
@@ -4168,11 +4677,18 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
         }
     }
 
-    fn typecheck_import(&mut self, import_module_id: &ModuleId, import_node: ImportNode) -> Result<TypedImportKind, TypeError> {
+    fn typecheck_import(&mut self, import_module_id: &ModuleId, import_node: ImportNode, stage: ImportStage) -> Result<TypedImportKind, TypeError> {
+        let should_import = |export: &ExportedValue| match stage {
+            ImportStage::All => true,
+            ImportStage::Types => matches!(export, ExportedValue::Type(_)),
+            ImportStage::Functions => matches!(export, ExportedValue::Function(_)),
+            ImportStage::Variables => matches!(export, ExportedValue::Variable(_)),
+        };
+
         let kind = match import_node.kind {
             ImportKind::ImportAll(star_token) => {
                 let import_module = &self.project.modules[import_module_id.0];
-                let exports = import_module.exports.values().map(|e| e.clone()).collect_vec();
+                let exports = import_module.exports.values().filter(|e| should_import(e)).map(|e| e.clone()).collect_vec();
 
                 for export in exports {
                     self.add_imported_value(*import_module_id, export, &star_token)?;
@@ -4185,21 +4701,29 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                 for import_tok in imports {
                     let import_name = Token::get_ident_name(&import_tok);
                     let import_module = &self.project.modules[import_module_id.0];
-                    let Some(export) = import_module.exports.get(&import_name) else {
-                        let span = self.make_span(&import_tok.get_range());
-                        return Err(TypeError::UnknownExport { span, module_id: *import_module_id, import_name, is_aliased: false });
+                    let export = match import_module.exports.get(&import_name) {
+                        Some(export) if should_import(export) => *export,
+                        // An export which hasn't been declared yet may still be a variable, which will be imported at a later stage
+                        None if stage != ImportStage::All && stage != ImportStage::Variables => continue,
+                        Some(_) => continue,
+                        None => {
+                            let span = self.make_span(&import_tok.get_range());
+                            return Err(TypeError::UnknownExport { span, module_id: *import_module_id, import_name, is_aliased: false });
+                        }
                     };
 
-                    let imported_value = self.add_imported_value(*import_module_id, *export, &import_tok)?;
+                    let imported_value = self.add_imported_value(*import_module_id, export, &import_tok)?;
                     imported_values.push(imported_value);
                 }
                 TypedImportKind::ImportList(imported_values)
             }
             ImportKind::Alias(alias_token) => {
-                let alias_name = Token::get_ident_name(&alias_token);
-                let module_type_id = TypeId::module_type_alias(import_module_id);
-                let span = self.make_span(&alias_token.get_range());
-                self.add_variable_to_current_scope(alias_name, module_type_id, false, true, &span, false)?;
+                if stage == ImportStage::All || stage == ImportStage::Types {
+                    let alias_name = Token::get_ident_name(&alias_token);
+                    let module_type_id = TypeId::module_type_alias(import_module_id);
+                    let span = self.make_span(&alias_token.get_range());
+                    self.add_variable_to_current_scope(alias_name, module_type_id, false, true, &span, false)?;
+                }
 
                 TypedImportKind::Alias(alias_token)
             }
@@ -4248,6 +4772,12 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             }
         };
 
+        let symbol = match exported_value {
+            ExportedValue::Function(func_id) => Symbol::Function(func_id),
+            ExportedValue::Type(type_kind) => Symbol::Type(type_kind),
+            ExportedValue::Variable(var_id) => Symbol::Variable(var_id),
+        };
+        self.add_reference(&import_token.get_range(), symbol);
         self.current_module_mut().imports.entry(module_id).or_default().push(imported_value.clone());
 
         Ok(imported_value)
@@ -4497,6 +5027,12 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                     let mut path_tokens_iter = path_tokens.into_iter();
                     let first_token = path_tokens_iter.next().expect("There should be at least 1 token in the path");
                     let first_token_str = Token::get_ident_name(&first_token);
+                    if let Some((_, var)) = self.project.find_variable_by_name(&ScopeId(self.current_module().id, 0), &first_token_str) {
+                        if var.type_id.as_module_type_alias().is_some() {
+                            let var_id = var.id;
+                            self.add_reference(&first_token.get_range(), Symbol::Variable(var_id));
+                        }
+                    }
 
                     let (enum_or_struct, name_token) = if let Some((_, var)) = self.project.find_variable_by_name(&ScopeId(self.current_module().id, 0), &first_token_str) {
                         if let Some(alias_module_id) = var.type_id.as_module_type_alias() {
@@ -4512,7 +5048,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
 
                             match export {
                                 ExportedValue::Variable(_) | ExportedValue::Function(_) => {
-                                    return Err(TypeError::UnknownType { span: self.make_span(&type_name_token.get_range()), name: type_name });
+                                    return Err(TypeError::UnknownType { span: self.make_span(&type_name_token.get_range()), name: type_name, suggestion: None });
                                 }
                                 ExportedValue::Type(TypeKind::Enum(enum_id)) => (Either::Left(self.project.get_enum_by_id(enum_id)), type_name_token),
                                 ExportedValue::Type(TypeKind::Struct(struct_id)) => (Either::Right(self.project.get_struct_by_id(struct_id)), type_name_token),
@@ -4521,14 +5057,16 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                             let enum_ = self.project.get_enum_by_id(enum_id);
                             (Either::Left(enum_), first_token)
                         } else {
-                            return Err(TypeError::UnknownType { span: self.make_span(&first_token.get_range()), name: first_token_str });
+                            return Err(TypeError::UnknownType { span: self.make_span(&first_token.get_range()), name: first_token_str, suggestion: None });
                         }
                     } else {
-                        return Err(TypeError::UnknownType { span: self.make_span(&first_token.get_range()), name: first_token_str });
+                        let suggestion = self.suggest_type_name(&first_token_str);
+                        return Err(TypeError::UnknownType { span: self.make_span(&first_token.get_range()), name: first_token_str, suggestion });
                     };
 
                     match enum_or_struct {
                         Either::Right(struct_) => {
+                            let struct_id = struct_.id;
                             if let Some(destructured_arg) = args.as_ref().and_then(|args| args.first()) {
                                 let tok = match destructured_arg {
                                     MatchCaseArgument::Pattern(p) => p.get_token(),
@@ -4555,6 +5093,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                             }
 
                             case_type_id = resolved_case_type_id;
+                            self.add_reference(&name_token.get_range(), Symbol::Type(TypeKind::Struct(struct_id)));
 
                             TypedMatchCaseKind::Type(resolved_case_type_id, vec![])
                         }
@@ -4591,6 +5130,9 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                                 None
                             };
 
+                            self.add_reference(&name_token.get_range(), Symbol::Type(TypeKind::Enum(enum_id)));
+                            self.add_reference(&variant_name_token.get_range(), Symbol::EnumVariant(enum_id, variant_idx));
+
                             let enum_ = self.project.get_enum_by_id(&enum_id);
                             debug_assert!(enum_.generic_ids.len() == generic_ids.len());
                             let enum_generics = enum_.generic_ids.iter().zip(&generic_ids)
@@ -4775,7 +5317,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
             all_branches_terminator = compound_terminator_kinds(&all_branches_terminator, &block_terminator);
             self.end_child_scope();
 
-            typed_match_cases.push(TypedMatchCase { body: typed_body, kind, case_binding, block_terminator })
+            typed_match_cases.push(TypedMatchCase { token: match_case_token, body: typed_body, kind, case_binding, block_terminator })
         }
 
         self.current_scope_mut().terminator = all_branches_terminator;
@@ -5242,10 +5784,13 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                 let variable = self.project.find_variable_by_name(&self.current_scope_id, &name);
                 let Some((_, Variable { id, type_id, .. })) = variable else {
                     let span = self.make_span(&token.get_range());
-                    return Err(TypeError::UnknownIdentifier { span, token });
+                    let suggestion = find_similar_name(&name, self.project.visible_variables(&self.current_scope_id).into_iter().map(|v| v.name.as_str()));
+                    return Err(TypeError::UnknownIdentifier { span, token, suggestion });
                 };
                 let var_id = *id;
                 let mut var_type_id = *type_id;
+                let symbol = self.project.symbol_for_var(&var_id);
+                self.add_reference(&token.get_range(), symbol);
 
                 if let Some(type_hint) = type_hint {
                     var_type_id = self.substitute_generics(&type_hint, &var_type_id);
@@ -5523,6 +6068,8 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                         ExportedValue::Variable(var_id) => *var_id,
                     };
                     let type_id = self.project.get_var_by_id(&var_id).type_id;
+                    let symbol = self.project.symbol_for_var(&var_id);
+                    self.add_reference(&field_ident.get_range(), symbol);
 
                     return Ok(TypedNode::Identifier { token: field_ident, var_id, type_arg_ids, type_id, resolved_type_id: type_id });
                 }
@@ -5668,6 +6215,9 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                 }
 
                 if let Some((kind, member_idx, mut type_id)) = field_data {
+                    if let Some(symbol) = self.project.accessor_symbol(&target_type_id, &kind, member_idx) {
+                        self.add_reference(&field_ident.get_range(), symbol);
+                    }
                     if n.is_opt_safe && target_is_option_type {
                         type_id = self.add_or_find_type_id(self.project.option_type(type_id))
                     }
@@ -5699,6 +6249,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                 let mut fn_is_variadic = false;
                 let mut forbid_labels = false;
                 let mut func_id = None;
+                let mut instantiated_struct_id = None;
                 match &typed_target {
                     TypedNode::Identifier { var_id, type_arg_ids, .. } if self.project.get_var_by_id(var_id).alias != VariableAlias::None => {
                         provided_type_arg_ids = type_arg_ids.clone();
@@ -5721,6 +6272,7 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
                                         params_data = struct_.fields.iter().enumerate().map(|(idx, f)| (idx, f.name.clone(), f.type_id, f.default_value.is_some(), false)).collect_vec();
                                         return_type_id = struct_.self_type_id;
                                         is_instantiation = true;
+                                        instantiated_struct_id = Some(alias_struct_id);
                                     }
                                     TypeKind::Enum(alias_enum_id) => {
                                         let type_id = self.project.get_enum_by_id(&alias_enum_id).self_type_id;
@@ -6000,6 +6552,21 @@ impl<'a, L: LoadModule> Typechecker2<'a, L> {
 
                         seen_labels.insert(label_name);
 
+                        let label_symbol = if let Some(struct_id) = instantiated_struct_id {
+                            Some(Symbol::Field(struct_id, param_data.0))
+                        } else if let Some(func_id) = func_id {
+                            let function = self.project.get_func_by_id(&func_id);
+                            let param_offset = if function.has_self() { 1 } else { 0 };
+                            function.params.get(param_data.0 + param_offset)
+                                .filter(|param| param.var_id != VarId::BOGUS)
+                                .map(|param| Symbol::Variable(param.var_id))
+                        } else {
+                            None
+                        };
+                        if let Some(symbol) = label_symbol {
+                            self.add_reference(&label.get_range(), symbol);
+                        }
+
                         param_idx = param_data.0;
                         param_type_id = param_data.2;
                         if param_data.4 { // is variadic
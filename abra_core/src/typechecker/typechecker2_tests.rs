@@ -58,6 +58,10 @@ impl LoadModule for TestModuleLoader {
         self.module_id_map_rev.get(m_id)
     }
 
+    fn get_m_id(&self, module_id: &ModuleId) -> Option<&parser::ast::ModuleId> {
+        self.module_id_map.get(module_id)
+    }
+
     fn module_exists(&self, m_id: &parser::ast::ModuleId, with_respect_to: Option<&ModuleId>) -> bool {
         let path = self.calculate_path_wrt_other(m_id, with_respect_to);
         self.files.contains_key(&path)
@@ -633,6 +637,117 @@ fn typecheck_imports() {
     );
 }
 
+#[test]
+fn typecheck_circular_imports() {
+    assert_typecheck_ok_modules(
+        r#"
+          import Graph from "./2"
+          import Node from "./3"
+
+          val g = Graph(nodes: [])
+          val n = Node(graph: Some(g))
+        "#,
+        &[
+            (
+                "./2",
+                r#"
+                  import Node from "./3"
+                  export type Graph { nodes: Node[] }
+                "#
+            ),
+            (
+                "./3",
+                r#"
+                  import Graph from "./2"
+                  export type Node { graph: Graph? }
+                "#
+            ),
+        ],
+    );
+    assert_typecheck_ok_modules(
+        r#"
+          import isEven from "./2"
+
+          val _: Bool = isEven(4)
+        "#,
+        &[
+            (
+                "./2",
+                r#"
+                  import isOdd from "./3"
+                  export func isEven(n: Int): Bool = if n == 0 { true } else { isOdd(n - 1) }
+                "#
+            ),
+            (
+                "./3",
+                r#"
+                  import isEven from "./2"
+                  export func isOdd(n: Int): Bool = if n == 0 { false } else { isEven(n - 1) }
+                "#
+            ),
+        ],
+    );
+    // Variables can be imported across a cycle, as long as the modules' top-level code doesn't depend on itself
+    let project = test_typecheck_with_modules(
+        r#"
+          import a from "./2"
+          import makeA from "./3"
+
+          val _: String = a.b + makeA().b
+        "#,
+        &[
+            (
+                "./2",
+                r#"
+                  import b from "./3"
+                  export type A { b: String }
+                  export val a = A(b: b)
+                "#
+            ),
+            (
+                "./3",
+                r#"
+                  import A from "./2"
+                  export val b = "b"
+                  export func makeA(): A = A(b: b)
+                "#
+            ),
+        ],
+    ).unwrap();
+    let expected = vec![ModuleId(1), ModuleId(2), PRELUDE_MODULE_ID, ModuleId(5), ModuleId(4), TEST_MODULE_ID];
+    assert_eq!(expected, project.module_order);
+}
+
+#[test]
+fn typecheck_failure_suggestions() {
+    let suggestion_for = |input: &str| {
+        let (project, err) = test_typecheck(input).unwrap_err();
+        let Either::Right(err) = err else { unreachable!() };
+        err.suggestion(&project)
+    };
+
+    // Identifiers
+    assert_eq!(Some("count".to_string()), suggestion_for("val count = 1\nval x = cont + 1"));
+    assert_eq!(Some("println".to_string()), suggestion_for("printlm(1)"));
+    assert_eq!(Some("arg".to_string()), suggestion_for("func f(arg: Int): Int = agr"));
+    assert_eq!(None, suggestion_for("val count = 1\nval x = total + 1"));
+
+    // Types
+    assert_eq!(Some("String".to_string()), suggestion_for("val s: Strin = \"\""));
+    assert_eq!(Some("Point".to_string()), suggestion_for("type Point { x: Int }\nval p: point = Point(x: 1)"));
+    assert_eq!(Some("Item".to_string()), suggestion_for("func f<Item>(i: Itme): Item = i"));
+
+    // Members
+    assert_eq!(Some("length".to_string()), suggestion_for("val s = \"abc\"\nval x = s.lenght"));
+    assert_eq!(Some("y".to_string()), suggestion_for("type Point { x: Int, yy: Int, y: Int }\nval p = Point(x: 1, yy: 2, y: 3)\np.Y"));
+    assert_eq!(Some("Green".to_string()), suggestion_for("enum Color { Red, Green }\nval c = Color.Gren"));
+
+    // Exports
+    let (project, err) = test_typecheck_with_modules("import helper from \"./2\"", &[("./2", "export val helpers = 1")]).unwrap_err();
+    let Either::Right(err) = err else { unreachable!() };
+    assert_eq!(Some("helpers".to_string()), err.suggestion(&project));
+}
+
 #[test]
 fn typecheck_failure_imports() {
     let (_, Either::Right(err)) = test_typecheck_with_modules(
@@ -644,6 +759,7 @@ fn typecheck_failure_imports() {
     let expected = TypeError::UnknownModule {
         span: Span::new(TEST_MODULE_ID, (1, 15), (1, 19)),
         module_path: "./3.abra".to_string(),
+        suggestion: None,
     };
     assert_eq!(expected, err);
     let (_, Either::Right(err)) = test_typecheck_with_modules(
@@ -656,6 +772,7 @@ fn typecheck_failure_imports() {
     let expected = TypeError::UnknownModule {
         span: Span::new(ModuleId(5), (1, 15), (1, 23)),
         module_path: "./bogus.abra".to_string(),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 
@@ -698,6 +815,17 @@ fn typecheck_failure_imports() {
         span: Span::new(ModuleId(5), (1, 15), (1, 19)),
     };
     assert_eq!(expected, err);
+    let (_, Either::Right(err)) = test_typecheck_with_modules(
+        "import a from \"./2\"",
+        &[
+            ("./2", "import b from \"./3\"\nexport val a = b"),
+            ("./3", "import * from \"./2\"\nexport val b = 1"),
+        ],
+    ).unwrap_err() else { unreachable!() };
+    let expected = TypeError::CircularModuleImport {
+        span: Span::new(ModuleId(5), (1, 15), (1, 19)),
+    };
+    assert_eq!(expected, err);
 
     let (_, Either::Right(err)) = test_typecheck_with_modules(
         "\
@@ -2114,6 +2242,7 @@ fn typecheck_failure_binding_declaration() {
     let expected = TypeError::UnknownType {
         span: Span::new(TEST_MODULE_ID, (1, 8), (1, 12)),
         name: "Bogus".to_string(),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 
@@ -2169,11 +2298,12 @@ fn typecheck_type_declaration() {
             generic_ids: vec![],
             self_type_id: self_instance_type_id,
             fields: vec![
-                StructField { name: "a".to_string(), type_id: PRELUDE_STRING_TYPE_ID, is_readonly: false, defined_span: Span::new(TEST_MODULE_ID, (2, 1), (2, 1)), default_value: None },
-                StructField { name: "b".to_string(), type_id: PRELUDE_INT_TYPE_ID, is_readonly: false, defined_span: Span::new(TEST_MODULE_ID, (3, 1), (3, 1)), default_value: None },
+                StructField { name: "a".to_string(), type_id: PRELUDE_STRING_TYPE_ID, is_readonly: false, defined_span: Span::new(TEST_MODULE_ID, (2, 1), (2, 1)), default_value: None, doc_comment: None },
+                StructField { name: "b".to_string(), type_id: PRELUDE_INT_TYPE_ID, is_readonly: false, defined_span: Span::new(TEST_MODULE_ID, (3, 1), (3, 1)), default_value: None, doc_comment: None },
             ],
             methods: vec![tostring_func_id, hash_func_id, eq_func_id],
             static_methods: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.structs);
@@ -2218,6 +2348,7 @@ fn typecheck_type_declaration() {
         body: vec![],
         captured_vars: vec![],
         captured_closures: vec![],
+        doc_comment: None,
     };
     assert_eq!(&expected, project.get_func_by_id(&tostring_func_id));
 
@@ -2253,6 +2384,7 @@ fn typecheck_type_declaration() {
             fields: vec![],
             methods: vec![tostring_func_id, hash_func_id, eq_func_id, foo_func_id],
             static_methods: vec![foostatic_func_id],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.structs);
@@ -2286,10 +2418,11 @@ fn typecheck_type_declaration() {
             generic_ids: vec![],
             self_type_id: self_instance_type_id,
             fields: vec![
-                StructField { name: "a".to_string(), type_id: PRELUDE_STRING_TYPE_ID, is_readonly: true, defined_span: Span::new(TEST_MODULE_ID, (2, 1), (2, 1)), default_value: None },
+                StructField { name: "a".to_string(), type_id: PRELUDE_STRING_TYPE_ID, is_readonly: true, defined_span: Span::new(TEST_MODULE_ID, (2, 1), (2, 1)), default_value: None, doc_comment: None },
             ],
             methods: vec![tostring_func_id, hash_func_id, eq_func_id, foo_func_id],
             static_methods: vec![foostatic_func_id],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.structs);
@@ -2325,6 +2458,7 @@ fn typecheck_type_declaration() {
             ],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         },
         Function {
             id: FuncId(ScopeId(TEST_MODULE_ID, 1), 1),
@@ -2347,6 +2481,7 @@ fn typecheck_type_declaration() {
             ],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         },
         Function {
             id: tostring_func_id,
@@ -2372,6 +2507,7 @@ fn typecheck_type_declaration() {
             body: vec![],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         },
         Function {
             id: hash_func_id,
@@ -2397,6 +2533,7 @@ fn typecheck_type_declaration() {
             body: vec![],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         },
         Function {
             id: eq_func_id,
@@ -2431,6 +2568,7 @@ fn typecheck_type_declaration() {
             body: vec![],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         },
     ];
     assert_eq!(expected, module.scopes[1].funcs);
@@ -2462,10 +2600,11 @@ fn typecheck_type_declaration() {
             generic_ids: vec![TypeId(struct_scope_id, 0)],
             self_type_id: TypeId(ScopeId(TEST_MODULE_ID, 0), 0),
             fields: vec![
-                StructField { name: "value".to_string(), type_id: TypeId(struct_scope_id, 0), is_readonly: false, defined_span: Span::new(TEST_MODULE_ID, (2, 1), (2, 5)), default_value: None },
+                StructField { name: "value".to_string(), type_id: TypeId(struct_scope_id, 0), is_readonly: false, defined_span: Span::new(TEST_MODULE_ID, (2, 1), (2, 5)), default_value: None, doc_comment: None },
             ],
             methods: vec![tostring_func_id, hash_func_id, eq_func_id, tuple_func_id],
             static_methods: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.structs);
@@ -2505,10 +2644,12 @@ fn typecheck_type_declaration() {
                         type_id: PRELUDE_INT_TYPE_ID,
                         resolved_type_id: PRELUDE_INT_TYPE_ID,
                     }),
+                    doc_comment: None,
                 },
             ],
             methods: vec![tostring_func_id, hash_func_id, eq_func_id],
             static_methods: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.structs);
@@ -2553,6 +2694,7 @@ fn typecheck_failure_type_declaration() {
     let expected = TypeError::UnknownType {
         span: Span::new(TEST_MODULE_ID, (2, 4), (2, 8)),
         name: "Bogus".to_string(),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 
@@ -2682,6 +2824,7 @@ fn typecheck_enum_declaration() {
             all_variants_constant: false,
             methods: vec![tostring_func_id, hash_func_id, eq_func_id],
             static_methods: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.enums);
@@ -2727,6 +2870,7 @@ fn typecheck_enum_declaration() {
         body: vec![],
         captured_vars: vec![],
         captured_closures: vec![],
+        doc_comment: None,
     };
     assert_eq!(baz_variant_func, module.scopes[1].funcs[0]);
 }
@@ -2807,6 +2951,7 @@ fn typecheck_function_declaration() {
             ],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.scopes[0].funcs);
@@ -2865,6 +3010,7 @@ fn typecheck_function_declaration() {
             ],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.scopes[0].funcs);
@@ -2903,6 +3049,7 @@ fn typecheck_function_declaration() {
             ],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.scopes[0].funcs);
@@ -2938,6 +3085,7 @@ fn typecheck_function_declaration() {
                 VarId(ScopeId(TEST_MODULE_ID, 0), 1),
             ],
             captured_closures: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.scopes[0].funcs);
@@ -3015,6 +3163,7 @@ fn typecheck_function_declaration() {
             ],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.scopes[0].funcs);
@@ -3048,6 +3197,7 @@ fn typecheck_function_declaration() {
             body: vec![],
             captured_vars: vec![],
             captured_closures: vec![],
+            doc_comment: None,
         }
     ];
     assert_eq!(expected, module.scopes[0].funcs);
@@ -3097,6 +3247,7 @@ fn typecheck_failure_function_declaration() {
     let expected = TypeError::UnknownIdentifier {
         span: Span::new(TEST_MODULE_ID, (1, 14), (1, 14)),
         token: Token::Ident(Position::new(1, 14), "x".to_string()),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 
@@ -3258,6 +3409,7 @@ fn typecheck_failure_function_default_param_values() {
     let expected = TypeError::UnknownIdentifier {
         span: Span::new(TEST_MODULE_ID, (1, 22), (1, 24)),
         token: Token::Ident(Position::new(1, 22), "bar".to_string()),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 
@@ -5012,12 +5164,14 @@ fn typecheck_failure_if_statement() {
     let expected = TypeError::UnknownIdentifier {
         span: Span::new(TEST_MODULE_ID, (2, 1), (2, 1)),
         token: Token::Ident(Position::new(2, 1), "a".to_string()),
+        suggestion: None,
     };
     assert_eq!(expected, err);
     let (_, Either::Right(err)) = test_typecheck("if true { } else { val a = 1 }\na + 1").unwrap_err() else { unreachable!() };
     let expected = TypeError::UnknownIdentifier {
         span: Span::new(TEST_MODULE_ID, (2, 1), (2, 1)),
         token: Token::Ident(Position::new(2, 1), "a".to_string()),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 }
@@ -5444,6 +5598,7 @@ fn typecheck_failure_match_expression() {
     let expected = TypeError::UnknownType {
         span: Span::new(TEST_MODULE_ID, (5, 5), (5, 9)),
         name: "hello".to_string(),
+        suggestion: None,
     };
     assert_eq!(expected, err);
     let (_, Either::Right(err)) = test_typecheck_with_modules(
@@ -5461,6 +5616,7 @@ fn typecheck_failure_match_expression() {
     let expected = TypeError::UnknownType {
         span: Span::new(TEST_MODULE_ID, (5, 1), (5, 1)),
         name: "b".to_string(),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 
@@ -5499,6 +5655,7 @@ fn typecheck_failure_match_expression() {
     let expected = TypeError::UnknownType {
         span: Span::new(TEST_MODULE_ID, (5, 1), (5, 1)),
         name: "b".to_string(),
+        suggestion: None,
     };
     assert_eq!(expected, err);
 }
@@ -1,4 +1,4 @@
-use crate::common::display_error::DisplayError;
+use crate::common::display_error::{DisplayError, ErrorDescription};
 use crate::lexer::tokens::{Range, Token};
 use crate::typechecker::types::Type;
 use crate::parser::ast::{BinaryOp, IndexingMode, AstNode, BindingPattern, ModuleId};
@@ -88,6 +88,75 @@ impl TypecheckerError {
     pub fn get_range(&self) -> Range {
         self.get_token().get_range()
     }
+
+    // Errors which represent the same mistake as one reported by typechecker2 share its code. See common::error_codes for
+    // the explanation of each code
+    pub fn code(&self) -> &'static str {
+        match &self.kind {
+            TypecheckerErrorKind::Unimplemented(..) => "E0200",
+            TypecheckerErrorKind::Mismatch { .. } => "E0201",
+            TypecheckerErrorKind::InvalidIfConditionType { .. } |
+            TypecheckerErrorKind::InvalidLoopTarget { .. } => "E0238",
+            TypecheckerErrorKind::InvalidOperator { .. } => "E0203",
+            TypecheckerErrorKind::MissingRequiredAssignment { .. } |
+            TypecheckerErrorKind::UnannotatedUninitialized { .. } => "E0206",
+            TypecheckerErrorKind::DuplicateBinding { .. } |
+            TypecheckerErrorKind::DuplicateField { .. } |
+            TypecheckerErrorKind::DuplicateType { .. } |
+            TypecheckerErrorKind::DuplicateTypeArgument { .. } => "E0207",
+            TypecheckerErrorKind::DuplicateMapKey { .. } => "E0300",
+            TypecheckerErrorKind::UnboundGeneric(..) => "E0301",
+            TypecheckerErrorKind::UnknownIdentifier { .. } => "E0205",
+            TypecheckerErrorKind::InvalidAssignmentTarget { .. } => "E0232",
+            TypecheckerErrorKind::AssignmentToImmutable { .. } => "E0228",
+            TypecheckerErrorKind::UnknownType { .. } => "E0204",
+            TypecheckerErrorKind::MissingIfExprBranch { .. } => "E0233",
+            TypecheckerErrorKind::IfExprBranchMismatch { .. } |
+            TypecheckerErrorKind::MatchBranchMismatch { .. } => "E0202",
+            TypecheckerErrorKind::InvalidInvocationTarget { .. } |
+            TypecheckerErrorKind::InvalidInstantiation { .. } => "E0213",
+            TypecheckerErrorKind::IncorrectArity { .. } |
+            TypecheckerErrorKind::MissingRequiredParams { .. } => "E0218",
+            TypecheckerErrorKind::UnexpectedParamName { .. } => "E0215",
+            TypecheckerErrorKind::DuplicateParamName { .. } => "E0211",
+            TypecheckerErrorKind::InvalidTerminatorPlacement(..) => "E0239",
+            TypecheckerErrorKind::InvalidRequiredArgPosition(..) => "E0221",
+            TypecheckerErrorKind::InvalidVarargPosition(..) => "E0222",
+            TypecheckerErrorKind::InvalidVarargUsage(..) => "E0303",
+            TypecheckerErrorKind::InvalidIndexingTarget { .. } => "E0229",
+            TypecheckerErrorKind::InvalidIndexingSelector { .. } => "E0230",
+            TypecheckerErrorKind::InvalidTupleIndexingSelector { .. } => "E0231",
+            TypecheckerErrorKind::UnknownMember { .. } => "E0225",
+            TypecheckerErrorKind::InvalidMixedParamType { .. } => "E0216",
+            TypecheckerErrorKind::InvalidTypeFuncInvocation { .. } => "E0226",
+            TypecheckerErrorKind::InvalidSelfParamPosition { .. } => "E0220",
+            TypecheckerErrorKind::InvalidSelfParam { .. } => "E0219",
+            TypecheckerErrorKind::InvalidTypeDeclDepth { .. } => "E0302",
+            TypecheckerErrorKind::InvalidExportDepth { .. } => "E0241",
+            TypecheckerErrorKind::ForbiddenVariableType { .. } => "E0208",
+            TypecheckerErrorKind::InvalidTypeArgumentArity { .. } => "E0224",
+            TypecheckerErrorKind::UnreachableMatchCase { .. } => "E0236",
+            TypecheckerErrorKind::DuplicateMatchCase { .. } => "E0234",
+            TypecheckerErrorKind::NonExhaustiveMatch { .. } => "E0237",
+            TypecheckerErrorKind::EmptyMatchBlock { .. } => "E0235",
+            TypecheckerErrorKind::InvalidUninitializedEnumVariant { .. } => "E0214",
+            TypecheckerErrorKind::InvalidMatchCaseDestructuring { .. } |
+            TypecheckerErrorKind::InvalidMatchCaseDestructuringArity { .. } |
+            TypecheckerErrorKind::InvalidAssignmentDestructuring { .. } => "E0209",
+            TypecheckerErrorKind::DuplicateSplatDestructuring { .. } => "E0210",
+            TypecheckerErrorKind::UnreachableCode { .. } => "E0240",
+            TypecheckerErrorKind::ReturnTypeMismatch { .. } => "E0212",
+            TypecheckerErrorKind::InvalidProtocolMethod { .. } => "E0304",
+            TypecheckerErrorKind::VarargMismatch { .. } => "E0223",
+            TypecheckerErrorKind::InvalidAccess { .. } => "E0305",
+            TypecheckerErrorKind::CircularModuleImport { .. } => "E0242",
+            TypecheckerErrorKind::InvalidModuleImport { .. } => "E0243",
+            TypecheckerErrorKind::InvalidImportValue { .. } => "E0244",
+            TypecheckerErrorKind::InvalidTryPlacement { .. } => "E0306",
+            TypecheckerErrorKind::InvalidTryType { .. } => "E0307",
+            TypecheckerErrorKind::TryMismatch { .. } => "E0308",
+        }
+    }
 }
 
 impl TypecheckerError {
@@ -160,137 +229,84 @@ impl TypecheckerError {
 }
 
 impl DisplayError for TypecheckerError {
-    fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String {
-        let pos = self.get_token().get_position();
-        let cursor_line = Self::get_underlined_line(lines, self.get_token());
-
-        let msg = match &self.kind {
+    fn describe(&self) -> ErrorDescription<Range> {
+        match &self.kind {
             TypecheckerErrorKind::Unimplemented(_, message) => {
-                format!(
-                    "This feature is not yet implemented\n{}\n{}",
-                    cursor_line, message
-                )
+                ErrorDescription::new("This feature is not yet implemented").note(message)
             }
             TypecheckerErrorKind::Mismatch { expected, actual, .. } => {
-                format!(
-                    "Type mismatch\n{}\n\
-                    Expected {}, got {}",
-                    cursor_line,
-                    expected.repr(), actual.repr()
-                )
+                ErrorDescription::new("Type mismatch").note(format!("Expected {}, got {}", expected.repr(), actual.repr()))
             }
             TypecheckerErrorKind::InvalidIfConditionType { actual, .. } => {
-                format!(
-                    "Invalid type for condition\n{}\n\
-                    Conditions must be an Option or Bool, got {}",
-                    cursor_line, actual.repr()
-                )
+                ErrorDescription::new("Invalid type for condition")
+                    .note(format!("Conditions must be an Option or Bool, got {}", actual.repr()))
             }
             TypecheckerErrorKind::InvalidLoopTarget { target_type: actual, .. } => {
-                format!(
-                    "Invalid type for for-loop target\n{}\n\
-                    Type {} is not iterable",
-                    cursor_line, actual.repr()
-                )
+                ErrorDescription::new("Invalid type for for-loop target").note(format!("Type {} is not iterable", actual.repr()))
             }
             TypecheckerErrorKind::InvalidOperator { op, ltype, rtype, .. } => {
-                format!(
-                    "Invalid operator\n{}\n\
-                    No operator exists to satisfy {} {} {}",
-                    cursor_line,
-                    ltype.repr(), op.repr(), rtype.repr()
-                )
+                ErrorDescription::new("Invalid operator")
+                    .note(format!("No operator exists to satisfy {} {} {}", ltype.repr(), op.repr(), rtype.repr()))
             }
             TypecheckerErrorKind::MissingRequiredAssignment { ident } => {
                 let ident = Token::get_ident_name(&ident);
-                format!(
-                    "Expected assignment for variable '{}'\n{}\n\
-                    Variables declared with 'val' must be initialized",
-                    ident, cursor_line
-                )
+                ErrorDescription::new(format!("Expected assignment for variable '{}'", ident))
+                    .note("Variables declared with 'val' must be initialized")
             }
             TypecheckerErrorKind::DuplicateBinding { ident, orig_ident } => {
                 let ident = Token::get_ident_name(&ident);
-                let first_msg = format!("Duplicate variable '{}'\n{}", &ident, cursor_line);
+                let description = ErrorDescription::new(format!("Duplicate variable '{}'", &ident));
 
-                let second_msg = if let Some(orig_ident) = orig_ident {
+                if let Some(orig_ident) = orig_ident {
                     let pos = orig_ident.get_position();
-                    let cursor_line = Self::get_underlined_line(lines, orig_ident);
-                    format!("'{}' already declared in scope at ({}:{})\n{}", ident, pos.line, pos.col, cursor_line)
+                    description.note_at(format!("'{}' already declared in scope at ({}:{})", ident, pos.line, pos.col), orig_ident.get_range())
                 } else {
-                    format!("'{}' already declared as built-in value", ident)
-                };
-
-                format!("{}\n{}", first_msg, second_msg)
+                    description.note(format!("'{}' already declared as built-in value", ident))
+                }
             }
             TypecheckerErrorKind::DuplicateField { ident, orig_ident, orig_is_field, orig_is_enum_variant } => {
                 let ident = Token::get_ident_name(&ident);
-                let first_msg = format!("Duplicate field '{}'\n{}", ident, cursor_line);
-
                 let pos = orig_ident.get_position();
-                let cursor_line = Self::get_underlined_line(lines, orig_ident);
-
                 let noun = if *orig_is_field { "Field" } else if *orig_is_enum_variant { "Enum variant" } else { "Method" };
-                let second_msg = format!("{} with that name is already declared in scope at ({}:{})\n{}", noun, pos.line, pos.col, cursor_line);
 
-                format!("{}\n{}", first_msg, second_msg)
+                ErrorDescription::new(format!("Duplicate field '{}'", ident))
+                    .note_at(format!("{} with that name is already declared in scope at ({}:{})", noun, pos.line, pos.col), orig_ident.get_range())
             }
             TypecheckerErrorKind::DuplicateType { ident, orig_ident } => { // orig_ident will be None if it's a builtin type
                 let ident = Token::get_ident_name(&ident);
-                let first_msg = format!("Duplicate type '{}'\n{}", ident, cursor_line);
+                let description = ErrorDescription::new(format!("Duplicate type '{}'", ident));
 
-                let second_msg = match orig_ident {
+                match orig_ident {
                     Some(orig_ident) => {
                         let pos = orig_ident.get_position();
-                        let cursor_line = Self::get_underlined_line(lines, orig_ident);
-
-                        format!("Type already declared in scope at ({}:{})\n{}", pos.line, pos.col, cursor_line)
+                        description.note_at(format!("Type already declared in scope at ({}:{})", pos.line, pos.col), orig_ident.get_range())
                     }
-                    None => format!("'{}' already declared as built-in type", ident)
-                };
-
-                format!("{}\n{}", first_msg, second_msg)
+                    None => description.note(format!("'{}' already declared as built-in type", ident))
+                }
             }
-            TypecheckerErrorKind::DuplicateTypeArgument { ident, orig_ident } => { // orig_ident will be None if it's a builtin type
+            TypecheckerErrorKind::DuplicateTypeArgument { ident, orig_ident } => {
                 let ident = Token::get_ident_name(&ident);
-                let first_msg = format!("Duplicate type argument '{}'\n{}", ident, cursor_line);
-
                 let pos = orig_ident.get_position();
-                let cursor_line = Self::get_underlined_line(lines, orig_ident);
 
-                let second_msg = format!("Type already declared in scope at ({}:{})\n{}", pos.line, pos.col, cursor_line);
-                format!("{}\n{}", first_msg, second_msg)
+                ErrorDescription::new(format!("Duplicate type argument '{}'", ident))
+                    .note_at(format!("Type already declared in scope at ({}:{})", pos.line, pos.col), orig_ident.get_range())
             }
             TypecheckerErrorKind::DuplicateMapKey { orig_key, .. } => {
-                let first_msg = format!("Duplicate map key\n{}", cursor_line);
-
                 let pos = orig_key.get_position();
-                let cursor_line = Self::get_underlined_line(lines, orig_key);
-                let second_msg = format!("Key already present at ({}:{})\n{}", pos.line, pos.col, cursor_line);
-
-                format!("{}\n{}", first_msg, second_msg)
+                ErrorDescription::new("Duplicate map key")
+                    .note_at(format!("Key already present at ({}:{})", pos.line, pos.col), orig_key.get_range())
             }
             TypecheckerErrorKind::UnboundGeneric(_, type_arg_ident) => {
-                format!(
-                    "Type argument '{}' is unbound\n{}\n\
-                    There is not enough information to determine a possible value for '{}'",
-                    type_arg_ident, cursor_line, type_arg_ident
-                )
+                ErrorDescription::new(format!("Type argument '{}' is unbound", type_arg_ident))
+                    .note(format!("There is not enough information to determine a possible value for '{}'", type_arg_ident))
             }
             TypecheckerErrorKind::UnknownIdentifier { ident } => {
                 let ident = Token::get_ident_name(&ident);
+                let description = ErrorDescription::new(format!("Unknown identifier '{}'", ident));
                 if &ident == "_" {
-                    format!(
-                        "Unknown identifier '{}'\n{}\n\
-                        The _ represents an anonymous identifier; please give the variable a name if you want to reference it",
-                        ident, cursor_line
-                    )
+                    description.note("The _ represents an anonymous identifier; please give the variable a name if you want to reference it")
                 } else {
-                    format!(
-                        "Unknown identifier '{}'\n{}\n\
-                        No variable with that name is visible in current scope",
-                        ident, cursor_line
-                    )
+                    description.note("No variable with that name is visible in current scope")
                 }
             }
             TypecheckerErrorKind::InvalidAssignmentTarget { typ, reason, .. } => {
@@ -304,20 +320,15 @@ impl DisplayError for TypecheckerError {
                     ),
                     InvalidAssignmentTargetReason::MethodTarget => "Methods cannot be reassigned to".to_string(),
                 };
-                format!(
-                    "Cannot perform assignment\n{}\n{}",
-                    cursor_line, msg
-                )
+                ErrorDescription::new("Cannot perform assignment").note(msg)
             }
             TypecheckerErrorKind::AssignmentToImmutable { orig_ident, token: _ } => {
                 let ident = Token::get_ident_name(&orig_ident);
-                let first_msg = format!("Cannot assign to variable '{}'\n{}", ident, cursor_line);
-
                 let pos = orig_ident.get_position();
-                let cursor_line = Self::get_underlined_line(lines, orig_ident);
-                let second_msg = format!("The variable has been declared in scope as immutable at ({}:{})\n{}", pos.line, pos.col, cursor_line);
 
-                format!("{}\n{}\nUse 'var' instead of 'val' to create a mutable variable", first_msg, second_msg)
+                ErrorDescription::new(format!("Cannot assign to variable '{}'", ident))
+                    .note_at(format!("The variable has been declared in scope as immutable at ({}:{})", pos.line, pos.col), orig_ident.get_range())
+                    .note("Use 'var' instead of 'val' to create a mutable variable")
             }
             TypecheckerErrorKind::UnannotatedUninitialized { ident, is_mutable } => {
                 let ident = Token::get_ident_name(&ident);
@@ -328,61 +339,35 @@ impl DisplayError for TypecheckerError {
                 };
 
                 let modifier = if *is_mutable { "mutable" } else { "immutable" };
-                format!(
-                    "Could not determine type of {} variable '{}'\n{}\n{}",
-                    modifier, ident, cursor_line, msg
-                )
+                ErrorDescription::new(format!("Could not determine type of {} variable '{}'", modifier, ident)).note(msg)
             }
             TypecheckerErrorKind::UnknownType { type_ident } => {
                 let ident = Token::get_ident_name(type_ident);
-                format!(
-                    "Unknown type '{}'\n{}\nNo type with that name is visible in current scope",
-                    ident, cursor_line
-                )
+                ErrorDescription::new(format!("Unknown type '{}'", ident))
+                    .note("No type with that name is visible in current scope")
             }
             TypecheckerErrorKind::MissingIfExprBranch { if_token: _, is_if_branch } => {
-                format!(
-                    "Missing {}-branch in if-else expression\n{}\n\
-                    Both branches must have some value when used as an expression",
-                    if *is_if_branch { "if" } else { "else" }, cursor_line
-                )
+                ErrorDescription::new(format!("Missing {}-branch in if-else expression", if *is_if_branch { "if" } else { "else" }))
+                    .note("Both branches must have some value when used as an expression")
             }
             TypecheckerErrorKind::IfExprBranchMismatch { if_token: _, if_type, else_type } => {
-                format!(
-                    "Type mismatch between the if-else expression branches\n{}\n\
-                    The if-branch had type {}, but the else-branch had type {}",
-                    cursor_line, if_type.repr(), else_type.repr()
-                )
+                ErrorDescription::new("Type mismatch between the if-else expression branches")
+                    .note(format!("The if-branch had type {}, but the else-branch had type {}", if_type.repr(), else_type.repr()))
             }
             TypecheckerErrorKind::InvalidInvocationTarget { target_type, .. } => {
-                format!(
-                    "Cannot call target as function\n{}\n\
-                    Type {} is not invokeable",
-                    cursor_line, target_type.repr()
-                )
+                ErrorDescription::new("Cannot call target as function").note(format!("Type {} is not invokeable", target_type.repr()))
             }
             TypecheckerErrorKind::IncorrectArity { expected, actual, .. } => {
-                format!(
-                    "Incorrect arity for invocation\n{}\n\
-                    Expected {} required argument{}, but {} were passed",
-                    cursor_line,
-                    expected, if *expected == 1 { "" } else { "s" }, actual
-                )
+                ErrorDescription::new("Incorrect arity for invocation")
+                    .note(format!("Expected {} required argument{}, but {} were passed", expected, if *expected == 1 { "" } else { "s" }, actual))
             }
             TypecheckerErrorKind::UnexpectedParamName { token } => {
                 let param_name = Token::get_ident_name(token);
-                format!(
-                    "Unexpected parameter name '{}'\n{}\n\
-                    This function doesn't have a parameter called '{}'",
-                    param_name, cursor_line, param_name,
-                )
+                ErrorDescription::new(format!("Unexpected parameter name '{}'", param_name))
+                    .note(format!("This function doesn't have a parameter called '{}'", param_name))
             }
             TypecheckerErrorKind::DuplicateParamName { .. } => {
-                format!(
-                    "Duplicate parameter name\n{}\n\
-                    A parameter of this name has already been passed",
-                    cursor_line,
-                )
+                ErrorDescription::new("Duplicate parameter name").note("A parameter of this name has already been passed")
             }
             TypecheckerErrorKind::InvalidTerminatorPlacement(token) => {
                 let (keyword, msg) = match token {
@@ -392,213 +377,121 @@ impl DisplayError for TypecheckerError {
                     _ => unreachable!()
                 };
 
-                format!(
-                    "Unexpected {} keyword\n{}\n{}",
-                    keyword, cursor_line, msg
-                )
+                ErrorDescription::new(format!("Unexpected {} keyword", keyword)).note(msg)
             }
             TypecheckerErrorKind::InvalidRequiredArgPosition(_token) => {
-                format!(
-                    "Invalid position for non-optional parameter\n{}\n\
-                    Required parameters must all be listed before any optional parameters",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid position for non-optional parameter")
+                    .note("Required parameters must all be listed before any optional parameters")
             }
             TypecheckerErrorKind::InvalidVarargPosition(_token) => {
-                format!(
-                    "Invalid position for vararg parameter\n{}\n\
-                    Vararg parameters must be the last in the parameter list",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid position for vararg parameter")
+                    .note("Vararg parameters must be the last in the parameter list")
             }
             TypecheckerErrorKind::InvalidVarargUsage(_token) => {
-                format!(
-                    "Invalid usage of vararg parameter\n{}\n\
-                    Vararg parameters cannot be used in this context",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid usage of vararg parameter")
+                    .note("Vararg parameters cannot be used in this context")
             }
             TypecheckerErrorKind::InvalidIndexingTarget { target_type, index_mode, .. } => {
                 let context = if let IndexingMode::Range(_, _) = index_mode { " as a range" } else { "" };
-                format!(
-                    "Unsupported indexing operation\n{}\n\
-                    Type {} is not indexable{}",
-                    cursor_line, target_type.repr(), context
-                )
+                ErrorDescription::new("Unsupported indexing operation")
+                    .note(format!("Type {} is not indexable{}", target_type.repr(), context))
             }
             TypecheckerErrorKind::InvalidIndexingSelector { target_type, selector_type, .. } => {
-                format!(
-                    "Invalid type for indexing operator argument\n{}\n\
-                    Cannot index into a target of type {}, using a selector of type {}",
-                    cursor_line, target_type.repr(), selector_type.repr()
-                )
+                ErrorDescription::new("Invalid type for indexing operator argument")
+                    .note(format!("Cannot index into a target of type {}, using a selector of type {}", target_type.repr(), selector_type.repr()))
             }
             TypecheckerErrorKind::InvalidTupleIndexingSelector { types, non_constant, index, .. } => {
-                let message = if *non_constant {
-                    "\nIndex values for tuples must be constant non-negative integers".to_string()
+                let description = ErrorDescription::new("Unsupported indexing into tuple");
+                if *non_constant {
+                    description.note("Index values for tuples must be constant non-negative integers")
                 } else if *index != -1 {
-                    format!(
-                        "\nNo value at index {} for tuple {}",
-                        index, Type::Tuple(types.clone()).repr(),
-                    )
-                } else { "".to_string() };
-
-                format!(
-                    "Unsupported indexing into tuple\n{}{}",
-                    cursor_line, message
-                )
+                    description.note(format!("No value at index {} for tuple {}", index, Type::Tuple(types.clone()).repr()))
+                } else {
+                    description
+                }
             }
             TypecheckerErrorKind::UnknownMember { token, target_type, module_name } => {
                 let field_name = Token::get_ident_name(token);
+                let description = ErrorDescription::new(format!("Unknown member '{}'", field_name));
 
                 if let Some(module_name) = module_name {
-                    format!(
-                        "Unknown member '{}'\n{}\n\
-                        Module '{}' does not have an export with name '{}'",
-                        field_name, cursor_line,
-                        module_name, field_name
-                    )
+                    description.note(format!("Module '{}' does not have an export with name '{}'", module_name, field_name))
                 } else {
-                    format!(
-                        "Unknown member '{}'\n{}\n\
-                        Type {} does not have a member with name '{}'",
-                        field_name, cursor_line,
-                        target_type.repr(), field_name
-                    )
+                    description.note(format!("Type {} does not have a member with name '{}'", target_type.repr(), field_name))
                 }
             }
             TypecheckerErrorKind::MissingRequiredParams { missing_params, .. } => {
-                let missing_params = missing_params.join(", ");
-                format!(
-                    "Missing required parameters in function call\n{}\n\
-                    These parameters are required but missing: {}",
-                    cursor_line,
-                    missing_params
-                )
+                ErrorDescription::new("Missing required parameters in function call")
+                    .note(format!("These parameters are required but missing: {}", missing_params.join(", ")))
             }
             TypecheckerErrorKind::InvalidMixedParamType { .. } => {
-                format!(
-                    "Invalid function call\n{}\n\
-                    Cannot mix named and positional arguments.",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid function call").note("Cannot mix named and positional arguments.")
             }
             TypecheckerErrorKind::InvalidTypeFuncInvocation { .. } => {
-                format!(
-                    "Invalid instantiation call\n{}\n\
-                    Constructor functions must be called with named parameters.",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid instantiation call").note("Constructor functions must be called with named parameters.")
             }
             TypecheckerErrorKind::InvalidSelfParamPosition { .. } => {
-                format!(
-                    "Invalid position for `self`\n{}\n\
-                    `self` must appear as the first parameter",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid position for `self`").note("`self` must appear as the first parameter")
             }
             TypecheckerErrorKind::InvalidSelfParam { .. } => {
-                format!(
-                    "Invalid usage of `self` parameter\n{}\n\
-                    `self` can only appear within methods on types",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid usage of `self` parameter").note("`self` can only appear within methods on types")
             }
             TypecheckerErrorKind::InvalidTypeDeclDepth { .. } => {
-                format!(
-                    "Invalid location for type declaration\n{}\n\
-                    Types may only be declared at the root level",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid location for type declaration").note("Types may only be declared at the root level")
             }
             TypecheckerErrorKind::InvalidExportDepth { .. } => {
-                format!(
-                    "Invalid export modifier\n{}\n\
-                    Exported values may only appear at the top level scope",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid export modifier").note("Exported values may only appear at the top level scope")
             }
             TypecheckerErrorKind::ForbiddenVariableType { typ, .. } => {
                 match typ {
-                    Type::Unknown => format!(
-                        "Could not determine type\n{}\n\
-                        Please use an explicit type annotation to denote the type",
-                        cursor_line
-                    ),
-                    Type::Unit => format!(
-                        "Forbidden type for variable\n{}\n\
-                        Variables cannot be of type {}",
-                        cursor_line, Type::Unit.repr(),
-                    ),
+                    Type::Unknown => ErrorDescription::new("Could not determine type")
+                        .note("Please use an explicit type annotation to denote the type"),
+                    Type::Unit => ErrorDescription::new("Forbidden type for variable")
+                        .note(format!("Variables cannot be of type {}", Type::Unit.repr())),
                     _ => unreachable!()
                 }
             }
             TypecheckerErrorKind::InvalidInstantiation { typ, .. } => {
-                format!(
-                    "Cannot create an instance of type {}\n{}",
-                    typ.repr(), cursor_line
-                )
+                ErrorDescription::new(format!("Cannot create an instance of type {}", typ.repr()))
             }
             TypecheckerErrorKind::InvalidTypeArgumentArity { actual_type, actual, expected, .. } => {
-                format!(
-                    "Expected {} type argument{}, but {} {} provided\n{}{}",
-                    expected, if *expected == 1 { "" } else { "s" }, actual, if *actual == 1 { "was" } else { "were" }, cursor_line,
-                    if *expected > 0 {
-                        format!(
-                            "\nProvide {} type argument{} to match type {}",
-                            expected, if *expected == 1 { "" } else { "s" }, actual_type.repr()
-                        )
-                    } else { "".to_string() }
-                )
+                let description = ErrorDescription::new(format!(
+                    "Expected {} type argument{}, but {} {} provided",
+                    expected, if *expected == 1 { "" } else { "s" }, actual, if *actual == 1 { "was" } else { "were" },
+                ));
+                if *expected > 0 {
+                    description.note(format!("Provide {} type argument{} to match type {}", expected, if *expected == 1 { "" } else { "s" }, actual_type.repr()))
+                } else {
+                    description
+                }
             }
             TypecheckerErrorKind::UnreachableMatchCase { typ, is_unreachable_none, prior_covering_case_tok: is_already_covered, .. } => {
-                format!(
-                    "Unreachable match case\n{}\n{}",
-                    cursor_line,
-                    if *is_unreachable_none {
-                        "Value cannot possibly be None at this point".to_string()
-                    } else if let Some(orig_case_token) = is_already_covered {
-                        let pos = orig_case_token.get_position();
-                        let cursor_line = Self::get_underlined_line(lines, orig_case_token);
-
-                        format!("This condition has already been handled by a previous case ({}:{})\n{}", pos.line, pos.col, cursor_line)
-                    } else if let Some(typ) = typ {
-                        format!("Value cannot possibly be of type {} at this point", typ.repr())
-                    } else {
-                        "All possible cases have already been handled".to_string()
-                    }
-                )
-            }
-            TypecheckerErrorKind::DuplicateMatchCase { .. } => {
-                format!("Duplicate match case\n{}", cursor_line)
+                let description = ErrorDescription::new("Unreachable match case");
+                if *is_unreachable_none {
+                    description.note("Value cannot possibly be None at this point")
+                } else if let Some(orig_case_token) = is_already_covered {
+                    let pos = orig_case_token.get_position();
+                    description.note_at(format!("This condition has already been handled by a previous case ({}:{})", pos.line, pos.col), orig_case_token.get_range())
+                } else if let Some(typ) = typ {
+                    description.note(format!("Value cannot possibly be of type {} at this point", typ.repr()))
+                } else {
+                    description.note("All possible cases have already been handled")
+                }
             }
+            TypecheckerErrorKind::DuplicateMatchCase { .. } => ErrorDescription::new("Duplicate match case"),
             TypecheckerErrorKind::NonExhaustiveMatch { .. } => {
-                format!(
-                    "Non-exhaustive match\n{}\n{}",
-                    cursor_line,
-                    "Please ensure each possible case is handled, or use the wildcard (_)"
-                )
+                ErrorDescription::new("Non-exhaustive match")
+                    .note("Please ensure each possible case is handled, or use the wildcard (_)")
             }
             TypecheckerErrorKind::EmptyMatchBlock { .. } => {
-                format!(
-                    "Empty block for match case\n{}\n{}",
-                    cursor_line,
-                    "Each case in a match expression must result in a value"
-                )
+                ErrorDescription::new("Empty block for match case").note("Each case in a match expression must result in a value")
             }
             TypecheckerErrorKind::MatchBranchMismatch { expected, actual, .. } => {
-                format!(
-                    "Type mismatch among the match-expression branches\n{}\n\
-                    The type {} does not match with the type {} of the other branches",
-                    cursor_line, actual.repr(), expected.repr()
-                )
+                ErrorDescription::new("Type mismatch among the match-expression branches")
+                    .note(format!("The type {} does not match with the type {} of the other branches", actual.repr(), expected.repr()))
             }
             TypecheckerErrorKind::InvalidUninitializedEnumVariant { .. } => {
-                format!(
-                    "Invalid usage of enum variant\n{}\n\
-                    This enum variant requires arguments",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid usage of enum variant").note("This enum variant requires arguments")
             }
             TypecheckerErrorKind::InvalidMatchCaseDestructuring { typ, enum_variant, .. } => {
                 let msg = match typ {
@@ -608,22 +501,17 @@ impl DisplayError for TypecheckerError {
                     },
                     None => "Cannot destructure instance of None".to_string(),
                 };
-                format!(
-                    "Invalid destructuring for match\n{}\n\n{}",
-                    cursor_line, msg
-                )
+                ErrorDescription::new("Invalid destructuring for match").note(msg)
             }
             TypecheckerErrorKind::InvalidMatchCaseDestructuringArity { typ, enum_variant, expected, actual, .. } => {
                 let type_displ = match enum_variant {
                     Some(variant_name) => format!("{}.{}", typ.repr(), variant_name),
                     None => format!("type {}", typ.repr())
                 };
-                format!(
-                    "Invalid destructuring pattern for match\n{}\n\
-                    Instances of {} have {} field{}, but the pattern attempts to extract {}",
-                    cursor_line,
+                ErrorDescription::new("Invalid destructuring pattern for match").note(format!(
+                    "Instances of {} have {} field{}, but the pattern attempts to extract {}",
                     type_displ, expected, if *expected == 1 { "" } else { "s" }, actual
-                )
+                ))
             }
             TypecheckerErrorKind::InvalidAssignmentDestructuring { binding, typ } => {
                 let msg = match (binding, typ) {
@@ -637,24 +525,14 @@ impl DisplayError for TypecheckerError {
                     _ => unreachable!()
                 };
 
-                format!(
-                    "Invalid destructuring pattern for assignment\n{}\n{}",
-                    cursor_line, msg
-                )
+                ErrorDescription::new("Invalid destructuring pattern for assignment").note(msg)
             }
             TypecheckerErrorKind::DuplicateSplatDestructuring { .. } => {
-                format!(
-                    "Invalid destructuring pattern for assignment\n{}\n\
-                    Cannot have more than one splat (*) instance in an array destructuring",
-                    cursor_line
-                )
+                ErrorDescription::new("Invalid destructuring pattern for assignment")
+                    .note("Cannot have more than one splat (*) instance in an array destructuring")
             }
             TypecheckerErrorKind::UnreachableCode { .. } => {
-                format!(
-                    "Unreachable code\n{}\n\
-                    Code comes after a return statement and will never be called",
-                    cursor_line
-                )
+                ErrorDescription::new("Unreachable code").note("Code comes after a return statement and will never be called")
             }
             TypecheckerErrorKind::ReturnTypeMismatch { fn_name, bare_return, expected, actual, .. } => {
                 let actual = match &actual {
@@ -664,109 +542,73 @@ impl DisplayError for TypecheckerError {
                     _ => actual.clone()
                 };
                 let msg = if *bare_return {
-                    format!(
-                        "Function '{}' has return type {}, but no expression was returned",
-                        fn_name, expected.repr()
-                    )
+                    format!("Function '{}' has return type {}, but no expression was returned", fn_name, expected.repr())
                 } else {
-                    format!(
-                        "Function '{}' has return type {}, but this is of type {}",
-                        fn_name, expected.repr(), actual.repr(),
-                    )
+                    format!("Function '{}' has return type {}, but this is of type {}", fn_name, expected.repr(), actual.repr())
                 };
-                let hint = if actual == Type::Option(Box::new(Type::Unit)) {
-                    format!("\n(Note: Values of type {} are redundant and can probably just be removed)", actual.repr())
+
+                let description = ErrorDescription::new("Invalid return type").note(msg);
+                if actual == Type::Option(Box::new(Type::Unit)) {
+                    description.help(format!("Values of type {} are redundant and can probably just be removed", actual.repr()))
                 } else if expected == &Type::Unit {
-                    "\n(Note: A function without a return type annotation is assumed to return Unit.\n       Try adding a return type annotation to the function.)".to_string()
-                } else { "".to_string() };
-
-                format!(
-                    "Invalid return type\n{}\n{}{}",
-                    cursor_line,
-                    msg, hint
-                )
+                    description.help("A function without a return type annotation is assumed to return Unit. Try adding a return type annotation to the function.")
+                } else {
+                    description
+                }
             }
             TypecheckerErrorKind::InvalidProtocolMethod { fn_name, expected, actual, .. } => {
-                format!(
-                    "Invalid type for method\n{}\n\
-                    Expected method {} to be of type {}, but instead got {}",
-                    cursor_line,
-                    fn_name, expected.repr(), actual.repr()
-                )
+                ErrorDescription::new("Invalid type for method")
+                    .note(format!("Expected method {} to be of type {}, but instead got {}", fn_name, expected.repr(), actual.repr()))
             }
             TypecheckerErrorKind::VarargMismatch { typ, .. } => {
-                format!(
-                    "Invalid type for vararg parameter\n{}\n\
-                    Vararg parameters must be an Array type, but got {}",
-                    cursor_line, typ.repr()
-                )
+                ErrorDescription::new("Invalid type for vararg parameter")
+                    .note(format!("Vararg parameters must be an Array type, but got {}", typ.repr()))
             }
             TypecheckerErrorKind::InvalidAccess { token, is_field, is_get, .. } => {
                 let target = Token::get_ident_name(token);
                 let target_kind = if *is_field { "field" } else { "method" };
                 let access_kind = if *is_get { "read" } else { "write" };
                 let access_str = if *is_get { "get" } else { "set" };
-                format!(
-                    "Invalid access for {} '{}'\n{}\n\
-                    Cannot {} field '{}' since it is not {}table",
-                    target_kind, target, cursor_line,
-                    access_kind, target, access_str
-                )
+                ErrorDescription::new(format!("Invalid access for {} '{}'", target_kind, target))
+                    .note(format!("Cannot {} field '{}' since it is not {}table", access_kind, target, access_str))
             }
             TypecheckerErrorKind::CircularModuleImport { module_name, .. } => {
-                let reason = format!(
+                ErrorDescription::new("Could not import module").note(format!(
                     "Circular dependency detected within the module '{}'. It appears that some \
                     module imported by '{}' is trying to import '{}', which resulted in a cycle.",
                     module_name, module_name, module_name
-                );
-                format!("Could not import module\n{}\n{}", cursor_line, reason)
+                ))
             }
             TypecheckerErrorKind::InvalidModuleImport { module_name, .. } => {
-                format!(
-                    "Could not import module '{}'\n{}\nNo such module exists",
-                    module_name, cursor_line
-                )
+                ErrorDescription::new(format!("Could not import module '{}'", module_name)).note("No such module exists")
             }
             TypecheckerErrorKind::InvalidImportValue { ident } => {
-                format!(
-                    "Invalid import\n{}\nThis module does not export any value called '{}'",
-                    cursor_line, Token::get_ident_name(ident)
-                )
+                ErrorDescription::new("Invalid import")
+                    .note(format!("This module does not export any value called '{}'", Token::get_ident_name(ident)))
             }
             TypecheckerErrorKind::InvalidTryPlacement { fn_ctx,.. } => {
                 if let Some((fn_token, fn_return_type)) = fn_ctx {
-                    let cursor_line2 = Self::get_underlined_line(lines, fn_token);
-
-                    format!(
-                        "Invalid enclosing function for try expression\n{}\n\
-                        The enclosing function has return type {}, which is not Tryable\n{}\n\
-                        Cannot bubble up failed try values from a function with this return type",
-                        cursor_line, fn_return_type.repr(), cursor_line2
-                    )
+                    ErrorDescription::new("Invalid enclosing function for try expression")
+                        .note_at(format!("The enclosing function has return type {}, which is not Tryable", fn_return_type.repr()), fn_token.get_range())
+                        .note("Cannot bubble up failed try values from a function with this return type")
                 } else {
-                    format!(
-                        "Invalid try expression\n{}\nA try expression cannot appear outside of a function",
-                        cursor_line,
-                    )
+                    ErrorDescription::new("Invalid try expression").note("A try expression cannot appear outside of a function")
                 }
             }
             TypecheckerErrorKind::InvalidTryType { typ, .. } => {
-                format!(
-                    "Invalid try expression\n{}\nThe type {} is not Tryable",
-                    cursor_line, typ.repr(),
-                )
+                ErrorDescription::new("Invalid try expression").note(format!("The type {} is not Tryable", typ.repr()))
             }
             TypecheckerErrorKind::TryMismatch { try_type, return_type, .. } => {
-                format!(
-                    "Invalid type for try expression\n{}\n\
-                    The expression would result in a value of type {}, but the function has a return type of {}",
-                    cursor_line, try_type.repr(), return_type.repr(),
-                )
+                ErrorDescription::new("Invalid type for try expression").note(format!(
+                    "The expression would result in a value of type {}, but the function has a return type of {}",
+                    try_type.repr(), return_type.repr(),
+                ))
             }
-        };
+        }
+    }
 
-        let error_line = format!("Error at {}:{}:{}", file_name, pos.line, pos.col);
-        format!("{}\n{}", error_line, msg)
+    fn message_for_error(&self, file_name: &String, lines: &Vec<&str>) -> String {
+        Self::render_description(file_name, lines, &self.get_range(), &self.describe())
     }
 }
 
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use crate::parser;
+use crate::manifest::ResolvedManifest;
+use crate::typechecker::typechecker2::{calculate_module_path, list_dir_on_disk, LoadModule, ModuleId};
+
+// Resolves `.` and `..` segments without touching the filesystem, so that the same file is always keyed by the same path
+// (eg. `/a/./b/../c.abra` and `/a/c.abra`), whether or not it actually exists on disk.
+pub fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.as_ref().components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+// A LoadModule implementation backed by a map of paths to sources, for typechecking sources which don't exist on disk (eg. unsaved
+// editor buffers, or programs synthesized by tests and the playground). Modules are resolved exactly as they are by ModuleLoader.
+// If filesystem fallback is enabled, any file not present in the map is read from disk instead; in this overlay mode the in-memory
+// files shadow their counterparts on disk.
+pub struct VirtualModuleLoader {
+    program_root: PathBuf,
+    std_path: PathBuf,
+    manifest: Option<ResolvedManifest>,
+    files: HashMap<String, String>,
+    fs_fallback: bool,
+    module_id_map: HashMap<ModuleId, parser::ast::ModuleId>,
+    module_id_map_rev: HashMap<parser::ast::ModuleId, ModuleId>,
+    module_id_paths: HashMap<ModuleId, String>,
+}
+
+impl VirtualModuleLoader {
+    pub fn new<P1: AsRef<Path>, P2: AsRef<Path>>(program_root: P1, std_path: P2) -> VirtualModuleLoader {
+        VirtualModuleLoader {
+            program_root: normalize_path(program_root),
+            std_path: normalize_path(std_path),
+            manifest: None,
+            files: HashMap::new(),
+            fs_fallback: false,
+            module_id_map: HashMap::new(),
+            module_id_map_rev: HashMap::new(),
+            module_id_paths: HashMap::new(),
+        }
+    }
+
+    pub fn with_fs_fallback<P1: AsRef<Path>, P2: AsRef<Path>>(program_root: P1, std_path: P2) -> VirtualModuleLoader {
+        VirtualModuleLoader { fs_fallback: true, ..VirtualModuleLoader::new(program_root, std_path) }
+    }
+
+    pub fn set_manifest(&mut self, manifest: Option<ResolvedManifest>) {
+        self.manifest = manifest;
+    }
+
+    // Adds (or replaces) the contents of the file at `path`; relative paths are taken to be relative to the program root.
+    pub fn add_file<P: AsRef<Path>, S: AsRef<str>>(&mut self, path: P, contents: S) {
+        let path = self.file_key(path);
+        self.files.insert(path, contents.as_ref().to_string());
+    }
+
+    // Removes the in-memory contents of the file at `path`, returning them if present. With filesystem fallback enabled, subsequent
+    // loads of that path will see the file on disk again.
+    pub fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> Option<String> {
+        let path = self.file_key(path);
+        self.files.remove(&path)
+    }
+
+    pub fn has_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.files.contains_key(&self.file_key(path))
+    }
+
+    pub fn program_root(&self) -> &Path {
+        &self.program_root
+    }
+
+    pub fn std_path(&self) -> &Path {
+        &self.std_path
+    }
+
+    pub fn is_std_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        Path::new(&self.file_key(path)).starts_with(&self.std_path)
+    }
+
+    // Forgets all registered modules (but not the files), so the loader can be reused for another typechecking pass over a fresh Project.
+    pub fn reset_modules(&mut self) {
+        self.module_id_map.clear();
+        self.module_id_map_rev.clear();
+        self.module_id_paths.clear();
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        let path = self.file_key(path);
+        self.files.contains_key(&path) || (self.fs_fallback && Path::new(&path).is_file())
+    }
+
+    fn file_key<P: AsRef<Path>>(&self, path: P) -> String {
+        let path = path.as_ref();
+        let path = if path.is_absolute() { normalize_path(path) } else { normalize_path(self.program_root.join(path)) };
+        path.to_str().unwrap().to_string()
+    }
+}
+
+impl LoadModule for VirtualModuleLoader {
+    fn get_path(&self, module_id: &ModuleId) -> Option<String> {
+        self.module_id_paths.get(module_id).map(|s| s.clone())
+    }
+
+    fn calculate_path_wrt_other(&self, m_id: &parser::ast::ModuleId, other: Option<&ModuleId>) -> String {
+        let path = calculate_module_path(&self.program_root, &self.std_path, self.manifest.as_ref(), &self.module_id_paths, m_id, other, |path| self.file_exists(path));
+        normalize_path(path).to_str().unwrap().to_string()
+    }
+
+    fn register(&mut self, m_id: &parser::ast::ModuleId, module_id: &ModuleId, with_respect_to: Option<&ModuleId>) {
+        self.module_id_map.insert(*module_id, m_id.clone());
+        self.module_id_map_rev.insert(m_id.clone(), *module_id);
+
+        let path = self.calculate_path_wrt_other(m_id, with_respect_to);
+        self.module_id_paths.insert(*module_id, path);
+    }
+
+    fn get_module_id(&self, m_id: &parser::ast::ModuleId) -> Option<&ModuleId> {
+        self.module_id_map_rev.get(m_id)
+    }
+
+    fn get_m_id(&self, module_id: &ModuleId) -> Option<&parser::ast::ModuleId> {
+        self.module_id_map.get(module_id)
+    }
+
+    fn module_exists(&self, m_id: &parser::ast::ModuleId, with_respect_to: Option<&ModuleId>) -> bool {
+        self.file_exists(&self.calculate_path_wrt_other(m_id, with_respect_to))
+    }
+
+    fn load_file(&self, file_name: &String) -> Option<String> {
+        if let Some(contents) = self.files.get(file_name) {
+            return Some(contents.clone());
+        }
+
+        if self.fs_fallback { std::fs::read_to_string(file_name).ok() } else { None }
+    }
+
+    fn list_dir(&self, dir: &Path) -> Vec<String> {
+        let dir = normalize_path(dir);
+        let mut paths = self.files.keys()
+            .filter(|path| Path::new(path).parent() == Some(dir.as_path()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if self.fs_fallback {
+            paths.extend(list_dir_on_disk(&dir).into_iter().filter(|path| !self.files.contains_key(path)));
+        }
+        paths.sort();
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use itertools::Either;
+    use crate::common::util::random_string;
+    use crate::manifest::ResolvedManifest;
+    use crate::parser;
+    use crate::typechecker::test_helpers::add_std;
+    use crate::typechecker::typechecker2::{Project, Typechecker2, TypecheckError};
+    use crate::typechecker::virtual_module_loader::{normalize_path, VirtualModuleLoader};
+
+    fn typecheck(loader: &mut VirtualModuleLoader, entry: &str) -> Result<Project, (Project, TypecheckError)> {
+        loader.reset_modules();
+        let mut project = Project::default();
+        let mut tc = Typechecker2::new(loader, &mut project);
+        tc.typecheck_prelude().unwrap();
+
+        let entry_module_id = parser::ast::ModuleId::parse_module_path(entry).unwrap();
+        match tc.typecheck_module(&entry_module_id, None) {
+            Ok(_) => Ok(project),
+            Err(e) => Err((project, e)),
+        }
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(PathBuf::from("/a/c.abra"), normalize_path("/a/./b/../c.abra"));
+        assert_eq!(PathBuf::from("/a/b/c.abra"), normalize_path("/a/b/./c.abra"));
+        assert_eq!(PathBuf::from("../c.abra"), normalize_path("a/../../c.abra"));
+    }
+
+    #[test]
+    fn test_in_memory_modules() {
+        let mut loader = VirtualModuleLoader::new("/project", "/std");
+        add_std(&mut loader);
+        loader.add_file("main.abra", "import double from \"./util/math\"\nval x: Int = double(2)");
+        loader.add_file("util/math.abra", "import triple from \"../other\"\nexport func double(i: Int): Int = i * 2");
+        loader.add_file("/project/other.abra", "export func triple(i: Int): Int = i * 3");
+
+        let project = typecheck(&mut loader, "./main").unwrap();
+        assert_eq!(6, project.modules.len());
+
+        // Modules which don't exist in memory are not found, even if they would exist on disk
+        loader.add_file("main.abra", "import foo from \"./foo\"");
+        let (project, err) = typecheck(&mut loader, "./main").unwrap_err();
+        let Either::Right(err) = err else { panic!("Expected a TypeError") };
+        assert!(err.message(&loader, &project).contains("No such module exists at '/project/foo.abra'"));
+
+        // Removed files are no longer visible
+        assert!(loader.remove_file("/project/other.abra").is_some());
+        assert!(!loader.has_file("other.abra"));
+        loader.add_file("main.abra", "import double from \"./util/math\"");
+        assert!(typecheck(&mut loader, "./main").is_err());
+    }
+
+    #[test]
+    fn test_unknown_module_suggestions() {
+        let mut loader = VirtualModuleLoader::new("/project", "/std");
+        add_std(&mut loader);
+        loader.add_file("util/strings.abra", "export val a = 1");
+
+        loader.add_file("main.abra", "import a from \"./util/string\"");
+        let (project, err) = typecheck(&mut loader, "./main").unwrap_err();
+        let Either::Right(err) = err else { panic!("Expected a TypeError") };
+        assert_eq!(Some("./util/strings".to_string()), err.suggestion(&project));
+        assert!(err.message(&loader, &project).ends_with("help: did you mean './util/strings'?"));
+
+        // Modules in the std library are suggested too, but private modules are not
+        loader.add_file("main.abra", "import \"libcc\" as libc");
+        let (project, err) = typecheck(&mut loader, "./main").unwrap_err();
+        let Either::Right(err) = err else { panic!("Expected a TypeError") };
+        assert_eq!(Some("libc".to_string()), err.suggestion(&project));
+
+        loader.add_file("main.abra", "import \"intrinsics\" as intrinsics");
+        let (project, err) = typecheck(&mut loader, "./main").unwrap_err();
+        let Either::Right(err) = err else { panic!("Expected a TypeError") };
+        assert_eq!(None, err.suggestion(&project));
+    }
+
+    #[test]
+    fn test_overlay_modules() {
+        let dir = std::env::temp_dir().join(format!("abra_virtual_loader_{}", random_string(8)));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.abra"), "import value from \"./dep\"\nval x: Int = value").unwrap();
+        std::fs::write(dir.join("dep.abra"), "export val value = 1").unwrap();
+
+        let mut loader = VirtualModuleLoader::with_fs_fallback(&dir, "/std");
+        add_std(&mut loader);
+        assert!(typecheck(&mut loader, "./main").is_ok());
+
+        // An unsaved buffer shadows the file on disk
+        loader.add_file(dir.join("dep.abra"), "export val value = \"one\"");
+        assert!(typecheck(&mut loader, "./main").is_err());
+
+        // Once the buffer is discarded, the file on disk is used again
+        loader.remove_file(dir.join("dep.abra"));
+        assert!(typecheck(&mut loader, "./main").is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_std_modules_are_not_shadowed_by_manifest() {
+        let dir = std::env::temp_dir().join(format!("abra_virtual_loader_{}", random_string(8)));
+        for (path, contents) in [
+            ("app/abra.toml", "[package]\nname = \"app\"\n"),
+            ("app/src/fs.abra", "export val notFs = 1"),
+            ("app/src/utils.abra", "export val value = 1"),
+            ("app2/abra.toml", "[package]\nname = \"app2\"\n\n[dependencies]\nfs = { path = \"../fs\" }\n"),
+            ("fs/fs.abra", "export val notFs = 1"),
+        ] {
+            std::fs::create_dir_all(dir.join(path).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(path), contents).unwrap();
+        }
+
+        for app in ["app", "app2"] {
+            let mut loader = VirtualModuleLoader::with_fs_fallback(dir.join(app).join("src"), "/std");
+            add_std(&mut loader);
+            loader.add_file("/std/fs.abra", include_str!("../../std/fs.abra"));
+            loader.set_manifest(Some(ResolvedManifest::load(dir.join(app).join("abra.toml")).unwrap()));
+
+            // Neither a project module nor a dependency named `fs` shadows the std library's module
+            loader.add_file("main.abra", "import readFile from \"fs\"\nval f = readFile(\"a.txt\")");
+            assert!(typecheck(&mut loader, "./main").is_ok());
+            loader.add_file("main.abra", "import notFs from \"fs\"");
+            assert!(typecheck(&mut loader, "./main").is_err());
+        }
+
+        // Other modules are still resolved via the manifest
+        let mut loader = VirtualModuleLoader::with_fs_fallback(dir.join("app/src"), "/std");
+        add_std(&mut loader);
+        loader.set_manifest(Some(ResolvedManifest::load(dir.join("app/abra.toml")).unwrap()));
+        loader.add_file("main.abra", "import value from \"utils\"\nval v: Int = value");
+        assert!(typecheck(&mut loader, "./main").is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
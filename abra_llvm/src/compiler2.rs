@@ -1,5 +1,5 @@
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::path::PathBuf;
@@ -171,7 +171,11 @@ impl<'a> LLVMCompiler2<'a> {
     pub fn compile_and_run(entrypoint_module_id: &ModuleId, project: &Project, out_dir: &PathBuf, out_file_name: Option<String>, program_args: &Vec<String>, use_gc: bool) -> ExitStatus {
         let exec_out_file = Self::compile(entrypoint_module_id, project, out_dir, out_file_name, use_gc);
 
-        let mut cmd = Command::new(&exec_out_file);
+        Self::run(&exec_out_file, program_args)
+    }
+
+    pub fn run(exec_out_file: &PathBuf, program_args: &Vec<String>) -> ExitStatus {
+        let mut cmd = Command::new(exec_out_file);
         for arg in program_args {
             cmd.arg(arg);
         }
@@ -925,31 +929,12 @@ impl<'a> LLVMCompiler2<'a> {
 
         let printf = self.main_module.add_function("printf", self.fn_type_variadic(self.i64(), &[self.ptr(self.i8()).into()]), None);
 
-        let mut compiled = HashSet::new();
-        let mut module_queue = VecDeque::new();
-        module_queue.push_back(PRELUDE_MODULE_ID);
-        module_queue.push_back(*entrypoint_module_id);
-
-        while let Some(module_id) = module_queue.pop_front() {
+        // Modules' top-level code is run in the order in which it was typechecked, which guarantees that a module's imported variables
+        // have been initialized, even when modules import each other.
+        for module_id in &project.module_order {
+            let module_id = *module_id;
             let m = &project.modules[module_id.0];
 
-            if compiled.contains(&m.id) { continue; }
-
-            let mut reenqueued = false;
-            for (import_id, _) in &m.imports {
-                if !compiled.contains(import_id) {
-                    if !reenqueued {
-                        reenqueued = true;
-                        module_queue.push_front(module_id);
-                    }
-                    module_queue.push_front(*import_id);
-                }
-            }
-            if reenqueued {
-                continue;
-            }
-            compiled.insert(module_id);
-
             for import in m.imports.iter().flat_map(|(_, imported_values)| imported_values.iter()) {
                 if let ImportedValue::Variable(_, var_id) = import {
                     let var = self.project.get_var_by_id(var_id);
@@ -1,64 +1,236 @@
-use crate::utils::abra_error_to_diagnostic;
-use abra_core::typecheck;
-use tower_lsp::jsonrpc::Result;
+use crate::documents::Documents;
+use crate::utils::{char_col_to_utf16, diagnostic_to_lsp, location_to_lsp, position_from_lsp, position_to_lsp, range_to_lsp};
+use abra_core::common::util::resolve_std_path;
+use abra_core::ide::Analysis;
+use abra_core::ide::code_actions::{CodeAction as AbraCodeAction, TextEdit as AbraTextEdit};
+use abra_core::ide::formatting::{format_document, format_range, on_type_formatting};
+use abra_core::ide::inlay_hints::{InlayHint as AbraInlayHint, InlayHintKind as AbraInlayHintKind};
+use abra_core::ide::completion::{Completion, CompletionKind};
+use abra_core::ide::semantic_tokens::{SemanticToken as AbraSemanticToken, SemanticTokenKind};
+use abra_core::ide::signature_help::SignatureHelp as AbraSignatureHelp;
+use abra_core::ide::symbols::{DocumentSymbol as AbraDocumentSymbol, SymbolKind as AbraSymbolKind};
+use abra_core::lexer::tokens::Range as AbraRange;
+use abra_core::manifest::ResolvedManifest;
+use abra_core::parser::ast::ModuleId;
+use abra_core::typechecker::typechecker2::LoadModule;
+use abra_core::typechecker::virtual_module_loader::VirtualModuleLoader;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Mutex as AsyncMutex;
+use tower_lsp::jsonrpc::{Error, Result};
 use tower_lsp::{Client, LanguageServer};
 use tower_lsp::lsp_types::notification::PublishDiagnostics;
-use tower_lsp::lsp_types::{Url, PublishDiagnosticsParams, InitializeParams, InitializeResult, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, InitializedParams, MessageType, DidOpenTextDocumentParams, TextDocumentItem, DidChangeTextDocumentParams, VersionedTextDocumentIdentifier};
-use abra_core::parser::ast::ModuleId;
-use abra_core::module_loader::{ModuleReader, ModuleLoader};
-use std::path::PathBuf;
-use abra_core::common::fs_module_reader::FsModuleReader;
+use tower_lsp::lsp_types::{Diagnostic as LspDiagnostic, Url, PublishDiagnosticsParams, InitializeParams, InitializeResult, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, InitializedParams, MessageType, DidOpenTextDocumentParams, TextDocumentItem, DidChangeTextDocumentParams, VersionedTextDocumentIdentifier, DidCloseTextDocumentParams, HoverParams, Hover, HoverContents, MarkupContent, MarkupKind, HoverProviderCapability, GotoDefinitionParams, GotoDefinitionResponse, ReferenceParams, Location, OneOf, RenameOptions, WorkDoneProgressOptions, TextDocumentPositionParams, PrepareRenameResponse, RenameParams, WorkspaceEdit, TextEdit, CompletionOptions, CompletionParams, CompletionResponse, CompletionItem, CompletionItemKind, Documentation, SignatureHelpOptions, SignatureHelpParams, SignatureHelp, SignatureInformation, ParameterInformation, ParameterLabel, DocumentSymbolParams, DocumentSymbolResponse, DocumentSymbol, SymbolKind, WorkspaceSymbolParams, SymbolInformation, SemanticTokensServerCapabilities, SemanticTokensOptions, SemanticTokensLegend, SemanticTokenType, SemanticTokenModifier, SemanticTokensFullOptions, SemanticTokensParams, SemanticTokensResult, SemanticTokens, SemanticToken, CodeActionParams, CodeActionResponse, CodeActionOrCommand, CodeAction, CodeActionKind, CodeActionProviderCapability, Range, DocumentFormattingParams, DocumentRangeFormattingParams, DocumentOnTypeFormattingParams, DocumentOnTypeFormattingOptions, InlayHintParams, InlayHint, InlayHintKind, InlayHintLabel};
+
+// The legend of semantic token types, in the order of `SemanticTokenKind`'s variants (which are encoded as indices into it)
+const SEMANTIC_TOKEN_TYPES: [SemanticTokenType; 9] = [
+    SemanticTokenType::NAMESPACE,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::ENUM,
+    SemanticTokenType::ENUM_MEMBER,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::METHOD,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::VARIABLE,
+];
+const MUTABLE_MODIFIER: SemanticTokenModifier = SemanticTokenModifier::new("mutable");
 
-#[derive(Debug)]
 pub struct Backend {
     client: Client,
+    std_path: Mutex<Option<PathBuf>>,
+    // The contents of every open document, which take precedence over the files on disk (so that unsaved edits to an imported
+    // module are seen by the modules which import it)
+    documents: Mutex<Documents>,
+    // The diagnostics which were last published to each file, so that they're only published again when they change (and are cleared
+    // once fixed)
+    published: Mutex<HashMap<Url, Vec<LspDiagnostic>>>,
+    // Each analysis has its own lock, which is held while it's typechecked again, so that requests against one document wait for its
+    // analysis to be up to date without holding up requests against the others
+    analyses: Mutex<HashMap<Url, Arc<AsyncMutex<Analysis>>>>,
 }
 
 impl Backend {
     pub fn new(client: Client) -> Self {
-        Self { client }
-    }
-
-    async fn get_diagnostics(&self, uri: Url, version: Option<i64>, text: String) -> PublishDiagnosticsParams {
-        let path = uri.to_file_path().unwrap();
-        let parent = path.parent().unwrap().to_path_buf();
-        let module_path = path.file_name().unwrap().to_str().unwrap().to_string();
-        let module_id = ModuleId::parse_module_path(&format!("./{}", module_path)).unwrap();
-
-        let mut module_reader = FsModuleReader::new(module_id.clone(), &parent);
-        let mut loader = ModuleLoader::new(&mut module_reader);
-
-        match typecheck(module_id, &text, &mut loader) {
-            Ok(_) =>  PublishDiagnosticsParams { uri, version, diagnostics: vec![] },
-            Err(e) => {
-                let file_name = PathBuf::from(module_reader.get_module_name(&e.module_id()))
-                    .with_extension("abra")
-                    .canonicalize()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-                let diagnostic = abra_error_to_diagnostic(e, &file_name, &text);
-
-                let uri = Url::from_file_path(file_name).unwrap();
-                PublishDiagnosticsParams { uri, version, diagnostics: vec![diagnostic] }
+        Self { client, std_path: Mutex::new(None), documents: Mutex::new(Documents::default()), published: Mutex::new(HashMap::new()), analyses: Mutex::new(HashMap::new()) }
+    }
+
+    // A loader which resolves modules exactly as `abra build` would for the document (against the std library, and with the enclosing
+    // project's abra.toml), along with the document's path and module id
+    fn loader(&self, uri: &Url) -> Option<(VirtualModuleLoader, PathBuf, ModuleId)> {
+        let std_path = self.std_path.lock().unwrap().clone()?;
+        let path = uri.to_file_path().ok()?;
+        let root = path.parent()?.to_path_buf();
+        let module_id = document_module_id(&path)?;
+
+        let mut loader = VirtualModuleLoader::with_fs_fallback(&root, &std_path);
+        loader.set_manifest(ResolvedManifest::find(&root).ok().flatten());
+        for (uri, text) in self.documents.lock().unwrap().iter() {
+            if let Ok(path) = uri.to_file_path() {
+                loader.add_file(path, text);
             }
         }
+
+        Some((loader, path, module_id))
+    }
+
+    // The document's current text, along with its module id
+    fn document_source(&self, uri: &Url) -> Option<(ModuleId, String)> {
+        let text = self.documents.lock().unwrap().get(uri).cloned()?;
+        let module_id = document_module_id(&uri.to_file_path().ok()?)?;
+        Some((module_id, text))
+    }
+
+    fn analysis(&self, uri: &Url) -> Option<Arc<AsyncMutex<Analysis>>> {
+        self.analyses.lock().unwrap().get(uri).cloned()
+    }
+
+    fn all_analyses(&self) -> Vec<(Url, Arc<AsyncMutex<Analysis>>)> {
+        self.analyses.lock().unwrap().iter().map(|(uri, analysis)| (uri.clone(), analysis.clone())).collect()
+    }
+
+    // The analysis is kept so that later requests (eg. hover) can be answered from it. Typechecking is done on the blocking thread
+    // pool, as is typechecking again in `update_analyses`.
+    async fn analyze(&self, uri: &Url) {
+        let Some((loader, _, module_id)) = self.loader(uri) else { return; };
+
+        let Ok(analysis) = tokio::task::spawn_blocking(move || Analysis::analyze(loader, &module_id)).await else { return; };
+        self.analyses.lock().unwrap().insert(uri.clone(), Arc::new(AsyncMutex::new(analysis)));
     }
+
+    // Gives the document's current contents (or, once it's been closed, none, so that its file on disk is seen again) to every
+    // analysis, typechecking again only the analyses whose modules depend on it (and within them, only the affected modules). The
+    // contents are read once the analysis is locked, so that if several changes are being handled at once the latest always wins.
+    async fn update_analyses(&self, uri: &Url) {
+        let Ok(path) = uri.to_file_path() else { return; };
+
+        for (analysis_uri, analysis) in self.all_analyses() {
+            let Some(module_id) = analysis_uri.to_file_path().ok().and_then(|path| document_module_id(&path)) else { continue; };
+            let mut analysis = analysis.lock_owned().await;
+            let text = self.documents.lock().unwrap().get(uri).cloned();
+
+            let path = path.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                match text {
+                    Some(text) => analysis.loader.add_file(&path, &text),
+                    None => { analysis.loader.remove_file(&path); }
+                }
+                analysis.reanalyze(&module_id, &[&path]);
+            }).await;
+        }
+    }
+
+    // Errors may be in a different file than the document whose analysis found them (eg. an imported module, or the std library), in
+    // which case they're published to that file. Every open document has its own analysis, so the diagnostics of all of them are gathered
+    // (without duplicates, since several documents may import the same broken module), and then only the files whose diagnostics have
+    // changed (along with the edited document, to acknowledge its version) are published.
+    async fn get_diagnostics(&self, uri: &Url, version: Option<i32>) -> Vec<PublishDiagnosticsParams> {
+        let mut diagnostics = HashMap::<Url, Vec<LspDiagnostic>>::new();
+        for (analysis_uri, analysis) in self.all_analyses() {
+            let analysis = analysis.lock().await;
+            let analysis = &*analysis;
+            diagnostics.entry(analysis_uri.clone()).or_default();
+            let Some(diagnostic) = analysis.diagnostic() else { continue; };
+            let file_uri = Url::from_file_path(&diagnostic.file).unwrap_or_else(|_| analysis_uri.clone());
+
+            let file_diagnostics = diagnostics.entry(file_uri).or_default();
+            let diagnostic = diagnostic_to_lsp(&diagnostic, |file| file_text(analysis, file));
+            if !file_diagnostics.contains(&diagnostic) {
+                file_diagnostics.push(diagnostic);
+            }
+        }
+
+        let mut published = self.published.lock().unwrap();
+        for stale_uri in published.keys() {
+            diagnostics.entry(stale_uri.clone()).or_default();
+        }
+
+        let mut params = vec![];
+        for (file_uri, file_diagnostics) in diagnostics {
+            let is_changed = published.get(&file_uri).map_or(&[][..], |d| d.as_slice()) != file_diagnostics.as_slice();
+            if !is_changed && &file_uri != uri { continue; }
+
+            let version = if &file_uri == uri { version } else { None };
+            if file_diagnostics.is_empty() {
+                published.remove(&file_uri);
+            } else {
+                published.insert(file_uri.clone(), file_diagnostics.clone());
+            }
+            params.push(PublishDiagnosticsParams { uri: file_uri, version, diagnostics: file_diagnostics });
+        }
+
+        params
+    }
+
+    async fn publish_diagnostics(&self, uri: Url, version: Option<i32>) {
+        for diagnostics in self.get_diagnostics(&uri, version).await {
+            self.client.send_notification::<PublishDiagnostics>(diagnostics).await;
+        }
+    }
+}
+
+// The text which positions in the file are relative to: its contents as the analysis sees them (which, for an open document, are its
+// current text)
+fn file_text(analysis: &Analysis, file: &str) -> String {
+    analysis.loader.load_file(&file.to_string()).unwrap_or_default()
+}
+
+fn document_text(analysis: &Analysis, uri: &Url) -> String {
+    uri.to_file_path().ok().and_then(|path| path.to_str().map(|path| file_text(analysis, path))).unwrap_or_default()
+}
+
+fn document_module_id(path: &Path) -> Option<ModuleId> {
+    let module_name = path.file_name()?.to_str()?;
+    ModuleId::parse_module_path(&format!("./{}", module_name))
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        // The std library can be configured by the client (like `--std` in the cli), and is otherwise resolved the same way as the cli
+        let std_path = params.initialization_options.as_ref()
+            .and_then(|options| options.get("stdPath"))
+            .and_then(|path| path.as_str());
+        *self.std_path.lock().unwrap() = resolve_std_path(std_path).ok();
+
         let capabilities = ServerCapabilities {
-            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::Full)),
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::INCREMENTAL)),
+            hover_provider: Some(HoverProviderCapability::Simple(true)),
+            definition_provider: Some(OneOf::Left(true)),
+            references_provider: Some(OneOf::Left(true)),
+            rename_provider: Some(OneOf::Right(RenameOptions { prepare_provider: Some(true), work_done_progress_options: WorkDoneProgressOptions::default() })),
+            completion_provider: Some(CompletionOptions { trigger_characters: Some(vec![".".to_string(), "\"".to_string(), "/".to_string()]), ..CompletionOptions::default() }),
+            signature_help_provider: Some(SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                retrigger_characters: None,
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
+            document_symbol_provider: Some(OneOf::Left(true)),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
+            semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+                legend: SemanticTokensLegend { token_types: SEMANTIC_TOKEN_TYPES.to_vec(), token_modifiers: vec![MUTABLE_MODIFIER] },
+                range: None,
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+            })),
+            inlay_hint_provider: Some(OneOf::Left(true)),
+            code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+            document_formatting_provider: Some(OneOf::Left(true)),
+            document_range_formatting_provider: Some(OneOf::Left(true)),
+            document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions { first_trigger_character: "}".to_string(), more_trigger_character: Some(vec!["\n".to_string()]) }),
             ..ServerCapabilities::default()
         };
         Ok(InitializeResult { capabilities, ..InitializeResult::default() })
     }
 
     async fn initialized(&self, _: InitializedParams) {
-        self.client.log_message(MessageType::Info, "abra language server initialized").await;
+        self.client.log_message(MessageType::INFO, "abra language server initialized").await;
+
+        let has_std_path = self.std_path.lock().unwrap().is_some();
+        if !has_std_path {
+            self.client.show_message(MessageType::ERROR, "Could not find the abra std library; set the stdPath initialization option").await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -67,16 +239,475 @@ impl LanguageServer for Backend {
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let TextDocumentItem { uri, version, text, .. } = params.text_document;
+        self.documents.lock().unwrap().open(uri.clone(), text);
+        self.update_analyses(&uri).await;
+        self.analyze(&uri).await;
 
-        let diagnostics = self.get_diagnostics(uri, Some(version), text).await;
-        self.client.send_custom_notification::<PublishDiagnostics>(diagnostics).await;
+        self.publish_diagnostics(uri, Some(version)).await;
     }
 
-    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
         let VersionedTextDocumentIdentifier { uri, version } = params.text_document;
-        let text = if let Some(contents) = params.content_changes.pop() { contents.text } else { return; };
+        if self.documents.lock().unwrap().change(&uri, params.content_changes).is_none() { return; }
+        self.update_analyses(&uri).await;
+
+        self.publish_diagnostics(uri, Some(version)).await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.lock().unwrap().close(&uri);
+        self.analyses.lock().unwrap().remove(&uri);
+        self.update_analyses(&uri).await;
+
+        self.publish_diagnostics(uri, None).await;
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params;
+        let Some(analysis) = self.analysis(&position.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+        let text = document_text(analysis, &position.text_document.uri);
+        let Some(hover) = analysis.hover(&position_from_lsp(&text, &position.position)) else { return Ok(None); };
+
+        let mut value = format!("```abra\n{}\n```", hover.signature);
+        if let Some(doc) = hover.doc {
+            value.push_str(&format!("\n\n{}", doc));
+        }
+        let contents = HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value });
+        Ok(Some(Hover { contents, range: Some(range_to_lsp(&text, &hover.range)) }))
+    }
+
+    async fn goto_definition(&self, params: GotoDefinitionParams) -> Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let Some(analysis) = self.analysis(&position.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+
+        let text = document_text(analysis, &position.text_document.uri);
+        let location = analysis.definition(&position_from_lsp(&text, &position.position))
+            .and_then(|location| location_to_lsp(&location, |file| file_text(analysis, file)));
+        Ok(location.map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let position = params.text_document_position;
+        let Some(analysis) = self.analysis(&position.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+
+        let text = document_text(analysis, &position.text_document.uri);
+        let locations = analysis.references(&position_from_lsp(&text, &position.position), params.context.include_declaration).iter()
+            .filter_map(|location| location_to_lsp(location, |file| file_text(analysis, file)))
+            .collect();
+        Ok(Some(locations))
+    }
+
+    async fn prepare_rename(&self, params: TextDocumentPositionParams) -> Result<Option<PrepareRenameResponse>> {
+        let Some(analysis) = self.analysis(&params.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+
+        let text = document_text(analysis, &params.text_document.uri);
+        match analysis.prepare_rename(&position_from_lsp(&text, &params.position)) {
+            Ok(range) => Ok(Some(PrepareRenameResponse::Range(range_to_lsp(&text, &range)))),
+            Err(e) => Err(Error::invalid_params(e.message())),
+        }
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let position = params.text_document_position;
+        let Some(analysis) = self.analysis(&position.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+
+        let text = document_text(analysis, &position.text_document.uri);
+        let locations = analysis.rename(&position_from_lsp(&text, &position.position), &params.new_name).map_err(|e| Error::invalid_params(e.message()))?;
+        let mut changes = HashMap::<Url, Vec<TextEdit>>::new();
+        for location in locations.iter().filter_map(|location| location_to_lsp(location, |file| file_text(analysis, file))) {
+            changes.entry(location.uri).or_default().push(TextEdit::new(location.range, params.new_name.clone()));
+        }
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    // Unlike other requests, completions are computed from the document's current text (which likely doesn't typecheck) rather than
+    // from its last analysis
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        let Some(text) = self.documents.lock().unwrap().get(&position.text_document.uri).cloned() else { return Ok(None); };
+        let Some((loader, path, module_id)) = self.loader(&position.text_document.uri) else { return Ok(None); };
+        let Some(path) = path.to_str() else { return Ok(None); };
+
+        let completions = Analysis::completions(loader, &module_id, path, &text, &position_from_lsp(&text, &position.position));
+        let items = completions.into_iter().map(completion_to_lsp).collect();
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    // Like completions, signature help is computed from the document's current text
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let position = params.text_document_position_params;
+        let Some(text) = self.documents.lock().unwrap().get(&position.text_document.uri).cloned() else { return Ok(None); };
+        let Some((loader, path, module_id)) = self.loader(&position.text_document.uri) else { return Ok(None); };
+        let Some(path) = path.to_str() else { return Ok(None); };
+
+        let help = Analysis::signature_help(loader, &module_id, path, &text, &position_from_lsp(&text, &position.position));
+        Ok(help.map(signature_help_to_lsp))
+    }
+
+    async fn document_symbol(&self, params: DocumentSymbolParams) -> Result<Option<DocumentSymbolResponse>> {
+        let Some(analysis) = self.analysis(&params.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+
+        let text = document_text(analysis, &params.text_document.uri);
+        let symbols = analysis.document_symbols().into_iter().map(|symbol| document_symbol_to_lsp(&text, symbol)).collect();
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+    }
 
-        let diagnostics = self.get_diagnostics(uri, version, text).await;
-        self.client.send_custom_notification::<PublishDiagnostics>(diagnostics).await;
+    // Every open document has its own analysis, so the symbols of modules which several of them import are only included once
+    async fn symbol(&self, params: WorkspaceSymbolParams) -> Result<Option<Vec<SymbolInformation>>> {
+        let mut seen = HashSet::new();
+        let mut symbols = vec![];
+        for (_, analysis) in self.all_analyses() {
+            let analysis = &*analysis.lock().await;
+            for symbol in analysis.workspace_symbols(&params.query) {
+                let start = &symbol.location.range.start;
+                if !seen.insert((symbol.location.file.clone(), start.line, start.col)) { continue; }
+                let Some(location) = location_to_lsp(&symbol.location, |file| file_text(analysis, file)) else { continue; };
+
+                #[allow(deprecated)]
+                symbols.push(SymbolInformation { name: symbol.name, kind: symbol_kind_to_lsp(symbol.kind), tags: None, deprecated: None, location, container_name: symbol.container_name });
+            }
+        }
+        Ok(Some(symbols))
+    }
+
+    async fn semantic_tokens_full(&self, params: SemanticTokensParams) -> Result<Option<SemanticTokensResult>> {
+        let Some(analysis) = self.analysis(&params.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+
+        let text = document_text(analysis, &params.text_document.uri);
+        let data = semantic_tokens_to_lsp(&text, &analysis.semantic_tokens());
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens { result_id: None, data })))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let Some(analysis) = self.analysis(&params.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+
+        let text = document_text(analysis, &params.text_document.uri);
+        let range = AbraRange { start: position_from_lsp(&text, &params.range.start), end: position_from_lsp(&text, &params.range.end) };
+        let hints = analysis.inlay_hints(&range).into_iter().map(|hint| inlay_hint_to_lsp(&text, hint)).collect();
+        Ok(Some(hints))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let Some(analysis) = self.analysis(&params.text_document.uri) else { return Ok(None); };
+        let analysis = &*analysis.lock().await;
+        let Ok(path) = params.text_document.uri.to_file_path() else { return Ok(None); };
+        let Some(path) = path.to_str() else { return Ok(None); };
+
+        let text = document_text(analysis, &params.text_document.uri);
+        let range = AbraRange { start: position_from_lsp(&text, &params.range.start), end: position_from_lsp(&text, &params.range.end) };
+        let diagnostic = analysis.diagnostic();
+        let actions = analysis.code_actions(path, &range).into_iter()
+            .map(|action| {
+                // An action is only associated with the diagnostic if it fixes the very error which the diagnostic reports
+                let fixed = diagnostic.as_ref().filter(|diagnostic| {
+                    action.fixes.as_ref().map_or(false, |(range, code)| diagnostic.range == *range && diagnostic.code.as_deref() == Some(*code))
+                });
+                let fixed = fixed.map(|diagnostic| diagnostic_to_lsp(diagnostic, |file| file_text(analysis, file)));
+                CodeActionOrCommand::CodeAction(code_action_to_lsp(&text, action, &params.text_document.uri, fixed))
+            })
+            .collect();
+        Ok(Some(actions))
+    }
+
+    // Formatting applies to the document's current text, and is only possible if it parses. The formatting options are ignored, since
+    // the layout is canonical.
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let Some((module_id, text)) = self.document_source(&params.text_document.uri) else { return Ok(None); };
+
+        let edit = format_document(&module_id, &text).ok().flatten();
+        Ok(Some(edit.into_iter().map(|edit| text_edit_to_lsp(&text, edit)).collect()))
+    }
+
+    async fn range_formatting(&self, params: DocumentRangeFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let Some((module_id, text)) = self.document_source(&params.text_document.uri) else { return Ok(None); };
+
+        let range = AbraRange { start: position_from_lsp(&text, &params.range.start), end: position_from_lsp(&text, &params.range.end) };
+        let edit = format_range(&module_id, &text, &range).ok().flatten();
+        Ok(Some(edit.into_iter().map(|edit| text_edit_to_lsp(&text, edit)).collect()))
+    }
+
+    async fn on_type_formatting(&self, params: DocumentOnTypeFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let position = params.text_document_position;
+        let Some(text) = self.documents.lock().unwrap().get(&position.text_document.uri).cloned() else { return Ok(None); };
+        let Some(ch) = params.ch.chars().next() else { return Ok(None); };
+
+        let edit = on_type_formatting(&text, &position_from_lsp(&text, &position.position), ch);
+        Ok(Some(edit.into_iter().map(|edit| text_edit_to_lsp(&text, edit)).collect()))
+    }
+}
+
+fn text_edit_to_lsp(text: &str, edit: AbraTextEdit) -> TextEdit {
+    TextEdit::new(Range::new(position_to_lsp(text, &edit.start), position_to_lsp(text, &edit.end)), edit.new_text)
+}
+
+fn code_action_to_lsp(text: &str, action: AbraCodeAction, uri: &Url, diagnostic: Option<LspDiagnostic>) -> CodeAction {
+    let edits = action.edits.into_iter().map(|edit| text_edit_to_lsp(text, edit)).collect();
+    let mut changes = HashMap::new();
+    changes.insert(uri.clone(), edits);
+
+    CodeAction {
+        title: action.title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: diagnostic.map(|diagnostic| vec![diagnostic]),
+        edit: Some(WorkspaceEdit::new(changes)),
+        ..CodeAction::default()
+    }
+}
+
+fn completion_to_lsp(completion: Completion) -> CompletionItem {
+    let kind = match completion.kind {
+        CompletionKind::Variable => CompletionItemKind::VARIABLE,
+        CompletionKind::Function => CompletionItemKind::FUNCTION,
+        CompletionKind::Method => CompletionItemKind::METHOD,
+        CompletionKind::Field => CompletionItemKind::FIELD,
+        CompletionKind::Struct => CompletionItemKind::STRUCT,
+        CompletionKind::Enum => CompletionItemKind::ENUM,
+        CompletionKind::EnumVariant => CompletionItemKind::ENUM_MEMBER,
+        CompletionKind::TypeParameter => CompletionItemKind::TYPE_PARAMETER,
+        CompletionKind::Module => CompletionItemKind::MODULE,
+        CompletionKind::Folder => CompletionItemKind::FOLDER,
+    };
+    let documentation = completion.doc.map(|doc| Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value: doc }));
+
+    CompletionItem { label: completion.label, kind: Some(kind), detail: completion.detail, documentation, ..CompletionItem::default() }
+}
+
+fn signature_help_to_lsp(help: AbraSignatureHelp) -> SignatureHelp {
+    let AbraSignatureHelp { signature, active_param } = help;
+    let parameters = signature.params.iter()
+        .map(|param| {
+            let (start, end) = param.label_offsets;
+            let offsets = [char_col_to_utf16(&signature.label, start), char_col_to_utf16(&signature.label, end)];
+            ParameterInformation { label: ParameterLabel::LabelOffsets(offsets), documentation: None }
+        })
+        .collect();
+    let documentation = signature.doc.map(|doc| Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value: doc }));
+    let signature = SignatureInformation { label: signature.label, documentation, parameters: Some(parameters), active_parameter: None };
+
+    SignatureHelp { signatures: vec![signature], active_signature: Some(0), active_parameter: active_param.map(|idx| idx as u32) }
+}
+
+// The labels include their own spacing, so no padding is needed
+fn inlay_hint_to_lsp(text: &str, hint: AbraInlayHint) -> InlayHint {
+    let kind = match hint.kind {
+        AbraInlayHintKind::Type => InlayHintKind::TYPE,
+        AbraInlayHintKind::Parameter => InlayHintKind::PARAMETER,
+    };
+
+    InlayHint {
+        position: position_to_lsp(text, &hint.position),
+        label: InlayHintLabel::String(hint.label),
+        kind: Some(kind),
+        text_edits: None,
+        tooltip: None,
+        padding_left: None,
+        padding_right: None,
+        data: None,
+    }
+}
+
+fn symbol_kind_to_lsp(kind: AbraSymbolKind) -> SymbolKind {
+    match kind {
+        AbraSymbolKind::Struct => SymbolKind::STRUCT,
+        AbraSymbolKind::Enum => SymbolKind::ENUM,
+        AbraSymbolKind::EnumVariant => SymbolKind::ENUM_MEMBER,
+        AbraSymbolKind::Field => SymbolKind::FIELD,
+        AbraSymbolKind::Function => SymbolKind::FUNCTION,
+        AbraSymbolKind::Method => SymbolKind::METHOD,
+        AbraSymbolKind::Variable => SymbolKind::VARIABLE,
+    }
+}
+
+fn document_symbol_to_lsp(text: &str, symbol: AbraDocumentSymbol) -> DocumentSymbol {
+    let children = symbol.children.into_iter().map(|child| document_symbol_to_lsp(text, child)).collect::<Vec<_>>();
+
+    // The `deprecated` field is superseded by `tags`, but must still be given
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: symbol.name,
+        detail: Some(symbol.detail),
+        kind: symbol_kind_to_lsp(symbol.kind),
+        tags: None,
+        deprecated: None,
+        range: range_to_lsp(text, &symbol.range),
+        selection_range: range_to_lsp(text, &symbol.selection_range),
+        children: if children.is_empty() { None } else { Some(children) },
+    }
+}
+
+// Each token's position is encoded relative to the previous token's (and, like other lsp positions, in utf-16 code units)
+fn semantic_tokens_to_lsp(text: &str, tokens: &[AbraSemanticToken]) -> Vec<SemanticToken> {
+    let mut prev_line = 0;
+    let mut prev_start = 0;
+    tokens.iter()
+        .map(|token| {
+            let range = range_to_lsp(text, &token.range);
+            let line = range.start.line;
+            let start = range.start.character;
+            let delta_start = if line == prev_line { start - prev_start } else { start };
+            let delta_line = line - prev_line;
+            prev_line = line;
+            prev_start = start;
+
+            let token_type = match token.kind {
+                SemanticTokenKind::Namespace => 0,
+                SemanticTokenKind::Type => 1,
+                SemanticTokenKind::Enum => 2,
+                SemanticTokenKind::EnumMember => 3,
+                SemanticTokenKind::Property => 4,
+                SemanticTokenKind::Function => 5,
+                SemanticTokenKind::Method => 6,
+                SemanticTokenKind::Parameter => 7,
+                SemanticTokenKind::Variable => 8,
+            };
+            let length = range.end.character - range.start.character;
+            let token_modifiers_bitset = if token.is_mutable { 1 } else { 0 };
+
+            SemanticToken { delta_line, delta_start, length, token_type, token_modifiers_bitset }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::Backend;
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use tower::{Service, ServiceExt};
+    use tower_lsp::{LanguageServer, LspService};
+    use tower_lsp::jsonrpc::Request;
+    use tower_lsp::lsp_types::{DiagnosticSeverity, DidChangeTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, InlayHint, InlayHintLabel, InlayHintParams, NumberOrString, PartialResultParams, Position, PublishDiagnosticsParams, Range, ReferenceContext, ReferenceParams, TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier, WorkDoneProgressParams};
+
+    type Notifications = Arc<Mutex<Vec<Request>>>;
+
+    // A server which has been initialized (with the repo's own std library), as an editor would initialize it. Documents are opened
+    // through `LspService::inner`. The notifications sent to the client must be read as they're sent (otherwise sending them blocks),
+    // so they're collected as they arrive.
+    async fn start_server() -> (LspService<Backend>, Notifications) {
+        let (mut service, socket) = LspService::new(Backend::new);
+        let notifications = Notifications::default();
+        let received = notifications.clone();
+        tokio::spawn(socket.for_each(move |notification| {
+            received.lock().unwrap().push(notification);
+            async {}
+        }));
+
+        let std_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../abra_core/std");
+        let initialize = Request::build("initialize").params(json!({ "capabilities": {}, "initializationOptions": { "stdPath": std_path } })).id(1).finish();
+        service.ready().await.unwrap().call(initialize).await.unwrap();
+        service.ready().await.unwrap().call(Request::build("initialized").params(json!({})).finish()).await.unwrap();
+
+        (service, notifications)
+    }
+
+    fn uri(path: &str) -> Url {
+        Url::from_file_path(path).unwrap()
+    }
+
+    async fn open(service: &LspService<Backend>, path: &str, text: &str) {
+        let text_document = TextDocumentItem { uri: uri(path), language_id: "abra".to_string(), version: 1, text: text.to_string() };
+        service.inner().did_open(DidOpenTextDocumentParams { text_document }).await;
+    }
+
+    // The notifications which have been sent to the client since they were last taken (the task collecting them is given the chance
+    // to receive any which are still in flight first)
+    async fn take(notifications: &Notifications) -> Vec<Request> {
+        tokio::task::yield_now().await;
+        std::mem::take(&mut *notifications.lock().unwrap())
+    }
+
+    async fn published_diagnostics(notifications: &Notifications) -> Vec<PublishDiagnosticsParams> {
+        take(notifications).await.into_iter()
+            .filter(|notification| notification.method() == "textDocument/publishDiagnostics")
+            .map(|notification| serde_json::from_value(notification.params().unwrap().clone()).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_publish_diagnostics() {
+        let (service, notifications) = start_server().await;
+        open(&service, "/project/main.abra", "val x = [\"😀\"].lenght").await;
+
+        let published = published_diagnostics(&notifications).await;
+        assert_eq!(1, published.len());
+        let PublishDiagnosticsParams { uri: published_uri, diagnostics, version } = &published[0];
+        assert_eq!((&uri("/project/main.abra"), Some(1)), (published_uri, *version));
+        assert_eq!(1, diagnostics.len());
+        // The emoji before the error is 2 utf-16 code units, so the error's range is 1 column further along than its char columns
+        assert_eq!(Range::new(Position::new(0, 15), Position::new(0, 21)), diagnostics[0].range);
+        assert_eq!(Some(DiagnosticSeverity::ERROR), diagnostics[0].severity);
+        assert_eq!(Some(NumberOrString::String("E0225".to_string())), diagnostics[0].code);
+
+        // Once the error is fixed the diagnostics are cleared, acknowledging the new version
+        let params = DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier { uri: uri("/project/main.abra"), version: 2 },
+            content_changes: vec![TextDocumentContentChangeEvent { range: None, range_length: None, text: "val x = [\"😀\"].length".to_string() }],
+        };
+        service.inner().did_change(params).await;
+        let expected = vec![PublishDiagnosticsParams { uri: uri("/project/main.abra"), diagnostics: vec![], version: Some(2) }];
+        assert_eq!(expected, published_diagnostics(&notifications).await);
+    }
+
+    #[tokio::test]
+    async fn test_position_mapping() {
+        let (service, _) = start_server().await;
+        open(&service, "/project/main.abra", "func id(s: String): String = s\nval x = id(\"😀\") + id(\"é\")").await;
+
+        // The second call to `id` is at char column 18, but utf-16 column 19 (since the emoji before it is 2 code units)
+        let position = TextDocumentPositionParams { text_document: TextDocumentIdentifier { uri: uri("/project/main.abra") }, position: Position::new(1, 19) };
+        let params = ReferenceParams {
+            text_document_position: position.clone(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: ReferenceContext { include_declaration: true },
+        };
+        let mut ranges = service.inner().references(params).await.unwrap().unwrap().into_iter().map(|location| location.range).collect::<Vec<_>>();
+        ranges.sort_by_key(|range| (range.start.line, range.start.character));
+        let expected = vec![
+            Range::new(Position::new(0, 5), Position::new(0, 7)),
+            Range::new(Position::new(1, 8), Position::new(1, 10)),
+            Range::new(Position::new(1, 19), Position::new(1, 21)),
+        ];
+        assert_eq!(expected, ranges);
+
+        let params = GotoDefinitionParams { text_document_position_params: position, work_done_progress_params: WorkDoneProgressParams::default(), partial_result_params: PartialResultParams::default() };
+        let Some(GotoDefinitionResponse::Scalar(location)) = service.inner().goto_definition(params).await.unwrap() else { panic!("Expected a definition"); };
+        assert_eq!(Range::new(Position::new(0, 5), Position::new(0, 7)), location.range);
+    }
+
+    #[tokio::test]
+    async fn test_inlay_hints() {
+        let (service, _) = start_server().await;
+        open(&service, "/project/main.abra", "func f(s: String, n: Int): Int = n\nval x = f(\"😀\", 1)").await;
+
+        // The emoji is 2 utf-16 code units, so the hint for the argument after it is 1 column further along than its char column
+        let params = InlayHintParams {
+            text_document: TextDocumentIdentifier { uri: uri("/project/main.abra") },
+            range: Range::new(Position::new(1, 0), Position::new(2, 0)),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let hints = service.inner().inlay_hint(params).await.unwrap().unwrap().into_iter()
+            .map(|InlayHint { position, label, .. }| match label {
+                InlayHintLabel::String(label) => (position, label),
+                InlayHintLabel::LabelParts(_) => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let expected = vec![
+            (Position::new(1, 5), ": Int".to_string()),
+            (Position::new(1, 10), "s: ".to_string()),
+            (Position::new(1, 16), "n: ".to_string()),
+        ];
+        assert_eq!(expected, hints);
     }
 }
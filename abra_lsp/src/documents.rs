@@ -0,0 +1,108 @@
+use crate::utils::utf16_col_to_chars;
+use std::collections::HashMap;
+use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent, Url};
+
+// The contents of every open document, kept up to date by applying the edits which the client sends as the documents change
+#[derive(Default)]
+pub struct Documents {
+    texts: HashMap<Url, String>,
+}
+
+impl Documents {
+    pub fn open(&mut self, uri: Url, text: String) {
+        self.texts.insert(uri, text);
+    }
+
+    // The changes are applied in order, each to the text which results from the ones before it
+    pub fn change(&mut self, uri: &Url, changes: Vec<TextDocumentContentChangeEvent>) -> Option<&String> {
+        let text = self.texts.get_mut(uri)?;
+        for change in changes {
+            apply_change(text, change);
+        }
+        Some(text)
+    }
+
+    pub fn close(&mut self, uri: &Url) -> Option<String> {
+        self.texts.remove(uri)
+    }
+
+    pub fn get(&self, uri: &Url) -> Option<&String> {
+        self.texts.get(uri)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Url, &String)> {
+        self.texts.iter()
+    }
+}
+
+// A change without a range replaces the whole text
+fn apply_change(text: &mut String, change: TextDocumentContentChangeEvent) {
+    match change.range {
+        Some(range) => {
+            let start = byte_offset(text, &range.start);
+            let end = byte_offset(text, &range.end).max(start);
+            text.replace_range(start..end, &change.text);
+        }
+        None => *text = change.text,
+    }
+}
+
+// Lsp positions count utf-16 code units within a line. Positions past the end of a line are taken to be at its end, and positions past
+// the last line at the end of the text.
+fn byte_offset(text: &str, position: &Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(idx) => line_start += idx + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line = &text[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    let col = utf16_col_to_chars(line, position.character);
+    line_start + line.char_indices().nth(col).map_or(line.len(), |(idx, _)| idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::documents::Documents;
+    use tower_lsp::lsp_types::{Position, Range, TextDocumentContentChangeEvent, Url};
+
+    fn change(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+        let range = Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1));
+        TextDocumentContentChangeEvent { range: Some(range), range_length: None, text: text.to_string() }
+    }
+
+    #[test]
+    fn test_incremental_changes() {
+        let uri = Url::from_file_path("/project/main.abra").unwrap();
+        let mut documents = Documents::default();
+        documents.open(uri.clone(), "val x = 1\nval y = x + 2\n".to_string());
+
+        let changes = vec![
+            change((0, 8), (0, 9), "24"),
+            change((1, 0), (1, 0), "// comment\n"),
+            change((2, 12), (2, 13), "3"),
+        ];
+        assert_eq!("val x = 24\n// comment\nval y = x + 3\n", documents.change(&uri, changes).unwrap());
+
+        // Edits may span lines, and extend past the end of the text
+        let changes = vec![change((0, 9), (1, 10), ""), change((1, 13), (5, 0), "")];
+        assert_eq!("val x = 2\nval y = x + 3", documents.change(&uri, changes).unwrap());
+
+        let changes = vec![TextDocumentContentChangeEvent { range: None, range_length: None, text: "val z = 3".to_string() }];
+        assert_eq!("val z = 3", documents.change(&uri, changes).unwrap());
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        let uri = Url::from_file_path("/project/main.abra").unwrap();
+        let mut documents = Documents::default();
+        documents.open(uri.clone(), "val s = \"😀é\" + x".to_string());
+
+        // The emoji is 2 utf-16 code units (and 4 bytes), and the accented char is 1 (and 2 bytes)
+        let changes = vec![change((0, 11), (0, 12), "e"), change((0, 16), (0, 17), "y")];
+        assert_eq!("val s = \"😀e\" + y", documents.change(&uri, changes).unwrap());
+    }
+}
@@ -2,6 +2,7 @@ use crate::backend::Backend;
 use tower_lsp::{LspService, Server};
 
 mod backend;
+mod documents;
 mod utils;
 
 #[tokio::main]
@@ -9,9 +10,8 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, messages) = LspService::new(Backend::new);
-    Server::new(stdin, stdout)
-        .interleave(messages)
+    let (service, socket) = LspService::new(Backend::new);
+    Server::new(stdin, stdout, socket)
         .serve(service)
         .await;
 }
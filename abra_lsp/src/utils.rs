@@ -1,30 +1,121 @@
-use abra_core::common::display_error::DisplayError;
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
-use abra_core::parser::parse_error::ParseErrorKind;
-
-pub fn abra_error_to_diagnostic(e: abra_core::Error, file_name: &String, source: &String) -> Diagnostic {
-    let range = match &e {
-        abra_core::Error::LexerError(e) => e.get_range(),
-        abra_core::Error::TypecheckerError(e) => e.get_token().get_range(),
-        abra_core::Error::ParseError(e) => match &e.kind {
-            ParseErrorKind::UnexpectedEof(range) => range.clone(),
-            ParseErrorKind::UnexpectedToken(tok) |
-            ParseErrorKind::ExpectedToken(_, tok) |
-            ParseErrorKind::ExpectedOneOf(_, tok) |
-            ParseErrorKind::InvalidImportPath(tok) => tok.get_range()
+use abra_core::common::diagnostic::{Diagnostic as AbraDiagnostic, Severity};
+use abra_core::ide::references::Location as AbraLocation;
+use abra_core::lexer::tokens::{Position as AbraPosition, Range as AbraRange};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Position, Range, Url};
+
+// Lsp columns count utf-16 code units, whereas abra columns count chars, so converting between them requires the text of the line.
+// Columns past the end of the line (or of a file whose text isn't known) are converted as if every char past the end were 1 unit.
+pub fn utf16_col_to_chars(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (idx, ch) in line.chars().enumerate() {
+        if units >= character {
+            return idx;
         }
-        abra_core::Error::InterpretError(_) => unreachable!()
-    };
+        units += ch.len_utf16() as u32;
+    }
+    line.chars().count() + character.saturating_sub(units) as usize
+}
+
+pub fn char_col_to_utf16(line: &str, col: usize) -> u32 {
+    let units = line.chars().take(col).map(char::len_utf16).sum::<usize>();
+    (units + col.saturating_sub(line.chars().count())) as u32
+}
+
+// The line's text, without its line ending (lines are 0-based, like lsp's)
+pub fn line_text(text: &str, line: usize) -> &str {
+    text.split('\n').nth(line).unwrap_or("")
+}
+
+// Abra ranges are 1-based and inclusive of their end column, whereas lsp ranges are 0-based and exclusive of their end. Positions are
+// converted using the text of the file they're in. Bogus (0:0) positions are clamped to the start of the file.
+pub fn range_to_lsp(text: &str, range: &AbraRange) -> Range {
+    let end_line = range.end.line.saturating_sub(1);
+    Range {
+        start: position_to_lsp(text, &range.start),
+        end: Position { line: end_line as u32, character: char_col_to_utf16(line_text(text, end_line), range.end.col) },
+    }
+}
+
+pub fn position_to_lsp(text: &str, position: &AbraPosition) -> Position {
+    let line = position.line.saturating_sub(1);
+    Position { line: line as u32, character: char_col_to_utf16(line_text(text, line), position.col.saturating_sub(1)) }
+}
+
+pub fn position_from_lsp(text: &str, position: &Position) -> AbraPosition {
+    let line = position.line as usize;
+    AbraPosition::new(line + 1, utf16_col_to_chars(line_text(text, line), position.character) + 1)
+}
 
-    let range = Range {
-        start: Position { line: (range.start.line - 1) as u64, character: (range.start.col - 1) as u64 },
-        end: Position { line: (range.end.line - 1) as u64, character: (range.end.col) as u64 },
+// The location's file may not be the document's, so the text of each file is given by `file_text`
+pub fn location_to_lsp<F: Fn(&str) -> String>(location: &AbraLocation, file_text: F) -> Option<Location> {
+    let uri = Url::from_file_path(&location.file).ok()?;
+    Some(Location { uri, range: range_to_lsp(&file_text(&location.file), &location.range) })
+}
+
+pub fn diagnostic_to_lsp<F: Fn(&str) -> String>(diagnostic: &AbraDiagnostic, file_text: F) -> Diagnostic {
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
     };
 
+    // Editors show the message in a hover, so the notes and help are included (but not the source excerpts, which are redundant there)
+    let mut message = vec![diagnostic.message.clone()];
+    message.extend(diagnostic.notes.iter().cloned());
+    if let Some(help) = &diagnostic.help {
+        message.push(format!("help: {}", help));
+    }
+
+    let related_information = diagnostic.related.iter()
+        .filter_map(|related| {
+            let uri = Url::from_file_path(&related.file).ok()?;
+            Some(DiagnosticRelatedInformation {
+                location: Location { uri, range: range_to_lsp(&file_text(&related.file), &related.range) },
+                message: related.message.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+
     Diagnostic {
-        severity: Some(DiagnosticSeverity::Error),
-        range,
-        message: e.get_message(file_name, &source),
+        range: range_to_lsp(&file_text(&diagnostic.file), &diagnostic.range),
+        severity: Some(severity),
+        code: diagnostic.code.clone().map(NumberOrString::String),
+        source: Some("abra".to_string()),
+        message: message.join("\n"),
+        related_information: if related_information.is_empty() { None } else { Some(related_information) },
         ..Diagnostic::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use abra_core::lexer::tokens::{Position as AbraPosition, Range as AbraRange};
+    use crate::utils::{position_from_lsp, position_to_lsp, range_to_lsp};
+    use tower_lsp::lsp_types::{Position, Range};
+
+    #[test]
+    fn test_utf16_columns() {
+        let text = "val s = \"😀é\" + x\nval t = 1";
+
+        // The emoji is 1 char but 2 utf-16 code units, so everything after it on its line is 1 column further along in lsp's terms
+        assert_eq!(Position::new(0, 16), position_to_lsp(text, &AbraPosition::new(1, 16)));
+        assert_eq!(AbraPosition::new(1, 16), position_from_lsp(text, &Position::new(0, 16)));
+        let range = AbraRange { start: AbraPosition::new(1, 10), end: AbraPosition::new(1, 11) };
+        assert_eq!(Range::new(Position::new(0, 9), Position::new(0, 12)), range_to_lsp(text, &range));
+
+        // A position between the emoji's 2 code units is taken to be after it
+        assert_eq!(AbraPosition::new(1, 11), position_from_lsp(text, &Position::new(0, 10)));
+
+        // Other lines are unaffected, and columns past the end of a line are converted as-is
+        assert_eq!(Position::new(1, 4), position_to_lsp(text, &AbraPosition::new(2, 5)));
+        assert_eq!(AbraPosition::new(2, 21), position_from_lsp(text, &Position::new(1, 20)));
+    }
+
+    #[test]
+    fn test_bogus_positions() {
+        let text = "val s = 1\nval t = 2";
+
+        assert_eq!(Position::new(0, 0), position_to_lsp(text, &AbraPosition::new(0, 0)));
+        let range = AbraRange { start: AbraPosition::new(0, 0), end: AbraPosition::new(0, 0) };
+        assert_eq!(Range::new(Position::new(0, 0), Position::new(0, 0)), range_to_lsp(text, &range));
+    }
+}